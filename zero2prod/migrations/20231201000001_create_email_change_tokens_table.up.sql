@@ -0,0 +1,12 @@
+-- Create email change tokens table for confirming a user's new email address
+CREATE TABLE email_change_tokens(
+    id uuid NOT NULL PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    new_email TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL
+);
+
+-- Index for user's pending email changes (lookup by user_id)
+CREATE INDEX idx_email_change_tokens_user_id ON email_change_tokens(user_id);
@@ -0,0 +1,17 @@
+-- Track when a user proved ownership of their email address
+ALTER TABLE users
+ADD COLUMN email_verified_at timestamptz;
+
+-- Accounts created before verification existed are treated as verified
+UPDATE users SET email_verified_at = created_at;
+
+-- Create email verification tokens table for newly registered users
+CREATE TABLE email_verification_tokens(
+    token_hash TEXT NOT NULL PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL
+);
+
+-- Index for user's verification tokens (lookup by user_id)
+CREATE INDEX idx_email_verification_tokens_user_id ON email_verification_tokens(user_id);
@@ -0,0 +1,31 @@
+-- TOTP (RFC 6238) two-factor authentication state
+-- totp_secret is set during enrollment; 2FA is only active once totp_enabled_at is set
+ALTER TABLE users
+ADD COLUMN totp_secret TEXT,
+ADD COLUMN totp_enabled_at timestamptz,
+ADD COLUMN totp_last_used_step BIGINT;
+
+-- Single-use recovery codes, stored hashed
+CREATE TABLE mfa_recovery_codes(
+    id uuid NOT NULL PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    created_at timestamptz NOT NULL,
+    used_at timestamptz
+);
+
+-- Index for user's unused recovery codes
+CREATE INDEX idx_mfa_recovery_codes_user_id ON mfa_recovery_codes(user_id)
+WHERE used_at IS NULL;
+
+-- Pending second-step logins, issued after a correct password
+CREATE TABLE mfa_challenges(
+    token_hash TEXT NOT NULL PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL
+);
+
+-- Index for user's pending challenges (lookup by user_id)
+CREATE INDEX idx_mfa_challenges_user_id ON mfa_challenges(user_id);
@@ -0,0 +1,18 @@
+-- Long-lived API keys for machine clients, stored hashed like refresh tokens
+CREATE TABLE api_keys(
+    id uuid NOT NULL PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    -- First characters of the key, kept so users can tell keys apart
+    key_prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    created_at timestamptz NOT NULL,
+    last_used_at timestamptz,
+    expires_at timestamptz,
+    revoked_at timestamptz
+);
+
+-- Index for user's active keys (lookup by user_id)
+CREATE INDEX idx_api_keys_user_id ON api_keys(user_id)
+WHERE revoked_at IS NULL;
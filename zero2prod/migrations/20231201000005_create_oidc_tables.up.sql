@@ -0,0 +1,25 @@
+-- External identities (OpenID Connect) linked to local users
+CREATE TABLE user_identities(
+    id uuid NOT NULL PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    provider TEXT NOT NULL,
+    -- The provider's stable `sub` claim
+    subject TEXT NOT NULL,
+    email TEXT,
+    created_at timestamptz NOT NULL,
+    last_login_at timestamptz NOT NULL,
+    UNIQUE (provider, subject)
+);
+
+-- Index for user's identities (lookup by user_id)
+CREATE INDEX idx_user_identities_user_id ON user_identities(user_id);
+
+-- In-flight authorization-code logins, keyed by the hashed `state`
+CREATE TABLE oidc_login_states(
+    state_hash TEXT NOT NULL PRIMARY KEY,
+    provider TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    code_verifier TEXT NOT NULL,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL
+);
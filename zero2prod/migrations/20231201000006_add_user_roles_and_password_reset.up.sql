@@ -0,0 +1,24 @@
+-- Roles for authorization; promote the first admin manually:
+--   UPDATE users SET role = 'admin' WHERE email = '...';
+ALTER TABLE users
+ADD COLUMN role TEXT NOT NULL DEFAULT 'user'
+CONSTRAINT users_role_check CHECK (role IN ('user', 'admin'));
+
+-- Set when an admin forces a password reset; password login is refused
+-- until the user picks a new password
+ALTER TABLE users
+ADD COLUMN password_reset_required_at timestamptz;
+
+-- Index for paginated user listings (ordered by creation time)
+CREATE INDEX idx_users_created_at ON users(created_at);
+
+-- Create password reset tokens table
+CREATE TABLE password_reset_tokens(
+    token_hash TEXT NOT NULL PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL
+);
+
+-- Index for user's reset tokens (lookup by user_id)
+CREATE INDEX idx_password_reset_tokens_user_id ON password_reset_tokens(user_id);
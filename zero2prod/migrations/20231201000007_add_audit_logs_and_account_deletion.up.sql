@@ -0,0 +1,29 @@
+-- Persisted audit trail for account events (also written to the log)
+CREATE TABLE audit_logs(
+    id uuid NOT NULL PRIMARY KEY,
+    created_at timestamptz NOT NULL,
+    action TEXT NOT NULL,
+    resource_type TEXT NOT NULL,
+    resource_id TEXT,
+    user_id TEXT,
+    status TEXT NOT NULL,
+    message TEXT NOT NULL,
+    previous_state TEXT,
+    new_state TEXT
+);
+
+-- Indexes for a user's audit entries (as actor or as subject)
+CREATE INDEX idx_audit_logs_user_id ON audit_logs(user_id);
+CREATE INDEX idx_audit_logs_resource ON audit_logs(resource_type, resource_id);
+
+-- Self-service account deletion: the account is deactivated immediately
+-- and erased by the purge job once deletion_scheduled_at has passed.
+-- anonymized_at marks accounts kept as anonymized placeholders.
+ALTER TABLE users
+ADD COLUMN deletion_requested_at timestamptz,
+ADD COLUMN deletion_scheduled_at timestamptz,
+ADD COLUMN anonymized_at timestamptz;
+
+-- Index for the purge job (only pending deletions)
+CREATE INDEX idx_users_deletion_scheduled_at ON users(deletion_scheduled_at)
+WHERE deletion_scheduled_at IS NOT NULL;
@@ -0,0 +1,9 @@
+-- Subscriber preference center. Topics are opt-out so that subscribers
+-- receive topics added later unless they turn them off.
+ALTER TABLE subscriptions
+ADD COLUMN excluded_topics TEXT[] NOT NULL DEFAULT '{}',
+ADD COLUMN digest_frequency TEXT NOT NULL DEFAULT 'immediate'
+    CONSTRAINT subscriptions_digest_frequency_check
+    CHECK (digest_frequency IN ('immediate', 'daily', 'weekly')),
+ADD COLUMN paused_until timestamptz,
+ADD COLUMN unsubscribed_at timestamptz;
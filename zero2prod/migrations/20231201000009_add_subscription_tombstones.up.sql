@@ -0,0 +1,6 @@
+-- Erased subscribers leave only a hash of their email address, so that
+-- re-imports can skip them without keeping the address itself.
+CREATE TABLE subscription_tombstones(
+    email_hash TEXT NOT NULL PRIMARY KEY,
+    erased_at timestamptz NOT NULL
+);
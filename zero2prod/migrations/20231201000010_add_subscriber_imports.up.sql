@@ -0,0 +1,32 @@
+-- Where consent came from for subscribers confirmed outside the
+-- double opt-in flow (such as an import from a previous provider)
+ALTER TABLE subscriptions
+ADD COLUMN consent_source TEXT,
+ADD COLUMN consent_recorded_at timestamptz;
+
+-- Bulk subscriber imports, processed in the background
+CREATE TABLE subscriber_import_jobs(
+    id uuid NOT NULL PRIMARY KEY,
+    created_by uuid REFERENCES users(id) ON DELETE SET NULL,
+    mode TEXT NOT NULL CHECK (mode IN ('skip_existing', 'update', 'confirm')),
+    consent_source TEXT,
+    status TEXT NOT NULL CHECK (status IN ('queued', 'running', 'completed', 'failed')),
+    total_rows INTEGER NOT NULL DEFAULT 0,
+    imported INTEGER NOT NULL DEFAULT 0,
+    updated INTEGER NOT NULL DEFAULT 0,
+    skipped INTEGER NOT NULL DEFAULT 0,
+    failed INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    created_at timestamptz NOT NULL,
+    started_at timestamptz,
+    finished_at timestamptz
+);
+
+-- Per-row error report of an import; row_number counts the header as row 1
+CREATE TABLE subscriber_import_errors(
+    job_id uuid NOT NULL REFERENCES subscriber_import_jobs(id) ON DELETE CASCADE,
+    row_number INTEGER NOT NULL,
+    email TEXT,
+    message TEXT NOT NULL,
+    PRIMARY KEY (job_id, row_number)
+);
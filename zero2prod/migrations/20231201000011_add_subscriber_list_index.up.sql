@@ -0,0 +1,3 @@
+-- Admin subscriber lists page newest first by (subscribed_at, id).
+CREATE INDEX idx_subscriptions_subscribed_at_id
+ON subscriptions(subscribed_at DESC, id DESC);
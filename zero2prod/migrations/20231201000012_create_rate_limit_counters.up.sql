@@ -0,0 +1,12 @@
+-- Per-minute request counters for rate limiting shared across instances.
+-- Only the current and previous window of each key are needed.
+CREATE TABLE rate_limit_counters(
+    policy TEXT NOT NULL,
+    key TEXT NOT NULL,
+    window_start timestamptz NOT NULL,
+    count BIGINT NOT NULL,
+    PRIMARY KEY (policy, key, window_start)
+);
+
+CREATE INDEX idx_rate_limit_counters_window_start
+ON rate_limit_counters(window_start);
@@ -0,0 +1,12 @@
+-- Confirmation emails sent per recipient, for throttling subscriptions that
+-- target somebody else's address. Only a hash of the address is kept.
+CREATE TABLE confirmation_email_sends(
+    email_hash TEXT NOT NULL,
+    sent_at timestamptz NOT NULL
+);
+
+CREATE INDEX idx_confirmation_email_sends_email_hash
+ON confirmation_email_sends(email_hash, sent_at);
+
+CREATE INDEX idx_confirmation_email_sends_sent_at
+ON confirmation_email_sends(sent_at);
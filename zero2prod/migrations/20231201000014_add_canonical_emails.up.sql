@@ -0,0 +1,14 @@
+-- Addresses are unique regardless of case. lower() only folds ASCII here,
+-- so the application stores the canonical form (see `canonical_email`)
+-- next to the address as given. Addresses so far were ASCII-only, where
+-- lower() gives the same result; ones differing only in case must be
+-- merged before this runs.
+ALTER TABLE subscriptions ADD COLUMN email_canonical TEXT;
+UPDATE subscriptions SET email_canonical = lower(email);
+ALTER TABLE subscriptions ALTER COLUMN email_canonical SET NOT NULL;
+CREATE UNIQUE INDEX idx_subscriptions_email_canonical ON subscriptions(email_canonical);
+
+ALTER TABLE users ADD COLUMN email_canonical TEXT;
+UPDATE users SET email_canonical = lower(email);
+ALTER TABLE users ALTER COLUMN email_canonical SET NOT NULL;
+CREATE UNIQUE INDEX idx_users_email_canonical ON users(email_canonical);
@@ -0,0 +1,9 @@
+-- Nonces of subscription form tokens that already sent a confirmation
+-- email, kept until the token would have expired anyway.
+CREATE TABLE used_form_tokens(
+    nonce TEXT PRIMARY KEY,
+    expires_at timestamptz NOT NULL
+);
+
+CREATE INDEX idx_used_form_tokens_expires_at
+ON used_form_tokens(expires_at);
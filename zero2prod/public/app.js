@@ -9,20 +9,34 @@ const API_ENDPOINTS = {
 
 // ===== Token Management =====
 const TokenManager = {
+    // In the cookie session mode the refresh token is an HttpOnly cookie
+    // this script never sees, and the access token is kept for this tab only
     getAccessToken() {
-        return localStorage.getItem('access_token');
+        return sessionStorage.getItem('access_token') || localStorage.getItem('access_token');
     },
     
     getRefreshToken() {
         return localStorage.getItem('refresh_token');
     },
     
+    getCsrfToken() {
+        const cookie = document.cookie
+            .split('; ')
+            .find((row) => row.startsWith('csrf_token='));
+        return cookie ? decodeURIComponent(cookie.split('=')[1]) : null;
+    },
+    
     setTokens(accessToken, refreshToken) {
-        localStorage.setItem('access_token', accessToken);
-        localStorage.setItem('refresh_token', refreshToken);
+        if (refreshToken) {
+            localStorage.setItem('access_token', accessToken);
+            localStorage.setItem('refresh_token', refreshToken);
+        } else {
+            sessionStorage.setItem('access_token', accessToken);
+        }
     },
     
     clearTokens() {
+        sessionStorage.removeItem('access_token');
         localStorage.removeItem('access_token');
         localStorage.removeItem('refresh_token');
     },
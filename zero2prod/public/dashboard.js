@@ -3,24 +3,39 @@ const API_BASE_URL = window.location.origin;
 const API_ENDPOINTS = {
     me: `${API_BASE_URL}/auth/me`,
     refresh: `${API_BASE_URL}/auth/refresh`,
+    logout: `${API_BASE_URL}/auth/logout`,
 };
 
 // ===== Token Management =====
 const TokenManager = {
+    // In the cookie session mode the refresh token is an HttpOnly cookie
+    // this script never sees, and the access token is kept for this tab only
     getAccessToken() {
-        return localStorage.getItem('access_token');
+        return sessionStorage.getItem('access_token') || localStorage.getItem('access_token');
     },
     
     getRefreshToken() {
         return localStorage.getItem('refresh_token');
     },
     
+    getCsrfToken() {
+        const cookie = document.cookie
+            .split('; ')
+            .find((row) => row.startsWith('csrf_token='));
+        return cookie ? decodeURIComponent(cookie.split('=')[1]) : null;
+    },
+    
     setTokens(accessToken, refreshToken) {
-        localStorage.setItem('access_token', accessToken);
-        localStorage.setItem('refresh_token', refreshToken);
+        if (refreshToken) {
+            localStorage.setItem('access_token', accessToken);
+            localStorage.setItem('refresh_token', refreshToken);
+        } else {
+            sessionStorage.setItem('access_token', accessToken);
+        }
     },
     
     clearTokens() {
+        sessionStorage.removeItem('access_token');
         localStorage.removeItem('access_token');
         localStorage.removeItem('refresh_token');
     },
@@ -106,23 +121,37 @@ async function apiCall(endpoint, method = 'GET', data = null) {
 }
 
 // ===== Token Refresh =====
-async function refreshAccessToken() {
+// Sends the stored refresh token, or else the session cookie with its CSRF token
+function sessionRequest(endpoint) {
     const refreshToken = TokenManager.getRefreshToken();
+    const csrfToken = TokenManager.getCsrfToken();
     
-    if (!refreshToken) {
-        return false;
+    if (!refreshToken && !csrfToken) {
+        return null;
+    }
+    
+    const headers = {
+        'Content-Type': 'application/json',
+    };
+    if (csrfToken) {
+        headers['X-CSRF-Token'] = csrfToken;
     }
     
+    return fetch(endpoint, {
+        method: 'POST',
+        headers,
+        credentials: 'same-origin',
+        body: JSON.stringify(refreshToken ? { refresh_token: refreshToken } : {})
+    });
+}
+
+async function refreshAccessToken() {
     try {
-        const response = await fetch(API_ENDPOINTS.refresh, {
-            method: 'POST',
-            headers: {
-                'Content-Type': 'application/json',
-            },
-            body: JSON.stringify({
-                refresh_token: refreshToken
-            })
-        });
+        const request = sessionRequest(API_ENDPOINTS.refresh);
+        if (!request) {
+            return false;
+        }
+        const response = await request;
         
         if (!response.ok) {
             return false;
@@ -194,7 +223,12 @@ async function loadUserInfo() {
 }
 
 // ===== Logout =====
-function logout() {
+async function logout() {
+    try {
+        await sessionRequest(API_ENDPOINTS.logout);
+    } catch (error) {
+        console.error('Logout request failed:', error);
+    }
     TokenManager.clearTokens();
     AlertSystem.info('로그아웃되었습니다.');
     setTimeout(() => {
@@ -225,9 +259,9 @@ async function testProtectedAPI() {
 }
 
 // ===== Initialize =====
-document.addEventListener('DOMContentLoaded', () => {
-    // Check if user is logged in
-    if (!TokenManager.hasValidToken()) {
+document.addEventListener('DOMContentLoaded', async () => {
+    // Check if user is logged in (a session cookie can still get us a token)
+    if (!TokenManager.hasValidToken() && !(await refreshAccessToken())) {
         AlertSystem.error('로그인이 필요합니다.');
         setTimeout(() => {
             window.location.href = '/';
@@ -0,0 +1,181 @@
+// ===== API Configuration =====
+const API_BASE_URL = window.location.origin;
+const PREFERENCE_TOKEN = new URLSearchParams(window.location.search).get('token');
+const API_ENDPOINTS = {
+    preferences: `${API_BASE_URL}/subscriptions/preferences?token=${encodeURIComponent(PREFERENCE_TOKEN || '')}`,
+    unsubscribe: `${API_BASE_URL}/subscriptions/unsubscribe?token=${encodeURIComponent(PREFERENCE_TOKEN || '')}`,
+    data: `${API_BASE_URL}/subscriptions/data?token=${encodeURIComponent(PREFERENCE_TOKEN || '')}`,
+};
+
+const STATUS_LABELS = {
+    pending: '확인 대기',
+    confirmed: '구독 중',
+    unsubscribed: '해지됨',
+};
+
+// ===== Alert System =====
+const AlertSystem = {
+    show(message, type = 'info') {
+        const container = document.getElementById('alert-container');
+        const alert = document.createElement('div');
+        alert.className = `alert alert-${type}`;
+        alert.textContent = message;
+
+        container.innerHTML = '';
+        container.appendChild(alert);
+
+        setTimeout(() => {
+            alert.style.opacity = '0';
+            setTimeout(() => alert.remove(), 300);
+        }, 5000);
+    },
+
+    success(message) {
+        this.show(message, 'success');
+    },
+
+    error(message) {
+        this.show(message, 'error');
+    },
+
+    info(message) {
+        this.show(message, 'info');
+    }
+};
+
+// ===== API Calls =====
+// The signed token in the URL is the only credential; no login needed
+async function apiCall(endpoint, method = 'GET', data = null) {
+    const options = {
+        method,
+        headers: { 'Content-Type': 'application/json' },
+    };
+
+    if (data && (method === 'POST' || method === 'PUT')) {
+        options.body = JSON.stringify(data);
+    }
+
+    const response = await fetch(endpoint, options);
+    const responseData = await response.json();
+
+    if (response.status === 401) {
+        throw new Error('링크가 만료되었거나 올바르지 않습니다. 최근 이메일의 링크를 다시 열어주세요.');
+    }
+
+    if (!response.ok) {
+        throw new Error(responseData.message || responseData.error || '요청 처리 중 오류가 발생했습니다.');
+    }
+
+    return responseData;
+}
+
+// ===== Rendering =====
+function renderPreferences(preferences) {
+    document.getElementById('subscription-status').textContent =
+        STATUS_LABELS[preferences.status] || preferences.status;
+    document.getElementById('subscriber-email').textContent = preferences.email;
+    document.getElementById('subscriber-name').value = preferences.name;
+    document.getElementById('digest-frequency').value = preferences.digest_frequency;
+    document.getElementById('pause-weeks').value = '';
+
+    const topicList = document.getElementById('topic-list');
+    topicList.innerHTML = '';
+    preferences.topics.forEach(topic => {
+        const label = document.createElement('label');
+        const checkbox = document.createElement('input');
+        checkbox.type = 'checkbox';
+        checkbox.name = 'topics';
+        checkbox.value = topic.name;
+        checkbox.checked = topic.subscribed;
+        label.appendChild(checkbox);
+        label.appendChild(document.createTextNode(topic.name));
+        topicList.appendChild(label);
+    });
+
+    document.getElementById('paused-until').textContent = preferences.paused_until
+        ? `${new Date(preferences.paused_until).toLocaleDateString('ko-KR')}까지 일시 중지됨`
+        : '';
+
+    document.getElementById('unsubscribe-btn').disabled = preferences.status === 'unsubscribed';
+}
+
+// ===== Load / Save =====
+async function loadPreferences() {
+    try {
+        renderPreferences(await apiCall(API_ENDPOINTS.preferences));
+    } catch (error) {
+        AlertSystem.error(error.message);
+    }
+}
+
+async function savePreferences(event) {
+    event.preventDefault();
+
+    const data = {
+        name: document.getElementById('subscriber-name').value,
+        topics: Array.from(document.querySelectorAll('input[name="topics"]:checked'))
+            .map(checkbox => checkbox.value),
+        digest_frequency: document.getElementById('digest-frequency').value,
+    };
+
+    const pauseWeeks = document.getElementById('pause-weeks').value;
+    if (pauseWeeks !== '') {
+        data.pause_weeks = parseInt(pauseWeeks, 10);
+    }
+
+    try {
+        renderPreferences(await apiCall(API_ENDPOINTS.preferences, 'PUT', data));
+        AlertSystem.success('설정이 저장되었습니다.');
+    } catch (error) {
+        AlertSystem.error(error.message);
+    }
+}
+
+async function unsubscribe() {
+    if (!confirm('정말 구독을 해지하시겠습니까?')) {
+        return;
+    }
+
+    try {
+        await apiCall(API_ENDPOINTS.unsubscribe, 'POST');
+        AlertSystem.success('구독이 해지되었습니다.');
+        await loadPreferences();
+    } catch (error) {
+        AlertSystem.error(error.message);
+    }
+}
+
+// ===== Data Requests =====
+function exportData() {
+    // Served as an attachment, so the browser downloads it
+    window.location.href = API_ENDPOINTS.data;
+}
+
+async function eraseData() {
+    if (!confirm('구독 정보를 완전히 삭제하시겠습니까? 되돌릴 수 없습니다.')) {
+        return;
+    }
+
+    try {
+        await apiCall(API_ENDPOINTS.data, 'DELETE');
+        document.getElementById('preferences-form').hidden = true;
+        document.querySelectorAll('button').forEach(button => { button.disabled = true; });
+        AlertSystem.success('구독 정보가 삭제되었습니다.');
+    } catch (error) {
+        AlertSystem.error(error.message);
+    }
+}
+
+// ===== Initialize =====
+document.addEventListener('DOMContentLoaded', () => {
+    if (!PREFERENCE_TOKEN) {
+        AlertSystem.error('이메일에 포함된 링크로 접속해주세요.');
+        return;
+    }
+
+    document.getElementById('preferences-form').addEventListener('submit', savePreferences);
+    document.getElementById('unsubscribe-btn').addEventListener('click', unsubscribe);
+    document.getElementById('export-data-btn').addEventListener('click', exportData);
+    document.getElementById('erase-data-btn').addEventListener('click', eraseData);
+    loadPreferences();
+});
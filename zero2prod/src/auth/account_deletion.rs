@@ -0,0 +1,268 @@
+//! Account Deletion
+//!
+//! Self-service deletion happens in two steps:
+//! - The request deactivates the account and signs it out everywhere
+//! - Once the grace period is over, the purge job erases the account
+//!   (hard delete or anonymization, see `AccountDeletionMode`)
+//!
+//! Until the purge, an admin can undo the deletion by reactivating the
+//! account.
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::configuration::{AccountDeletionMode, AccountDeletionSettings};
+use crate::error::AppError;
+use crate::request_logging::{AuditLog, RequestFailureLogger};
+
+/// Accounts erased per purge batch
+const PURGE_BATCH_SIZE: i64 = 100;
+
+/// Name left on anonymized accounts
+const ANONYMIZED_NAME: &str = "Deleted user";
+
+/// Deactivate an account and schedule it for erasure
+///
+/// Refresh tokens and API keys are revoked right away.
+///
+/// # Returns
+/// When the account will be erased
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn schedule_account_deletion(
+    pool: &PgPool,
+    user_id: Uuid,
+    grace_period_days: i64,
+) -> Result<DateTime<Utc>, AppError> {
+    let now = Utc::now();
+    let scheduled_at = now + Duration::days(grace_period_days);
+
+    let mut transaction = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET is_active = false, deletion_requested_at = $1, deletion_scheduled_at = $2,
+            updated_at = $1
+        WHERE id = $3
+        "#,
+    )
+    .bind(now)
+    .bind(scheduled_at)
+    .bind(user_id)
+    .execute(&mut transaction)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE refresh_tokens
+        SET is_revoked = true, revoked_at = $1
+        WHERE user_id = $2 AND is_revoked = false
+        "#,
+    )
+    .bind(now)
+    .bind(user_id)
+    .execute(&mut transaction)
+    .await?;
+
+    sqlx::query("UPDATE api_keys SET revoked_at = $1 WHERE user_id = $2 AND revoked_at IS NULL")
+        .bind(now)
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(scheduled_at)
+}
+
+/// Erase every account whose grace period is over
+///
+/// Runs in batches; each account is erased in its own transaction so a
+/// failure doesn't hold back the others.
+///
+/// # Returns
+/// Number of accounts erased
+///
+/// # Errors
+/// Returns error if the due accounts can't be looked up
+pub async fn purge_deleted_accounts(
+    pool: &PgPool,
+    mode: AccountDeletionMode,
+) -> Result<u64, AppError> {
+    let mut purged = 0;
+
+    loop {
+        let due = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT id FROM users
+            WHERE deletion_scheduled_at <= $1
+            ORDER BY deletion_scheduled_at
+            LIMIT $2
+            "#,
+        )
+        .bind(Utc::now())
+        .bind(PURGE_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+
+        if due.is_empty() {
+            return Ok(purged);
+        }
+
+        let mut batch_purged = 0;
+        for user_id in due {
+            match purge_account(pool, user_id, mode).await {
+                Ok(true) => {
+                    batch_purged += 1;
+                    let audit_log = AuditLog::new(
+                        "PURGE_ACCOUNT".to_string(),
+                        "user".to_string(),
+                        "SUCCESS".to_string(),
+                        match mode {
+                            AccountDeletionMode::HardDelete => "Account deleted",
+                            AccountDeletionMode::Anonymize => "Account anonymized",
+                        }
+                        .to_string(),
+                    )
+                    .with_resource_id(user_id.to_string());
+                    RequestFailureLogger::record_audit(pool, &audit_log).await;
+                }
+                // Reactivated since the lookup
+                Ok(false) => {}
+                Err(e) => {
+                    tracing::error!(user_id = %user_id, error = %e, "Failed to purge account");
+                }
+            }
+        }
+
+        purged += batch_purged;
+        // Everything left failed; retry on the next run instead of spinning
+        if batch_purged == 0 {
+            return Ok(purged);
+        }
+    }
+}
+
+/// Run `purge_deleted_accounts` every `purge_interval_seconds`, forever
+pub async fn run_account_purge_worker(pool: PgPool, settings: AccountDeletionSettings) {
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_secs(settings.purge_interval_seconds.max(1)));
+
+    loop {
+        interval.tick().await;
+        match purge_deleted_accounts(&pool, settings.mode).await {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!(purged, "Purged deleted accounts"),
+            Err(e) => tracing::error!(error = %e, "Account purge failed"),
+        }
+    }
+}
+
+async fn purge_account(
+    pool: &PgPool,
+    user_id: Uuid,
+    mode: AccountDeletionMode,
+) -> Result<bool, AppError> {
+    let mut transaction = pool.begin().await?;
+
+    // Re-checked under lock in case an admin reactivated the account
+    let still_due = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM users WHERE id = $1 AND deletion_scheduled_at <= $2 FOR UPDATE",
+    )
+    .bind(user_id)
+    .bind(Utc::now())
+    .fetch_optional(&mut transaction)
+    .await?;
+    if still_due.is_none() {
+        return Ok(false);
+    }
+
+    // Audit entries stay, but not the old and new values they recorded
+    // (such as previous email addresses)
+    sqlx::query(
+        r#"
+        UPDATE audit_logs SET previous_state = NULL, new_state = NULL
+        WHERE resource_type = 'user' AND resource_id = $1
+        "#,
+    )
+    .bind(user_id.to_string())
+    .execute(&mut transaction)
+    .await?;
+
+    match mode {
+        AccountDeletionMode::HardDelete => {
+            sqlx::query("DELETE FROM users WHERE id = $1")
+                .bind(user_id)
+                .execute(&mut transaction)
+                .await?;
+        }
+        AccountDeletionMode::Anonymize => anonymize_account(&mut transaction, user_id).await?,
+    }
+
+    transaction.commit().await?;
+
+    Ok(true)
+}
+
+async fn anonymize_account(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<(), AppError> {
+    for table in [
+        "refresh_tokens",
+        "email_change_tokens",
+        "email_verification_tokens",
+        "password_reset_tokens",
+        "mfa_recovery_codes",
+        "mfa_challenges",
+        "api_keys",
+        "user_identities",
+    ] {
+        sqlx::query(&format!("DELETE FROM {} WHERE user_id = $1", table))
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    let now = Utc::now();
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET email = $1, email_canonical = $1, name = $2, password_hash = '', is_active = false,
+            email_verified_at = NULL, totp_secret = NULL, totp_enabled_at = NULL,
+            totp_last_used_step = NULL, password_reset_required_at = NULL,
+            deletion_scheduled_at = NULL, anonymized_at = $3, updated_at = $3
+        WHERE id = $4
+        "#,
+    )
+    .bind(anonymized_email(user_id))
+    .bind(ANONYMIZED_NAME)
+    .bind(now)
+    .bind(user_id)
+    .execute(&mut *transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Unique placeholder under a reserved TLD, so it can never receive mail
+fn anonymized_email(user_id: Uuid) -> String {
+    format!("deleted-{}@deleted.invalid", user_id.simple())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anonymized_email_is_unique_and_undeliverable() {
+        let user_id = Uuid::new_v4();
+        let email = anonymized_email(user_id);
+        assert!(email.ends_with("@deleted.invalid"));
+        assert!(email.contains(&user_id.simple().to_string()));
+        assert_ne!(email, anonymized_email(Uuid::new_v4()));
+    }
+}
@@ -0,0 +1,285 @@
+//! API Key Management
+//!
+//! Long-lived, scoped credentials for machine clients (e.g. CI publishing
+//! newsletters) that can't do interactive login/refresh.
+//! API keys are:
+//! - Random 48-character strings behind a recognizable `z2p_` prefix
+//! - Hashed with SHA-256 before storage, like refresh tokens
+//! - Shown to the user once, at creation
+//! - Limited to the scopes chosen at creation, with optional expiry
+
+use chrono::{DateTime, Duration, Utc};
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::refresh_token::hash_token;
+use crate::auth::Claims;
+use crate::error::{AppError, AuthError, ValidationError};
+
+/// Prefix that distinguishes API keys from JWTs in the Authorization header
+pub const API_KEY_PREFIX: &str = "z2p_";
+
+/// Read the authenticated user's profile (`GET /api/me`)
+pub const SCOPE_PROFILE_READ: &str = "profile:read";
+/// Publish newsletters (`POST /api/newsletters/*`); admins only
+pub const SCOPE_NEWSLETTERS_SEND: &str = "newsletters:send";
+
+/// Every scope an API key can be granted
+pub const API_KEY_SCOPES: &[&str] = &[SCOPE_PROFILE_READ, SCOPE_NEWSLETTERS_SEND];
+
+const API_KEY_RANDOM_LENGTH: usize = 48;
+/// Characters of the key stored in plaintext for display
+const API_KEY_DISPLAY_PREFIX_LENGTH: usize = 12;
+/// Skip `last_used_at` writes if the key was used this recently
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60;
+
+/// A newly created API key; `key` is never retrievable again
+#[derive(Debug)]
+pub struct NewApiKey {
+    pub id: Uuid,
+    pub key: String,
+    pub key_prefix: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Stored API key metadata (never includes the key itself)
+#[derive(Debug, sqlx::FromRow)]
+pub struct ApiKeyRecord {
+    pub id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Generate a new API key in plaintext
+pub fn generate_api_key() -> String {
+    let random: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(API_KEY_RANDOM_LENGTH)
+        .map(char::from)
+        .collect();
+    format!("{}{}", API_KEY_PREFIX, random)
+}
+
+/// Whether a bearer credential looks like an API key rather than a JWT
+pub fn is_api_key(credential: &str) -> bool {
+    credential.starts_with(API_KEY_PREFIX)
+}
+
+/// Validate requested scopes, returning them sorted and de-duplicated
+///
+/// # Errors
+/// Returns validation error if no scopes are given or any scope is unknown
+pub fn normalize_scopes(scopes: &[String]) -> Result<Vec<String>, AppError> {
+    if scopes.is_empty() {
+        return Err(AppError::Validation(ValidationError::EmptyField(
+            "scopes".to_string(),
+        )));
+    }
+
+    if let Some(unknown) = scopes.iter().find(|s| !API_KEY_SCOPES.contains(&s.as_str())) {
+        tracing::warn!(scope = %unknown, "Unknown API key scope requested");
+        return Err(AppError::Validation(ValidationError::InvalidFormat(
+            "scopes".to_string(),
+        )));
+    }
+
+    let mut scopes = scopes.to_vec();
+    scopes.sort();
+    scopes.dedup();
+    Ok(scopes)
+}
+
+/// Create and store a new API key
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User the key acts as
+/// * `name` - Label to identify the key
+/// * `scopes` - Already normalized scopes
+/// * `expires_in_days` - Optional lifetime; `None` never expires
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn create_api_key(
+    pool: &PgPool,
+    user_id: Uuid,
+    name: &str,
+    scopes: &[String],
+    expires_in_days: Option<i64>,
+) -> Result<NewApiKey, AppError> {
+    let id = Uuid::new_v4();
+    let key = generate_api_key();
+    let key_prefix = key[..API_KEY_DISPLAY_PREFIX_LENGTH].to_string();
+    let created_at = Utc::now();
+    let expires_at = expires_in_days.map(|days| created_at + Duration::days(days));
+
+    sqlx::query(
+        r#"
+        INSERT INTO api_keys (id, user_id, name, key_prefix, key_hash, scopes, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(name)
+    .bind(&key_prefix)
+    .bind(hash_token(&key))
+    .bind(scopes)
+    .bind(created_at)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    tracing::info!(user_id = %user_id, api_key_id = %id, "API key created");
+
+    Ok(NewApiKey {
+        id,
+        key,
+        key_prefix,
+        created_at,
+        expires_at,
+    })
+}
+
+/// List a user's API keys that have not been revoked
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn list_api_keys(pool: &PgPool, user_id: Uuid) -> Result<Vec<ApiKeyRecord>, AppError> {
+    let keys = sqlx::query_as::<_, ApiKeyRecord>(
+        r#"
+        SELECT id, name, key_prefix, scopes, created_at, last_used_at, expires_at
+        FROM api_keys
+        WHERE user_id = $1 AND revoked_at IS NULL
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(keys)
+}
+
+/// Revoke one of the user's API keys
+///
+/// # Returns
+/// `true` if a key was revoked, `false` if the user has no such active key
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn revoke_api_key(pool: &PgPool, user_id: Uuid, key_id: Uuid) -> Result<bool, AppError> {
+    let result = sqlx::query(
+        "UPDATE api_keys SET revoked_at = $1 WHERE id = $2 AND user_id = $3 AND revoked_at IS NULL",
+    )
+    .bind(Utc::now())
+    .bind(key_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        tracing::info!(user_id = %user_id, api_key_id = %key_id, "API key revoked");
+    }
+    Ok(result.rows_affected() > 0)
+}
+
+/// Authenticate a request with an API key
+///
+/// Returns claims for the key's owner limited to the key's scopes, and
+/// records when the key was last used.
+///
+/// # Errors
+/// Returns `TokenInvalid` if the key is unknown, revoked or expired, or the
+/// owner's account is inactive
+pub async fn authenticate_api_key(
+    pool: &PgPool,
+    key: &str,
+    issuer: &str,
+) -> Result<Claims, AppError> {
+    let now = Utc::now();
+
+    let (key_id, user_id, email, scopes, expires_at) =
+        sqlx::query_as::<_, (Uuid, Uuid, String, Vec<String>, Option<DateTime<Utc>>)>(
+            r#"
+            SELECT k.id, k.user_id, u.email, k.scopes, k.expires_at
+            FROM api_keys k
+            JOIN users u ON u.id = k.user_id
+            WHERE k.key_hash = $1 AND k.revoked_at IS NULL AND u.is_active = true
+            "#,
+        )
+        .bind(hash_token(key))
+        .fetch_optional(pool)
+        .await?
+        .ok_or(AppError::Auth(AuthError::TokenInvalid))?;
+
+    if expires_at.is_some_and(|expires_at| expires_at <= now) {
+        return Err(AppError::Auth(AuthError::TokenExpired));
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE api_keys SET last_used_at = $1
+        WHERE id = $2 AND (last_used_at IS NULL OR last_used_at < $3)
+        "#,
+    )
+    .bind(now)
+    .bind(key_id)
+    .bind(now - Duration::seconds(LAST_USED_RESOLUTION_SECONDS))
+    .execute(pool)
+    .await?;
+
+    Ok(Claims {
+        sub: user_id.to_string(),
+        email,
+        exp: expires_at.map_or(i64::MAX, |expires_at| expires_at.timestamp()),
+        iat: now.timestamp(),
+        iss: issuer.to_string(),
+        scopes: Some(scopes),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_api_key() {
+        let key = generate_api_key();
+        assert!(key.starts_with(API_KEY_PREFIX));
+        assert_eq!(key.len(), API_KEY_PREFIX.len() + API_KEY_RANDOM_LENGTH);
+        assert!(is_api_key(&key));
+        assert_ne!(key, generate_api_key());
+    }
+
+    #[test]
+    fn test_jwt_is_not_api_key() {
+        assert!(!is_api_key("eyJhbGciOiJIUzI1NiJ9.e30.sig"));
+    }
+
+    #[test]
+    fn test_normalize_scopes() {
+        let scopes = vec![
+            SCOPE_PROFILE_READ.to_string(),
+            SCOPE_NEWSLETTERS_SEND.to_string(),
+            SCOPE_PROFILE_READ.to_string(),
+        ];
+        assert_eq!(
+            normalize_scopes(&scopes).unwrap(),
+            vec![SCOPE_NEWSLETTERS_SEND.to_string(), SCOPE_PROFILE_READ.to_string()]
+        );
+    }
+
+    #[test]
+    fn test_normalize_scopes_rejects_empty_and_unknown() {
+        assert!(normalize_scopes(&[]).is_err());
+        assert!(normalize_scopes(&["admin".to_string()]).is_err());
+    }
+}
@@ -5,7 +5,7 @@
 
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use crate::error::AppError;
+use crate::error::{AppError, AuthError};
 
 /// JWT Claims for access tokens
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,6 +20,10 @@ pub struct Claims {
     pub iat: i64,
     /// Issuer
     pub iss: String,
+    /// Scopes granted to an API key; `None` for interactive login sessions,
+    /// which have full access
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<String>>,
 }
 
 impl Claims {
@@ -43,6 +47,7 @@ impl Claims {
             exp: now + expiry_seconds,
             iat: now,
             iss: issuer,
+            scopes: None,
         }
     }
 
@@ -55,6 +60,41 @@ impl Claims {
             .map_err(|_| AppError::Internal("Invalid user ID in token".to_string()))
     }
 
+    /// Check whether these claims allow `scope`
+    pub fn has_scope(&self, scope: &str) -> bool {
+        match &self.scopes {
+            None => true,
+            Some(scopes) => scopes.iter().any(|s| s == scope),
+        }
+    }
+
+    /// Require `scope`, which interactive sessions always have
+    ///
+    /// # Errors
+    /// Returns `InsufficientScope` if an API key lacks the scope
+    pub fn require_scope(&self, scope: &str) -> Result<(), AppError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(AppError::Auth(AuthError::InsufficientScope))
+        }
+    }
+
+    /// Require an interactive login session rather than an API key
+    ///
+    /// Used for account management, so a leaked key can't change the
+    /// password, email, 2FA settings or mint more keys.
+    ///
+    /// # Errors
+    /// Returns `InsufficientScope` for API key requests
+    pub fn require_session(&self) -> Result<(), AppError> {
+        if self.scopes.is_none() {
+            Ok(())
+        } else {
+            Err(AppError::Auth(AuthError::InsufficientScope))
+        }
+    }
+
     /// Check if token has expired
     pub fn is_expired(&self) -> bool {
         let now = chrono::Utc::now().timestamp();
@@ -98,4 +138,22 @@ mod tests {
 
         assert!(claims.user_id().is_err());
     }
+
+    #[test]
+    fn test_session_claims_have_every_scope() {
+        let claims = Claims::new(Uuid::new_v4(), "test@example.com".to_string(), 3600, "test".to_string());
+
+        assert!(claims.has_scope("newsletters:send"));
+        assert!(claims.require_session().is_ok());
+    }
+
+    #[test]
+    fn test_api_key_claims_are_limited_to_their_scopes() {
+        let mut claims = Claims::new(Uuid::new_v4(), "test@example.com".to_string(), 3600, "test".to_string());
+        claims.scopes = Some(vec!["profile:read".to_string()]);
+
+        assert!(claims.require_scope("profile:read").is_ok());
+        assert!(claims.require_scope("newsletters:send").is_err());
+        assert!(claims.require_session().is_err());
+    }
 }
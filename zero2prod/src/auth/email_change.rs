@@ -0,0 +1,153 @@
+//! Email Change Tokens
+//!
+//! Handles the two-step email change flow for authenticated users.
+//! The new address is only written to `users.email` once the user follows
+//! the confirmation link sent to that address. Tokens are:
+//! - Cryptographically secure random 64-byte strings
+//! - Hashed with SHA-256 before storage (same as refresh tokens)
+//! - Single-use and valid for 24 hours
+
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::refresh_token::{generate_refresh_token, hash_token};
+use crate::error::{AppError, DatabaseError, ValidationError};
+use crate::validators::canonical_email;
+
+/// Email change token lifetime in hours
+const EMAIL_CHANGE_TOKEN_EXPIRY_HOURS: i64 = 24;
+
+/// A confirmed email change, returned once the new address has been applied
+#[derive(Debug)]
+pub struct EmailChange {
+    pub user_id: Uuid,
+    pub previous_email: String,
+    pub new_email: String,
+}
+
+/// Create a pending email change request
+///
+/// Any earlier pending request for the same user is discarded, so only the
+/// most recently requested address can be confirmed.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User requesting the change
+/// * `new_email` - Validated new email address
+///
+/// # Returns
+/// Plaintext confirmation token to embed in the confirmation link
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn create_email_change_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    new_email: &str,
+) -> Result<String, AppError> {
+    let token = generate_refresh_token();
+    let now = Utc::now();
+
+    let mut transaction = pool.begin().await?;
+
+    sqlx::query("DELETE FROM email_change_tokens WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO email_change_tokens (id, user_id, new_email, token_hash, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(user_id)
+    .bind(new_email)
+    .bind(hash_token(&token))
+    .bind(now)
+    .bind(now + Duration::hours(EMAIL_CHANGE_TOKEN_EXPIRY_HOURS))
+    .execute(&mut transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(token)
+}
+
+/// Confirm a pending email change and apply it to the user
+///
+/// Consumes the token, swaps `users.email` and bumps `updated_at`
+/// in a single transaction. Following the link proves ownership of the
+/// new address, so it is also marked as verified.
+///
+/// # Errors
+/// - Validation error if the token is unknown or expired
+/// - Duplicate entry if the new address was registered in the meantime
+/// - Database error if the update fails
+pub async fn confirm_email_change_token(
+    pool: &PgPool,
+    token: &str,
+) -> Result<EmailChange, AppError> {
+    let mut transaction = pool.begin().await?;
+
+    let pending = sqlx::query_as::<_, (Uuid, String, chrono::DateTime<Utc>)>(
+        r#"
+        DELETE FROM email_change_tokens
+        WHERE token_hash = $1
+        RETURNING user_id, new_email, expires_at
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(&mut transaction)
+    .await?;
+
+    let (user_id, new_email, expires_at) = match pending {
+        Some(pending) => pending,
+        None => {
+            tracing::warn!("Email change token not found in database");
+            return Err(AppError::Validation(ValidationError::InvalidFormat(
+                "Invalid email change token".to_string(),
+            )));
+        }
+    };
+
+    if expires_at < Utc::now() {
+        // Keep the deletion of the stale token
+        transaction.commit().await?;
+        tracing::info!(user_id = %user_id, "Email change token expired");
+        return Err(AppError::Validation(ValidationError::InvalidFormat(
+            "Email change token has expired".to_string(),
+        )));
+    }
+
+    let previous_email = sqlx::query_scalar::<_, String>(
+        "SELECT email FROM users WHERE id = $1 AND is_active = true",
+    )
+    .bind(user_id)
+    .fetch_optional(&mut transaction)
+    .await?
+    .ok_or_else(|| AppError::Database(DatabaseError::NotFound("User not found".to_string())))?;
+
+    sqlx::query(
+        r#"
+        UPDATE users SET email = $1, email_canonical = $2, email_verified_at = $3, updated_at = $3
+        WHERE id = $4
+        "#,
+    )
+    .bind(&new_email)
+    .bind(canonical_email(&new_email))
+    .bind(Utc::now())
+    .bind(user_id)
+    .execute(&mut transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(EmailChange {
+        user_id,
+        previous_email,
+        new_email,
+    })
+}
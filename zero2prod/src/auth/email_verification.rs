@@ -0,0 +1,158 @@
+//! Email Verification Tokens
+//!
+//! Handles proving ownership of the email address a user registered with.
+//! Verification tokens are:
+//! - Cryptographically secure random 64-byte strings
+//! - Hashed with SHA-256 before storage (same as refresh tokens)
+//! - Single-use and valid for 24 hours
+
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::refresh_token::{generate_refresh_token, hash_token};
+use crate::configuration::AuthSettings;
+use crate::error::{AppError, AuthError, ValidationError};
+
+/// Email verification token lifetime in hours
+const EMAIL_VERIFICATION_TOKEN_EXPIRY_HOURS: i64 = 24;
+
+/// Create a new email verification token for a user
+///
+/// Earlier tokens for the same user are discarded so only the most
+/// recently sent link works.
+///
+/// # Returns
+/// Plaintext verification token to embed in the verification link
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn create_email_verification_token(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<String, AppError> {
+    let token = generate_refresh_token();
+    let now = Utc::now();
+
+    let mut transaction = pool.begin().await?;
+
+    sqlx::query("DELETE FROM email_verification_tokens WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO email_verification_tokens (token_hash, user_id, created_at, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(hash_token(&token))
+    .bind(user_id)
+    .bind(now)
+    .bind(now + Duration::hours(EMAIL_VERIFICATION_TOKEN_EXPIRY_HOURS))
+    .execute(&mut transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(token)
+}
+
+/// Consume a verification token and mark the user's email as verified
+///
+/// # Returns
+/// ID of the verified user
+///
+/// # Errors
+/// - Validation error if the token is unknown or expired
+/// - Database error if the update fails
+pub async fn confirm_email_verification_token(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Uuid, AppError> {
+    let mut transaction = pool.begin().await?;
+
+    let pending = sqlx::query_as::<_, (Uuid, chrono::DateTime<Utc>)>(
+        r#"
+        DELETE FROM email_verification_tokens
+        WHERE token_hash = $1
+        RETURNING user_id, expires_at
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(&mut transaction)
+    .await?;
+
+    let (user_id, expires_at) = match pending {
+        Some(pending) => pending,
+        None => {
+            tracing::warn!("Email verification token not found in database");
+            return Err(AppError::Validation(ValidationError::InvalidFormat(
+                "Invalid email verification token".to_string(),
+            )));
+        }
+    };
+
+    if expires_at < Utc::now() {
+        // Keep the deletion of the stale token
+        transaction.commit().await?;
+        tracing::info!(user_id = %user_id, "Email verification token expired");
+        return Err(AppError::Validation(ValidationError::InvalidFormat(
+            "Email verification token has expired".to_string(),
+        )));
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET email_verified_at = COALESCE(email_verified_at, $1), updated_at = $1
+        WHERE id = $2
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .execute(&mut transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(user_id)
+}
+
+/// Check whether a user has verified their email address
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn is_email_verified(pool: &PgPool, user_id: Uuid) -> Result<bool, AppError> {
+    let verified = sqlx::query_scalar::<_, bool>(
+        "SELECT email_verified_at IS NOT NULL FROM users WHERE id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(verified.unwrap_or(false))
+}
+
+/// Reject sensitive actions for unverified users when the policy requires it
+///
+/// # Errors
+/// - `AuthError::EmailNotVerified` if the policy blocks the action
+/// - Database error if the lookup fails
+pub async fn require_verified_email(
+    pool: &PgPool,
+    user_id: Uuid,
+    settings: &AuthSettings,
+) -> Result<(), AppError> {
+    if !settings.email_verification.blocks_sensitive_actions() {
+        return Ok(());
+    }
+
+    if !is_email_verified(pool, user_id).await? {
+        tracing::warn!(user_id = %user_id, "Sensitive action blocked: email not verified");
+        return Err(AppError::Auth(AuthError::EmailNotVerified));
+    }
+
+    Ok(())
+}
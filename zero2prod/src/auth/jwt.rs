@@ -1,11 +1,13 @@
 /// JWT Token Generation and Validation
 ///
 /// Handles creation and validation of JWT tokens for authentication.
+/// Keys and algorithms come from `JwtKeys`.
 
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Validation};
 use uuid::Uuid;
 
 use crate::auth::claims::Claims;
+use crate::auth::jwt_keys::JwtKeys;
 use crate::configuration::JwtSettings;
 use crate::error::AppError;
 
@@ -15,6 +17,7 @@ use crate::error::AppError;
 /// * `user_id` - User's UUID
 /// * `email` - User's email address
 /// * `config` - JWT configuration settings
+/// * `keys` - Loaded signing keys; the active key signs the token
 ///
 /// # Errors
 /// Returns error if token generation fails
@@ -22,6 +25,7 @@ pub fn generate_access_token(
     user_id: &Uuid,
     email: &str,
     config: &JwtSettings,
+    keys: &JwtKeys,
 ) -> Result<String, AppError> {
     let claims = Claims::new(
         *user_id,
@@ -30,12 +34,8 @@ pub fn generate_access_token(
         config.issuer.clone(),
     );
 
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(config.secret.as_bytes()),
-    )
-    .map_err(|e| AppError::Internal(format!("Token generation failed: {}", e)))
+    encode(&keys.header(), &claims, keys.encoding_key())
+        .map_err(|e| AppError::Internal(format!("Token generation failed: {}", e)))
 }
 
 /// Validate and extract claims from an access token
@@ -43,47 +43,76 @@ pub fn generate_access_token(
 /// # Arguments
 /// * `token` - JWT token string
 /// * `config` - JWT configuration settings
+/// * `keys` - Loaded verification keys, selected by the token's `kid`
 ///
 /// # Errors
-/// Returns error if token is invalid, expired, or tampered with
-pub fn validate_access_token(token: &str, config: &JwtSettings) -> Result<Claims, AppError> {
-    let mut validation = Validation::new(Algorithm::HS256);
+/// Returns error if token is invalid, expired, tampered with, or signed
+/// with an unknown key
+pub fn validate_access_token(
+    token: &str,
+    config: &JwtSettings,
+    keys: &JwtKeys,
+) -> Result<Claims, AppError> {
+    let header = decode_header(token).map_err(|e| {
+        tracing::warn!("JWT header error: {}", e);
+        AppError::Internal("Invalid or expired token".to_string())
+    })?;
+
+    let (algorithm, key) = keys.verification_key(header.kid.as_deref()).ok_or_else(|| {
+        tracing::warn!(kid = ?header.kid, "JWT signed with unknown key");
+        AppError::Internal("Invalid or expired token".to_string())
+    })?;
+
+    // Only the algorithm of the selected key is accepted
+    let mut validation = Validation::new(algorithm);
     // Verify issuer matches configuration
     validation.set_issuer(&[&config.issuer]);
 
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(config.secret.as_bytes()),
-        &validation,
-    )
-    .map(|data| data.claims)
-    .map_err(|e| {
-        tracing::warn!("JWT validation error: {}", e);
-        AppError::Internal("Invalid or expired token".to_string())
-    })
+    decode::<Claims>(token, key, &validation)
+        .map(|data| data.claims)
+        .map_err(|e| {
+            tracing::warn!("JWT validation error: {}", e);
+            AppError::Internal("Invalid or expired token".to_string())
+        })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::configuration::{JwtKeyAlgorithm, JwtKeySettings};
+
     fn get_test_config() -> JwtSettings {
         JwtSettings {
             secret: "test-secret-key-at-least-32-characters-long".to_string(),
             access_token_expiry: 3600,
             refresh_token_expiry: 604800,
             issuer: "test".to_string(),
+            active_key_id: None,
+            keys: vec![],
+            accept_legacy_hs256: true,
+        }
+    }
+
+    fn rsa_key(kid: &str, name: &str, private: bool) -> JwtKeySettings {
+        let fixture = |file: String| format!("{}/tests/fixtures/jwt/{}", env!("CARGO_MANIFEST_DIR"), file);
+        JwtKeySettings {
+            kid: kid.to_string(),
+            algorithm: JwtKeyAlgorithm::RS256,
+            public_key_path: fixture(format!("{}.pub.pem", name)),
+            private_key_path: private.then(|| fixture(format!("{}.pem", name))),
         }
     }
 
     #[test]
     fn test_generate_and_validate_token() {
         let config = get_test_config();
+        let keys = JwtKeys::from_settings(&config).unwrap();
         let user_id = Uuid::new_v4();
         let email = "test@example.com";
 
-        let token = generate_access_token(&user_id, email, &config).expect("Failed to generate token");
-        let claims = validate_access_token(&token, &config).expect("Failed to validate token");
+        let token = generate_access_token(&user_id, email, &config, &keys).expect("Failed to generate token");
+        let claims = validate_access_token(&token, &config, &keys).expect("Failed to validate token");
 
         assert_eq!(claims.sub, user_id.to_string());
         assert_eq!(claims.email, email);
@@ -93,7 +122,8 @@ mod tests {
     #[test]
     fn test_invalid_token() {
         let config = get_test_config();
-        let result = validate_access_token("invalid.token.here", &config);
+        let keys = JwtKeys::from_settings(&config).unwrap();
+        let result = validate_access_token("invalid.token.here", &config, &keys);
 
         assert!(result.is_err());
     }
@@ -101,14 +131,15 @@ mod tests {
     #[test]
     fn test_tampered_token() {
         let config = get_test_config();
+        let keys = JwtKeys::from_settings(&config).unwrap();
         let user_id = Uuid::new_v4();
 
-        let token = generate_access_token(&user_id, "test@example.com", &config)
+        let token = generate_access_token(&user_id, "test@example.com", &config, &keys)
             .expect("Failed to generate token");
 
         // Tamper with token
         let tampered = format!("{}X", token);
-        let result = validate_access_token(&tampered, &config);
+        let result = validate_access_token(&tampered, &config, &keys);
 
         assert!(result.is_err());
     }
@@ -116,15 +147,67 @@ mod tests {
     #[test]
     fn test_wrong_issuer() {
         let mut config = get_test_config();
+        let keys = JwtKeys::from_settings(&config).unwrap();
         let user_id = Uuid::new_v4();
 
-        let token = generate_access_token(&user_id, "test@example.com", &config)
+        let token = generate_access_token(&user_id, "test@example.com", &config, &keys)
             .expect("Failed to generate token");
 
         // Change issuer in validation config
         config.issuer = "wrong-issuer".to_string();
-        let result = validate_access_token(&token, &config);
+        let result = validate_access_token(&token, &config, &keys);
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_rotation_keeps_retired_key_tokens_valid() {
+        let mut config = get_test_config();
+        config.active_key_id = Some("old".to_string());
+        config.keys = vec![rsa_key("old", "rsa-a", true)];
+        let old_keys = JwtKeys::from_settings(&config).unwrap();
+        let hs256_config = get_test_config();
+        let hs256_keys = JwtKeys::from_settings(&hs256_config).unwrap();
+        let hs256_token =
+            generate_access_token(&Uuid::new_v4(), "a@example.com", &hs256_config, &hs256_keys).unwrap();
+        let old_token = generate_access_token(&Uuid::new_v4(), "a@example.com", &config, &old_keys).unwrap();
+
+        // Rotate: new active key, old one kept for verification only
+        config.active_key_id = Some("new".to_string());
+        config.keys = vec![rsa_key("new", "rsa-b", true), rsa_key("old", "rsa-a", false)];
+        let keys = JwtKeys::from_settings(&config).unwrap();
+        let new_token = generate_access_token(&Uuid::new_v4(), "a@example.com", &config, &keys).unwrap();
+
+        assert_eq!(decode_header(&new_token).unwrap().kid.as_deref(), Some("new"));
+        assert!(validate_access_token(&new_token, &config, &keys).is_ok());
+        assert!(validate_access_token(&old_token, &config, &keys).is_ok());
+        assert!(validate_access_token(&hs256_token, &config, &keys).is_ok());
+
+        // Dropping the retired key invalidates its tokens
+        config.keys.truncate(1);
+        let keys = JwtKeys::from_settings(&config).unwrap();
+        assert!(validate_access_token(&old_token, &config, &keys).is_err());
+    }
+
+    #[test]
+    fn test_rejects_hs256_token_claiming_asymmetric_kid() {
+        let mut config = get_test_config();
+        config.keys = vec![rsa_key("rsa", "rsa-a", false)];
+        let keys = JwtKeys::from_settings(&config).unwrap();
+
+        // Signed with the shared secret but pointing at the RSA key
+        let header = jsonwebtoken::Header {
+            kid: Some("rsa".to_string()),
+            ..Default::default()
+        };
+        let claims = Claims::new(Uuid::new_v4(), "a@example.com".to_string(), 3600, "test".to_string());
+        let token = encode(
+            &header,
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(config.secret.as_bytes()),
+        )
+        .unwrap();
+
+        assert!(validate_access_token(&token, &config, &keys).is_err());
+    }
 }
@@ -0,0 +1,368 @@
+//! JWT Signing Keys
+//!
+//! Loads the keys that sign and verify access tokens and builds the
+//! public JWKS document served at `/.well-known/jwks.json`.
+//! Supported keys:
+//! - HS256 with the shared `JwtSettings.secret` (the default signer, and the
+//!   verifier for tokens without a `kid` until `accept_legacy_hs256` is
+//!   turned off after moving to an asymmetric key)
+//! - RS256 and EdDSA keys identified by `kid`: one active signing key plus
+//!   retired keys that only verify, so rotation doesn't log anyone out
+
+use std::collections::HashMap;
+use std::fs;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use pkcs1::der::Decode;
+use spki::{ObjectIdentifier, SubjectPublicKeyInfoRef};
+
+use crate::configuration::{JwtKeyAlgorithm, JwtKeySettings, JwtSettings};
+use crate::error::ConfigError;
+
+const RSA_ENCRYPTION_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+const ED25519_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+
+/// Key used to sign new access tokens
+struct SigningKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    key: EncodingKey,
+}
+
+/// Key used to verify access tokens
+struct VerificationKey {
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+/// Loaded signing and verification keys
+///
+/// Built once at startup from `JwtSettings` and shared by the token
+/// issuing routes, `JwtMiddleware` and the JWKS endpoint.
+pub struct JwtKeys {
+    signing: SigningKey,
+    /// Verifies tokens without a `kid`; `None` once the secret is retired
+    hmac: Option<DecodingKey>,
+    by_kid: HashMap<String, VerificationKey>,
+    jwks: JwkSet,
+}
+
+impl JwtKeys {
+    /// Load the configured keys
+    ///
+    /// # Errors
+    /// Returns error if a key file can't be read or parsed, `kid`s are
+    /// duplicated, the active key is missing or has no private key, or the
+    /// active private key doesn't match its public key
+    pub fn from_settings(settings: &JwtSettings) -> Result<Self, ConfigError> {
+        let mut by_kid = HashMap::new();
+        let mut jwks = Vec::new();
+
+        for key_settings in &settings.keys {
+            if by_kid.contains_key(&key_settings.kid) {
+                return Err(ConfigError::InvalidValue(format!(
+                    "duplicate JWT key id '{}'",
+                    key_settings.kid
+                )));
+            }
+
+            let public_pem = read_key_file(&key_settings.public_key_path)?;
+            let jwk = public_jwk(key_settings, &public_pem)?;
+            let key = DecodingKey::from_jwk(&jwk).map_err(|e| {
+                ConfigError::InvalidValue(format!("JWT key '{}': {}", key_settings.kid, e))
+            })?;
+
+            by_kid.insert(
+                key_settings.kid.clone(),
+                VerificationKey {
+                    algorithm: algorithm_of(key_settings.algorithm),
+                    key,
+                },
+            );
+            jwks.push(jwk);
+        }
+
+        let signing = match &settings.active_key_id {
+            None => SigningKey {
+                kid: None,
+                algorithm: Algorithm::HS256,
+                key: EncodingKey::from_secret(settings.secret.as_bytes()),
+            },
+            Some(active_kid) => {
+                let key_settings = settings
+                    .keys
+                    .iter()
+                    .find(|k| &k.kid == active_kid)
+                    .ok_or_else(|| {
+                        ConfigError::MissingRequired(format!(
+                            "active JWT key '{}' is not listed in jwt.keys",
+                            active_kid
+                        ))
+                    })?;
+                load_signing_key(key_settings)?
+            }
+        };
+
+        let keys = Self {
+            signing,
+            hmac: (settings.active_key_id.is_none() || settings.accept_legacy_hs256)
+                .then(|| DecodingKey::from_secret(settings.secret.as_bytes())),
+            by_kid,
+            jwks: JwkSet { keys: jwks },
+        };
+        keys.check_signing_key()?;
+        Ok(keys)
+    }
+
+    /// Public keys for downstream verifiers (never includes the HS256 secret)
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+
+    /// Header for newly issued tokens
+    pub(crate) fn header(&self) -> Header {
+        let mut header = Header::new(self.signing.algorithm);
+        header.kid = self.signing.kid.clone();
+        header
+    }
+
+    pub(crate) fn encoding_key(&self) -> &EncodingKey {
+        &self.signing.key
+    }
+
+    /// Verification key for a token's `kid`; tokens without one use HS256
+    /// unless the shared secret has been retired
+    pub(crate) fn verification_key(&self, kid: Option<&str>) -> Option<(Algorithm, &DecodingKey)> {
+        match kid {
+            None => self.hmac.as_ref().map(|key| (Algorithm::HS256, key)),
+            Some(kid) => self.by_kid.get(kid).map(|k| (k.algorithm, &k.key)),
+        }
+    }
+
+    /// Sign and verify a probe token so a mismatched key pair fails at startup
+    fn check_signing_key(&self) -> Result<(), ConfigError> {
+        let mismatch = || {
+            ConfigError::InvalidValue(format!(
+                "JWT private key for '{}' does not match its public key",
+                self.signing.kid.as_deref().unwrap_or("secret")
+            ))
+        };
+
+        let token = encode(&self.header(), &serde_json::json!({}), self.encoding_key())
+            .map_err(|_| mismatch())?;
+        let (algorithm, key) = self
+            .verification_key(self.signing.kid.as_deref())
+            .ok_or_else(mismatch)?;
+
+        let mut validation = Validation::new(algorithm);
+        validation.validate_exp = false;
+        validation.required_spec_claims.clear();
+        decode::<serde_json::Value>(&token, key, &validation).map_err(|_| mismatch())?;
+        Ok(())
+    }
+}
+
+fn algorithm_of(algorithm: JwtKeyAlgorithm) -> Algorithm {
+    match algorithm {
+        JwtKeyAlgorithm::RS256 => Algorithm::RS256,
+        JwtKeyAlgorithm::EdDSA => Algorithm::EdDSA,
+    }
+}
+
+fn read_key_file(path: &str) -> Result<Vec<u8>, ConfigError> {
+    fs::read(path)
+        .map_err(|e| ConfigError::InvalidValue(format!("cannot read JWT key '{}': {}", path, e)))
+}
+
+fn load_signing_key(key_settings: &JwtKeySettings) -> Result<SigningKey, ConfigError> {
+    let path = key_settings.private_key_path.as_ref().ok_or_else(|| {
+        ConfigError::MissingRequired(format!(
+            "private_key_path for active JWT key '{}'",
+            key_settings.kid
+        ))
+    })?;
+    let pem = read_key_file(path)?;
+
+    let key = match key_settings.algorithm {
+        JwtKeyAlgorithm::RS256 => EncodingKey::from_rsa_pem(&pem),
+        JwtKeyAlgorithm::EdDSA => EncodingKey::from_ed_pem(&pem),
+    }
+    .map_err(|e| ConfigError::ParseError(format!("JWT key '{}': {}", key_settings.kid, e)))?;
+
+    Ok(SigningKey {
+        kid: Some(key_settings.kid.clone()),
+        algorithm: algorithm_of(key_settings.algorithm),
+        key,
+    })
+}
+
+/// Build the public JWK for a PEM public key
+fn public_jwk(key_settings: &JwtKeySettings, public_pem: &[u8]) -> Result<Jwk, ConfigError> {
+    let parse_error =
+        |reason: &str| ConfigError::ParseError(format!("JWT key '{}': {}", key_settings.kid, reason));
+
+    let pem = pem::parse(public_pem).map_err(|e| parse_error(&e.to_string()))?;
+
+    let (key_algorithm, algorithm) = match (key_settings.algorithm, pem.tag()) {
+        // PKCS#1 RSAPublicKey
+        (JwtKeyAlgorithm::RS256, "RSA PUBLIC KEY") => {
+            (KeyAlgorithm::RS256, rsa_parameters(pem.contents()).map_err(parse_error)?)
+        }
+        (key_type, "PUBLIC KEY") => {
+            let spki = SubjectPublicKeyInfoRef::from_der(pem.contents())
+                .map_err(|e| parse_error(&e.to_string()))?;
+            let public_key = spki.subject_public_key.raw_bytes();
+
+            match (key_type, spki.algorithm.oid) {
+                (JwtKeyAlgorithm::RS256, RSA_ENCRYPTION_OID) => {
+                    (KeyAlgorithm::RS256, rsa_parameters(public_key).map_err(parse_error)?)
+                }
+                (JwtKeyAlgorithm::EdDSA, ED25519_OID) => (
+                    KeyAlgorithm::EdDSA,
+                    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                        key_type: OctetKeyPairType::OctetKeyPair,
+                        curve: EllipticCurve::Ed25519,
+                        x: URL_SAFE_NO_PAD.encode(public_key),
+                    }),
+                ),
+                _ => return Err(parse_error("public key type does not match algorithm")),
+            }
+        }
+        _ => return Err(parse_error("expected a PUBLIC KEY or RSA PUBLIC KEY PEM")),
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(key_settings.kid.clone()),
+            ..Default::default()
+        },
+        algorithm,
+    })
+}
+
+fn rsa_parameters(pkcs1_der: &[u8]) -> Result<AlgorithmParameters, &'static str> {
+    let key = pkcs1::RsaPublicKey::from_der(pkcs1_der).map_err(|_| "invalid RSA public key")?;
+    Ok(AlgorithmParameters::RSA(RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: URL_SAFE_NO_PAD.encode(key.modulus.as_bytes()),
+        e: URL_SAFE_NO_PAD.encode(key.public_exponent.as_bytes()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/jwt/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn key(kid: &str, algorithm: JwtKeyAlgorithm, name: &str, private: bool) -> JwtKeySettings {
+        JwtKeySettings {
+            kid: kid.to_string(),
+            algorithm,
+            public_key_path: fixture(&format!("{}.pub.pem", name)),
+            private_key_path: private.then(|| fixture(&format!("{}.pem", name))),
+        }
+    }
+
+    fn settings(active_key_id: Option<&str>, keys: Vec<JwtKeySettings>) -> JwtSettings {
+        JwtSettings {
+            secret: "test-secret-key-at-least-32-characters-long".to_string(),
+            access_token_expiry: 3600,
+            refresh_token_expiry: 604800,
+            issuer: "test".to_string(),
+            active_key_id: active_key_id.map(str::to_string),
+            keys,
+            accept_legacy_hs256: true,
+        }
+    }
+
+    #[test]
+    fn test_defaults_to_hs256_without_active_key() {
+        let keys = JwtKeys::from_settings(&settings(None, vec![])).unwrap();
+
+        assert_eq!(keys.header().alg, Algorithm::HS256);
+        assert!(keys.header().kid.is_none());
+        assert!(keys.jwks().keys.is_empty());
+    }
+
+    #[test]
+    fn test_publishes_active_and_retired_keys() {
+        let keys = JwtKeys::from_settings(&settings(
+            Some("ed-1"),
+            vec![
+                key("ed-1", JwtKeyAlgorithm::EdDSA, "ed25519", true),
+                key("rsa-old", JwtKeyAlgorithm::RS256, "rsa-b", false),
+            ],
+        ))
+        .unwrap();
+
+        assert_eq!(keys.header().alg, Algorithm::EdDSA);
+        assert_eq!(keys.header().kid.as_deref(), Some("ed-1"));
+        assert!(keys.jwks().find("ed-1").is_some());
+        assert!(keys.jwks().find("rsa-old").is_some());
+        assert!(keys.verification_key(Some("rsa-old")).is_some());
+        assert!(keys.verification_key(Some("unknown")).is_none());
+
+        // Only public parameters are published
+        let json = serde_json::to_value(keys.jwks()).unwrap();
+        let rsa = json["keys"].as_array().unwrap().iter().find(|k| k["kid"] == "rsa-old").unwrap();
+        assert_eq!(rsa["kty"], "RSA");
+        assert_eq!(rsa["e"], "AQAB");
+        assert!(rsa.get("d").is_none());
+    }
+
+    #[test]
+    fn test_legacy_hs256_can_be_retired() {
+        let mut settings = settings(
+            Some("rsa-a"),
+            vec![key("rsa-a", JwtKeyAlgorithm::RS256, "rsa-a", true)],
+        );
+        let keys = JwtKeys::from_settings(&settings).unwrap();
+        assert!(keys.verification_key(None).is_some());
+
+        settings.accept_legacy_hs256 = false;
+        let keys = JwtKeys::from_settings(&settings).unwrap();
+        assert!(keys.verification_key(None).is_none());
+        assert!(keys.verification_key(Some("rsa-a")).is_some());
+    }
+
+    #[test]
+    fn test_secret_is_not_retired_while_it_signs() {
+        let mut settings = settings(None, vec![]);
+        settings.accept_legacy_hs256 = false;
+
+        let keys = JwtKeys::from_settings(&settings).unwrap();
+        assert!(keys.verification_key(None).is_some());
+    }
+
+    #[test]
+    fn test_rejects_mismatched_key_pair() {
+        let mut active = key("rsa-a", JwtKeyAlgorithm::RS256, "rsa-a", true);
+        active.public_key_path = fixture("rsa-b.pub.pem");
+
+        assert!(JwtKeys::from_settings(&settings(Some("rsa-a"), vec![active])).is_err());
+    }
+
+    #[test]
+    fn test_rejects_misconfigured_active_key() {
+        let retired_only = vec![key("rsa-a", JwtKeyAlgorithm::RS256, "rsa-a", false)];
+        assert!(JwtKeys::from_settings(&settings(Some("rsa-a"), retired_only)).is_err());
+        assert!(JwtKeys::from_settings(&settings(Some("missing"), vec![])).is_err());
+    }
+
+    #[test]
+    fn test_rejects_algorithm_mismatch() {
+        let wrong = vec![key("ed", JwtKeyAlgorithm::RS256, "ed25519", false)];
+        assert!(JwtKeys::from_settings(&settings(None, wrong)).is_err());
+    }
+}
@@ -1,19 +1,95 @@
 /// Authentication module
 ///
 /// Handles JWT token generation/validation, password hashing,
-/// and refresh token management.
+/// refresh token, API key and OpenID Connect login management, user
+/// roles, cookie sessions and account deletion.
 
 mod jwt;
+mod jwt_keys;
 mod password;
+mod password_strength;
+mod password_policy;
 mod claims;
 mod refresh_token;
+mod email_change;
+mod email_verification;
+mod totp;
+mod api_key;
+mod oidc;
+mod roles;
+mod password_reset;
+mod account_deletion;
+mod session;
 
 pub use jwt::generate_access_token;
 pub use jwt::validate_access_token;
+pub use jwt_keys::JwtKeys;
 pub use password::hash_password;
+pub use password::needs_rehash;
+pub use password::upgrade_password_hash;
+pub use password::verify_dummy_password;
 pub use password::verify_password;
+pub use password_strength::estimate_password_strength;
+pub use password_strength::PasswordStrength;
+pub use password_policy::PasswordPolicy;
 pub use claims::Claims;
 pub use refresh_token::generate_refresh_token;
 pub use refresh_token::save_refresh_token;
 pub use refresh_token::validate_refresh_token;
 pub use refresh_token::revoke_refresh_token;
+pub use refresh_token::revoke_all_user_tokens;
+pub use email_change::create_email_change_token;
+pub use email_change::confirm_email_change_token;
+pub use email_change::EmailChange;
+pub use email_verification::create_email_verification_token;
+pub use email_verification::confirm_email_verification_token;
+pub use email_verification::is_email_verified;
+pub use email_verification::require_verified_email;
+pub use totp::begin_totp_enrollment;
+pub use totp::complete_mfa_challenge;
+pub use totp::create_mfa_challenge;
+pub use totp::disable_totp;
+pub use totp::enable_totp;
+pub use totp::generate_totp_secret;
+pub use totp::is_totp_enabled;
+pub use totp::mfa_challenge_owner;
+pub use totp::totp_provisioning_uri;
+pub use totp::verify_second_factor;
+pub use totp::verify_totp_code_at;
+pub use totp::MFA_CHALLENGE_EXPIRY_SECONDS;
+pub use api_key::authenticate_api_key;
+pub use api_key::create_api_key;
+pub use api_key::is_api_key;
+pub use api_key::list_api_keys;
+pub use api_key::normalize_scopes;
+pub use api_key::revoke_api_key;
+pub use api_key::ApiKeyRecord;
+pub use api_key::NewApiKey;
+pub use api_key::API_KEY_SCOPES;
+pub use api_key::SCOPE_NEWSLETTERS_SEND;
+pub use api_key::SCOPE_PROFILE_READ;
+pub use oidc::link_or_provision_user;
+pub use oidc::OidcClient;
+pub use oidc::OidcIdentity;
+pub use oidc::OidcLinkOutcome;
+pub use roles::parse_role;
+pub use roles::require_admin;
+pub use roles::require_admin_role;
+pub use roles::ROLES;
+pub use roles::ROLE_ADMIN;
+pub use roles::ROLE_USER;
+pub use password_reset::complete_password_reset;
+pub use password_reset::create_password_reset_token;
+pub use password_reset::find_password_reset_account;
+pub use password_reset::PasswordResetAccount;
+pub use account_deletion::purge_deleted_accounts;
+pub use account_deletion::run_account_purge_worker;
+pub use account_deletion::schedule_account_deletion;
+pub use session::cleared_session_cookies;
+pub use session::csrf_token_for;
+pub use session::refresh_token_cookie;
+pub use session::session_cookies;
+pub use session::verify_csrf_token;
+pub use session::CSRF_COOKIE;
+pub use session::CSRF_HEADER;
+pub use session::REFRESH_TOKEN_COOKIE;
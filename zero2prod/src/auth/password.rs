@@ -1,41 +1,187 @@
 /// Password Hashing and Verification
 ///
-/// Handles password hashing with bcrypt and password strength validation.
+/// Handles password hashing and password strength validation.
+/// - New hashes use Argon2id with the configured parameters, stored as PHC
+///   strings (`$argon2id$v=19$m=...,t=...,p=...$salt$hash`)
+/// - Legacy bcrypt hashes (`$2a$`/`$2b$`/`$2y$`) still verify and are
+///   replaced on the next successful login, as are Argon2 hashes made with
+///   outdated parameters
+/// - Hashing and verification run on the blocking thread pool so they don't
+///   stall the async workers
 
-use bcrypt::{hash, verify, DEFAULT_COST};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
 
+use crate::configuration::PasswordHashingSettings;
 use crate::error::{AppError, ValidationError};
 
 const MIN_PASSWORD_LENGTH: usize = 8;
 const MAX_PASSWORD_LENGTH: usize = 128;
 
-/// Hash a password using bcrypt
+/// Hash a password using Argon2id
 ///
 /// # Arguments
 /// * `password` - Plain text password to hash
+/// * `settings` - Argon2id cost parameters
 ///
 /// # Errors
 /// Returns error if:
 /// - Password fails validation (too short, weak, etc.)
-/// - Bcrypt hashing fails
-pub fn hash_password(password: &str) -> Result<String, AppError> {
+/// - The parameters are invalid or hashing fails
+pub async fn hash_password(
+    password: &str,
+    settings: &PasswordHashingSettings,
+) -> Result<String, AppError> {
     validate_password_strength(password)?;
 
-    hash(password, DEFAULT_COST)
-        .map_err(|e| AppError::Internal(format!("Password hashing failed: {}", e)))
+    let password = password.to_string();
+    let settings = settings.clone();
+    run_blocking(move || argon2_hash(&password, &settings)).await
 }
 
 /// Verify a password against its hash
 ///
 /// # Arguments
 /// * `password` - Plain text password to verify
-/// * `hash` - Bcrypt hash to verify against
+/// * `hash` - Argon2 PHC string or legacy bcrypt hash to verify against
 ///
 /// # Errors
-/// Returns error if verification fails
-pub fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
-    verify(password, hash)
-        .map_err(|e| AppError::Internal(format!("Password verification failed: {}", e)))
+/// Returns error if the hash is malformed or in an unknown format
+pub async fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
+    let password = password.to_string();
+    let hash = hash.to_string();
+    run_blocking(move || verify_password_blocking(&password, &hash)).await
+}
+
+/// Do the work of a password check when there is no hash to check against
+///
+/// Logins for unknown emails call this so they take as long as logins for
+/// real accounts and response times don't reveal which emails exist.
+///
+/// # Errors
+/// Returns error if the parameters are invalid or hashing fails
+pub async fn verify_dummy_password(
+    password: &str,
+    settings: &PasswordHashingSettings,
+) -> Result<(), AppError> {
+    let password = password.to_string();
+    let settings = settings.clone();
+    // Hashing with fresh salt costs the same as verifying a stored hash
+    run_blocking(move || argon2_hash(&password, &settings).map(|_| ())).await
+}
+
+/// Whether a stored hash should be replaced with one using `settings`
+pub fn needs_rehash(hash: &str, settings: &PasswordHashingSettings) -> bool {
+    if is_bcrypt_hash(hash) {
+        return true;
+    }
+
+    let Ok(parsed) = PasswordHash::new(hash) else {
+        return false;
+    };
+    let Ok(params) = Params::try_from(&parsed) else {
+        return true;
+    };
+
+    parsed.algorithm != Algorithm::Argon2id.ident()
+        || parsed.version != Some(Version::V0x13.into())
+        || params.m_cost() != settings.memory_kib
+        || params.t_cost() != settings.iterations
+        || params.p_cost() != settings.parallelism
+}
+
+/// Replace an outdated hash after the password was verified
+///
+/// Must only be called with a password that matched `current_hash`. The
+/// password isn't re-validated against the strength rules, which may have
+/// changed since it was set. The update is skipped if the stored hash
+/// changed in the meantime (e.g. a concurrent password change).
+///
+/// # Returns
+/// Whether the stored hash was replaced
+///
+/// # Errors
+/// Returns error if hashing or the database update fails
+pub async fn upgrade_password_hash(
+    pool: &PgPool,
+    user_id: Uuid,
+    password: &str,
+    current_hash: &str,
+    settings: &PasswordHashingSettings,
+) -> Result<bool, AppError> {
+    if !needs_rehash(current_hash, settings) {
+        return Ok(false);
+    }
+
+    let password = password.to_string();
+    let hash_settings = settings.clone();
+    let new_hash = run_blocking(move || argon2_hash(&password, &hash_settings)).await?;
+
+    let result = sqlx::query(
+        "UPDATE users SET password_hash = $1, updated_at = $2 WHERE id = $3 AND password_hash = $4",
+    )
+    .bind(&new_hash)
+    .bind(Utc::now())
+    .bind(user_id)
+    .bind(current_hash)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+async fn run_blocking<T, F>(f: F) -> Result<T, AppError>
+where
+    F: FnOnce() -> Result<T, AppError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| AppError::Internal(format!("Password hashing task failed: {}", e)))?
+}
+
+fn argon2_hasher(settings: &PasswordHashingSettings) -> Result<Argon2<'static>, AppError> {
+    let params = Params::new(
+        settings.memory_kib,
+        settings.iterations,
+        settings.parallelism,
+        None,
+    )
+    .map_err(|e| AppError::Internal(format!("Invalid Argon2 parameters: {}", e)))?;
+    Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+}
+
+fn argon2_hash(password: &str, settings: &PasswordHashingSettings) -> Result<String, AppError> {
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    argon2_hasher(settings)?
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::Internal(format!("Password hashing failed: {}", e)))
+}
+
+fn verify_password_blocking(password: &str, hash: &str) -> Result<bool, AppError> {
+    if is_bcrypt_hash(hash) {
+        return bcrypt::verify(password, hash)
+            .map_err(|e| AppError::Internal(format!("Password verification failed: {}", e)));
+    }
+
+    let parsed = PasswordHash::new(hash)
+        .map_err(|e| AppError::Internal(format!("Password verification failed: {}", e)))?;
+    // Parameters come from the PHC string, so older hashes keep verifying
+    match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+        Ok(()) => Ok(true),
+        Err(argon2::password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(AppError::Internal(format!("Password verification failed: {}", e))),
+    }
+}
+
+fn is_bcrypt_hash(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
 }
 
 /// Validate password strength requirements
@@ -46,7 +192,7 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
 /// - At least one digit
 /// - At least one lowercase letter
 /// - At least one uppercase letter
-fn validate_password_strength(password: &str) -> Result<(), AppError> {
+pub(crate) fn validate_password_strength(password: &str) -> Result<(), AppError> {
     // Check minimum length
     if password.len() < MIN_PASSWORD_LENGTH {
         return Err(AppError::Validation(ValidationError::TooShort(
@@ -55,7 +201,7 @@ fn validate_password_strength(password: &str) -> Result<(), AppError> {
         )));
     }
 
-    // Check maximum length (bcrypt limitation and DoS prevention)
+    // Check maximum length (DoS prevention)
     if password.len() > MAX_PASSWORD_LENGTH {
         return Err(AppError::Validation(ValidationError::TooLong(
             "password".to_string(),
@@ -82,69 +228,119 @@ fn validate_password_strength(password: &str) -> Result<(), AppError> {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_hash_password() {
+    /// Cheap parameters so the tests stay fast
+    fn test_settings() -> PasswordHashingSettings {
+        PasswordHashingSettings {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hash_password() {
         let password = "ValidPassword123";
-        let hash = hash_password(password).expect("Failed to hash password");
+        let hash = hash_password(password, &test_settings()).await.expect("Failed to hash password");
 
         // Hash should not be the same as password
         assert_ne!(password, hash);
-        // Hash should start with bcrypt identifier
-        assert!(hash.starts_with("$2"));
+        // Hash should be an Argon2id PHC string with the configured parameters
+        assert!(hash.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
     }
 
-    #[test]
-    fn test_verify_password() {
+    #[tokio::test]
+    async fn test_verify_password() {
         let password = "ValidPassword123";
-        let hash = hash_password(password).expect("Failed to hash password");
+        let hash = hash_password(password, &test_settings()).await.expect("Failed to hash password");
 
-        let is_valid = verify_password(password, &hash).expect("Failed to verify password");
+        let is_valid = verify_password(password, &hash).await.expect("Failed to verify password");
         assert!(is_valid);
     }
 
-    #[test]
-    fn test_verify_wrong_password() {
+    #[tokio::test]
+    async fn test_verify_wrong_password() {
         let password = "ValidPassword123";
-        let hash = hash_password(password).expect("Failed to hash password");
+        let hash = hash_password(password, &test_settings()).await.expect("Failed to hash password");
 
-        let is_valid = verify_password("WrongPassword123", &hash).expect("Failed to verify password");
+        let is_valid = verify_password("WrongPassword123", &hash)
+            .await
+            .expect("Failed to verify password");
         assert!(!is_valid);
     }
 
-    #[test]
-    fn test_too_short_password() {
-        let result = hash_password("Short1");
+    #[tokio::test]
+    async fn test_verify_legacy_bcrypt_hash() {
+        let hash = bcrypt::hash("ValidPassword123", 4).unwrap();
+
+        assert!(verify_password("ValidPassword123", &hash).await.unwrap());
+        assert!(!verify_password("WrongPassword123", &hash).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_long_passwords_are_not_truncated() {
+        // bcrypt ignores everything after 72 bytes
+        let password = format!("{}A1", "a".repeat(80));
+        let hash = hash_password(&password, &test_settings()).await.unwrap();
+
+        let other = format!("{}B2", "a".repeat(80));
+        assert!(!verify_password(&other, &hash).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_unknown_hash_format() {
+        assert!(verify_password("ValidPassword123", "not-a-hash").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_needs_rehash() {
+        let settings = test_settings();
+        let current = hash_password("ValidPassword123", &settings).await.unwrap();
+        assert!(!needs_rehash(&current, &settings));
+
+        let stronger = PasswordHashingSettings {
+            memory_kib: 2048,
+            ..settings.clone()
+        };
+        assert!(needs_rehash(&current, &stronger));
+
+        let bcrypt_hash = bcrypt::hash("ValidPassword123", 4).unwrap();
+        assert!(needs_rehash(&bcrypt_hash, &settings));
+    }
+
+    #[tokio::test]
+    async fn test_too_short_password() {
+        let result = hash_password("Short1", &test_settings()).await;
         assert!(result.is_err());
     }
 
-    #[test]
-    fn test_too_long_password() {
+    #[tokio::test]
+    async fn test_too_long_password() {
         let long_password = "a".repeat(MAX_PASSWORD_LENGTH + 1) + "A1";
-        let result = hash_password(&long_password);
+        let result = hash_password(&long_password, &test_settings()).await;
         assert!(result.is_err());
     }
 
-    #[test]
-    fn test_no_digits() {
-        let result = hash_password("NoDigitsPassword");
+    #[tokio::test]
+    async fn test_no_digits() {
+        let result = hash_password("NoDigitsPassword", &test_settings()).await;
         assert!(result.is_err());
     }
 
-    #[test]
-    fn test_no_lowercase() {
-        let result = hash_password("NOLOWERCASE1");
+    #[tokio::test]
+    async fn test_no_lowercase() {
+        let result = hash_password("NOLOWERCASE1", &test_settings()).await;
         assert!(result.is_err());
     }
 
-    #[test]
-    fn test_no_uppercase() {
-        let result = hash_password("nouppercase1");
+    #[tokio::test]
+    async fn test_no_uppercase() {
+        let result = hash_password("nouppercase1", &test_settings()).await;
         assert!(result.is_err());
     }
 
-    #[test]
-    fn test_valid_password() {
-        let result = hash_password("ValidPassword123");
+    #[tokio::test]
+    async fn test_valid_password() {
+        let result = hash_password("ValidPassword123", &test_settings()).await;
         assert!(result.is_ok());
     }
 }
@@ -0,0 +1,259 @@
+//! Password Policy
+//!
+//! Checks a new password (at registration or password change) for:
+//! - Appearing in breach data: the SHA-1 of the password is split into a
+//!   5-hex-digit prefix and a suffix, and only the prefix selects the range
+//!   searched (the same k-anonymity layout as Have I Been Pwned), so a local
+//!   copy of the full dataset can be dropped in as-is
+//! - Guessability: the zxcvbn-style score must reach the configured minimum,
+//!   with the user's own name and email counting against the password
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use sha1::{Digest, Sha1};
+
+use crate::auth::password::validate_password_strength;
+use crate::auth::password_strength::{common_passwords, estimate_password_strength};
+use crate::configuration::PasswordPolicySettings;
+use crate::error::{AppError, ConfigError, ValidationError};
+
+const PREFIX_LENGTH: usize = 5;
+
+/// Password rules shared by the registration and password change routes
+pub struct PasswordPolicy {
+    min_score: u8,
+    check_breached: bool,
+    /// SHA-1 suffixes of the bundled common passwords, by prefix
+    bundled: HashMap<String, HashSet<String>>,
+    range_dir: Option<PathBuf>,
+}
+
+impl PasswordPolicy {
+    /// # Errors
+    /// Returns error if `min_score` is above 4 or the breached password
+    /// directory doesn't exist
+    pub fn new(settings: &PasswordPolicySettings) -> Result<Self, ConfigError> {
+        if settings.min_score > 4 {
+            return Err(ConfigError::InvalidValue(format!(
+                "auth.password_policy.min_score must be 0-4, got {}",
+                settings.min_score
+            )));
+        }
+
+        let range_dir = settings.breached_passwords_dir.as_ref().map(PathBuf::from);
+        if let Some(dir) = &range_dir {
+            if !dir.is_dir() {
+                return Err(ConfigError::InvalidValue(format!(
+                    "breached_passwords_dir '{}' is not a directory",
+                    dir.display()
+                )));
+            }
+        }
+
+        // Breach data is case-sensitive; the list is lowercase, so also
+        // include the capitalized forms people commonly use
+        let mut bundled: HashMap<String, HashSet<String>> = HashMap::new();
+        for password in common_passwords() {
+            let mut capitalized = password.chars();
+            let capitalized = capitalized
+                .next()
+                .map(|first| first.to_uppercase().chain(capitalized).collect::<String>())
+                .unwrap_or_default();
+            for variant in [password.to_string(), capitalized] {
+                let (prefix, suffix) = hash_range(&variant);
+                bundled.entry(prefix).or_default().insert(suffix);
+            }
+        }
+
+        Ok(Self {
+            min_score: settings.min_score,
+            check_breached: settings.check_breached,
+            bundled,
+            range_dir,
+        })
+    }
+
+    /// Check a new password
+    ///
+    /// `user_inputs` are the account's name, email and similar values the
+    /// password shouldn't be based on.
+    ///
+    /// # Errors
+    /// - TooShort/TooLong/InvalidFormat if the basic rules aren't met
+    /// - BreachedPassword if the password appears in breach data
+    /// - WeakPassword with feedback if it scores below the minimum
+    /// - Internal if the breach data can't be read
+    pub async fn check(&self, password: &str, user_inputs: &[&str]) -> Result<(), AppError> {
+        validate_password_strength(password)?;
+
+        if self.check_breached && self.is_breached(password).await? {
+            return Err(AppError::Validation(ValidationError::BreachedPassword));
+        }
+
+        let strength = estimate_password_strength(password, user_inputs);
+        if strength.score < self.min_score {
+            let mut feedback: Vec<String> = strength.warning.into_iter().collect();
+            feedback.extend(strength.suggestions);
+            if feedback.is_empty() {
+                feedback.push("Use a longer password with a few uncommon words".to_string());
+            }
+            let feedback: Vec<&str> = feedback.iter().map(|f| f.trim_end_matches('.')).collect();
+            return Err(AppError::Validation(ValidationError::WeakPassword(
+                feedback.join(". "),
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Look the password up by hash prefix in the bundled and configured data
+    ///
+    /// # Errors
+    /// Returns error if a range file exists but can't be read
+    pub async fn is_breached(&self, password: &str) -> Result<bool, AppError> {
+        let (prefix, suffix) = hash_range(password);
+
+        if self
+            .bundled
+            .get(&prefix)
+            .is_some_and(|suffixes| suffixes.contains(&suffix))
+        {
+            return Ok(true);
+        }
+
+        let Some(dir) = &self.range_dir else {
+            return Ok(false);
+        };
+        let range = match tokio::fs::read_to_string(dir.join(format!("{}.txt", prefix))).await {
+            Ok(range) => range,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => {
+                return Err(AppError::Internal(format!(
+                    "Failed to read breached password range {}: {}",
+                    prefix, e
+                )))
+            }
+        };
+
+        Ok(range_contains(&range, &suffix))
+    }
+}
+
+/// Uppercase SHA-1 hex of the password, split into (prefix, suffix)
+fn hash_range(password: &str) -> (String, String) {
+    let hash: String = Sha1::digest(password.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect();
+    let (prefix, suffix) = hash.split_at(PREFIX_LENGTH);
+    (prefix.to_string(), suffix.to_string())
+}
+
+/// Whether a range file (`SUFFIX:COUNT` per line) lists `suffix`
+///
+/// Padding entries with a count of 0 don't count as breached.
+fn range_contains(range: &str, suffix: &str) -> bool {
+    range.lines().any(|line| {
+        let mut parts = line.trim().splitn(2, ':');
+        let entry = parts.next().unwrap_or_default();
+        let count = parts
+            .next()
+            .and_then(|count| count.trim().parse::<u64>().ok())
+            .unwrap_or(1);
+        count > 0 && entry.eq_ignore_ascii_case(suffix)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy::new(&PasswordPolicySettings::default()).unwrap()
+    }
+
+    #[test]
+    fn test_hash_range_splits_sha1() {
+        // SHA-1("password") = 5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8
+        let (prefix, suffix) = hash_range("password");
+        assert_eq!("5BAA6", prefix);
+        assert_eq!("1E4C9B93F3F0682250B6CF8331B7EE68FD8", suffix);
+    }
+
+    #[test]
+    fn test_range_contains_ignores_case_and_padding() {
+        let range = "1E4C9B93F3F0682250B6CF8331B7EE68FD8:10434004\r\n0018A45C4D1DEF81644B54AB7F969B88D65:0\n";
+        assert!(range_contains(range, "1e4c9b93f3f0682250b6cf8331b7ee68fd8"));
+        assert!(!range_contains(range, "0018A45C4D1DEF81644B54AB7F969B88D65"));
+        assert!(!range_contains(range, "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"));
+    }
+
+    #[tokio::test]
+    async fn test_bundled_list_catches_common_passwords() {
+        let policy = policy();
+        assert!(policy.is_breached("password123").await.unwrap());
+        assert!(policy.is_breached("Password123").await.unwrap());
+        assert!(!policy.is_breached("EvenBetterPass456").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_check_reports_breached_before_weak() {
+        let result = policy().check("Password123", &[]).await;
+        assert!(matches!(
+            result,
+            Err(AppError::Validation(ValidationError::BreachedPassword))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_check_rejects_weak_password_with_feedback() {
+        let result = policy()
+            .check("Lovelace1815x", &["ada.lovelace@example.com", "Ada Lovelace"])
+            .await;
+        match result {
+            Err(AppError::Validation(ValidationError::WeakPassword(feedback))) => {
+                assert!(feedback.contains("name or email"), "{}", feedback);
+            }
+            other => panic!("expected WeakPassword, got {:?}", other.err()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_accepts_strong_password() {
+        assert!(policy().check("EvenBetterPass456", &["john@example.com"]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reads_configured_range_files() {
+        let dir = std::env::temp_dir().join(format!("pwned-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (prefix, suffix) = hash_range("EvenBetterPass456");
+        std::fs::write(dir.join(format!("{}.txt", prefix)), format!("{}:3\n", suffix)).unwrap();
+
+        let policy = PasswordPolicy::new(&PasswordPolicySettings {
+            breached_passwords_dir: Some(dir.to_string_lossy().to_string()),
+            ..PasswordPolicySettings::default()
+        })
+        .unwrap();
+
+        assert!(policy.is_breached("EvenBetterPass456").await.unwrap());
+        assert!(!policy.is_breached("NewSecurePass456").await.unwrap());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_invalid_settings() {
+        let settings = PasswordPolicySettings {
+            min_score: 5,
+            ..PasswordPolicySettings::default()
+        };
+        assert!(PasswordPolicy::new(&settings).is_err());
+
+        let settings = PasswordPolicySettings {
+            breached_passwords_dir: Some("/nonexistent/pwned".to_string()),
+            ..PasswordPolicySettings::default()
+        };
+        assert!(PasswordPolicy::new(&settings).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! Password Reset Tokens
+//!
+//! Issued when an admin forces a password reset. Reset tokens are:
+//! - Cryptographically secure random 64-byte strings
+//! - Hashed with SHA-256 before storage (same as refresh tokens)
+//! - Single-use and valid for 24 hours
+//!
+//! Completing a reset clears the reset requirement and revokes every
+//! refresh token of the user.
+
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::refresh_token::{generate_refresh_token, hash_token};
+use crate::error::{AppError, ValidationError};
+
+/// Password reset token lifetime in hours
+const PASSWORD_RESET_TOKEN_EXPIRY_HOURS: i64 = 24;
+
+/// Account a reset token was issued for
+pub struct PasswordResetAccount {
+    pub user_id: Uuid,
+    pub email: String,
+    pub name: String,
+}
+
+/// Create a new password reset token for a user
+///
+/// Earlier tokens for the same user are discarded so only the most
+/// recently sent link works.
+///
+/// # Returns
+/// Plaintext reset token to embed in the reset link
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn create_password_reset_token(pool: &PgPool, user_id: Uuid) -> Result<String, AppError> {
+    let token = generate_refresh_token();
+    let now = Utc::now();
+
+    let mut transaction = pool.begin().await?;
+
+    sqlx::query("DELETE FROM password_reset_tokens WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO password_reset_tokens (token_hash, user_id, created_at, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(hash_token(&token))
+    .bind(user_id)
+    .bind(now)
+    .bind(now + Duration::hours(PASSWORD_RESET_TOKEN_EXPIRY_HOURS))
+    .execute(&mut transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(token)
+}
+
+/// Look up the account a valid reset token belongs to, without consuming it
+///
+/// Lets the caller check the new password against the account's name and
+/// email before committing the reset.
+///
+/// # Errors
+/// - Validation error if the token is unknown or expired
+/// - Database error if the lookup fails
+pub async fn find_password_reset_account(
+    pool: &PgPool,
+    token: &str,
+) -> Result<PasswordResetAccount, AppError> {
+    let account = sqlx::query_as::<_, (Uuid, String, String)>(
+        r#"
+        SELECT u.id, u.email, u.name
+        FROM password_reset_tokens t
+        JOIN users u ON u.id = t.user_id
+        WHERE t.token_hash = $1 AND t.expires_at > $2
+        "#,
+    )
+    .bind(hash_token(token))
+    .bind(Utc::now())
+    .fetch_optional(pool)
+    .await?;
+
+    match account {
+        Some((user_id, email, name)) => Ok(PasswordResetAccount {
+            user_id,
+            email,
+            name,
+        }),
+        None => Err(invalid_token()),
+    }
+}
+
+/// Consume a reset token and set the new password hash
+///
+/// # Returns
+/// ID of the user whose password was reset
+///
+/// # Errors
+/// - Validation error if the token is unknown or expired
+/// - Database error if the update fails
+pub async fn complete_password_reset(
+    pool: &PgPool,
+    token: &str,
+    new_password_hash: &str,
+) -> Result<Uuid, AppError> {
+    let mut transaction = pool.begin().await?;
+
+    let pending = sqlx::query_as::<_, (Uuid, chrono::DateTime<Utc>)>(
+        r#"
+        DELETE FROM password_reset_tokens
+        WHERE token_hash = $1
+        RETURNING user_id, expires_at
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(&mut transaction)
+    .await?;
+
+    let Some((user_id, expires_at)) = pending else {
+        tracing::warn!("Password reset token not found in database");
+        return Err(invalid_token());
+    };
+
+    if expires_at < Utc::now() {
+        // Keep the deletion of the stale token
+        transaction.commit().await?;
+        tracing::info!(user_id = %user_id, "Password reset token expired");
+        return Err(AppError::Validation(ValidationError::InvalidFormat(
+            "Password reset token has expired".to_string(),
+        )));
+    }
+
+    let now = Utc::now();
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET password_hash = $1, password_reset_required_at = NULL, updated_at = $2
+        WHERE id = $3
+        "#,
+    )
+    .bind(new_password_hash)
+    .bind(now)
+    .bind(user_id)
+    .execute(&mut transaction)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE refresh_tokens
+        SET is_revoked = true, revoked_at = $1
+        WHERE user_id = $2 AND is_revoked = false
+        "#,
+    )
+    .bind(now)
+    .bind(user_id)
+    .execute(&mut transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(user_id)
+}
+
+fn invalid_token() -> AppError {
+    AppError::Validation(ValidationError::InvalidFormat(
+        "Invalid password reset token".to_string(),
+    ))
+}
@@ -0,0 +1,682 @@
+//! Password Strength Estimation
+//!
+//! A compact take on zxcvbn: the password is split into the cheapest
+//! sequence of guessable patterns and scored by the number of guesses an
+//! attacker would need. Recognised patterns:
+//! - Words from ranked dictionaries (common passwords, English words and the
+//!   user's own inputs such as their name and email), including reversed and
+//!   l33t-speak variants, with extra guesses for capitalization
+//! - Sequences (`abc`, `6543`), repeats (`aaa`), keyboard walks (`qwer`,
+//!   `1qaz`) and recent years
+//! - Anything else is brute-forced at 10 guesses per character
+//!
+//! Scores follow zxcvbn: 0 (< 10^3 guesses) up to 4 (>= 10^10 guesses).
+
+use std::collections::HashMap;
+
+use chrono::Datelike;
+use lazy_static::lazy_static;
+
+const BRUTEFORCE_CARDINALITY: f64 = 10.0;
+const MIN_GUESSES_BEFORE_GROWING_SEQUENCE: f64 = 10_000.0;
+const MIN_SUBMATCH_GUESSES_SINGLE_CHAR: f64 = 10.0;
+const MIN_SUBMATCH_GUESSES_MULTI_CHAR: f64 = 50.0;
+const MIN_YEAR_SPACE: f64 = 20.0;
+const KEYBOARD_STARTING_POSITIONS: f64 = 47.0;
+const KEYBOARD_AVERAGE_DEGREE: f64 = 4.0;
+const MIN_DICTIONARY_WORD_LENGTH: usize = 3;
+const MIN_KEYBOARD_WALK_LENGTH: usize = 4;
+
+/// Rows and columns of a US keyboard, unshifted
+const KEYBOARD_LINES: [&str; 14] = [
+    "`1234567890-=",
+    "qwertyuiop[]\\",
+    "asdfghjkl;'",
+    "zxcvbnm,./",
+    "1qaz",
+    "2wsx",
+    "3edc",
+    "4rfv",
+    "5tgb",
+    "6yhn",
+    "7ujm",
+    "8ik,",
+    "9ol.",
+    "0p;/",
+];
+
+/// Common l33t substitutions; `1` is tried as both `i` and `l`
+const L33T_TABLE: [(char, &[char]); 12] = [
+    ('4', &['a']),
+    ('@', &['a']),
+    ('8', &['b']),
+    ('(', &['c']),
+    ('3', &['e']),
+    ('6', &['g']),
+    ('1', &['i', 'l']),
+    ('!', &['i']),
+    ('0', &['o']),
+    ('$', &['s']),
+    ('5', &['s']),
+    ('7', &['t']),
+];
+
+lazy_static! {
+    static ref COMMON_PASSWORDS: HashMap<&'static str, usize> =
+        ranked(include_str!("data/common_passwords.txt"));
+    static ref ENGLISH_WORDS: HashMap<&'static str, usize> =
+        ranked(include_str!("data/english_words.txt"));
+}
+
+fn ranked(list: &'static str) -> HashMap<&'static str, usize> {
+    let mut ranks = HashMap::new();
+    for (index, word) in list.lines().map(str::trim).filter(|w| !w.is_empty()).enumerate() {
+        ranks.entry(word).or_insert(index + 1);
+    }
+    ranks
+}
+
+/// The bundled common password list, most common first
+pub(crate) fn common_passwords() -> impl Iterator<Item = &'static str> {
+    include_str!("data/common_passwords.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dictionary {
+    CommonPasswords,
+    EnglishWords,
+    UserInputs,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Pattern {
+    Dictionary {
+        dictionary: Dictionary,
+        rank: usize,
+        reversed: bool,
+        l33t: bool,
+    },
+    Sequence,
+    Repeat,
+    Keyboard,
+    Year,
+    Bruteforce,
+}
+
+/// A guessable span of the password, `start..=end` in chars
+#[derive(Debug, Clone)]
+struct Match {
+    start: usize,
+    end: usize,
+    pattern: Pattern,
+    guesses: f64,
+}
+
+/// Result of estimating a password's strength
+#[derive(Debug, Clone)]
+pub struct PasswordStrength {
+    /// 0 (too guessable) to 4 (very unguessable)
+    pub score: u8,
+    pub guesses_log10: f64,
+    /// Why the password is guessable, when there's a clear reason
+    pub warning: Option<String>,
+    /// How to pick a better password
+    pub suggestions: Vec<String>,
+}
+
+/// Estimate how hard a password is to guess
+///
+/// `user_inputs` are strings an attacker targeting this user would try
+/// first (name, email, ...); passwords built from them score very low.
+pub fn estimate_password_strength(password: &str, user_inputs: &[&str]) -> PasswordStrength {
+    let chars: Vec<char> = password.chars().collect();
+    if chars.is_empty() {
+        return PasswordStrength {
+            score: 0,
+            guesses_log10: 0.0,
+            warning: None,
+            suggestions: vec!["Use a few words, avoid common phrases".to_string()],
+        };
+    }
+
+    let user_dictionary = user_input_dictionary(user_inputs);
+    let mut matches = dictionary_matches(&chars, &user_dictionary);
+    matches.extend(sequence_matches(&chars));
+    matches.extend(repeat_matches(&chars));
+    matches.extend(keyboard_matches(&chars));
+    matches.extend(year_matches(&chars));
+
+    let (guesses, sequence) = most_guessable_sequence(&chars, matches);
+    let score = score_for(guesses);
+    let (warning, suggestions) = feedback(score, &sequence, &chars);
+
+    PasswordStrength {
+        score,
+        guesses_log10: guesses.log10(),
+        warning,
+        suggestions,
+    }
+}
+
+fn score_for(guesses: f64) -> u8 {
+    const DELTA: f64 = 5.0;
+    match guesses {
+        g if g < 1e3 + DELTA => 0,
+        g if g < 1e6 + DELTA => 1,
+        g if g < 1e8 + DELTA => 2,
+        g if g < 1e10 + DELTA => 3,
+        _ => 4,
+    }
+}
+
+/// Split user inputs into lowercase tokens, ranked in the order given
+fn user_input_dictionary(user_inputs: &[&str]) -> HashMap<String, usize> {
+    let mut dictionary = HashMap::new();
+    let mut rank = 1;
+    for input in user_inputs {
+        let input = input.trim().to_lowercase();
+        let tokens = std::iter::once(input.as_str())
+            .chain(input.split(|c: char| !c.is_alphanumeric()));
+        for token in tokens {
+            if token.chars().count() >= MIN_DICTIONARY_WORD_LENGTH
+                && !dictionary.contains_key(token)
+            {
+                dictionary.insert(token.to_string(), rank);
+                rank += 1;
+            }
+        }
+    }
+    dictionary
+}
+
+fn dictionary_matches(chars: &[char], user_dictionary: &HashMap<String, usize>) -> Vec<Match> {
+    let original: Vec<char> = chars.to_vec();
+    let lower: Vec<char> = chars.iter().flat_map(|c| c.to_lowercase()).collect();
+    // Lowercasing can change the length of some non-ASCII strings; those are
+    // left to the brute-force estimate
+    if lower.len() != original.len() {
+        return Vec::new();
+    }
+
+    let mut variants = vec![(lower.clone(), false)];
+    for variant in unl33t_variants(&lower) {
+        if variant != lower {
+            variants.push((variant, true));
+        }
+    }
+
+    let mut matches = Vec::new();
+    let n = lower.len();
+    for (candidate, l33t) in &variants {
+        for start in 0..n {
+            for end in (start + MIN_DICTIONARY_WORD_LENGTH - 1)..n {
+                let token: String = candidate[start..=end].iter().collect();
+                let reversed_token: String = token.chars().rev().collect();
+                for (word, reversed) in [(&token, false), (&reversed_token, true)] {
+                    if reversed && token == reversed_token {
+                        continue;
+                    }
+                    for (dictionary, rank) in lookup(word, user_dictionary) {
+                        let original_token = &original[start..=end];
+                        let mut guesses = rank as f64 * uppercase_variations(original_token);
+                        if *l33t {
+                            guesses *= l33t_variations(original_token);
+                        }
+                        if reversed {
+                            guesses *= 2.0;
+                        }
+                        matches.push(Match {
+                            start,
+                            end,
+                            pattern: Pattern::Dictionary {
+                                dictionary,
+                                rank,
+                                reversed,
+                                l33t: *l33t,
+                            },
+                            guesses,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    matches
+}
+
+fn lookup(word: &str, user_dictionary: &HashMap<String, usize>) -> Vec<(Dictionary, usize)> {
+    let mut found = Vec::new();
+    if let Some(rank) = COMMON_PASSWORDS.get(word) {
+        found.push((Dictionary::CommonPasswords, *rank));
+    }
+    if let Some(rank) = ENGLISH_WORDS.get(word) {
+        found.push((Dictionary::EnglishWords, *rank));
+    }
+    if let Some(rank) = user_dictionary.get(word) {
+        found.push((Dictionary::UserInputs, *rank));
+    }
+    found
+}
+
+/// The password with l33t characters replaced; one variant per reading of
+/// ambiguous characters
+fn unl33t_variants(lower: &[char]) -> Vec<Vec<char>> {
+    let mut variants = vec![lower.to_vec()];
+    for (position, c) in lower.iter().enumerate() {
+        let Some((_, replacements)) = L33T_TABLE.iter().find(|(l33t, _)| l33t == c) else {
+            continue;
+        };
+        if replacements.len() == 1 {
+            for variant in &mut variants {
+                variant[position] = replacements[0];
+            }
+        } else if variants.len() < 4 {
+            let mut extra = Vec::new();
+            for variant in &mut variants {
+                let mut alternative = variant.clone();
+                alternative[position] = replacements[1];
+                variant[position] = replacements[0];
+                extra.push(alternative);
+            }
+            variants.extend(extra);
+        } else {
+            for variant in &mut variants {
+                variant[position] = replacements[0];
+            }
+        }
+    }
+    variants
+}
+
+fn uppercase_variations(token: &[char]) -> f64 {
+    let upper = token.iter().filter(|c| c.is_uppercase()).count();
+    let lower = token.iter().filter(|c| c.is_lowercase()).count();
+    if upper == 0 {
+        return 1.0;
+    }
+    let first_only = token[0].is_uppercase() && upper == 1;
+    let last_only = token[token.len() - 1].is_uppercase() && upper == 1;
+    if first_only || last_only || lower == 0 {
+        return 2.0;
+    }
+    (1..=upper.min(lower)).map(|i| n_choose_k(upper + lower, i)).sum()
+}
+
+fn l33t_variations(token: &[char]) -> f64 {
+    let substituted = token
+        .iter()
+        .filter(|c| L33T_TABLE.iter().any(|(l33t, _)| l33t == *c))
+        .count();
+    2f64.powi(substituted.max(1) as i32)
+}
+
+fn n_choose_k(n: usize, k: usize) -> f64 {
+    (1..=k).fold(1.0, |acc, i| acc * (n + 1 - i) as f64 / i as f64)
+}
+
+fn sequence_matches(chars: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    let n = chars.len();
+    let mut start = 0;
+    while start + 2 < n {
+        let delta = chars[start + 1] as i64 - chars[start] as i64;
+        let same_class = |a: char, b: char| {
+            (a.is_ascii_digit() && b.is_ascii_digit())
+                || (a.is_ascii_lowercase() && b.is_ascii_lowercase())
+                || (a.is_ascii_uppercase() && b.is_ascii_uppercase())
+        };
+        let mut end = start + 1;
+        if delta.abs() == 1 && same_class(chars[start], chars[end]) {
+            while end + 1 < n
+                && chars[end + 1] as i64 - chars[end] as i64 == delta
+                && same_class(chars[end], chars[end + 1])
+            {
+                end += 1;
+            }
+        }
+        if end - start + 1 >= 3 {
+            let first = chars[start];
+            let base = if "aAzZ019".contains(first) {
+                4.0
+            } else if first.is_ascii_digit() {
+                10.0
+            } else {
+                26.0
+            };
+            let direction = if delta < 0 { 2.0 } else { 1.0 };
+            matches.push(Match {
+                start,
+                end,
+                pattern: Pattern::Sequence,
+                guesses: base * direction * (end - start + 1) as f64,
+            });
+            start = end;
+        } else {
+            start += 1;
+        }
+    }
+    matches
+}
+
+fn repeat_matches(chars: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = start;
+        while end + 1 < chars.len() && chars[end + 1] == chars[start] {
+            end += 1;
+        }
+        if end - start + 1 >= 3 {
+            matches.push(Match {
+                start,
+                end,
+                pattern: Pattern::Repeat,
+                guesses: cardinality(chars[start]) * (end - start + 1) as f64,
+            });
+        }
+        start = end + 1;
+    }
+    matches
+}
+
+fn cardinality(c: char) -> f64 {
+    if c.is_ascii_digit() {
+        10.0
+    } else if c.is_ascii_alphabetic() {
+        26.0
+    } else {
+        33.0
+    }
+}
+
+fn keyboard_matches(chars: &[char]) -> Vec<Match> {
+    let lower: Vec<char> = chars.iter().map(|c| c.to_ascii_lowercase()).collect();
+    let mut matches = Vec::new();
+    for line in KEYBOARD_LINES {
+        let forward: Vec<char> = line.chars().collect();
+        let backward: Vec<char> = line.chars().rev().collect();
+        for keys in [&forward, &backward] {
+            for start in 0..lower.len() {
+                let Some(offset) = keys.iter().position(|k| *k == lower[start]) else {
+                    continue;
+                };
+                let mut length = 1;
+                while start + length < lower.len()
+                    && offset + length < keys.len()
+                    && lower[start + length] == keys[offset + length]
+                {
+                    length += 1;
+                }
+                if length >= MIN_KEYBOARD_WALK_LENGTH {
+                    matches.push(Match {
+                        start,
+                        end: start + length - 1,
+                        pattern: Pattern::Keyboard,
+                        guesses: KEYBOARD_STARTING_POSITIONS
+                            * KEYBOARD_AVERAGE_DEGREE
+                            * length as f64
+                            * uppercase_variations(&chars[start..start + length]),
+                    });
+                }
+            }
+        }
+    }
+    matches
+}
+
+fn year_matches(chars: &[char]) -> Vec<Match> {
+    let reference_year = chrono::Utc::now().year() as f64;
+    let mut matches = Vec::new();
+    for start in 0..chars.len().saturating_sub(3) {
+        let digits: String = chars[start..start + 4].iter().collect();
+        let Ok(year) = digits.parse::<u32>() else {
+            continue;
+        };
+        if digits.chars().all(|c| c.is_ascii_digit()) && (1900..=2099).contains(&year) {
+            matches.push(Match {
+                start,
+                end: start + 3,
+                pattern: Pattern::Year,
+                guesses: (year as f64 - reference_year).abs().max(MIN_YEAR_SPACE),
+            });
+        }
+    }
+    matches
+}
+
+fn bruteforce_match(start: usize, end: usize) -> Match {
+    let length = (end - start + 1) as i32;
+    let minimum = if length == 1 {
+        MIN_SUBMATCH_GUESSES_SINGLE_CHAR + 1.0
+    } else {
+        MIN_SUBMATCH_GUESSES_MULTI_CHAR + 1.0
+    };
+    Match {
+        start,
+        end,
+        pattern: Pattern::Bruteforce,
+        guesses: BRUTEFORCE_CARDINALITY.powi(length).max(minimum),
+    }
+}
+
+/// Find the cover of the password with the fewest total guesses
+///
+/// Same search as zxcvbn: a sequence of `l` matches costs
+/// `l! * product(guesses) + 10000^(l - 1)`, so long chains of small matches
+/// aren't rewarded, and two brute-forced spans are never adjacent.
+fn most_guessable_sequence(chars: &[char], matches: Vec<Match>) -> (f64, Vec<Match>) {
+    let n = chars.len();
+
+    let mut by_end: Vec<Vec<Match>> = vec![Vec::new(); n];
+    for mut m in matches {
+        // A pattern covering only part of the password is worth a minimum
+        let covers_all = m.start == 0 && m.end == n - 1;
+        if !covers_all {
+            let minimum = if m.start == m.end {
+                MIN_SUBMATCH_GUESSES_SINGLE_CHAR
+            } else {
+                MIN_SUBMATCH_GUESSES_MULTI_CHAR
+            };
+            m.guesses = m.guesses.max(minimum);
+        }
+        by_end[m.end].push(m);
+    }
+
+    // best[k][l]: cheapest way to cover chars[..=k] with exactly l matches,
+    // as (product of guesses, overall guesses, last match)
+    let mut best: Vec<HashMap<usize, (f64, f64, Match)>> = vec![HashMap::new(); n];
+
+    let update = |best: &mut Vec<HashMap<usize, (f64, f64, Match)>>, m: Match, l: usize| {
+        let k = m.end;
+        let mut product = m.guesses;
+        if l > 1 {
+            product *= best[m.start - 1][&(l - 1)].0;
+        }
+        let factorial: f64 = (1..=l).map(|i| i as f64).product();
+        let guesses = factorial * product + MIN_GUESSES_BEFORE_GROWING_SEQUENCE.powi(l as i32 - 1);
+        let dominated = best[k]
+            .iter()
+            .any(|(&other_l, &(_, other_guesses, _))| other_l <= l && other_guesses <= guesses);
+        if !dominated {
+            best[k].insert(l, (product, guesses, m));
+        }
+    };
+
+    for (k, ending_here) in by_end.iter().enumerate() {
+        for m in ending_here {
+            if m.start == 0 {
+                update(&mut best, m.clone(), 1);
+            } else {
+                let lengths: Vec<usize> = best[m.start - 1].keys().copied().collect();
+                for l in lengths {
+                    update(&mut best, m.clone(), l + 1);
+                }
+            }
+        }
+
+        update(&mut best, bruteforce_match(0, k), 1);
+        for start in 1..=k {
+            let previous: Vec<(usize, bool)> = best[start - 1]
+                .iter()
+                .map(|(&l, (_, _, last))| (l, last.pattern == Pattern::Bruteforce))
+                .collect();
+            for (l, previous_is_bruteforce) in previous {
+                if !previous_is_bruteforce {
+                    update(&mut best, bruteforce_match(start, k), l + 1);
+                }
+            }
+        }
+    }
+
+    let (&best_l, &(_, guesses, _)) = best[n - 1]
+        .iter()
+        .min_by(|a, b| a.1 .1.total_cmp(&b.1 .1))
+        .expect("the whole password can always be brute-forced");
+
+    let mut sequence = Vec::with_capacity(best_l);
+    let mut k = n as isize - 1;
+    let mut l = best_l;
+    while k >= 0 {
+        let m = best[k as usize][&l].2.clone();
+        k = m.start as isize - 1;
+        l -= 1;
+        sequence.push(m);
+    }
+    sequence.reverse();
+
+    (guesses, sequence)
+}
+
+fn feedback(score: u8, sequence: &[Match], chars: &[char]) -> (Option<String>, Vec<String>) {
+    if score > 2 {
+        return (None, Vec::new());
+    }
+
+    let extra = "Add another word or two. Uncommon words are better.".to_string();
+
+    // A user input anywhere is the most important thing to point out
+    let user_input = sequence.iter().find(|m| {
+        matches!(
+            m.pattern,
+            Pattern::Dictionary { dictionary: Dictionary::UserInputs, .. }
+        )
+    });
+    let Some(longest) = user_input.or_else(|| {
+        sequence
+            .iter()
+            .filter(|m| m.pattern != Pattern::Bruteforce)
+            .max_by_key(|m| m.end - m.start)
+    }) else {
+        return (None, vec![extra, "Use a longer password".to_string()]);
+    };
+
+    let mut suggestions = vec![extra];
+    let warning = match &longest.pattern {
+        Pattern::Dictionary { dictionary, rank, reversed, l33t } => {
+            let token = &chars[longest.start..=longest.end];
+            if token.iter().skip(1).any(|c| c.is_uppercase()) || token[0].is_uppercase() {
+                suggestions.push("Capitalization doesn't help very much".to_string());
+            }
+            if *reversed {
+                suggestions.push("Reversed words aren't much harder to guess".to_string());
+            }
+            if *l33t {
+                suggestions.push(
+                    "Predictable substitutions like '@' instead of 'a' don't help very much"
+                        .to_string(),
+                );
+            }
+            match dictionary {
+                Dictionary::UserInputs => {
+                    "Avoid using your name or email address in your password".to_string()
+                }
+                Dictionary::CommonPasswords if *rank <= 10 => {
+                    "This is a top-10 common password".to_string()
+                }
+                Dictionary::CommonPasswords => "This is a very common password".to_string(),
+                Dictionary::EnglishWords if sequence.len() == 1 => {
+                    "A word by itself is easy to guess".to_string()
+                }
+                Dictionary::EnglishWords => {
+                    "Common words are easy to guess, even combined".to_string()
+                }
+            }
+        }
+        Pattern::Sequence => "Sequences like abc or 6543 are easy to guess".to_string(),
+        Pattern::Repeat => "Repeats like \"aaa\" are easy to guess".to_string(),
+        Pattern::Keyboard => "Keyboard patterns like qwerty are easy to guess".to_string(),
+        Pattern::Year => "Recent years are easy to guess".to_string(),
+        Pattern::Bruteforce => unreachable!("brute-force matches are filtered out"),
+    };
+
+    (Some(warning), suggestions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_passwords_score_zero() {
+        for password in ["password", "123456", "qwerty", "Password1", "p@ssw0rd", "iloveyou"] {
+            let strength = estimate_password_strength(password, &[]);
+            assert_eq!(0, strength.score, "{} scored {}", password, strength.score);
+            assert!(strength.warning.is_some());
+        }
+    }
+
+    #[test]
+    fn test_password123_is_weak() {
+        let strength = estimate_password_strength("Password123", &[]);
+        assert!(strength.score <= 1);
+    }
+
+    #[test]
+    fn test_patterns_score_low() {
+        for password in ["abcdefgh", "aaaaaaaaaa", "qwertyuiop", "asdfghjkl1", "12345678"] {
+            let strength = estimate_password_strength(password, &[]);
+            assert!(strength.score <= 1, "{} scored {}", password, strength.score);
+        }
+    }
+
+    #[test]
+    fn test_user_inputs_are_penalised() {
+        let without = estimate_password_strength("Lovelace1815", &[]);
+        let with = estimate_password_strength("Lovelace1815", &["ada.lovelace@example.com", "Ada Lovelace"]);
+        assert!(with.guesses_log10 < without.guesses_log10);
+        assert_eq!(
+            Some("Avoid using your name or email address in your password".to_string()),
+            with.warning
+        );
+    }
+
+    #[test]
+    fn test_random_and_multi_word_passwords_score_high() {
+        for password in ["xK9#mQ2$vL7!pR", "correct horse battery staple", "EvenBetterPass456"] {
+            let strength = estimate_password_strength(password, &[]);
+            assert!(strength.score >= 3, "{} scored {}", password, strength.score);
+            assert!(strength.warning.is_none());
+        }
+    }
+
+    #[test]
+    fn test_l33t_and_reversed_words_are_recognised() {
+        let plain = estimate_password_strength("dragon", &[]);
+        let l33t = estimate_password_strength("dr4g0n", &[]);
+        let reversed = estimate_password_strength("nogard", &[]);
+        assert_eq!(0, l33t.score);
+        assert_eq!(0, reversed.score);
+        assert!(l33t.guesses_log10 > plain.guesses_log10);
+    }
+
+    #[test]
+    fn test_empty_and_non_ascii_passwords() {
+        assert_eq!(0, estimate_password_strength("", &[]).score);
+        // Brute-forced, but must not panic
+        let strength = estimate_password_strength("Straße-İstanbul-Ωmega", &[]);
+        assert!(strength.score >= 3);
+    }
+}
@@ -32,7 +32,7 @@ pub fn generate_refresh_token() -> String {
 /// Hash a refresh token using SHA-256
 ///
 /// Never store plaintext tokens in the database.
-fn hash_token(token: &str) -> String {
+pub(crate) fn hash_token(token: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(token.as_bytes());
     format!("{:x}", hasher.finalize())
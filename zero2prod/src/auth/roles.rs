@@ -0,0 +1,84 @@
+//! User Roles
+//!
+//! Every user has a role stored in `users.role`. Roles are looked up on
+//! each request rather than embedded in the JWT, so demoting or
+//! deactivating an admin takes effect immediately.
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::Claims;
+use crate::error::{AppError, AuthError, ValidationError};
+
+/// Default role for registered users
+pub const ROLE_USER: &str = "user";
+/// Can manage other users through the admin API
+pub const ROLE_ADMIN: &str = "admin";
+/// All assignable roles
+pub const ROLES: &[&str] = &[ROLE_USER, ROLE_ADMIN];
+
+/// Check that `role` is one of the known roles
+///
+/// # Errors
+/// Returns `InvalidFormat` for unknown roles
+pub fn parse_role(role: &str) -> Result<&'static str, AppError> {
+    let role = role.trim().to_lowercase();
+    ROLES
+        .iter()
+        .find(|known| **known == role)
+        .copied()
+        .ok_or_else(|| AppError::Validation(ValidationError::InvalidFormat("role".to_string())))
+}
+
+/// Require an interactive session of an active admin
+///
+/// # Returns
+/// ID of the admin making the request
+///
+/// # Errors
+/// - `InsufficientScope` for API key requests
+/// - `PermissionDenied` if the user isn't an active admin
+/// - Database error if the lookup fails
+pub async fn require_admin(pool: &PgPool, claims: &Claims) -> Result<Uuid, AppError> {
+    claims.require_session()?;
+    let user_id = claims.user_id()?;
+    require_admin_role(pool, user_id).await?;
+    Ok(user_id)
+}
+
+/// Require that `user_id` is an active admin, whatever the credential
+///
+/// Unlike `require_admin` this accepts API keys, for admin-only actions
+/// that machine clients may perform with the right scope.
+///
+/// # Errors
+/// - `PermissionDenied` if the user isn't an active admin
+/// - Database error if the lookup fails
+pub async fn require_admin_role(pool: &PgPool, user_id: Uuid) -> Result<(), AppError> {
+    let is_admin = sqlx::query_scalar::<_, bool>(
+        "SELECT role = $1 AND is_active FROM users WHERE id = $2",
+    )
+    .bind(ROLE_ADMIN)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or(false);
+
+    if !is_admin {
+        return Err(AppError::Auth(AuthError::PermissionDenied));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_role_accepts_known_roles() {
+        assert_eq!(ROLE_ADMIN, parse_role(" Admin ").unwrap());
+        assert_eq!(ROLE_USER, parse_role("user").unwrap());
+        assert!(parse_role("superuser").is_err());
+    }
+}
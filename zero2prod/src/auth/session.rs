@@ -0,0 +1,118 @@
+//! Cookie Sessions
+//!
+//! In the `cookie` session mode the refresh token lives in an HttpOnly
+//! cookie scoped to `/auth`, out of reach of page scripts. Because browsers
+//! attach it to cross-site requests too, every state-changing request that
+//! carries it must also send the session's CSRF token in `X-CSRF-Token`.
+//!
+//! The CSRF token is derived from the refresh token, so it rotates with it
+//! and needs no storage: only someone who knows the refresh token (the
+//! server) can work it out. It is handed to the client in the login
+//! response and in a script-readable cookie.
+
+use actix_web::cookie::{time::Duration, Cookie, SameSite};
+use actix_web::HttpRequest;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+use super::totp::constant_time_eq;
+use crate::configuration::{CookieSameSite, SessionSettings};
+
+/// HttpOnly cookie holding the refresh token
+pub const REFRESH_TOKEN_COOKIE: &str = "refresh_token";
+/// Script-readable cookie holding the CSRF token
+pub const CSRF_COOKIE: &str = "csrf_token";
+/// Request header the CSRF token must be sent in
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+/// CSRF token of the session with `refresh_token`
+pub fn csrf_token_for(refresh_token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"csrf:");
+    hasher.update(refresh_token.as_bytes());
+    URL_SAFE_NO_PAD.encode(hasher.finalize())
+}
+
+/// Whether `csrf_token` belongs to the session with `refresh_token`
+pub fn verify_csrf_token(refresh_token: &str, csrf_token: &str) -> bool {
+    constant_time_eq(csrf_token_for(refresh_token).as_bytes(), csrf_token.as_bytes())
+}
+
+/// Refresh token from the session cookie, if the request has one
+pub fn refresh_token_cookie(req: &HttpRequest) -> Option<String> {
+    req.cookie(REFRESH_TOKEN_COOKIE)
+        .map(|cookie| cookie.value().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// Cookies starting a session with `refresh_token`
+pub fn session_cookies(
+    settings: &SessionSettings,
+    refresh_token: &str,
+    max_age_seconds: i64,
+) -> [Cookie<'static>; 2] {
+    [
+        session_cookie(settings, REFRESH_TOKEN_COOKIE, refresh_token.to_string(), max_age_seconds),
+        session_cookie(settings, CSRF_COOKIE, csrf_token_for(refresh_token), max_age_seconds),
+    ]
+}
+
+/// Cookies removing the session from the browser
+pub fn cleared_session_cookies(settings: &SessionSettings) -> [Cookie<'static>; 2] {
+    [
+        session_cookie(settings, REFRESH_TOKEN_COOKIE, String::new(), 0),
+        session_cookie(settings, CSRF_COOKIE, String::new(), 0),
+    ]
+}
+
+fn session_cookie(
+    settings: &SessionSettings,
+    name: &'static str,
+    value: String,
+    max_age_seconds: i64,
+) -> Cookie<'static> {
+    let same_site = match settings.same_site {
+        CookieSameSite::Strict => SameSite::Strict,
+        CookieSameSite::Lax => SameSite::Lax,
+    };
+    // The refresh token is only ever needed by /auth endpoints
+    let (path, http_only) = match name {
+        REFRESH_TOKEN_COOKIE => ("/auth", true),
+        _ => ("/", false),
+    };
+
+    Cookie::build(name, value)
+        .path(path)
+        .http_only(http_only)
+        .secure(settings.secure_cookies)
+        .same_site(same_site)
+        .max_age(Duration::seconds(max_age_seconds))
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csrf_token_is_bound_to_the_refresh_token() {
+        let token = csrf_token_for("refresh-a");
+
+        assert!(verify_csrf_token("refresh-a", &token));
+        assert!(!verify_csrf_token("refresh-b", &token));
+        assert!(!verify_csrf_token("refresh-a", ""));
+    }
+
+    #[test]
+    fn test_refresh_cookie_is_http_only_and_scoped_to_auth() {
+        let [refresh, csrf] = session_cookies(&SessionSettings::default(), "refresh-a", 60);
+
+        assert_eq!(Some(true), refresh.http_only());
+        assert_eq!(Some("/auth"), refresh.path());
+        assert_eq!(Some(true), refresh.secure());
+        assert_eq!(Some(SameSite::Strict), refresh.same_site());
+        assert_eq!(Some(false), csrf.http_only());
+        assert_eq!(csrf_token_for("refresh-a"), csrf.value());
+    }
+}
@@ -0,0 +1,473 @@
+//! TOTP Two-Factor Authentication
+//!
+//! Implements RFC 6238 time-based one-time passwords (SHA-1, 6 digits,
+//! 30 second steps) plus single-use recovery codes.
+//!
+//! - Secrets are 160-bit random values, stored base32-encoded
+//! - Codes from the previous and next step are accepted to allow clock drift
+//! - The last accepted step is stored so a code cannot be replayed
+//! - Recovery codes are hashed with SHA-256 before storage (never store plaintext)
+//! - Login uses a short-lived, single-use MFA challenge token between
+//!   the password step and the code step
+
+use chrono::{Duration, Utc};
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng, RngCore};
+use sqlx::PgPool;
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use crate::auth::refresh_token::{generate_refresh_token, hash_token};
+use crate::error::{AppError, AuthError};
+
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECONDS: u64 = 30;
+const TOTP_SKEW_STEPS: u64 = 1;
+const TOTP_SECRET_BYTES: usize = 20;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+/// MFA challenge token lifetime in seconds
+pub const MFA_CHALLENGE_EXPIRY_SECONDS: i64 = 300;
+/// Wrong codes allowed per challenge before it is discarded
+const MFA_CHALLENGE_MAX_ATTEMPTS: i32 = 5;
+
+/// Generate a new random TOTP secret (base32, no padding)
+pub fn generate_totp_secret() -> String {
+    let mut secret = vec![0u8; TOTP_SECRET_BYTES];
+    thread_rng().fill_bytes(&mut secret);
+    match Secret::Raw(secret).to_encoded() {
+        Secret::Encoded(encoded) => encoded,
+        Secret::Raw(_) => unreachable!("to_encoded always returns an encoded secret"),
+    }
+}
+
+fn build_totp(secret: &str, issuer: &str, account_name: &str) -> Result<TOTP, AppError> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| AppError::Internal(format!("Invalid TOTP secret: {:?}", e)))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        TOTP_SKEW_STEPS as u8,
+        TOTP_STEP_SECONDS,
+        secret,
+        Some(issuer.replace(':', "")),
+        account_name.replace(':', ""),
+    )
+    .map_err(|e| AppError::Internal(format!("Failed to build TOTP: {}", e)))
+}
+
+/// Build the `otpauth://` provisioning URI for authenticator apps
+///
+/// # Errors
+/// Returns error if the stored secret is malformed
+pub fn totp_provisioning_uri(
+    secret: &str,
+    issuer: &str,
+    account_name: &str,
+) -> Result<String, AppError> {
+    Ok(build_totp(secret, issuer, account_name)?.get_url())
+}
+
+/// Check a TOTP code at the given Unix time
+///
+/// # Returns
+/// The matched time step, or `None` if the code does not match any step
+/// within the allowed skew
+pub fn verify_totp_code_at(secret: &str, code: &str, time: u64) -> Result<Option<i64>, AppError> {
+    let code = normalize_code(code);
+    if code.len() != TOTP_DIGITS || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let totp = build_totp(secret, "", "")?;
+    let current_step = time / TOTP_STEP_SECONDS;
+    let first_step = current_step.saturating_sub(TOTP_SKEW_STEPS);
+
+    for step in first_step..=current_step + TOTP_SKEW_STEPS {
+        let expected = totp.generate(step * TOTP_STEP_SECONDS);
+        if constant_time_eq(expected.as_bytes(), code.as_bytes()) {
+            return Ok(Some(step as i64));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Generate a fresh set of plaintext recovery codes (`xxxxx-xxxxx`)
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let raw: String = thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(RECOVERY_CODE_LENGTH)
+                .map(|b| char::from(b).to_ascii_lowercase())
+                .collect();
+            format!("{}-{}", &raw[..RECOVERY_CODE_LENGTH / 2], &raw[RECOVERY_CODE_LENGTH / 2..])
+        })
+        .collect()
+}
+
+/// Strip whitespace and dashes and lowercase, so codes can be typed loosely
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Store a pending TOTP secret for a user (2FA stays disabled until confirmed)
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn begin_totp_enrollment(
+    pool: &PgPool,
+    user_id: Uuid,
+    secret: &str,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET totp_secret = $1, totp_enabled_at = NULL, totp_last_used_step = NULL
+        WHERE id = $2
+        "#,
+    )
+    .bind(secret)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Enable 2FA for a user and replace their recovery codes
+///
+/// # Returns
+/// The new plaintext recovery codes, to be shown to the user exactly once
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn enable_totp(
+    pool: &PgPool,
+    user_id: Uuid,
+    used_step: i64,
+) -> Result<Vec<String>, AppError> {
+    let recovery_codes = generate_recovery_codes();
+    let now = Utc::now();
+
+    let mut transaction = pool.begin().await?;
+
+    sqlx::query(
+        "UPDATE users SET totp_enabled_at = $1, totp_last_used_step = $2, updated_at = $1 WHERE id = $3",
+    )
+    .bind(now)
+    .bind(used_step)
+    .bind(user_id)
+    .execute(&mut transaction)
+    .await?;
+
+    sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await?;
+
+    for code in &recovery_codes {
+        sqlx::query(
+            r#"
+            INSERT INTO mfa_recovery_codes (id, user_id, code_hash, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(hash_token(&normalize_code(code)))
+        .bind(now)
+        .execute(&mut transaction)
+        .await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(recovery_codes)
+}
+
+/// Disable 2FA for a user, removing the secret, recovery codes and pending challenges
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn disable_totp(pool: &PgPool, user_id: Uuid) -> Result<(), AppError> {
+    let mut transaction = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_used_step = NULL, updated_at = $1
+        WHERE id = $2
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .execute(&mut transaction)
+    .await?;
+
+    sqlx::query("DELETE FROM mfa_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await?;
+
+    sqlx::query("DELETE FROM mfa_challenges WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// Check whether a user has 2FA enabled
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn is_totp_enabled(pool: &PgPool, user_id: Uuid) -> Result<bool, AppError> {
+    let enabled = sqlx::query_scalar::<_, bool>(
+        "SELECT totp_enabled_at IS NOT NULL FROM users WHERE id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(enabled.unwrap_or(false))
+}
+
+/// Verify a second factor for a user with 2FA enabled
+///
+/// Accepts either a current TOTP code or an unused recovery code.
+/// Accepted TOTP steps and recovery codes are consumed so they cannot be reused.
+///
+/// # Errors
+/// - `AuthError::InvalidMfaCode` if the code is wrong, reused, or 2FA is not enabled
+/// - Database error if the lookup fails
+pub async fn verify_second_factor(pool: &PgPool, user_id: Uuid, code: &str) -> Result<(), AppError> {
+    let secret = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT totp_secret FROM users WHERE id = $1 AND totp_enabled_at IS NOT NULL",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .flatten()
+    .ok_or(AppError::Auth(AuthError::InvalidMfaCode))?;
+
+    let now = Utc::now().timestamp() as u64;
+    if let Some(step) = verify_totp_code_at(&secret, code, now)? {
+        // Only accept steps newer than the last one used (replay protection)
+        let result = sqlx::query(
+            r#"
+            UPDATE users SET totp_last_used_step = $1
+            WHERE id = $2 AND (totp_last_used_step IS NULL OR totp_last_used_step < $1)
+            "#,
+        )
+        .bind(step)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 1 {
+            return Ok(());
+        }
+
+        tracing::warn!(user_id = %user_id, "Attempt to reuse TOTP code");
+        return Err(AppError::Auth(AuthError::InvalidMfaCode));
+    }
+
+    let result = sqlx::query(
+        r#"
+        UPDATE mfa_recovery_codes SET used_at = $1
+        WHERE user_id = $2 AND code_hash = $3 AND used_at IS NULL
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .bind(hash_token(&normalize_code(code)))
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 1 {
+        tracing::info!(user_id = %user_id, "Recovery code used");
+        return Ok(());
+    }
+
+    Err(AppError::Auth(AuthError::InvalidMfaCode))
+}
+
+/// Create an MFA challenge after a successful password check
+///
+/// # Returns
+/// Plaintext challenge token the client sends back with the code
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn create_mfa_challenge(pool: &PgPool, user_id: Uuid) -> Result<String, AppError> {
+    let token = generate_refresh_token();
+    let now = Utc::now();
+
+    sqlx::query(
+        r#"
+        INSERT INTO mfa_challenges (token_hash, user_id, created_at, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(hash_token(&token))
+    .bind(user_id)
+    .bind(now)
+    .bind(now + Duration::seconds(MFA_CHALLENGE_EXPIRY_SECONDS))
+    .execute(pool)
+    .await?;
+
+    Ok(token)
+}
+
+/// ID, email and name of the user an MFA challenge was issued to
+///
+/// Lets the second login step throttle by account before checking a code.
+///
+/// # Errors
+/// Returns error if the lookup fails
+pub async fn mfa_challenge_owner(
+    pool: &PgPool,
+    challenge_token: &str,
+) -> Result<Option<(Uuid, String, String)>, AppError> {
+    let owner = sqlx::query_as::<_, (Uuid, String, String)>(
+        r#"
+        SELECT u.id, u.email, u.name
+        FROM mfa_challenges c JOIN users u ON u.id = c.user_id
+        WHERE c.token_hash = $1
+        "#,
+    )
+    .bind(hash_token(challenge_token))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(owner)
+}
+
+/// Complete an MFA challenge with a TOTP or recovery code
+///
+/// Every code counts as an attempt, reserved atomically before the code is
+/// checked so concurrent guesses can't exceed the limit. Once the limit is
+/// reached the challenge is discarded and the user must log in with their
+/// password again. The challenge is consumed on success.
+///
+/// # Returns
+/// ID of the user who passed the challenge
+///
+/// # Errors
+/// - `AuthError::TokenInvalid` if the challenge is unknown, expired or exhausted
+/// - `AuthError::InvalidMfaCode` if the code is wrong
+pub async fn complete_mfa_challenge(
+    pool: &PgPool,
+    challenge_token: &str,
+    code: &str,
+) -> Result<Uuid, AppError> {
+    let token_hash = hash_token(challenge_token);
+
+    let reserved = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE mfa_challenges
+        SET attempts = attempts + 1
+        WHERE token_hash = $1 AND attempts < $2 AND expires_at > $3
+        RETURNING user_id
+        "#,
+    )
+    .bind(&token_hash)
+    .bind(MFA_CHALLENGE_MAX_ATTEMPTS)
+    .bind(Utc::now())
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(user_id) = reserved else {
+        // Unknown, expired or out of attempts; discard it if it still exists
+        let discarded = sqlx::query("DELETE FROM mfa_challenges WHERE token_hash = $1")
+            .bind(&token_hash)
+            .execute(pool)
+            .await?;
+        tracing::info!(
+            discarded = discarded.rows_affected() > 0,
+            "MFA challenge not found, expired or exhausted"
+        );
+        return Err(AppError::Auth(AuthError::TokenInvalid));
+    };
+
+    verify_second_factor(pool, user_id, code).await?;
+
+    // Only one request may turn the challenge into a session
+    let consumed = sqlx::query("DELETE FROM mfa_challenges WHERE token_hash = $1")
+        .bind(&token_hash)
+        .execute(pool)
+        .await?;
+    if consumed.rows_affected() == 0 {
+        return Err(AppError::Auth(AuthError::TokenInvalid));
+    }
+
+    Ok(user_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 Appendix B test secret ("12345678901234567890")
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn test_rfc6238_test_vectors() {
+        // Last 6 digits of the RFC's 8-digit SHA-1 values
+        assert_eq!(verify_totp_code_at(RFC_SECRET, "287082", 59).unwrap(), Some(1));
+        assert_eq!(verify_totp_code_at(RFC_SECRET, "081804", 1111111109).unwrap(), Some(37037036));
+        assert_eq!(verify_totp_code_at(RFC_SECRET, "050471", 1111111111).unwrap(), Some(37037037));
+    }
+
+    #[test]
+    fn test_code_accepted_within_skew() {
+        // Code for step 37037036 is still accepted one step later
+        assert!(verify_totp_code_at(RFC_SECRET, "081804", 1111111109 + 30).unwrap().is_some());
+        // ...but not two steps later
+        assert!(verify_totp_code_at(RFC_SECRET, "081804", 1111111109 + 60).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_malformed_codes_rejected() {
+        assert!(verify_totp_code_at(RFC_SECRET, "", 59).unwrap().is_none());
+        assert!(verify_totp_code_at(RFC_SECRET, "28708", 59).unwrap().is_none());
+        assert!(verify_totp_code_at(RFC_SECRET, "abcdef", 59).unwrap().is_none());
+        // Whitespace is tolerated
+        assert!(verify_totp_code_at(RFC_SECRET, "287 082", 59).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_generated_secret_round_trips() {
+        let secret = generate_totp_secret();
+        assert_eq!(secret.len(), 32);
+
+        let uri = totp_provisioning_uri(&secret, "zero2prod", "user@example.com").unwrap();
+        assert!(uri.starts_with("otpauth://totp/"));
+        assert!(uri.contains(&format!("secret={}", secret)));
+        assert!(uri.contains("issuer=zero2prod"));
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(codes.iter().all(|c| c.len() == RECOVERY_CODE_LENGTH + 1 && c.contains('-')));
+
+        // Dashes and case don't matter when a code is entered
+        assert_eq!(normalize_code("ABCDE-fghij"), normalize_code("abcdefghij"));
+    }
+}
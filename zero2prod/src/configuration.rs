@@ -1,15 +1,33 @@
 use config::ConfigError;
 
+use crate::email_client::{ConfirmedSubscriber, EmailClient};
+use crate::error::EmailError;
+
 #[derive(serde::Deserialize, Clone)]
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
     pub jwt: JwtSettings,
+    pub email_client: EmailClientSettings,
+    pub auth: AuthSettings,
+    pub subscriptions: SubscriptionSettings,
+    #[serde(default)]
+    pub rate_limit: RateLimitSettings,
+    #[serde(default)]
+    pub security_headers: SecurityHeaderSettings,
+    #[serde(default)]
+    pub request_limits: RequestLimitSettings,
+    #[serde(default)]
+    pub cors: CorsSettings,
+    #[serde(default)]
+    pub input_policy: InputPolicySettings,
 }
 
 #[derive(serde::Deserialize, Clone)]
 pub struct ApplicationSettings {
     pub port: u16,
+    /// Public URL used when building links sent by email
+    pub base_url: String,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -40,10 +58,751 @@ impl DatabaseSettings {
 /// JWT authentication settings
 #[derive(serde::Deserialize, Clone)]
 pub struct JwtSettings {
+    /// HS256 secret; signs tokens when no `active_key_id` is set and
+    /// verifies tokens without a `kid` (see `accept_legacy_hs256`)
     pub secret: String,
     pub access_token_expiry: i64,   // seconds (e.g., 900 for 15 minutes)
     pub refresh_token_expiry: i64,  // seconds (e.g., 604800 for 7 days)
     pub issuer: String,
+    /// `kid` of the asymmetric key that signs new tokens
+    #[serde(default)]
+    pub active_key_id: Option<String>,
+    /// Asymmetric keys: the active one plus retired ones that still verify
+    /// tokens and stay published in the JWKS
+    #[serde(default)]
+    pub keys: Vec<JwtKeySettings>,
+    /// Keep verifying tokens without a `kid` with `secret` while an
+    /// asymmetric key signs. Turn off once those tokens have expired to
+    /// retire the secret; ignored when the secret is still the signer.
+    #[serde(default = "default_true")]
+    pub accept_legacy_hs256: bool,
+}
+
+/// An asymmetric JWT key identified by `kid`
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct JwtKeySettings {
+    pub kid: String,
+    pub algorithm: JwtKeyAlgorithm,
+    /// PEM public key (SPKI, or PKCS#1 for RSA)
+    pub public_key_path: String,
+    /// PEM private key (PKCS#8); only needed for the active key
+    pub private_key_path: Option<String>,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JwtKeyAlgorithm {
+    RS256,
+    EdDSA,
+}
+
+/// Account security policy settings
+#[derive(serde::Deserialize, Clone)]
+pub struct AuthSettings {
+    pub email_verification: EmailVerificationPolicy,
+    pub login_throttle: LoginThrottleSettings,
+    #[serde(default)]
+    pub password_hashing: PasswordHashingSettings,
+    #[serde(default)]
+    pub password_policy: PasswordPolicySettings,
+    #[serde(default)]
+    pub oidc: OidcSettings,
+    #[serde(default)]
+    pub account_deletion: AccountDeletionSettings,
+    #[serde(default)]
+    pub session: SessionSettings,
+}
+
+/// How browsers hold on to a session
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SessionSettings {
+    #[serde(default)]
+    pub mode: SessionMode,
+    #[serde(default)]
+    pub same_site: CookieSameSite,
+    /// Only turn off for local development over plain HTTP
+    #[serde(default = "default_true")]
+    pub secure_cookies: bool,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            mode: SessionMode::default(),
+            same_site: CookieSameSite::default(),
+            secure_cookies: true,
+        }
+    }
+}
+
+/// Where the refresh token is kept between requests
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionMode {
+    /// Returned in the response body; the client stores it
+    #[default]
+    Tokens,
+    /// Set as an HttpOnly cookie that `POST /auth/refresh` reads, with a
+    /// CSRF token required on state-changing requests that send it
+    Cookie,
+}
+
+/// `SameSite` attribute of session cookies
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CookieSameSite {
+    #[default]
+    Strict,
+    Lax,
+}
+
+/// Argon2id cost parameters for new password hashes
+///
+/// Hashes made with other parameters (or with bcrypt) keep verifying and are
+/// upgraded on the user's next successful login.
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PasswordHashingSettings {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for PasswordHashingSettings {
+    /// OWASP recommended minimum for Argon2id
+    fn default() -> Self {
+        Self {
+            memory_kib: 19456,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// Rules new passwords must pass on top of the basic length and
+/// character-class checks
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct PasswordPolicySettings {
+    /// Minimum strength score, 0 (anything) to 4 (very unguessable)
+    #[serde(default = "default_min_password_score")]
+    pub min_score: u8,
+    /// Reject passwords found in breach data
+    #[serde(default = "default_true")]
+    pub check_breached: bool,
+    /// Directory of breached-password hash ranges in the Have I Been Pwned
+    /// layout: `{PREFIX}.txt` per 5-hex-digit SHA-1 prefix, with
+    /// `SUFFIX:COUNT` lines. A bundled list of common passwords is always
+    /// checked.
+    #[serde(default)]
+    pub breached_passwords_dir: Option<String>,
+}
+
+impl Default for PasswordPolicySettings {
+    fn default() -> Self {
+        Self {
+            min_score: default_min_password_score(),
+            check_breached: true,
+            breached_passwords_dir: None,
+        }
+    }
+}
+
+fn default_min_password_score() -> u8 {
+    3
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// OpenID Connect login providers
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct OidcSettings {
+    #[serde(default)]
+    pub providers: Vec<OidcProviderSettings>,
+    /// How long a started login may take before its `state` expires
+    #[serde(default = "default_oidc_login_timeout")]
+    pub login_timeout_seconds: i64,
+}
+
+impl Default for OidcSettings {
+    fn default() -> Self {
+        Self {
+            providers: Vec::new(),
+            login_timeout_seconds: default_oidc_login_timeout(),
+        }
+    }
+}
+
+fn default_oidc_login_timeout() -> i64 {
+    600
+}
+
+/// A single OpenID Connect provider, addressed as `/auth/oidc/{name}/...`
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct OidcProviderSettings {
+    pub name: String,
+    /// Issuer URL; discovery is read from `{issuer_url}/.well-known/openid-configuration`
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Extra scopes requested on top of `openid email profile`
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+/// Self-service account deletion (`DELETE /api/me`)
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct AccountDeletionSettings {
+    /// Days between the request and the account being erased
+    pub grace_period_days: i64,
+    pub mode: AccountDeletionMode,
+    /// How often the purge job looks for accounts past their grace period
+    pub purge_interval_seconds: u64,
+}
+
+impl Default for AccountDeletionSettings {
+    fn default() -> Self {
+        Self {
+            grace_period_days: 30,
+            mode: AccountDeletionMode::Anonymize,
+            purge_interval_seconds: 3600,
+        }
+    }
+}
+
+/// What happens to an account once its deletion grace period is over
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountDeletionMode {
+    /// Delete the user row and everything that references it
+    HardDelete,
+    /// Keep the row (so IDs in the audit trail still resolve) but replace
+    /// personal data and delete everything else belonging to the user
+    Anonymize,
+}
+
+/// What an unverified account is allowed to do
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailVerificationPolicy {
+    /// Verification is requested but never enforced
+    Optional,
+    /// Unverified users can log in but not change account details
+    BlockSensitiveActions,
+    /// Unverified users cannot log in at all
+    BlockLogin,
+}
+
+impl EmailVerificationPolicy {
+    pub fn blocks_login(&self) -> bool {
+        matches!(self, EmailVerificationPolicy::BlockLogin)
+    }
+
+    pub fn blocks_sensitive_actions(&self) -> bool {
+        !matches!(self, EmailVerificationPolicy::Optional)
+    }
+}
+
+/// Failed-login throttling and account lockout
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct LoginThrottleSettings {
+    /// Failures per account before logins start being delayed
+    pub delay_after_failures: u32,
+    /// First delay; doubles with every further failure
+    pub base_delay_seconds: u64,
+    /// Failures per account before it is locked
+    pub max_account_failures: u32,
+    /// Failures per client IP (across all accounts) before it is locked out
+    pub max_ip_failures: u32,
+    pub lockout_seconds: u64,
+    /// Failures older than this are forgotten
+    pub failure_window_seconds: u64,
+}
+
+/// Newsletter subscription settings
+#[derive(serde::Deserialize, Clone)]
+pub struct SubscriptionSettings {
+    /// HMAC key for preference center links (at least 32 bytes)
+    pub preference_link_secret: String,
+    /// How long a preference link from an email keeps working
+    #[serde(default = "default_preference_link_expiry_days")]
+    pub preference_link_expiry_days: i64,
+    /// Topics subscribers can opt out of individually
+    #[serde(default = "default_topics")]
+    pub topics: Vec<String>,
+    #[serde(default)]
+    pub bot_protection: BotProtectionSettings,
+    #[serde(default)]
+    pub deliverability: DeliverabilitySettings,
+}
+
+fn default_preference_link_expiry_days() -> i64 {
+    90
+}
+
+fn default_topics() -> Vec<String> {
+    vec!["general".to_string()]
+}
+
+/// Defenses against bots using the public subscription form to send
+/// confirmation emails to third parties
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct BotProtectionSettings {
+    /// Silently drop submissions filling in the hidden `website` field
+    #[serde(default = "default_true")]
+    pub honeypot: bool,
+    /// Least time between fetching a form token and submitting the form;
+    /// 0 makes the form token optional unless proof of work is required
+    #[serde(default = "default_min_fill_seconds")]
+    pub min_fill_seconds: i64,
+    /// How long a form token from `/subscriptions/challenge` is accepted
+    #[serde(default = "default_form_token_expiry_minutes")]
+    pub form_token_expiry_minutes: i64,
+    /// Leading zero bits required of the proof of work hash (0 disables)
+    #[serde(default)]
+    pub proof_of_work_difficulty: u32,
+    /// Confirmation emails sent to one address per window (0 disables)
+    #[serde(default = "default_max_confirmation_emails")]
+    pub max_confirmation_emails_per_address: i64,
+    #[serde(default = "default_confirmation_email_window_hours")]
+    pub confirmation_email_window_hours: i32,
+}
+
+impl Default for BotProtectionSettings {
+    fn default() -> Self {
+        Self {
+            honeypot: true,
+            min_fill_seconds: default_min_fill_seconds(),
+            form_token_expiry_minutes: default_form_token_expiry_minutes(),
+            proof_of_work_difficulty: 0,
+            max_confirmation_emails_per_address: default_max_confirmation_emails(),
+            confirmation_email_window_hours: default_confirmation_email_window_hours(),
+        }
+    }
+}
+
+fn default_min_fill_seconds() -> i64 {
+    3
+}
+
+fn default_form_token_expiry_minutes() -> i64 {
+    60
+}
+
+fn default_max_confirmation_emails() -> i64 {
+    3
+}
+
+fn default_confirmation_email_window_hours() -> i32 {
+    24
+}
+
+/// Checks that a new subscriber's address can receive mail before a
+/// confirmation email is sent to it
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct DeliverabilitySettings {
+    /// Require the domain to have MX records, or A/AAAA records to fall
+    /// back to
+    #[serde(default)]
+    pub check_dns: bool,
+    /// Name servers to query, as `ip:port`; empty uses the system resolver
+    #[serde(default)]
+    pub dns_servers: Vec<std::net::SocketAddr>,
+    #[serde(default = "default_dns_timeout_milliseconds")]
+    pub dns_timeout_milliseconds: u64,
+    /// Reject throwaway mail domains. A bundled list is always checked.
+    #[serde(default = "default_true")]
+    pub reject_disposable: bool,
+    /// More disposable domains, one per line (`#` starts a comment); the
+    /// file is read again whenever it changes
+    #[serde(default)]
+    pub disposable_domains_file: Option<String>,
+    /// Suggest the corrected address for domains one or two typos away
+    /// from a well-known mail provider. The suggestion doesn't reject the
+    /// address; with `check_dns` it is added to the rejection of a domain
+    /// without mail servers.
+    #[serde(default = "default_true")]
+    pub suggest_corrections: bool,
+    /// Mail providers to check for typos on top of the bundled ones
+    #[serde(default)]
+    pub known_domains: Vec<String>,
+}
+
+impl Default for DeliverabilitySettings {
+    fn default() -> Self {
+        Self {
+            check_dns: false,
+            dns_servers: Vec::new(),
+            dns_timeout_milliseconds: default_dns_timeout_milliseconds(),
+            reject_disposable: true,
+            disposable_domains_file: None,
+            suggest_corrections: true,
+            known_domains: Vec::new(),
+        }
+    }
+}
+
+fn default_dns_timeout_milliseconds() -> u64 {
+    2000
+}
+
+/// Rules user-supplied text must pass before it is stored
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+pub struct InputPolicySettings {
+    #[serde(default)]
+    pub names: NamePolicySettings,
+}
+
+/// Rules for subscriber and account names
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct NamePolicySettings {
+    /// Length limits in characters, after normalization
+    #[serde(default = "default_min_name_length")]
+    pub min_length: usize,
+    #[serde(default = "default_max_name_length")]
+    pub max_length: usize,
+    /// Scripts letters may come from; empty allows every script
+    #[serde(default)]
+    pub allowed_scripts: Vec<InputScript>,
+    /// Reject words mixing scripts with lookalike letters, such as a
+    /// Cyrillic "а" among Latin ones
+    #[serde(default = "default_true")]
+    pub reject_confusables: bool,
+    /// Remove bidi overrides, zero-width spaces and similar invisible
+    /// characters instead of storing them
+    #[serde(default = "default_true")]
+    pub strip_invisible: bool,
+}
+
+impl Default for NamePolicySettings {
+    fn default() -> Self {
+        Self {
+            min_length: default_min_name_length(),
+            max_length: default_max_name_length(),
+            allowed_scripts: Vec::new(),
+            reject_confusables: true,
+            strip_invisible: true,
+        }
+    }
+}
+
+fn default_min_name_length() -> usize {
+    1
+}
+
+fn default_max_name_length() -> usize {
+    256
+}
+
+/// Writing systems an input policy can allow
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputScript {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Bengali,
+    Thai,
+    Georgian,
+    Hangul,
+    Hiragana,
+    Katakana,
+    Han,
+}
+
+/// Request rate limiting
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct RateLimitSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub storage: RateLimitStorage,
+    /// How long the `postgres` storage may take before a request is counted
+    /// locally instead
+    #[serde(default = "default_rate_limit_storage_timeout")]
+    pub storage_timeout_milliseconds: u64,
+    /// Reverse proxies (IP addresses or CIDR ranges) whose `X-Forwarded-For`
+    /// header is believed when working out the client IP
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+    /// The first policy matching a request applies; requests matching none
+    /// aren't limited
+    #[serde(default = "default_rate_limit_policies")]
+    pub policies: Vec<RateLimitPolicySettings>,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            storage: RateLimitStorage::default(),
+            storage_timeout_milliseconds: default_rate_limit_storage_timeout(),
+            trusted_proxies: Vec::new(),
+            policies: default_rate_limit_policies(),
+        }
+    }
+}
+
+fn default_rate_limit_storage_timeout() -> u64 {
+    50
+}
+
+/// Where rate limit counters are kept
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitStorage {
+    /// Token buckets in this instance's memory
+    #[default]
+    Memory,
+    /// Sliding window counters in Postgres, shared by all instances
+    Postgres,
+}
+
+/// Limit for requests under a path prefix
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct RateLimitPolicySettings {
+    pub name: String,
+    /// Matches the path itself and everything below it
+    pub path_prefix: String,
+    /// HTTP methods the policy applies to; empty for all
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// Sustained rate; also the most requests allowed in a burst
+    pub requests_per_minute: u32,
+    #[serde(default)]
+    pub key: RateLimitKey,
+}
+
+/// Who a rate limit is counted against
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    /// Client IP address
+    #[default]
+    Ip,
+    /// User id of a valid access token, else the client IP
+    User,
+    /// The API key, else as `User`
+    ApiKey,
+}
+
+fn default_rate_limit_policies() -> Vec<RateLimitPolicySettings> {
+    let policy = |name: &str, path_prefix: &str, methods: &[&str], rpm, key| {
+        RateLimitPolicySettings {
+            name: name.to_string(),
+            path_prefix: path_prefix.to_string(),
+            methods: methods.iter().map(|m| m.to_string()).collect(),
+            requests_per_minute: rpm,
+            key,
+        }
+    };
+    vec![
+        policy("subscribe", "/subscriptions", &["POST"], 10, RateLimitKey::Ip),
+        policy("login", "/auth/login", &["POST"], 10, RateLimitKey::Ip),
+        policy("auth", "/auth", &[], 30, RateLimitKey::Ip),
+        policy("api_write", "/api", &["POST", "PUT", "DELETE"], 60, RateLimitKey::ApiKey),
+        policy("api_read", "/api", &[], 300, RateLimitKey::ApiKey),
+        policy("read", "/", &[], 600, RateLimitKey::Ip),
+    ]
+}
+
+/// Security headers added to every response
+///
+/// Empty values leave a header out.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SecurityHeaderSettings {
+    /// `{nonce}` is replaced with a fresh nonce for each request, which also
+    /// fills in `{{csp_nonce}}` in HTML pages
+    #[serde(default = "default_content_security_policy")]
+    pub content_security_policy: String,
+    #[serde(default = "default_permissions_policy")]
+    pub permissions_policy: String,
+    #[serde(default = "default_same_origin")]
+    pub cross_origin_opener_policy: String,
+    #[serde(default = "default_same_origin")]
+    pub cross_origin_resource_policy: String,
+    /// `Strict-Transport-Security` is only sent on requests that arrived
+    /// over HTTPS, directly or through a trusted proxy
+    #[serde(default = "default_hsts_max_age")]
+    pub hsts_max_age_seconds: u64,
+    #[serde(default = "default_true")]
+    pub hsts_include_subdomains: bool,
+    /// Overrides for paths under a prefix; the first matching scope applies
+    #[serde(default)]
+    pub scopes: Vec<SecurityHeaderScopeSettings>,
+}
+
+impl Default for SecurityHeaderSettings {
+    fn default() -> Self {
+        Self {
+            content_security_policy: default_content_security_policy(),
+            permissions_policy: default_permissions_policy(),
+            cross_origin_opener_policy: default_same_origin(),
+            cross_origin_resource_policy: default_same_origin(),
+            hsts_max_age_seconds: default_hsts_max_age(),
+            hsts_include_subdomains: true,
+            scopes: Vec::new(),
+        }
+    }
+}
+
+/// Security headers for paths under `path_prefix`; unset ones are inherited
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SecurityHeaderScopeSettings {
+    pub path_prefix: String,
+    #[serde(default)]
+    pub content_security_policy: Option<String>,
+    #[serde(default)]
+    pub permissions_policy: Option<String>,
+    #[serde(default)]
+    pub cross_origin_opener_policy: Option<String>,
+    #[serde(default)]
+    pub cross_origin_resource_policy: Option<String>,
+}
+
+fn default_content_security_policy() -> String {
+    "default-src 'self'; script-src 'self' 'nonce-{nonce}'; \
+     style-src 'self' 'nonce-{nonce}' https://fonts.googleapis.com; \
+     font-src 'self' https://fonts.gstatic.com; img-src 'self' data:; object-src 'none'; \
+     base-uri 'self'; form-action 'self'; frame-ancestors 'self'"
+        .to_string()
+}
+
+fn default_permissions_policy() -> String {
+    "camera=(), microphone=(), geolocation=(), payment=(), usb=()".to_string()
+}
+
+fn default_same_origin() -> String {
+    "same-origin".to_string()
+}
+
+fn default_hsts_max_age() -> u64 {
+    31_536_000
+}
+
+/// Largest request bodies accepted, in bytes
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct RequestLimitSettings {
+    /// Applies where no route matches
+    #[serde(default = "default_max_body_bytes")]
+    pub default_max_bytes: usize,
+    /// The first route matching the path applies
+    #[serde(default = "default_request_limit_routes")]
+    pub routes: Vec<RequestLimitRouteSettings>,
+}
+
+impl Default for RequestLimitSettings {
+    fn default() -> Self {
+        Self {
+            default_max_bytes: default_max_body_bytes(),
+            routes: default_request_limit_routes(),
+        }
+    }
+}
+
+/// Body size limit for requests under a path prefix
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct RequestLimitRouteSettings {
+    /// Matches the path itself and everything below it
+    pub path_prefix: String,
+    pub max_bytes: usize,
+}
+
+fn default_max_body_bytes() -> usize {
+    16 * 1024
+}
+
+fn default_request_limit_routes() -> Vec<RequestLimitRouteSettings> {
+    let route = |path_prefix: &str, max_bytes: usize| RequestLimitRouteSettings {
+        path_prefix: path_prefix.to_string(),
+        max_bytes,
+    };
+    vec![
+        route("/subscriptions", 1024),
+        route("/api/newsletters", 1024 * 1024),
+        route("/api/admin/subscribers/import", 100 * 1024 * 1024),
+    ]
+}
+
+/// Cross-origin access to the API
+///
+/// Without scopes no CORS headers are sent, so browsers only let pages on
+/// our own origin call us.
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+pub struct CorsSettings {
+    /// The first scope matching the path applies
+    #[serde(default)]
+    pub scopes: Vec<CorsScopeSettings>,
+}
+
+/// Origins allowed to call paths under `path_prefix`
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct CorsScopeSettings {
+    /// Matches the path itself and everything below it
+    pub path_prefix: String,
+    /// Exact origins (`https://admin.example.com`), or `"*"` for any origin
+    /// when credentials aren't allowed
+    pub allowed_origins: Vec<String>,
+    #[serde(default = "default_cors_methods")]
+    pub allowed_methods: Vec<String>,
+    #[serde(default = "default_cors_headers")]
+    pub allowed_headers: Vec<String>,
+    /// Response headers readable by the calling page
+    #[serde(default)]
+    pub expose_headers: Vec<String>,
+    /// Whether cookies and `Authorization` may be sent cross-origin
+    #[serde(default)]
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight response
+    #[serde(default = "default_cors_max_age")]
+    pub max_age_seconds: u64,
+}
+
+fn default_cors_methods() -> Vec<String> {
+    vec!["GET".to_string(), "POST".to_string()]
+}
+
+fn default_cors_headers() -> Vec<String> {
+    vec!["Content-Type".to_string(), "Authorization".to_string()]
+}
+
+fn default_cors_max_age() -> u64 {
+    600
+}
+
+/// Email delivery service settings
+#[derive(serde::Deserialize, Clone)]
+pub struct EmailClientSettings {
+    pub base_url: String,
+    pub sender_email: String,
+    pub timeout_milliseconds: u64,
+    /// Whether the delivery service accepts SMTPUTF8 (RFC 6531) messages,
+    /// i.e. recipients with non-ASCII local parts
+    #[serde(default = "default_true")]
+    pub smtputf8: bool,
+}
+
+impl EmailClientSettings {
+    pub fn sender(&self) -> Result<ConfirmedSubscriber, EmailError> {
+        ConfirmedSubscriber::parse(self.sender_email.clone())
+    }
+
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.timeout_milliseconds)
+    }
+
+    /// Build an `EmailClient` from these settings
+    pub fn client(&self) -> Result<EmailClient, EmailError> {
+        let http_client = reqwest::Client::builder()
+            .timeout(self.timeout())
+            .build()
+            .map_err(|e| EmailError::ConfigurationError(e.to_string()))?;
+        Ok(EmailClient::new(self.base_url.clone(), self.sender()?, http_client)
+            .with_smtputf8(self.smtputf8))
+    }
 }
 
 pub fn get_configuration() -> Result<Settings, ConfigError> {
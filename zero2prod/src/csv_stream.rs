@@ -0,0 +1,173 @@
+//! CSV Streaming
+//!
+//! Incremental CSV reading and writing for bulk subscriber transfers, so
+//! that neither side has to hold a whole file in memory.
+
+use csv_core::{ReadRecordResult, Reader};
+
+/// Parses CSV records out of a byte stream, chunk by chunk
+///
+/// Records may span chunks; they are returned once complete. Blank lines
+/// are skipped and invalid UTF-8 is replaced rather than rejected.
+pub struct CsvRecordReader {
+    reader: Reader,
+    output: Vec<u8>,
+    ends: Vec<usize>,
+    output_len: usize,
+    ends_len: usize,
+}
+
+impl Default for CsvRecordReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CsvRecordReader {
+    pub fn new() -> Self {
+        Self {
+            reader: Reader::new(),
+            output: vec![0; 1024],
+            ends: vec![0; 16],
+            output_len: 0,
+            ends_len: 0,
+        }
+    }
+
+    /// Feed the next chunk of input and return the records it completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Vec<String>> {
+        // An empty slice means end of input to the parser
+        if chunk.is_empty() {
+            return Vec::new();
+        }
+        self.read(chunk)
+    }
+
+    /// Signal the end of input and return the last record, if it had no
+    /// trailing newline
+    pub fn finish(&mut self) -> Vec<Vec<String>> {
+        self.read(&[])
+    }
+
+    fn read(&mut self, mut input: &[u8]) -> Vec<Vec<String>> {
+        let at_end = input.is_empty();
+        let mut records = Vec::new();
+
+        loop {
+            let (result, nin, nout, nend) = self.reader.read_record(
+                input,
+                &mut self.output[self.output_len..],
+                &mut self.ends[self.ends_len..],
+            );
+            input = &input[nin..];
+            self.output_len += nout;
+            self.ends_len += nend;
+
+            match result {
+                ReadRecordResult::InputEmpty => {
+                    if !at_end {
+                        return records;
+                    }
+                }
+                ReadRecordResult::OutputFull => {
+                    let len = self.output.len();
+                    self.output.resize(len * 2, 0);
+                }
+                ReadRecordResult::OutputEndsFull => {
+                    let len = self.ends.len();
+                    self.ends.resize(len * 2, 0);
+                }
+                ReadRecordResult::Record => records.push(self.take_record()),
+                ReadRecordResult::End => return records,
+            }
+        }
+    }
+
+    fn take_record(&mut self) -> Vec<String> {
+        let mut start = 0;
+        let fields = self.ends[..self.ends_len]
+            .iter()
+            .map(|&end| {
+                let field = String::from_utf8_lossy(&self.output[start..end]).into_owned();
+                start = end;
+                field
+            })
+            .collect();
+        self.output_len = 0;
+        self.ends_len = 0;
+        fields
+    }
+}
+
+/// Format one CSV line (with trailing newline)
+///
+/// Fields that could be read as a formula by spreadsheet software are
+/// prefixed with a single quote.
+pub fn csv_line<I, S>(fields: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut line = fields
+        .into_iter()
+        .map(|field| csv_field(field.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_spanning_chunks() {
+        let mut reader = CsvRecordReader::new();
+        let mut records = reader.feed(b"email,name\njane@exa");
+        records.extend(reader.feed(b"mple.com,\"Doe, Jane\"\n\njohn@example.com,John"));
+        records.extend(reader.finish());
+
+        assert_eq!(
+            records,
+            vec![
+                vec!["email", "name"],
+                vec!["jane@example.com", "Doe, Jane"],
+                vec!["john@example.com", "John"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_long_fields_grow_buffers() {
+        let long_name = "a".repeat(5000);
+        let mut reader = CsvRecordReader::new();
+        let input = format!("{},{}\n", long_name, "b,".repeat(40));
+        let records = reader.feed(input.as_bytes());
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0][0], long_name);
+        assert_eq!(records[0].len(), 42);
+    }
+
+    #[test]
+    fn test_csv_line_quotes_and_neutralizes_formulas() {
+        assert_eq!(csv_line(["a", "b"]), "a,b\r\n");
+        assert_eq!(csv_line(["Doe, \"J\""]), "\"Doe, \"\"J\"\"\"\r\n");
+        assert_eq!(csv_line(["=HYPERLINK(1)"]), "'=HYPERLINK(1)\r\n");
+    }
+}
@@ -9,7 +9,7 @@
 use crate::error::ValidationError;
 use crate::validators::is_valid_email;
 
-const VALID_STATUSES: &[&str] = &["pending", "confirmed"];
+const VALID_STATUSES: &[&str] = &["pending", "confirmed", "unsubscribed"];
 const MIN_NAME_LENGTH: usize = 1;
 const MAX_NAME_LENGTH: usize = 256;
 
@@ -63,11 +63,13 @@ pub fn validate_stored_name(name: &str) -> Result<(), ValidationError> {
         return Err(ValidationError::EmptyField("name".to_string()));
     }
 
-    if trimmed.len() < MIN_NAME_LENGTH {
+    // Counted in characters, like the input policy does
+    let length = trimmed.chars().count();
+    if length < MIN_NAME_LENGTH {
         return Err(ValidationError::TooShort("name".to_string(), MIN_NAME_LENGTH));
     }
 
-    if trimmed.len() > MAX_NAME_LENGTH {
+    if length > MAX_NAME_LENGTH {
         return Err(ValidationError::TooLong("name".to_string(), MAX_NAME_LENGTH));
     }
 
@@ -1,4 +1,4 @@
-use crate::validators::is_valid_email;
+use crate::validators::{is_valid_email, requires_smtputf8};
 use crate::error::EmailError;
 use serde::Serialize;
 
@@ -7,6 +7,7 @@ pub struct EmailClient {
     http_client: reqwest::Client,
     base_url: String,
     sender: ConfirmedSubscriber,
+    smtputf8: bool,
 }
 
 #[derive(Clone)]
@@ -33,6 +34,10 @@ pub struct SendEmailRequest {
     html: String,
     #[serde(rename = "Subject")]
     subject: String,
+    /// Set when the recipient has a UTF-8 local part, so the message must
+    /// be relayed with SMTPUTF8
+    #[serde(rename = "SmtpUtf8", skip_serializing_if = "std::ops::Not::not")]
+    smtp_utf8: bool,
 }
 
 impl EmailClient {
@@ -45,26 +50,46 @@ impl EmailClient {
             http_client,
             base_url,
             sender,
+            smtputf8: true,
         }
     }
 
+    /// Set whether the delivery service supports SMTPUTF8 (on by default)
+    pub fn with_smtputf8(mut self, supported: bool) -> Self {
+        self.smtputf8 = supported;
+        self
+    }
+
+    /// Whether mail to a valid address can be delivered, i.e. it doesn't
+    /// need SMTPUTF8 or the service supports it
+    pub fn can_deliver_to(&self, recipient: &str) -> bool {
+        self.smtputf8 || !requires_smtputf8(recipient)
+    }
+
     pub async fn send_email(
         &self,
         recipient: &str,
         subject: &str,
         html_content: &str,
     ) -> Result<(), EmailError> {
-        // Validate recipient email
-        is_valid_email(recipient)
+        // Validate recipient email; internationalized domains are sent in punycode
+        let recipient = is_valid_email(recipient)
             .map_err(|_| EmailError::InvalidRecipient(
                 format!("Invalid recipient email: {}", recipient)
             ))?;
+        if !self.can_deliver_to(&recipient) {
+            return Err(EmailError::InvalidRecipient(format!(
+                "Recipient {} requires SMTPUTF8, which the email service does not support",
+                recipient
+            )));
+        }
 
         let url = format!("{}/email", self.base_url);
         let request = SendEmailRequest {
-            to: recipient.to_string(),
             subject: subject.to_string(),
             html: html_content.to_string(),
+            smtp_utf8: requires_smtputf8(&recipient),
+            to: recipient,
         };
 
         let response = self.http_client
@@ -96,10 +121,38 @@ impl EmailClient {
     }
 }
 
+/// Escape text for use in an email's HTML body
+///
+/// User-supplied values such as names must go through this before being
+/// put into a template, or they could add markup and links to our emails.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_escape_html_escapes_markup() {
+        assert_eq!(
+            "&lt;a href=&quot;https://evil.example&quot;&gt;O&#x27;Brien &amp; co&lt;/a&gt;",
+            escape_html("<a href=\"https://evil.example\">O'Brien & co</a>")
+        );
+        assert_eq!("Jane Doe", escape_html("Jane Doe"));
+    }
+
     #[test]
     fn test_confirmed_subscriber_parse_valid_email() {
         let email = "test@example.com".to_string();
@@ -119,4 +172,20 @@ mod tests {
             _ => panic!("Expected InvalidRecipient error"),
         }
     }
+
+    #[test]
+    fn test_smtputf8_flag_is_only_sent_when_needed() {
+        let request = SendEmailRequest {
+            to: "hans@xn--bcher-kva.de".to_string(),
+            html: String::new(),
+            subject: String::new(),
+            smtp_utf8: false,
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert!(json.get("SmtpUtf8").is_none());
+
+        let request = SendEmailRequest { to: "jürgen@example.de".to_string(), smtp_utf8: true, ..request };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["SmtpUtf8"], true);
+    }
 }
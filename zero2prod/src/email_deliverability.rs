@@ -0,0 +1,443 @@
+//! Email Deliverability Pre-checks
+//!
+//! Many pending subscriptions never get confirmed because the address can't
+//! receive mail: a typo in the domain (`gmial.com`), a throwaway inbox, or a
+//! domain that doesn't exist. Before a confirmation email is sent, the
+//! address's domain is checked (see `DeliverabilitySettings`):
+//!
+//! - against a list of disposable mail domains, bundled and optionally
+//!   extended from a file that is read again whenever it changes,
+//! - for being a typo or two away from a well-known mail provider, in which
+//!   case the corrected address is suggested. Regional domains are often
+//!   that close to each other (`yahoo.es` and `yahoo.de`), so a suggestion
+//!   alone never rejects the address,
+//! - optionally, for MX records, or A/AAAA records that mail falls back to
+//!   (RFC 5321 section 5.1), looked up through the configured name servers.
+//!
+//! DNS failures other than "no such records" let the address through, so an
+//! unreachable resolver doesn't stop subscriptions.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+
+use hickory_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use hickory_resolver::error::{ResolveError, ResolveErrorKind};
+use hickory_resolver::proto::op::ResponseCode;
+use hickory_resolver::system_conf::read_system_conf;
+use hickory_resolver::TokioAsyncResolver;
+use lazy_static::lazy_static;
+
+use crate::configuration::DeliverabilitySettings;
+use crate::error::{AppError, ConfigError, ValidationError};
+
+lazy_static! {
+    static ref BUNDLED_DISPOSABLE_DOMAINS: HashSet<String> =
+        domain_list(include_str!("data/disposable_domains.txt"));
+    static ref BUNDLED_MAIL_PROVIDERS: HashSet<String> =
+        domain_list(include_str!("data/mail_providers.txt"));
+}
+
+/// Domains one per line, lowercased; blank lines and `#` comments skipped
+fn domain_list(list: &str) -> HashSet<String> {
+    list.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.trim_end_matches('.').to_ascii_lowercase())
+        .collect()
+}
+
+/// Deliverability checks for new subscribers' addresses
+pub struct EmailDeliverability {
+    resolver: Option<TokioAsyncResolver>,
+    reject_disposable: bool,
+    disposable_file: Option<DisposableDomainsFile>,
+    /// Providers typos are checked against; empty when suggestions are off
+    known_domains: HashSet<String>,
+}
+
+impl EmailDeliverability {
+    /// # Errors
+    /// Returns error if the disposable domains file can't be read or the
+    /// system DNS configuration is needed but can't be read
+    pub fn new(settings: &DeliverabilitySettings) -> Result<Self, ConfigError> {
+        let resolver = if settings.check_dns {
+            Some(resolver(settings)?)
+        } else {
+            None
+        };
+
+        let disposable_file = settings
+            .disposable_domains_file
+            .as_ref()
+            .map(|path| DisposableDomainsFile::open(PathBuf::from(path)))
+            .transpose()?;
+
+        let mut known_domains = HashSet::new();
+        if settings.suggest_corrections {
+            known_domains.extend(BUNDLED_MAIL_PROVIDERS.iter().cloned());
+            known_domains.extend(
+                settings.known_domains.iter().map(|domain| domain.to_ascii_lowercase()),
+            );
+        }
+
+        Ok(Self {
+            resolver,
+            reject_disposable: settings.reject_disposable,
+            disposable_file,
+            known_domains,
+        })
+    }
+
+    /// Check that a valid address (see `is_valid_email`) can receive mail
+    ///
+    /// # Returns
+    /// The corrected address if the domain looks misspelled. It is only a
+    /// suggestion: the address as typed passed every check.
+    ///
+    /// # Errors
+    /// UndeliverableEmail if the domain is disposable or has no mail servers,
+    /// with the corrected address as suggestion if it looks misspelled
+    pub async fn check(&self, email: &str) -> Result<Option<String>, AppError> {
+        let (local_part, domain) = email
+            .rsplit_once('@')
+            .ok_or_else(|| ValidationError::InvalidFormat("email".to_string()))?;
+        let domain = domain.to_ascii_lowercase();
+
+        if self.reject_disposable && self.is_disposable(&domain) {
+            return Err(undeliverable("disposable email addresses are not accepted", None));
+        }
+
+        let suggestion = self
+            .suggest_domain(&domain)
+            .map(|corrected| format!("{}@{}", local_part, corrected));
+
+        if let Some(resolver) = &self.resolver {
+            match accepts_mail(resolver, &domain).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    return Err(undeliverable(
+                        &format!("{} has no mail servers", domain),
+                        suggestion,
+                    ));
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        domain = %domain,
+                        error = %e,
+                        "Mail server lookup failed; accepting the address"
+                    );
+                }
+            }
+        }
+
+        Ok(suggestion)
+    }
+
+    /// Whether the domain or one of its parents is a disposable mail domain
+    pub fn is_disposable(&self, domain: &str) -> bool {
+        let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+        if let Some(file) = &self.disposable_file {
+            file.refresh();
+        }
+
+        let mut candidates = parent_domains(&domain);
+        candidates.any(|candidate| {
+            BUNDLED_DISPOSABLE_DOMAINS.contains(candidate)
+                || self.disposable_file.as_ref().is_some_and(|file| file.contains(candidate))
+        })
+    }
+
+    /// The known provider a domain is most likely a misspelling of
+    ///
+    /// Up to two edits (insertions, deletions, substitutions or swapped
+    /// neighbours) are corrected, one for short domains, which are closer
+    /// to each other to begin with.
+    pub fn suggest_domain(&self, domain: &str) -> Option<String> {
+        let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+        if self.known_domains.contains(&domain) {
+            return None;
+        }
+
+        let mut best: Option<(usize, &String)> = None;
+        for known in &self.known_domains {
+            let max_distance = if known.len() < 8 { 1 } else { 2 };
+            let distance = edit_distance(&domain, known);
+            if distance > max_distance {
+                continue;
+            }
+            // Nearest wins; ties go to the alphabetically first provider so
+            // the suggestion doesn't depend on hash order
+            if best.is_none_or(|(d, b)| (distance, known) < (d, b)) {
+                best = Some((distance, known));
+            }
+        }
+        best.map(|(_, known)| known.clone())
+    }
+}
+
+fn undeliverable(reason: &str, suggestion: Option<String>) -> AppError {
+    AppError::Validation(ValidationError::UndeliverableEmail {
+        reason: reason.to_string(),
+        suggestion,
+    })
+}
+
+fn resolver(settings: &DeliverabilitySettings) -> Result<TokioAsyncResolver, ConfigError> {
+    let (config, mut options) = if settings.dns_servers.is_empty() {
+        read_system_conf().map_err(|e| {
+            ConfigError::InvalidValue(format!(
+                "subscriptions.deliverability: cannot read the system DNS configuration: {}",
+                e
+            ))
+        })?
+    } else {
+        let name_servers: Vec<NameServerConfig> = settings
+            .dns_servers
+            .iter()
+            .flat_map(|&address| {
+                [
+                    NameServerConfig::new(address, Protocol::Udp),
+                    NameServerConfig::new(address, Protocol::Tcp),
+                ]
+            })
+            .collect();
+        (ResolverConfig::from_parts(None, vec![], name_servers), ResolverOpts::default())
+    };
+    options.timeout = Duration::from_millis(settings.dns_timeout_milliseconds);
+    options.attempts = 1;
+
+    Ok(TokioAsyncResolver::tokio(config, options))
+}
+
+/// Whether the domain has a mail server: an MX record that isn't a null MX
+/// (RFC 7505), or failing any MX records, an address
+async fn accepts_mail(resolver: &TokioAsyncResolver, domain: &str) -> Result<bool, ResolveError> {
+    // Fully qualified, so the resolver's search domains aren't appended
+    let name = format!("{}.", domain);
+
+    match resolver.mx_lookup(name.as_str()).await {
+        Ok(records) => return Ok(records.iter().any(|mx| !mx.exchange().is_root())),
+        Err(e) => match e.kind() {
+            ResolveErrorKind::NoRecordsFound { response_code, .. } => {
+                if *response_code == ResponseCode::NXDomain {
+                    return Ok(false);
+                }
+            }
+            _ => return Err(e),
+        },
+    }
+
+    match resolver.lookup_ip(name.as_str()).await {
+        Ok(addresses) => Ok(addresses.iter().next().is_some()),
+        Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// `a.b.example.com`, `b.example.com`, `example.com` and `com`
+fn parent_domains(domain: &str) -> impl Iterator<Item = &str> {
+    std::iter::once(domain).chain(
+        domain
+            .match_indices('.')
+            .map(move |(index, _)| &domain[index + 1..]),
+    )
+}
+
+/// Optimal string alignment distance: edits, counting a swap of adjacent
+/// characters as one
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // rows[i][j]: distance between the first i chars of a and j chars of b
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Disposable domains from an operator-maintained file
+struct DisposableDomainsFile {
+    path: PathBuf,
+    loaded: RwLock<LoadedDomains>,
+}
+
+#[derive(Default)]
+struct LoadedDomains {
+    /// Modification time and size of the file when `domains` was read
+    version: Option<(SystemTime, u64)>,
+    domains: HashSet<String>,
+}
+
+impl DisposableDomainsFile {
+    fn open(path: PathBuf) -> Result<Self, ConfigError> {
+        let file = Self {
+            path,
+            loaded: RwLock::new(LoadedDomains::default()),
+        };
+        file.reload().map_err(|e| {
+            ConfigError::InvalidValue(format!(
+                "disposable_domains_file '{}' cannot be read: {}",
+                file.path.display(),
+                e
+            ))
+        })?;
+        Ok(file)
+    }
+
+    /// Read the file again if it changed since it was last read
+    ///
+    /// If the file has become unreadable the last list read stays in use.
+    fn refresh(&self) {
+        let current = self.version().ok();
+        let stale = self.loaded.read().map(|loaded| loaded.version != current).unwrap_or(true);
+        if stale {
+            if let Err(e) = self.reload() {
+                tracing::warn!(
+                    path = %self.path.display(),
+                    error = %e,
+                    "Failed to re-read disposable domains file; keeping the previous list"
+                );
+            }
+        }
+    }
+
+    fn contains(&self, domain: &str) -> bool {
+        self.loaded.read().is_ok_and(|loaded| loaded.domains.contains(domain))
+    }
+
+    fn version(&self) -> std::io::Result<(SystemTime, u64)> {
+        let metadata = std::fs::metadata(&self.path)?;
+        Ok((metadata.modified()?, metadata.len()))
+    }
+
+    fn reload(&self) -> std::io::Result<()> {
+        let version = self.version()?;
+        let domains = domain_list(&std::fs::read_to_string(&self.path)?);
+        if let Ok(mut loaded) = self.loaded.write() {
+            *loaded = LoadedDomains { version: Some(version), domains };
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deliverability(settings: DeliverabilitySettings) -> EmailDeliverability {
+        EmailDeliverability::new(&settings).unwrap()
+    }
+
+    fn rejection_suggestion(result: Result<Option<String>, AppError>) -> Option<String> {
+        match result {
+            Err(AppError::Validation(ValidationError::UndeliverableEmail { suggestion, .. })) => {
+                suggestion
+            }
+            other => panic!("expected UndeliverableEmail, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(0, edit_distance("gmail.com", "gmail.com"));
+        assert_eq!(1, edit_distance("gmial.com", "gmail.com"));
+        assert_eq!(1, edit_distance("gmail.co", "gmail.com"));
+        assert_eq!(2, edit_distance("hotmial.cm", "hotmail.com"));
+        assert_eq!(3, edit_distance("example.com", "exmpl.co"));
+    }
+
+    #[tokio::test]
+    async fn test_typos_of_known_providers_get_a_suggestion() {
+        let checks = deliverability(DeliverabilitySettings::default());
+
+        assert_eq!(
+            Some("jane@gmail.com".to_string()),
+            checks.check("jane@gmial.com").await.unwrap()
+        );
+        assert_eq!(
+            Some("Jane.Doe@hotmail.com".to_string()),
+            checks.check("Jane.Doe@hotmal.con").await.unwrap()
+        );
+        assert_eq!(None, checks.check("jane@gmail.com").await.unwrap());
+        assert_eq!(None, checks.check("jane@email.com").await.unwrap());
+        assert_eq!(None, checks.check("jane@example.com").await.unwrap());
+
+        let checks = deliverability(DeliverabilitySettings {
+            suggest_corrections: false,
+            ..DeliverabilitySettings::default()
+        });
+        assert_eq!(None, checks.check("jane@gmial.com").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_regional_provider_domains_are_accepted() {
+        let checks = deliverability(DeliverabilitySettings::default());
+
+        // Close to bundled providers, but real: a suggestion at most
+        for email in ["jane@hotmail.es", "jane@yahoo.es", "jane@yahoo.co.in", "jane@outlook.fr"] {
+            assert!(checks.check(email).await.is_ok(), "{} was rejected", email);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disposable_domains_and_their_subdomains_are_rejected() {
+        let checks = deliverability(DeliverabilitySettings::default());
+
+        assert_eq!(None, rejection_suggestion(checks.check("bot@mailinator.com").await));
+        assert!(checks.check("bot@inbox.Mailinator.com").await.is_err());
+        assert!(checks.check("bot@notmailinator.com").await.is_ok());
+
+        let checks = deliverability(DeliverabilitySettings {
+            reject_disposable: false,
+            ..DeliverabilitySettings::default()
+        });
+        assert!(checks.check("bot@mailinator.com").await.is_ok());
+    }
+
+    #[test]
+    fn test_disposable_domains_file_is_reread_when_changed() {
+        let path = std::env::temp_dir().join(format!("disposable-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "# extra\nthrowaway.example\n").unwrap();
+        let checks = deliverability(DeliverabilitySettings {
+            disposable_domains_file: Some(path.to_string_lossy().to_string()),
+            ..DeliverabilitySettings::default()
+        });
+
+        assert!(checks.is_disposable("throwaway.example"));
+        assert!(checks.is_disposable("mailinator.com"));
+        assert!(!checks.is_disposable("burner.example"));
+
+        std::fs::write(&path, "throwaway.example\nburner.example # added later\n").unwrap();
+        assert!(checks.is_disposable("burner.example"));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(checks.is_disposable("burner.example"));
+    }
+
+    #[test]
+    fn test_missing_disposable_domains_file_is_a_config_error() {
+        let result = EmailDeliverability::new(&DeliverabilitySettings {
+            disposable_domains_file: Some("/nonexistent/disposable.txt".to_string()),
+            ..DeliverabilitySettings::default()
+        });
+        assert!(result.is_err());
+    }
+}
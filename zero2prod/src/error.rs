@@ -8,7 +8,7 @@
 /// 4. Domain-Specific Error Types (avoiding ball of mud)
 /// 5. Structured Error Logging with Context
 
-use actix_web::{error::ResponseError, http::StatusCode, HttpResponse};
+use actix_web::{error::ResponseError, http::header, http::StatusCode, HttpResponse};
 use std::error::Error as StdError;
 use std::fmt;
 
@@ -24,7 +24,21 @@ pub enum ValidationError {
     TooLong(String, usize),
     InvalidFormat(String),
     SuspiciousContent(String),
-    PossibleSQLInjection,
+    /// Rejected by an input policy rule (see `input_policy`)
+    InputPolicy { field: String, rule: String, detail: String },
+    /// Password is too easy to guess; carries feedback for the user
+    WeakPassword(String),
+    /// Password appears in breach data
+    BreachedPassword,
+    /// Request body is over the route's limit (bytes)
+    PayloadTooLarge(usize),
+    /// Subscription form failed the bot checks; carries the reason
+    BotCheckFailed(String),
+    /// The address got as many confirmation emails as it may for now
+    TooManyConfirmationEmails { retry_after_seconds: u64 },
+    /// The address failed a deliverability check (see `email_deliverability`);
+    /// carries a corrected address when the domain looks like a typo
+    UndeliverableEmail { reason: String, suggestion: Option<String> },
 }
 
 impl fmt::Display for ValidationError {
@@ -41,8 +55,33 @@ impl fmt::Display for ValidationError {
             ValidationError::SuspiciousContent(field) => {
                 write!(f, "{} contains suspicious content", field)
             }
-            ValidationError::PossibleSQLInjection => {
-                write!(f, "input contains potentially dangerous SQL patterns")
+            ValidationError::InputPolicy { field, rule, detail } => {
+                write!(f, "{} rejected by input rule '{}': {}", field, rule, detail)
+            }
+            ValidationError::WeakPassword(feedback) => {
+                write!(f, "password is too easy to guess: {}", feedback)
+            }
+            ValidationError::BreachedPassword => write!(
+                f,
+                "password has appeared in a data breach and must not be used; choose a different one"
+            ),
+            ValidationError::PayloadTooLarge(max) => {
+                write!(f, "request body is too large (maximum {} bytes)", max)
+            }
+            ValidationError::BotCheckFailed(reason) => {
+                write!(f, "form submission was rejected: {}", reason)
+            }
+            ValidationError::TooManyConfirmationEmails { retry_after_seconds } => write!(
+                f,
+                "too many confirmation emails sent to this address; try again in {} seconds",
+                retry_after_seconds
+            ),
+            ValidationError::UndeliverableEmail { reason, suggestion } => {
+                write!(f, "email cannot receive mail: {}", reason)?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, " (did you mean {}?)", suggestion)?;
+                }
+                Ok(())
             }
         }
     }
@@ -128,6 +167,22 @@ pub enum AuthError {
     TokenInvalid,
     MissingToken,
     AccountInactive,
+    EmailNotVerified,
+    InvalidMfaCode,
+    /// Too many failed logins from this client, or too soon after the last one
+    TooManyLoginAttempts { retry_after_seconds: u64 },
+    /// Account temporarily locked after repeated failed logins
+    AccountLocked { retry_after_seconds: u64 },
+    /// Credential is valid but not allowed to perform this action
+    InsufficientScope,
+    /// External identity provider failed or returned an unusable response
+    IdentityProvider(String),
+    /// Authenticated, but the account's role doesn't allow the action
+    PermissionDenied,
+    /// An admin required a new password before the next password login
+    PasswordResetRequired,
+    /// Cookie-authenticated request without the session's CSRF token
+    CsrfTokenInvalid,
 }
 
 impl fmt::Display for AuthError {
@@ -138,6 +193,23 @@ impl fmt::Display for AuthError {
             AuthError::TokenInvalid => write!(f, "Invalid token"),
             AuthError::MissingToken => write!(f, "Missing authentication token"),
             AuthError::AccountInactive => write!(f, "Account is inactive"),
+            AuthError::EmailNotVerified => write!(f, "Email address is not verified"),
+            AuthError::InvalidMfaCode => write!(f, "Invalid two-factor authentication code"),
+            AuthError::TooManyLoginAttempts { retry_after_seconds } => write!(
+                f,
+                "Too many login attempts, retry after {} seconds",
+                retry_after_seconds
+            ),
+            AuthError::AccountLocked { retry_after_seconds } => write!(
+                f,
+                "Account temporarily locked, retry after {} seconds",
+                retry_after_seconds
+            ),
+            AuthError::InsufficientScope => write!(f, "Insufficient scope for this action"),
+            AuthError::IdentityProvider(msg) => write!(f, "Identity provider error: {}", msg),
+            AuthError::PermissionDenied => write!(f, "Permission denied"),
+            AuthError::PasswordResetRequired => write!(f, "Password reset required"),
+            AuthError::CsrfTokenInvalid => write!(f, "Missing or invalid CSRF token"),
         }
     }
 }
@@ -175,6 +247,22 @@ impl fmt::Display for AppError {
 
 impl StdError for AppError {}
 
+impl AppError {
+    /// Seconds a client should wait before retrying, sent as `Retry-After`
+    pub fn retry_after_seconds(&self) -> Option<u64> {
+        match self {
+            AppError::Auth(AuthError::TooManyLoginAttempts { retry_after_seconds })
+            | AppError::Auth(AuthError::AccountLocked { retry_after_seconds })
+            | AppError::Validation(ValidationError::TooManyConfirmationEmails {
+                retry_after_seconds,
+            }) => {
+                Some(*retry_after_seconds)
+            }
+            _ => None,
+        }
+    }
+}
+
 // ============================================================================
 // FROM IMPLEMENTATIONS (Control Flow Error Conversion)
 // ============================================================================
@@ -258,6 +346,9 @@ pub struct ErrorResponse {
     pub status: u16,
     /// Timestamp when error occurred
     pub timestamp: String,
+    /// Corrected input to offer the user, e.g. an address without a typo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
 }
 
 impl ErrorResponse {
@@ -269,6 +360,7 @@ impl ErrorResponse {
             code,
             status,
             timestamp: chrono::Utc::now().to_rfc3339(),
+            suggestion: None,
         }
     }
 }
@@ -282,12 +374,29 @@ pub trait ErrorHandler {
 impl ErrorHandler for AppError {
     fn error_response(&self, request_id: &str) -> (StatusCode, ErrorResponse) {
         let (status, code, message) = match self {
-            // Validation errors -> 400 Bad Request
-            AppError::Validation(e) => (
-                StatusCode::BAD_REQUEST,
-                "VALIDATION_ERROR".to_string(),
-                e.to_string(),
+            // Validation errors -> 400 Bad Request (413 for oversized bodies,
+            // 429 for throttled confirmation emails)
+            AppError::Validation(ValidationError::PayloadTooLarge(_)) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "PAYLOAD_TOO_LARGE".to_string(),
+                self.to_string(),
+            ),
+            AppError::Validation(ValidationError::TooManyConfirmationEmails { .. }) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "TOO_MANY_CONFIRMATION_EMAILS".to_string(),
+                self.to_string(),
             ),
+            AppError::Validation(e) => {
+                let code = match e {
+                    ValidationError::WeakPassword(_) => "WEAK_PASSWORD",
+                    ValidationError::BreachedPassword => "BREACHED_PASSWORD",
+                    ValidationError::BotCheckFailed(_) => "BOT_CHECK_FAILED",
+                    ValidationError::InputPolicy { .. } => "INPUT_POLICY_VIOLATION",
+                    ValidationError::UndeliverableEmail { .. } => "UNDELIVERABLE_EMAIL",
+                    _ => "VALIDATION_ERROR",
+                };
+                (StatusCode::BAD_REQUEST, code.to_string(), e.to_string())
+            }
 
             // Database errors -> appropriate HTTP status
             AppError::Database(e) => match e {
@@ -342,6 +451,51 @@ impl ErrorHandler for AppError {
                     "ACCOUNT_INACTIVE".to_string(),
                     "Account is inactive".to_string(),
                 ),
+                AuthError::EmailNotVerified => (
+                    StatusCode::FORBIDDEN,
+                    "EMAIL_NOT_VERIFIED".to_string(),
+                    "Email address is not verified".to_string(),
+                ),
+                AuthError::InvalidMfaCode => (
+                    StatusCode::UNAUTHORIZED,
+                    "INVALID_MFA_CODE".to_string(),
+                    "Invalid two-factor authentication code".to_string(),
+                ),
+                AuthError::TooManyLoginAttempts { .. } => (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "TOO_MANY_LOGIN_ATTEMPTS".to_string(),
+                    "Too many login attempts, please try again later".to_string(),
+                ),
+                AuthError::AccountLocked { .. } => (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "ACCOUNT_LOCKED".to_string(),
+                    "Account temporarily locked due to repeated failed logins".to_string(),
+                ),
+                AuthError::InsufficientScope => (
+                    StatusCode::FORBIDDEN,
+                    "INSUFFICIENT_SCOPE".to_string(),
+                    "Credential is not allowed to perform this action".to_string(),
+                ),
+                AuthError::IdentityProvider(_) => (
+                    StatusCode::BAD_GATEWAY,
+                    "IDENTITY_PROVIDER_ERROR".to_string(),
+                    "Identity provider request failed".to_string(),
+                ),
+                AuthError::PermissionDenied => (
+                    StatusCode::FORBIDDEN,
+                    "PERMISSION_DENIED".to_string(),
+                    "You do not have permission to perform this action".to_string(),
+                ),
+                AuthError::PasswordResetRequired => (
+                    StatusCode::FORBIDDEN,
+                    "PASSWORD_RESET_REQUIRED".to_string(),
+                    "A password reset is required; use the link sent to your email".to_string(),
+                ),
+                AuthError::CsrfTokenInvalid => (
+                    StatusCode::FORBIDDEN,
+                    "CSRF_TOKEN_INVALID".to_string(),
+                    "Missing or invalid CSRF token".to_string(),
+                ),
             },
 
             // Config errors -> 500 Internal Server Error
@@ -359,12 +513,15 @@ impl ErrorHandler for AppError {
             ),
         };
 
-        let error_response = ErrorResponse::new(
+        let mut error_response = ErrorResponse::new(
             request_id.to_string(),
             message,
             code,
             status.as_u16(),
         );
+        if let AppError::Validation(ValidationError::UndeliverableEmail { suggestion, .. }) = self {
+            error_response.suggestion = suggestion.clone();
+        }
 
         (status, error_response)
     }
@@ -443,11 +600,21 @@ impl ResponseError for AppError {
 
         let (status, error_response) = <Self as ErrorHandler>::error_response(self, &request_id);
 
-        HttpResponse::build(status).json(error_response)
+        let mut response = HttpResponse::build(status);
+        if let Some(seconds) = self.retry_after_seconds() {
+            response.insert_header((header::RETRY_AFTER, seconds.to_string()));
+        }
+        response.json(error_response)
     }
 
     fn status_code(&self) -> StatusCode {
         match self {
+            AppError::Validation(ValidationError::PayloadTooLarge(_)) => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            AppError::Validation(ValidationError::TooManyConfirmationEmails { .. }) => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
             AppError::Validation(_) => StatusCode::BAD_REQUEST,
             AppError::Database(e) => match e {
                 DatabaseError::UniqueConstraintViolation(_) => StatusCode::CONFLICT,
@@ -457,7 +624,16 @@ impl ResponseError for AppError {
             },
             AppError::Email(_) => StatusCode::SERVICE_UNAVAILABLE,
             AppError::Auth(e) => match e {
-                AuthError::AccountInactive => StatusCode::FORBIDDEN,
+                AuthError::AccountInactive
+                | AuthError::EmailNotVerified
+                | AuthError::InsufficientScope
+                | AuthError::PermissionDenied
+                | AuthError::PasswordResetRequired
+                | AuthError::CsrfTokenInvalid => StatusCode::FORBIDDEN,
+                AuthError::TooManyLoginAttempts { .. } | AuthError::AccountLocked { .. } => {
+                    StatusCode::TOO_MANY_REQUESTS
+                }
+                AuthError::IdentityProvider(_) => StatusCode::BAD_GATEWAY,
                 _ => StatusCode::UNAUTHORIZED,
             },
             AppError::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
@@ -624,4 +800,48 @@ mod tests {
         let ctx_with_user = ctx.with_user_id("user-123".to_string());
         assert_eq!(ctx_with_user.user_id, Some("user-123".to_string()));
     }
+
+    #[test]
+    fn test_lockout_errors_set_retry_after() {
+        let err = AppError::Auth(AuthError::AccountLocked { retry_after_seconds: 120 });
+        assert_eq!(err.status_code(), StatusCode::TOO_MANY_REQUESTS);
+
+        let response = ResponseError::error_response(&err);
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "120");
+
+        let err = AppError::Auth(AuthError::InvalidCredentials);
+        assert!(ResponseError::error_response(&err)
+            .headers()
+            .get(header::RETRY_AFTER)
+            .is_none());
+    }
+
+    #[test]
+    fn test_payload_too_large_is_413() {
+        let err = AppError::Validation(ValidationError::PayloadTooLarge(1024));
+        assert_eq!(err.status_code(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let (status, body) = <AppError as ErrorHandler>::error_response(&err, "test-123");
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body.code, "PAYLOAD_TOO_LARGE");
+        assert_eq!(body.message, "request body is too large (maximum 1024 bytes)");
+    }
+
+    #[test]
+    fn test_undeliverable_email_carries_suggestion() {
+        let err = AppError::Validation(ValidationError::UndeliverableEmail {
+            reason: "domain looks like a typo".to_string(),
+            suggestion: Some("jane@gmail.com".to_string()),
+        });
+
+        let (status, body) = <AppError as ErrorHandler>::error_response(&err, "test-123");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body.code, "UNDELIVERABLE_EMAIL");
+        assert_eq!(body.suggestion.as_deref(), Some("jane@gmail.com"));
+        assert_eq!(
+            body.message,
+            "email cannot receive mail: domain looks like a typo (did you mean jane@gmail.com?)"
+        );
+    }
 }
@@ -0,0 +1,474 @@
+//! Input Policy
+//!
+//! User-supplied text goes through an ordered list of rules before it is
+//! stored. Some rules clean the value up (Unicode NFC normalization,
+//! stripping invisible characters), the others accept or reject it. A
+//! rejection names the rule that fired, so clients and logs can tell a
+//! length problem from a homoglyph.
+//!
+//! Queries are parameterized, so there is no attempt to spot SQL in input:
+//! names like "O'Brien-Smith" are fine. The rules are about what a name may
+//! look like to the people reading it.
+//!
+//! Rules are pluggable: anything implementing `InputRule` can be added to a
+//! policy with `with_name_rule`.
+
+use lazy_static::lazy_static;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::configuration::{InputPolicySettings, InputScript, NamePolicySettings};
+use crate::error::{ConfigError, ValidationError};
+
+/// A check or clean-up step of an input policy
+pub trait InputRule: Send + Sync {
+    /// Name reported when the rule fires
+    fn name(&self) -> &'static str;
+
+    /// Check `value`, rewriting it if the rule cleans input up
+    ///
+    /// # Returns
+    /// Whether the value was changed
+    ///
+    /// # Errors
+    /// Why the value is rejected
+    fn apply(&self, value: &mut String) -> Result<bool, String>;
+}
+
+/// Value that passed a policy, with the rules that changed it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputReport {
+    pub value: String,
+    pub rewritten_by: Vec<&'static str>,
+}
+
+/// Rules applied to one kind of field, in order
+#[derive(Default)]
+pub struct FieldPolicy {
+    rules: Vec<Box<dyn InputRule>>,
+}
+
+impl FieldPolicy {
+    pub fn with_rule(mut self, rule: impl InputRule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Run every rule on the trimmed `input`
+    ///
+    /// # Errors
+    /// - `ValidationError::EmptyField`: Nothing left after trimming
+    /// - `ValidationError::InputPolicy`: The first rule rejecting the value
+    pub fn check(&self, field: &str, input: &str) -> Result<InputReport, ValidationError> {
+        let mut value = input.trim().to_string();
+        let mut rewritten_by = Vec::new();
+
+        for rule in &self.rules {
+            if value.is_empty() {
+                break;
+            }
+            match rule.apply(&mut value) {
+                Ok(true) => {
+                    rewritten_by.push(rule.name());
+                    value = value.trim().to_string();
+                }
+                Ok(false) => {}
+                Err(detail) => {
+                    return Err(ValidationError::InputPolicy {
+                        field: field.to_string(),
+                        rule: rule.name().to_string(),
+                        detail,
+                    })
+                }
+            }
+        }
+
+        if value.is_empty() {
+            return Err(ValidationError::EmptyField(field.to_string()));
+        }
+        Ok(InputReport { value, rewritten_by })
+    }
+}
+
+/// Policies for every kind of user-supplied text we check
+pub struct InputPolicy {
+    names: FieldPolicy,
+}
+
+impl InputPolicy {
+    /// # Errors
+    /// Returns error if the length limits are contradictory
+    pub fn from_settings(settings: &InputPolicySettings) -> Result<Self, ConfigError> {
+        let names = &settings.names;
+        if names.min_length == 0 || names.min_length > names.max_length {
+            return Err(ConfigError::InvalidValue(format!(
+                "input_policy.names: need 0 < min_length <= max_length, got {} and {}",
+                names.min_length, names.max_length
+            )));
+        }
+
+        Ok(Self {
+            names: name_policy(names),
+        })
+    }
+
+    /// Add a rule run on names after the configured ones
+    pub fn with_name_rule(mut self, rule: impl InputRule + 'static) -> Self {
+        self.names = self.names.with_rule(rule);
+        self
+    }
+
+    /// Check a subscriber or account name
+    ///
+    /// # Returns
+    /// The normalized name to store
+    pub fn check_name(&self, name: &str) -> Result<String, ValidationError> {
+        let report = self.names.check("name", name).inspect_err(|e| {
+            if let ValidationError::InputPolicy { rule, .. } = e {
+                tracing::info!(rule = %rule, "Name rejected by input policy");
+            }
+        })?;
+        if !report.rewritten_by.is_empty() {
+            tracing::debug!(rules = ?report.rewritten_by, "Name rewritten by input policy");
+        }
+        Ok(report.value)
+    }
+}
+
+impl Default for InputPolicy {
+    fn default() -> Self {
+        Self {
+            names: name_policy(&NamePolicySettings::default()),
+        }
+    }
+}
+
+lazy_static! {
+    /// Policy with the default settings, for checks made outside a request
+    pub static ref DEFAULT_INPUT_POLICY: InputPolicy = InputPolicy::default();
+}
+
+fn name_policy(settings: &NamePolicySettings) -> FieldPolicy {
+    let mut policy = FieldPolicy::default().with_rule(NfcNormalization);
+    if settings.strip_invisible {
+        policy = policy.with_rule(StripInvisibleCharacters);
+    }
+    policy = policy
+        .with_rule(ControlCharacters)
+        .with_rule(LengthLimits {
+            min: settings.min_length,
+            max: settings.max_length,
+        })
+        .with_rule(RequiresLetter);
+    if !settings.allowed_scripts.is_empty() {
+        policy = policy.with_rule(AllowedScripts(settings.allowed_scripts.clone()));
+    }
+    if settings.reject_confusables {
+        policy = policy.with_rule(MixedScriptConfusables);
+    }
+    policy
+}
+
+// ============================================================================
+// RULES
+// ============================================================================
+
+/// Unicode canonical composition, so "é" is stored the same however typed
+pub struct NfcNormalization;
+
+impl InputRule for NfcNormalization {
+    fn name(&self) -> &'static str {
+        "nfc_normalization"
+    }
+
+    fn apply(&self, value: &mut String) -> Result<bool, String> {
+        if is_nfc(value) {
+            return Ok(false);
+        }
+        *value = value.nfc().collect();
+        Ok(true)
+    }
+}
+
+/// Removes characters that render as nothing but can reorder or hide text
+pub struct StripInvisibleCharacters;
+
+impl InputRule for StripInvisibleCharacters {
+    fn name(&self) -> &'static str {
+        "invisible_characters"
+    }
+
+    fn apply(&self, value: &mut String) -> Result<bool, String> {
+        if !value.chars().any(is_invisible) {
+            return Ok(false);
+        }
+        value.retain(|c| !is_invisible(c));
+        Ok(true)
+    }
+}
+
+/// Bidi controls and zero-width characters; zero-width (non-)joiners are
+/// kept, as some scripts need them
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}'
+            | '\u{061C}'
+            | '\u{200B}'
+            | '\u{200E}'
+            | '\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{2069}'
+            | '\u{FEFF}'
+    )
+}
+
+/// Rejects tabs, newlines, NUL and other control characters
+pub struct ControlCharacters;
+
+impl InputRule for ControlCharacters {
+    fn name(&self) -> &'static str {
+        "control_characters"
+    }
+
+    fn apply(&self, value: &mut String) -> Result<bool, String> {
+        match value.chars().find(|c| c.is_control()) {
+            Some(c) => Err(format!("contains control character U+{:04X}", c as u32)),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Length in characters
+pub struct LengthLimits {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl InputRule for LengthLimits {
+    fn name(&self) -> &'static str {
+        "length"
+    }
+
+    fn apply(&self, value: &mut String) -> Result<bool, String> {
+        let length = value.chars().count();
+        if length < self.min {
+            return Err(format!("must be at least {} characters", self.min));
+        }
+        if length > self.max {
+            return Err(format!("must be at most {} characters", self.max));
+        }
+        Ok(false)
+    }
+}
+
+/// Rejects values made of punctuation, digits and symbols only
+pub struct RequiresLetter;
+
+impl InputRule for RequiresLetter {
+    fn name(&self) -> &'static str {
+        "no_letters"
+    }
+
+    fn apply(&self, value: &mut String) -> Result<bool, String> {
+        if value.chars().any(char::is_alphabetic) {
+            Ok(false)
+        } else {
+            Err("must contain at least one letter".to_string())
+        }
+    }
+}
+
+/// Letters must come from one of the listed scripts
+pub struct AllowedScripts(pub Vec<InputScript>);
+
+impl InputRule for AllowedScripts {
+    fn name(&self) -> &'static str {
+        "disallowed_script"
+    }
+
+    fn apply(&self, value: &mut String) -> Result<bool, String> {
+        let disallowed = value.chars().filter(|c| c.is_alphabetic()).find(|&c| {
+            !is_inherited(c) && !script_of(c).is_some_and(|script| self.0.contains(&script))
+        });
+        match disallowed {
+            Some(c) => Err(format!("'{}' (U+{:04X}) is not from an allowed script", c, c as u32)),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Rejects words mixing Latin, Greek and Cyrillic letters when one of them
+/// passes for a Latin letter, the classic way to imitate another name
+pub struct MixedScriptConfusables;
+
+impl InputRule for MixedScriptConfusables {
+    fn name(&self) -> &'static str {
+        "confusable_characters"
+    }
+
+    fn apply(&self, value: &mut String) -> Result<bool, String> {
+        for word in value.split(|c: char| !c.is_alphabetic() && !is_inherited(c)) {
+            let scripts: Vec<InputScript> = word.chars().filter_map(script_of).collect();
+            let has_latin = scripts.contains(&InputScript::Latin);
+            let has_lookalike_script = scripts
+                .iter()
+                .any(|script| matches!(script, InputScript::Greek | InputScript::Cyrillic));
+            if !(has_latin && has_lookalike_script) {
+                continue;
+            }
+            if let Some((c, latin)) = word.chars().find_map(|c| latin_lookalike(c).map(|l| (c, l)))
+            {
+                return Err(format!(
+                    "'{}' (U+{:04X}) looks like Latin '{}' in \"{}\"",
+                    c, c as u32, latin, word
+                ));
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Combining diacritics take the script of the letter they follow
+fn is_inherited(c: char) -> bool {
+    matches!(c, '\u{0300}'..='\u{036F}' | '\u{1AB0}'..='\u{1AFF}' | '\u{20D0}'..='\u{20FF}')
+}
+
+/// Script of a letter, for the scripts policies can name
+fn script_of(c: char) -> Option<InputScript> {
+    let script = match c as u32 {
+        0x0041..=0x005A
+        | 0x0061..=0x007A
+        | 0x00AA
+        | 0x00BA
+        | 0x00C0..=0x00D6
+        | 0x00D8..=0x00F6
+        | 0x00F8..=0x02AF
+        | 0x1E00..=0x1EFF
+        | 0x2C60..=0x2C7F
+        | 0xA720..=0xA7FF
+        | 0xFF21..=0xFF3A
+        | 0xFF41..=0xFF5A => InputScript::Latin,
+        0x0370..=0x03FF | 0x1F00..=0x1FFF => InputScript::Greek,
+        0x0400..=0x052F | 0x1C80..=0x1C8F | 0x2DE0..=0x2DFF | 0xA640..=0xA69F => {
+            InputScript::Cyrillic
+        }
+        0x0530..=0x058F => InputScript::Armenian,
+        0x0590..=0x05FF | 0xFB1D..=0xFB4F => InputScript::Hebrew,
+        0x0600..=0x06FF | 0x0750..=0x077F | 0x08A0..=0x08FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => {
+            InputScript::Arabic
+        }
+        0x0900..=0x097F => InputScript::Devanagari,
+        0x0980..=0x09FF => InputScript::Bengali,
+        0x0E00..=0x0E7F => InputScript::Thai,
+        0x10A0..=0x10FF | 0x2D00..=0x2D2F => InputScript::Georgian,
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => InputScript::Hangul,
+        0x3040..=0x309F => InputScript::Hiragana,
+        0x30A0..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => InputScript::Katakana,
+        0x2E80..=0x2FDF
+        | 0x3005
+        | 0x3007
+        | 0x3021..=0x3029
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xF900..=0xFAFF
+        | 0x20000..=0x2FA1F => InputScript::Han,
+        _ => return None,
+    };
+    Some(script)
+}
+
+/// Latin letter a Greek or Cyrillic letter is commonly mistaken for
+fn latin_lookalike(c: char) -> Option<char> {
+    let latin = match c {
+        // Cyrillic
+        'а' => 'a', 'е' => 'e', 'о' => 'o', 'р' => 'p', 'с' => 'c', 'у' => 'y', 'х' => 'x',
+        'і' => 'i', 'ј' => 'j', 'ѕ' => 's', 'ԁ' => 'd', 'ԛ' => 'q', 'ԝ' => 'w', 'һ' => 'h',
+        'ӏ' => 'l', 'А' => 'A', 'В' => 'B', 'Е' => 'E', 'К' => 'K', 'М' => 'M', 'Н' => 'H',
+        'О' => 'O', 'Р' => 'P', 'С' => 'C', 'Т' => 'T', 'Х' => 'X', 'І' => 'I', 'Ј' => 'J',
+        'Ѕ' => 'S', 'У' => 'Y',
+        // Greek
+        'ο' => 'o', 'α' => 'a', 'ν' => 'v', 'ρ' => 'p', 'ι' => 'i', 'κ' => 'k', 'Α' => 'A',
+        'Β' => 'B', 'Ε' => 'E', 'Ζ' => 'Z', 'Η' => 'H', 'Ι' => 'I', 'Κ' => 'K', 'Μ' => 'M',
+        'Ν' => 'N', 'Ο' => 'O', 'Ρ' => 'P', 'Τ' => 'T', 'Χ' => 'X', 'Υ' => 'Y',
+        _ => return None,
+    };
+    Some(latin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule_fired(result: Result<String, ValidationError>) -> String {
+        match result {
+            Err(ValidationError::InputPolicy { rule, .. }) => rule,
+            other => panic!("expected a policy violation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_names_with_punctuation_are_accepted() {
+        let policy = InputPolicy::default();
+
+        assert_eq!("O'Brien-Smith", policy.check_name("O'Brien-Smith").unwrap());
+        assert_eq!("Jean-Pierre d'Arc", policy.check_name(" Jean-Pierre d'Arc ").unwrap());
+        assert_eq!("Ursula K. Le Guin", policy.check_name("Ursula K. Le Guin").unwrap());
+        assert_eq!("Мария Петрова", policy.check_name("Мария Петрова").unwrap());
+        assert_eq!("山田 太郎", policy.check_name("山田 太郎").unwrap());
+    }
+
+    #[test]
+    fn test_names_are_normalized_and_cleaned() {
+        let policy = InputPolicy::default();
+
+        // "e" + combining acute accent
+        assert_eq!("Ren\u{00E9}e", policy.check_name("Rene\u{0301}e").unwrap());
+        // Right-to-left override used to disguise text
+        assert_eq!("Evilgpj.exe", policy.check_name("Evil\u{202E}gpj.exe").unwrap());
+        assert!(matches!(
+            policy.check_name("\u{200B}"),
+            Err(ValidationError::EmptyField(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejections_report_the_rule() {
+        let policy = InputPolicy::default();
+
+        assert_eq!("control_characters", rule_fired(policy.check_name("Name\0with\0null")));
+        assert_eq!("length", rule_fired(policy.check_name(&"a".repeat(257))));
+        assert_eq!("no_letters", rule_fired(policy.check_name("!!!!!!@@@@")));
+        // Cyrillic "а" in an otherwise Latin word
+        assert_eq!("confusable_characters", rule_fired(policy.check_name("P\u{0430}ypal")));
+    }
+
+    #[test]
+    fn test_allowed_scripts() {
+        let mut settings = InputPolicySettings::default();
+        settings.names.allowed_scripts = vec![InputScript::Latin];
+        let policy = InputPolicy::from_settings(&settings).unwrap();
+
+        assert!(policy.check_name("Zoë Müller").is_ok());
+        assert_eq!("disallowed_script", rule_fired(policy.check_name("Мария")));
+    }
+
+    #[test]
+    fn test_custom_rules_can_be_added() {
+        struct NoDigits;
+        impl InputRule for NoDigits {
+            fn name(&self) -> &'static str {
+                "no_digits"
+            }
+            fn apply(&self, value: &mut String) -> Result<bool, String> {
+                match value.chars().any(|c| c.is_ascii_digit()) {
+                    true => Err("contains a digit".to_string()),
+                    false => Ok(false),
+                }
+            }
+        }
+        let policy = InputPolicy::default().with_name_rule(NoDigits);
+
+        assert_eq!("no_digits", rule_fired(policy.check_name("Agent 47")));
+    }
+}
@@ -4,9 +4,16 @@ pub mod routes;
 pub mod startup;
 pub mod telemetry;
 pub mod validators;
+pub mod input_policy;
 pub mod security;
 pub mod email_client;
 pub mod confirmation_token;
+pub mod preference_link;
+pub mod subscription_guard;
+pub mod email_deliverability;
+pub mod subscriber_data;
+pub mod subscriber_import;
+pub mod csv_stream;
 pub mod error;
 pub mod request_logging;
 pub mod data_validation;
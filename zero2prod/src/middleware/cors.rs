@@ -0,0 +1,351 @@
+//! CORS Middleware
+//!
+//! Lets the origins configured for a path prefix (see `CorsSettings`) call
+//! the API from the browser. Preflight requests are answered here: 204 with
+//! the allowed methods and headers, or 403 when the origin, method or a
+//! requested header isn't allowed. Other requests from an allowed origin
+//! get `Access-Control-Allow-Origin`; from any other origin they are served
+//! without it, so the browser keeps the response from the calling page.
+
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::InternalError,
+    http::{
+        header::{self, HeaderMap, HeaderName, HeaderValue},
+        Method,
+    },
+    web, Error, HttpResponse,
+};
+use futures::future::LocalBoxFuture;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::configuration::{CorsScopeSettings, CorsSettings};
+use crate::error::{ConfigError, ErrorResponse};
+use crate::security::path_in_scope;
+
+struct CorsPolicy {
+    path_prefix: String,
+    /// Serialized origins other than `*`
+    allowed_origins: Vec<String>,
+    any_origin: bool,
+    allowed_methods: Vec<Method>,
+    /// Lowercase header names
+    allowed_headers: Vec<String>,
+    expose_headers: Option<HeaderValue>,
+    allow_credentials: bool,
+    max_age_seconds: u64,
+}
+
+impl CorsPolicy {
+    fn from_settings(scope: &CorsScopeSettings) -> Result<Self, ConfigError> {
+        let invalid = |what: String| {
+            ConfigError::InvalidValue(format!("cors.scopes.{}: {}", scope.path_prefix, what))
+        };
+
+        if !scope.path_prefix.starts_with('/') {
+            return Err(invalid("path_prefix must start with '/'".to_string()));
+        }
+
+        let any_origin = scope.allowed_origins.iter().any(|origin| origin == "*");
+        if any_origin && scope.allow_credentials {
+            return Err(invalid(
+                "allowed_origins can't be \"*\" when credentials are allowed".to_string(),
+            ));
+        }
+        let allowed_origins = scope
+            .allowed_origins
+            .iter()
+            .filter(|origin| *origin != "*")
+            .map(|origin| {
+                parse_origin(origin).ok_or_else(|| invalid(format!("invalid origin '{}'", origin)))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let allowed_methods = scope
+            .allowed_methods
+            .iter()
+            .map(|method| {
+                Method::from_str(&method.to_uppercase())
+                    .map_err(|_| invalid(format!("unknown method '{}'", method)))
+            })
+            .collect::<Result<_, _>>()?;
+
+        for name in scope.allowed_headers.iter().chain(&scope.expose_headers) {
+            if HeaderName::from_str(name).is_err() {
+                return Err(invalid(format!("invalid header name '{}'", name)));
+            }
+        }
+        let expose_headers = match scope.expose_headers.is_empty() {
+            true => None,
+            false => HeaderValue::from_str(&scope.expose_headers.join(", ")).ok(),
+        };
+
+        Ok(Self {
+            path_prefix: scope.path_prefix.trim_end_matches('/').to_string(),
+            allowed_origins,
+            any_origin,
+            allowed_methods,
+            allowed_headers: scope.allowed_headers.iter().map(|h| h.to_lowercase()).collect(),
+            expose_headers,
+            allow_credentials: scope.allow_credentials,
+            max_age_seconds: scope.max_age_seconds,
+        })
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        path_in_scope(path, &self.path_prefix)
+    }
+
+    fn allows_origin(&self, origin: &str) -> bool {
+        self.any_origin || self.allowed_origins.iter().any(|allowed| allowed == origin)
+    }
+
+    /// Whether a preflight for `method` with `requested_headers` passes
+    fn allows_preflight(&self, method: &str, requested_headers: &str) -> bool {
+        let method_allowed = self.allowed_methods.iter().any(|allowed| allowed.as_str() == method);
+        let headers_allowed = requested_headers
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .all(|name| self.allowed_headers.contains(&name));
+        method_allowed && headers_allowed
+    }
+
+    /// Headers for a response to `origin`, which must be allowed
+    fn insert_headers(&self, headers: &mut HeaderMap, origin: &HeaderValue) {
+        let allow_origin = if self.any_origin {
+            HeaderValue::from_static("*")
+        } else {
+            origin.clone()
+        };
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        if self.allow_credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+        if let Some(expose_headers) = &self.expose_headers {
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, expose_headers.clone());
+        }
+    }
+
+    fn preflight_response(&self, origin: &HeaderValue) -> HttpResponse {
+        let methods: Vec<&str> = self.allowed_methods.iter().map(|m| m.as_str()).collect();
+
+        let mut response = HttpResponse::NoContent()
+            .insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, methods.join(", ")))
+            .insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, self.allowed_headers.join(", ")))
+            .insert_header((header::ACCESS_CONTROL_MAX_AGE, self.max_age_seconds.to_string()))
+            .insert_header((
+                header::VARY,
+                "Origin, Access-Control-Request-Method, Access-Control-Request-Headers",
+            ))
+            .finish();
+        self.insert_headers(response.headers_mut(), origin);
+        response
+    }
+}
+
+/// `scheme://host[:port]` of an origin, or `None` if it isn't one
+fn parse_origin(origin: &str) -> Option<String> {
+    let url = reqwest::Url::parse(origin).ok()?;
+    let is_origin = matches!(url.scheme(), "http" | "https")
+        && url.host().is_some()
+        && url.path() == "/"
+        && url.query().is_none()
+        && url.fragment().is_none()
+        && url.username().is_empty();
+    is_origin.then(|| url.origin().ascii_serialization())
+}
+
+/// CORS policies by path prefix
+pub struct CorsPolicies {
+    policies: Vec<CorsPolicy>,
+}
+
+impl CorsPolicies {
+    pub fn from_settings(settings: &CorsSettings) -> Result<Self, ConfigError> {
+        let policies = settings
+            .scopes
+            .iter()
+            .map(CorsPolicy::from_settings)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { policies })
+    }
+
+    fn policy_for(&self, path: &str) -> Option<usize> {
+        self.policies.iter().position(|policy| policy.matches(path))
+    }
+}
+
+/// Middleware applying `CorsPolicies`
+pub struct CorsMiddleware {
+    policies: web::Data<CorsPolicies>,
+}
+
+impl CorsMiddleware {
+    pub fn new(policies: web::Data<CorsPolicies>) -> Self {
+        Self { policies }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CorsMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CorsMiddlewareService<S>;
+    type Future = std::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        std::future::ready(Ok(CorsMiddlewareService {
+            service: Rc::new(service),
+            policies: self.policies.clone(),
+        }))
+    }
+}
+
+pub struct CorsMiddlewareService<S> {
+    service: Rc<S>,
+    policies: web::Data<CorsPolicies>,
+}
+
+impl<S, B> Service<ServiceRequest> for CorsMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let (Some(policy_index), Some(origin)) = (
+            self.policies.policy_for(req.path()),
+            req.headers().get(header::ORIGIN).cloned(),
+        ) else {
+            return Box::pin(async move { Ok(service.call(req).await?.map_into_left_body()) });
+        };
+        let policies = self.policies.clone();
+        let policy = &policies.policies[policy_index];
+        let origin_allowed = origin.to_str().is_ok_and(|origin| policy.allows_origin(origin));
+
+        let requested_method = req
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|h| h.to_str().ok());
+        if let (&Method::OPTIONS, Some(requested_method)) = (req.method(), requested_method) {
+            let requested_headers = req
+                .headers()
+                .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+                .and_then(|h| h.to_str().ok())
+                .unwrap_or_default();
+
+            let response = if origin_allowed
+                && policy.allows_preflight(requested_method, requested_headers)
+            {
+                policy.preflight_response(&origin)
+            } else {
+                tracing::warn!(
+                    path = %req.path(),
+                    origin = ?origin,
+                    method = requested_method,
+                    "CORS preflight refused"
+                );
+                HttpResponse::Forbidden().json(ErrorResponse::new(
+                    uuid::Uuid::new_v4().to_string(),
+                    "Cross-origin request not allowed".to_string(),
+                    "CORS_NOT_ALLOWED".to_string(),
+                    403,
+                ))
+            };
+            return Box::pin(async move { Ok(req.into_response(response).map_into_right_body()) });
+        }
+
+        Box::pin(async move {
+            let policy = &policies.policies[policy_index];
+            match service.call(req).await {
+                Ok(mut response) => {
+                    let headers = response.headers_mut();
+                    headers.append(header::VARY, HeaderValue::from_static("Origin"));
+                    if origin_allowed {
+                        policy.insert_headers(headers, &origin);
+                    }
+                    Ok(response.map_into_left_body())
+                }
+                // Errors from inner middleware (401, 429) must be readable
+                // by the calling page too
+                Err(e) => {
+                    let mut response = e.error_response();
+                    response.headers_mut().append(header::VARY, HeaderValue::from_static("Origin"));
+                    if origin_allowed {
+                        policy.insert_headers(response.headers_mut(), &origin);
+                    }
+                    Err(InternalError::from_response(e, response).into())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(origins: &[&str], allow_credentials: bool) -> CorsScopeSettings {
+        CorsScopeSettings {
+            path_prefix: "/api".to_string(),
+            allowed_origins: origins.iter().map(|o| o.to_string()).collect(),
+            allowed_methods: vec!["get".to_string(), "POST".to_string()],
+            allowed_headers: vec!["Content-Type".to_string(), "Authorization".to_string()],
+            expose_headers: Vec::new(),
+            allow_credentials,
+            max_age_seconds: 600,
+        }
+    }
+
+    #[test]
+    fn test_origins_are_normalized_and_validated() {
+        assert_eq!(
+            Some("https://admin.example.com".to_string()),
+            parse_origin("https://Admin.Example.com/")
+        );
+        assert_eq!(
+            Some("http://localhost:3000".to_string()),
+            parse_origin("http://localhost:3000")
+        );
+        assert_eq!(None, parse_origin("https://example.com/app"));
+        assert_eq!(None, parse_origin("example.com"));
+        assert_eq!(None, parse_origin("ftp://example.com"));
+
+        assert!(CorsPolicy::from_settings(&scope(&["https://example.com/app"], false)).is_err());
+        assert!(CorsPolicy::from_settings(&scope(&["*"], true)).is_err());
+        assert!(CorsPolicy::from_settings(&scope(&["*"], false)).is_ok());
+    }
+
+    #[test]
+    fn test_preflight_checks_method_and_headers() {
+        let policy =
+            CorsPolicy::from_settings(&scope(&["https://admin.example.com"], true)).unwrap();
+
+        assert!(policy.allows_origin("https://admin.example.com"));
+        assert!(!policy.allows_origin("https://evil.example.com"));
+        assert!(policy.allows_preflight("GET", ""));
+        assert!(policy.allows_preflight("POST", "content-type, Authorization"));
+        assert!(!policy.allows_preflight("DELETE", ""));
+        assert!(!policy.allows_preflight("POST", "x-custom"));
+        assert!(policy.matches("/api/me"));
+        assert!(!policy.matches("/apiary"));
+    }
+}
@@ -0,0 +1,92 @@
+//! CSRF Middleware
+//!
+//! State-changing requests (anything but GET, HEAD and OPTIONS) that carry
+//! the refresh token cookie must send the session's CSRF token in
+//! `X-CSRF-Token` (see `auth::session`), or they are refused with 403.
+//! Requests without the cookie, such as bearer token API calls, can't be
+//! forged by another site and pass through untouched.
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::InternalError,
+    http::Method,
+    Error, ResponseError,
+};
+use futures::future::LocalBoxFuture;
+use std::rc::Rc;
+
+use crate::auth::{refresh_token_cookie, verify_csrf_token, CSRF_HEADER};
+use crate::error::{AppError, AuthError};
+
+/// Middleware requiring CSRF tokens on cookie-authenticated requests
+pub struct CsrfMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for CsrfMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CsrfMiddlewareService<S>;
+    type Future = std::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        std::future::ready(Ok(CsrfMiddlewareService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct CsrfMiddlewareService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for CsrfMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        if is_safe(req.method()) {
+            return Box::pin(async move { service.call(req).await });
+        }
+        let Some(refresh_token) = refresh_token_cookie(req.request()) else {
+            return Box::pin(async move { service.call(req).await });
+        };
+
+        let csrf_token = req
+            .headers()
+            .get(CSRF_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or_default();
+        if !verify_csrf_token(&refresh_token, csrf_token) {
+            tracing::warn!(
+                path = %req.path(),
+                method = %req.method(),
+                "Cookie-authenticated request without a valid CSRF token"
+            );
+            let error = AppError::Auth(AuthError::CsrfTokenInvalid);
+            let response = error.error_response();
+            return Box::pin(async move {
+                Err(InternalError::from_response(error, response).into())
+            });
+        }
+
+        Box::pin(async move { service.call(req).await })
+    }
+}
+
+fn is_safe(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
@@ -1,30 +1,34 @@
 /// JWT Authentication Middleware
 ///
-/// Validates JWT tokens from the Authorization header and injects
-/// claims into request extensions for use by route handlers.
+/// Validates JWT tokens or API keys from the Authorization header and
+/// injects claims into request extensions for use by route handlers.
 
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpMessage, HttpResponse,
+    web, Error, HttpMessage, HttpResponse,
 };
 use futures::future::LocalBoxFuture;
+use sqlx::PgPool;
 use std::rc::Rc;
 
-use crate::auth::validate_access_token;
+use crate::auth::{authenticate_api_key, is_api_key, validate_access_token, JwtKeys};
 use crate::configuration::JwtSettings;
 
 /// JWT middleware for protecting routes
 ///
 /// Must be applied to routes that require authentication.
-/// Extracts and validates JWT from Authorization header.
+/// Extracts and validates the bearer credential from the Authorization
+/// header: either a JWT access token or an API key (`z2p_...`), which is
+/// looked up in the database and yields claims limited to its scopes.
 pub struct JwtMiddleware {
     jwt_config: JwtSettings,
+    jwt_keys: web::Data<JwtKeys>,
 }
 
 impl JwtMiddleware {
     /// Create new JWT middleware instance
-    pub fn new(jwt_config: JwtSettings) -> Self {
-        Self { jwt_config }
+    pub fn new(jwt_config: JwtSettings, jwt_keys: web::Data<JwtKeys>) -> Self {
+        Self { jwt_config, jwt_keys }
     }
 }
 
@@ -44,6 +48,7 @@ where
         std::future::ready(Ok(JwtMiddlewareService {
             service: Rc::new(service),
             jwt_config: self.jwt_config.clone(),
+            jwt_keys: self.jwt_keys.clone(),
         }))
     }
 }
@@ -51,6 +56,7 @@ where
 pub struct JwtMiddlewareService<S> {
     service: Rc<S>,
     jwt_config: JwtSettings,
+    jwt_keys: web::Data<JwtKeys>,
 }
 
 impl<S, B> Service<ServiceRequest> for JwtMiddlewareService<S>
@@ -96,8 +102,31 @@ where
                     .into())
                 })
             }
+            Some(token) if is_api_key(&token) => {
+                let Some(pool) = req.app_data::<web::Data<PgPool>>().cloned() else {
+                    tracing::error!("API key authentication requires a database pool");
+                    return Box::pin(async move {
+                        Err(actix_web::error::ErrorInternalServerError("Internal server error"))
+                    });
+                };
+                let service = self.service.clone();
+
+                Box::pin(async move {
+                    match authenticate_api_key(pool.get_ref(), &token, &jwt_config.issuer).await {
+                        Ok(claims) => {
+                            tracing::debug!(user_id = %claims.sub, "API key validated successfully");
+                            req.extensions_mut().insert(claims);
+                            service.call(req).await
+                        }
+                        Err(e) => {
+                            tracing::warn!("API key validation failed: {}", e);
+                            Err(invalid_token_error())
+                        }
+                    }
+                })
+            }
             Some(token) => {
-                match validate_access_token(&token, &jwt_config) {
+                match validate_access_token(&token, &jwt_config, &self.jwt_keys) {
                     Ok(claims) => {
                         // Inject claims into request extensions
                         req.extensions_mut().insert(claims.clone());
@@ -113,20 +142,19 @@ where
                     }
                     Err(e) => {
                         tracing::warn!("JWT validation failed: {}", e);
-                        let response = HttpResponse::Unauthorized().json(serde_json::json!({
-                            "error": "Invalid or expired token",
-                            "code": "TOKEN_INVALID"
-                        }));
-                        Box::pin(async move {
-                            Err(actix_web::error::InternalError::from_response(
-                                "Invalid token",
-                                response,
-                            )
-                            .into())
-                        })
+                        Box::pin(async move { Err(invalid_token_error()) })
                     }
                 }
             }
         }
     }
 }
+
+/// 401 response shared by invalid JWTs and API keys
+fn invalid_token_error() -> Error {
+    let response = HttpResponse::Unauthorized().json(serde_json::json!({
+        "error": "Invalid or expired token",
+        "code": "TOKEN_INVALID"
+    }));
+    actix_web::error::InternalError::from_response("Invalid token", response).into()
+}
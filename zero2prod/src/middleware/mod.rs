@@ -2,6 +2,18 @@
 ///
 /// Custom middleware for authentication, logging, and other concerns.
 
+mod cors;
+mod csrf;
 mod jwt_middleware;
+mod payload_limit;
+mod rate_limit;
+mod security_headers;
 
+pub use cors::{CorsMiddleware, CorsPolicies};
+pub use csrf::CsrfMiddleware;
 pub use jwt_middleware::JwtMiddleware;
+pub use payload_limit::{PayloadLimit, PayloadLimitMiddleware, PayloadLimits};
+pub use rate_limit::{
+    run_rate_limit_cleanup_worker, ClientIp, RateLimitMiddleware, RateLimiter,
+};
+pub use security_headers::{CspNonce, SecurityHeadersMiddleware};
@@ -0,0 +1,234 @@
+//! Payload Limit Middleware
+//!
+//! Caps request bodies per route. A `Content-Length` over the route's limit
+//! is refused with 413 before any of the body is read; bodies without one
+//! (chunked) fail with an overflow once they grow past it. Extractors report
+//! the overflow through `json_config`/`form_config`, so every oversized
+//! request gets the same `PAYLOAD_TOO_LARGE` error.
+
+use actix_web::{
+    dev::{self, forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::{InternalError, JsonPayloadError, PayloadError, UrlencodedError},
+    http::header,
+    web, Error, HttpMessage, HttpRequest, ResponseError,
+};
+use futures::future::LocalBoxFuture;
+use futures::StreamExt;
+use std::rc::Rc;
+
+use crate::configuration::RequestLimitSettings;
+use crate::error::{AppError, ValidationError};
+use crate::security::path_in_scope;
+
+/// Body size limit of the current request, in bytes
+#[derive(Clone, Copy, Debug)]
+pub struct PayloadLimit(pub usize);
+
+impl PayloadLimit {
+    /// Error for a body over the current request's limit
+    pub fn exceeded(req: &HttpRequest) -> AppError {
+        let limit = req
+            .extensions()
+            .get::<PayloadLimit>()
+            .map(|limit| limit.0)
+            .unwrap_or_default();
+        AppError::Validation(ValidationError::PayloadTooLarge(limit))
+    }
+}
+
+struct RouteLimit {
+    path_prefix: String,
+    max_bytes: usize,
+}
+
+/// Body size limits by path prefix
+pub struct PayloadLimits {
+    default_max_bytes: usize,
+    routes: Vec<RouteLimit>,
+}
+
+impl PayloadLimits {
+    pub fn from_settings(settings: &RequestLimitSettings) -> Self {
+        Self {
+            default_max_bytes: settings.default_max_bytes,
+            routes: settings
+                .routes
+                .iter()
+                .map(|route| RouteLimit {
+                    path_prefix: route.path_prefix.trim_end_matches('/').to_string(),
+                    max_bytes: route.max_bytes,
+                })
+                .collect(),
+        }
+    }
+
+    /// Limit for requests to `path`; the first matching route applies
+    pub fn limit_for(&self, path: &str) -> usize {
+        self.routes
+            .iter()
+            .find(|route| path_in_scope(path, &route.path_prefix))
+            .map(|route| route.max_bytes)
+            .unwrap_or(self.default_max_bytes)
+    }
+
+    /// The largest limit of any route
+    fn max_bytes(&self) -> usize {
+        self.routes
+            .iter()
+            .map(|route| route.max_bytes)
+            .fold(self.default_max_bytes, usize::max)
+    }
+
+    /// `JsonConfig` reporting overflows as `PAYLOAD_TOO_LARGE`
+    ///
+    /// Its own limit is the largest route limit; the middleware enforces
+    /// the one for each route.
+    pub fn json_config(&self) -> web::JsonConfig {
+        web::JsonConfig::default()
+            .limit(self.max_bytes())
+            .error_handler(|err, req| match err {
+                JsonPayloadError::Overflow { .. }
+                | JsonPayloadError::OverflowKnownLength { .. }
+                | JsonPayloadError::Payload(PayloadError::Overflow) => {
+                    PayloadLimit::exceeded(req).into()
+                }
+                err => err.into(),
+            })
+    }
+
+    /// `FormConfig` reporting overflows as `PAYLOAD_TOO_LARGE`
+    pub fn form_config(&self) -> web::FormConfig {
+        web::FormConfig::default()
+            .limit(self.max_bytes())
+            .error_handler(|err, req| match err {
+                UrlencodedError::Overflow { .. }
+                | UrlencodedError::Payload(PayloadError::Overflow) => {
+                    PayloadLimit::exceeded(req).into()
+                }
+                err => err.into(),
+            })
+    }
+}
+
+/// Middleware enforcing `PayloadLimits`
+pub struct PayloadLimitMiddleware {
+    limits: web::Data<PayloadLimits>,
+}
+
+impl PayloadLimitMiddleware {
+    pub fn new(limits: web::Data<PayloadLimits>) -> Self {
+        Self { limits }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for PayloadLimitMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = PayloadLimitMiddlewareService<S>;
+    type Future = std::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        std::future::ready(Ok(PayloadLimitMiddlewareService {
+            service: Rc::new(service),
+            limits: self.limits.clone(),
+        }))
+    }
+}
+
+pub struct PayloadLimitMiddlewareService<S> {
+    service: Rc<S>,
+    limits: web::Data<PayloadLimits>,
+}
+
+impl<S, B> Service<ServiceRequest> for PayloadLimitMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let limit = self.limits.limit_for(req.path());
+        req.extensions_mut().insert(PayloadLimit(limit));
+
+        let content_length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.parse::<u64>().ok());
+        if content_length.is_some_and(|length| length > limit as u64) {
+            tracing::warn!(
+                path = %req.path(),
+                content_length = content_length,
+                limit = limit,
+                "Request body over the limit refused"
+            );
+            let error = PayloadLimit::exceeded(req.request());
+            let response = error.error_response();
+            return Box::pin(async move {
+                Err(InternalError::from_response(error, response).into())
+            });
+        }
+
+        let mut received = 0;
+        let payload = req.take_payload().map(move |chunk| {
+            let chunk = chunk?;
+            received += chunk.len();
+            if received > limit {
+                return Err(PayloadError::Overflow);
+            }
+            Ok(chunk)
+        });
+        req.set_payload(dev::Payload::Stream {
+            payload: Box::pin(payload),
+        });
+
+        let service = self.service.clone();
+        Box::pin(async move { service.call(req).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::RequestLimitRouteSettings;
+
+    fn limits() -> PayloadLimits {
+        PayloadLimits::from_settings(&RequestLimitSettings {
+            default_max_bytes: 16 * 1024,
+            routes: vec![
+                RequestLimitRouteSettings {
+                    path_prefix: "/subscriptions".to_string(),
+                    max_bytes: 1024,
+                },
+                RequestLimitRouteSettings {
+                    path_prefix: "/api/newsletters/".to_string(),
+                    max_bytes: 1024 * 1024,
+                },
+            ],
+        })
+    }
+
+    #[test]
+    fn test_limit_for_picks_the_first_matching_route() {
+        let limits = limits();
+
+        assert_eq!(1024, limits.limit_for("/subscriptions"));
+        assert_eq!(1024, limits.limit_for("/subscriptions/unsubscribe"));
+        assert_eq!(16 * 1024, limits.limit_for("/subscriptionsx"));
+        assert_eq!(1024 * 1024, limits.limit_for("/api/newsletters/send-all"));
+        assert_eq!(16 * 1024, limits.limit_for("/api/me"));
+        assert_eq!(1024 * 1024, limits.max_bytes());
+    }
+}
@@ -0,0 +1,463 @@
+//! Rate Limiting Middleware
+//!
+//! Applies the first configured policy matching a request's method and path
+//! (see `RateLimitSettings`), counting requests per client IP, user or API
+//! key. Limited responses carry `RateLimit-*` headers; rejected requests get
+//! 429 with `Retry-After`.
+//!
+//! Counters live in memory (token buckets per instance) or, with the
+//! `postgres` storage, in sliding window counters shared by every instance.
+//! A Postgres check that fails or takes longer than the configured timeout
+//! is counted in memory instead, so a struggling database doesn't stall or
+//! unprotect the application.
+//!
+//! The client IP worked out here (honouring trusted proxies) is stored in
+//! the request extensions as `ClientIp` for handlers to use.
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header::HeaderMap, header::HeaderName, header::HeaderValue, Method},
+    web, Error, HttpMessage, HttpRequest, HttpResponse,
+};
+use futures::future::LocalBoxFuture;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::auth::{is_api_key, validate_access_token, JwtKeys};
+use crate::configuration::{JwtSettings, RateLimitKey, RateLimitSettings, RateLimitStorage};
+use crate::error::{ConfigError, ErrorResponse};
+use crate::security::{
+    path_in_scope, sliding_window_decision, RateLimitConfig, RateLimitExceeded, RateLimitStatus,
+    RateLimiterManager, TrustedProxies, RATE_LIMIT_WINDOW_SECONDS,
+};
+
+/// How often expired Postgres counters are deleted
+const COUNTER_CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Client IP address of the request, behind trusted proxies if any
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+impl ClientIp {
+    /// The client IP stored by the rate limiter, else the peer address
+    pub fn of(req: &HttpRequest) -> Option<IpAddr> {
+        req.extensions()
+            .get::<ClientIp>()
+            .map(|client_ip| client_ip.0)
+            .or_else(|| req.peer_addr().map(|addr| addr.ip()))
+    }
+}
+
+struct RateLimitPolicy {
+    name: String,
+    path_prefix: String,
+    methods: Vec<Method>,
+    key: RateLimitKey,
+    limiter: RateLimiterManager,
+}
+
+impl RateLimitPolicy {
+    fn matches(&self, method: &Method, path: &str) -> bool {
+        path_in_scope(path, self.path_prefix.trim_end_matches('/'))
+            && (self.methods.is_empty() || self.methods.contains(method))
+    }
+}
+
+struct PostgresStorage {
+    pool: PgPool,
+    timeout: Duration,
+}
+
+/// Rate limit policies with their counters, shared by all workers
+pub struct RateLimiter {
+    enabled: bool,
+    trusted_proxies: TrustedProxies,
+    policies: Vec<RateLimitPolicy>,
+    /// Set with the `postgres` storage; the in-memory buckets are the fallback
+    postgres: Option<PostgresStorage>,
+}
+
+impl RateLimiter {
+    pub fn from_settings(settings: &RateLimitSettings, pool: &PgPool) -> Result<Self, ConfigError> {
+        let trusted_proxies = TrustedProxies::parse(&settings.trusted_proxies)?;
+
+        let policies = settings
+            .policies
+            .iter()
+            .map(|policy| {
+                if policy.requests_per_minute == 0 {
+                    return Err(ConfigError::InvalidValue(format!(
+                        "rate_limit.policies.{}: requests_per_minute must be positive",
+                        policy.name
+                    )));
+                }
+                if !policy.path_prefix.starts_with('/') {
+                    return Err(ConfigError::InvalidValue(format!(
+                        "rate_limit.policies.{}: path_prefix must start with '/'",
+                        policy.name
+                    )));
+                }
+                let methods = policy
+                    .methods
+                    .iter()
+                    .map(|method| {
+                        Method::from_str(&method.to_uppercase()).map_err(|_| {
+                            ConfigError::InvalidValue(format!(
+                                "rate_limit.policies.{}: unknown method '{}'",
+                                policy.name, method
+                            ))
+                        })
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                Ok(RateLimitPolicy {
+                    name: policy.name.clone(),
+                    path_prefix: policy.path_prefix.clone(),
+                    methods,
+                    key: policy.key,
+                    limiter: RateLimiterManager::new(RateLimitConfig {
+                        requests_per_minute: policy.requests_per_minute,
+                    }),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let postgres = match settings.storage {
+            RateLimitStorage::Memory => None,
+            RateLimitStorage::Postgres => Some(PostgresStorage {
+                pool: pool.clone(),
+                timeout: Duration::from_millis(settings.storage_timeout_milliseconds),
+            }),
+        };
+
+        Ok(Self {
+            enabled: settings.enabled,
+            trusted_proxies,
+            policies,
+            postgres,
+        })
+    }
+
+    /// Whether counters are kept in Postgres
+    pub fn uses_postgres(&self) -> bool {
+        self.postgres.is_some()
+    }
+
+    /// Proxies whose forwarding headers are honoured
+    pub fn trusted_proxies(&self) -> &TrustedProxies {
+        &self.trusted_proxies
+    }
+
+    fn policy_for(&self, method: &Method, path: &str) -> Option<usize> {
+        if !self.enabled {
+            return None;
+        }
+        self.policies.iter().position(|policy| policy.matches(method, path))
+    }
+
+    async fn check(
+        &self,
+        policy: &RateLimitPolicy,
+        key: &str,
+    ) -> Result<RateLimitStatus, RateLimitExceeded> {
+        let Some(postgres) = &self.postgres else {
+            return policy.limiter.check_rate_limit(key);
+        };
+
+        let limit = policy.limiter.requests_per_minute();
+        let check = check_postgres(&postgres.pool, &policy.name, key, limit);
+        match tokio::time::timeout(postgres.timeout, check).await {
+            Ok(Ok(decision)) => decision,
+            Ok(Err(e)) => {
+                tracing::warn!(error = %e, "Rate limit storage failed; counting locally");
+                policy.limiter.check_rate_limit(key)
+            }
+            Err(_) => {
+                tracing::warn!("Rate limit storage timed out; counting locally");
+                policy.limiter.check_rate_limit(key)
+            }
+        }
+    }
+}
+
+/// Count the request in the current window and read the previous one
+///
+/// Windows are aligned on the database clock so that every instance agrees
+/// on them.
+async fn check_postgres(
+    pool: &PgPool,
+    policy: &str,
+    key: &str,
+    limit: u32,
+) -> Result<Result<RateLimitStatus, RateLimitExceeded>, sqlx::Error> {
+    let (current, previous, elapsed) = sqlx::query_as::<_, (i64, i64, f64)>(
+        r#"
+        WITH clock AS (
+            SELECT extract(epoch FROM clock_timestamp())::float8 AS epoch
+        ),
+        bucket AS (
+            SELECT to_timestamp(floor(epoch / $3) * $3) AS start,
+                   epoch - floor(epoch / $3) * $3 AS elapsed
+            FROM clock
+        ),
+        current AS (
+            INSERT INTO rate_limit_counters (policy, key, window_start, count)
+            SELECT $1, $2, start, 1 FROM bucket
+            ON CONFLICT (policy, key, window_start)
+            DO UPDATE SET count = rate_limit_counters.count + 1
+            RETURNING count
+        )
+        SELECT
+            (SELECT count FROM current),
+            COALESCE((
+                SELECT c.count FROM rate_limit_counters c, bucket
+                WHERE c.policy = $1 AND c.key = $2
+                  AND c.window_start = bucket.start - make_interval(secs => $3)
+            ), 0),
+            (SELECT elapsed FROM bucket)
+        "#,
+    )
+    .bind(policy)
+    .bind(key)
+    .bind(RATE_LIMIT_WINDOW_SECONDS)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(sliding_window_decision(
+        limit,
+        previous.max(0) as u64,
+        current.max(0) as u64,
+        elapsed,
+    ))
+}
+
+/// Delete Postgres counters too old to affect any decision
+pub async fn run_rate_limit_cleanup_worker(pool: PgPool) {
+    let mut interval = tokio::time::interval(COUNTER_CLEANUP_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        let result = sqlx::query(
+            r#"
+            DELETE FROM rate_limit_counters
+            WHERE window_start < now() - make_interval(secs => $1)
+            "#,
+        )
+        .bind(2.0 * RATE_LIMIT_WINDOW_SECONDS)
+        .execute(&pool)
+        .await;
+        if let Err(e) = result {
+            tracing::error!(error = %e, "Rate limit counter cleanup failed");
+        }
+    }
+}
+
+/// Middleware enforcing the `RateLimiter` policies
+pub struct RateLimitMiddleware {
+    limiter: web::Data<RateLimiter>,
+}
+
+impl RateLimitMiddleware {
+    pub fn new(limiter: web::Data<RateLimiter>) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimitMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddlewareService<S>;
+    type Future = std::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        std::future::ready(Ok(RateLimitMiddlewareService {
+            service: Rc::new(service),
+            limiter: self.limiter.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddlewareService<S> {
+    service: Rc<S>,
+    limiter: web::Data<RateLimiter>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let forwarded_for = req
+            .headers()
+            .get("X-Forwarded-For")
+            .and_then(|h| h.to_str().ok());
+        let client_ip = self
+            .limiter
+            .trusted_proxies
+            .client_ip(req.peer_addr().map(|addr| addr.ip()), forwarded_for);
+        if let Some(ip) = client_ip {
+            req.extensions_mut().insert(ClientIp(ip));
+        }
+
+        let service = self.service.clone();
+        let Some(policy_index) = self.limiter.policy_for(req.method(), req.path()) else {
+            return Box::pin(async move { service.call(req).await });
+        };
+
+        let limiter = self.limiter.clone();
+        let key = rate_limit_key(&req, limiter.policies[policy_index].key, client_ip);
+
+        Box::pin(async move {
+            let policy = &limiter.policies[policy_index];
+            match limiter.check(policy, &key).await {
+                Ok(status) => {
+                    let mut response = service.call(req).await?;
+                    insert_rate_limit_headers(response.headers_mut(), &status);
+                    Ok(response)
+                }
+                Err(exceeded) => {
+                    tracing::warn!(
+                        policy = %policy.name,
+                        key = %key,
+                        path = %req.path(),
+                        "Rate limit exceeded"
+                    );
+                    Err(rate_limited_error(&exceeded))
+                }
+            }
+        })
+    }
+}
+
+/// Bucket key for the request; credentials are hashed rather than stored
+fn rate_limit_key(req: &ServiceRequest, key: RateLimitKey, client_ip: Option<IpAddr>) -> String {
+    let bearer = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+
+    if let Some(token) = bearer {
+        if key == RateLimitKey::ApiKey && is_api_key(token) {
+            return format!("api_key:{:x}", Sha256::digest(token.as_bytes()));
+        }
+        if key != RateLimitKey::Ip && !is_api_key(token) {
+            let jwt_config = req.app_data::<web::Data<JwtSettings>>();
+            let jwt_keys = req.app_data::<web::Data<JwtKeys>>();
+            if let (Some(jwt_config), Some(jwt_keys)) = (jwt_config, jwt_keys) {
+                if let Ok(claims) = validate_access_token(token, jwt_config, jwt_keys) {
+                    return format!("user:{}", claims.sub);
+                }
+            }
+        }
+    }
+
+    match client_ip {
+        Some(ip) => format!("ip:{}", ip),
+        None => "ip:unknown".to_string(),
+    }
+}
+
+fn insert_rate_limit_headers(headers: &mut HeaderMap, status: &RateLimitStatus) {
+    let values = [
+        ("ratelimit-limit", status.limit.to_string()),
+        ("ratelimit-remaining", status.remaining.to_string()),
+        ("ratelimit-reset", status.reset_seconds.to_string()),
+        ("ratelimit-policy", format!("{};w=60", status.limit)),
+    ];
+    for (name, value) in values {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(HeaderName::from_static(name), value);
+        }
+    }
+}
+
+/// 429 response with the quota headers and `Retry-After`
+fn rate_limited_error(exceeded: &RateLimitExceeded) -> Error {
+    let body = ErrorResponse::new(
+        uuid::Uuid::new_v4().to_string(),
+        format!(
+            "Too many requests. Try again in {} seconds.",
+            exceeded.retry_after_seconds
+        ),
+        "RATE_LIMITED".to_string(),
+        429,
+    );
+    let mut response = HttpResponse::TooManyRequests()
+        .insert_header(("Retry-After", exceeded.retry_after_seconds.to_string()))
+        .json(body);
+    insert_rate_limit_headers(response.headers_mut(), &exceeded.status);
+    actix_web::error::InternalError::from_response("Rate limit exceeded", response).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::RateLimitPolicySettings;
+
+    fn settings(policies: Vec<RateLimitPolicySettings>) -> RateLimitSettings {
+        RateLimitSettings {
+            enabled: true,
+            storage: RateLimitStorage::Memory,
+            storage_timeout_milliseconds: 50,
+            trusted_proxies: Vec::new(),
+            policies,
+        }
+    }
+
+    /// Needs a runtime; the pool never connects
+    fn limiter(settings: &RateLimitSettings) -> Result<RateLimiter, ConfigError> {
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        RateLimiter::from_settings(settings, &pool)
+    }
+
+    fn policy(path_prefix: &str, methods: &[&str]) -> RateLimitPolicySettings {
+        RateLimitPolicySettings {
+            name: "test".to_string(),
+            path_prefix: path_prefix.to_string(),
+            methods: methods.iter().map(|m| m.to_string()).collect(),
+            requests_per_minute: 10,
+            key: RateLimitKey::Ip,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_policies_match_by_path_segment_and_method() {
+        let settings = settings(vec![policy("/auth/login", &["post"]), policy("/", &[])]);
+        let limiter = limiter(&settings).unwrap();
+        let prefix_of = |method: Method, path: &str| {
+            limiter
+                .policy_for(&method, path)
+                .map(|index| limiter.policies[index].path_prefix.clone())
+        };
+
+        assert_eq!(Some("/auth/login".to_string()), prefix_of(Method::POST, "/auth/login"));
+        assert_eq!(Some("/auth/login".to_string()), prefix_of(Method::POST, "/auth/login/mfa"));
+        assert_eq!(Some("/".to_string()), prefix_of(Method::POST, "/auth/loginx"));
+        assert_eq!(Some("/".to_string()), prefix_of(Method::GET, "/auth/login"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_policies_are_rejected() {
+        assert!(limiter(&settings(vec![policy("/", &["FETCH IT"])])).is_err());
+        assert!(limiter(&settings(vec![policy("auth", &[])])).is_err());
+    }
+}
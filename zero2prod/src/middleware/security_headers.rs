@@ -0,0 +1,235 @@
+//! Security Headers Middleware
+//!
+//! Adds the configured `SecurityHeaders` to every response, including
+//! errors raised by inner middleware. Each request gets a fresh CSP nonce
+//! (also available to handlers as `CspNonce`). HTML pages ask for it with
+//! `nonce="{{csp_nonce}}"` on the tags that need it; only that placeholder is
+//! filled in, and such pages are served without validators so a cached copy
+//! with an old nonce is never reused.
+
+use actix_web::{
+    body::{to_bytes, BoxBody, EitherBody, MessageBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::InternalError,
+    http::header::{self, HeaderMap, HeaderName, HeaderValue},
+    web, Error, HttpMessage,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use futures::future::LocalBoxFuture;
+use rand::RngCore;
+use std::rc::Rc;
+
+use crate::security::{SecurityHeaders, TrustedProxies};
+
+/// Written in HTML pages where the request's CSP nonce goes
+pub const CSP_NONCE_PLACEHOLDER: &str = "{{csp_nonce}}";
+
+/// CSP nonce of the current request
+#[derive(Clone, Debug)]
+pub struct CspNonce(pub String);
+
+/// Middleware applying `SecurityHeaders`
+pub struct SecurityHeadersMiddleware {
+    headers: web::Data<SecurityHeaders>,
+    trusted_proxies: TrustedProxies,
+}
+
+impl SecurityHeadersMiddleware {
+    /// `trusted_proxies` decides whose `X-Forwarded-Proto` counts as HTTPS
+    pub fn new(headers: web::Data<SecurityHeaders>, trusted_proxies: TrustedProxies) -> Self {
+        Self {
+            headers,
+            trusted_proxies,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for SecurityHeadersMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = SecurityHeadersMiddlewareService<S>;
+    type Future = std::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        std::future::ready(Ok(SecurityHeadersMiddlewareService {
+            service: Rc::new(service),
+            headers: self.headers.clone(),
+            trusted_proxies: self.trusted_proxies.clone(),
+        }))
+    }
+}
+
+pub struct SecurityHeadersMiddlewareService<S> {
+    service: Rc<S>,
+    headers: web::Data<SecurityHeaders>,
+    trusted_proxies: TrustedProxies,
+}
+
+impl<S, B> Service<ServiceRequest> for SecurityHeadersMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let nonce = generate_nonce();
+        req.extensions_mut().insert(CspNonce(nonce.clone()));
+
+        let headers = self
+            .headers
+            .headers_for(req.path(), &nonce, is_https(&req, &self.trusted_proxies));
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            let mut response = match service.call(req).await {
+                Ok(response) => response.map_into_left_body(),
+                Err(e) => {
+                    // Errors raised by inner middleware get the headers too
+                    let mut response = e.error_response();
+                    add_headers(response.headers_mut(), headers);
+                    return Err(InternalError::from_response(e, response).into());
+                }
+            };
+
+            if is_html(&response) {
+                response = fill_in_nonce(response, &nonce).await?;
+            }
+            add_headers(response.headers_mut(), headers);
+
+            Ok(response)
+        })
+    }
+}
+
+fn add_headers(response_headers: &mut HeaderMap, headers: Vec<(String, String)>) {
+    for (name, value) in headers {
+        let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::try_from(value))
+        else {
+            continue;
+        };
+        // Handlers may set their own, stricter values
+        if !response_headers.contains_key(&name) {
+            response_headers.insert(name, value);
+        }
+    }
+}
+
+fn generate_nonce() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Whether the request reached us over TLS, directly or via a trusted proxy
+fn is_https(req: &ServiceRequest, trusted_proxies: &TrustedProxies) -> bool {
+    if req.app_config().secure() {
+        return true;
+    }
+
+    let from_trusted_proxy = req
+        .peer_addr()
+        .is_some_and(|addr| trusted_proxies.is_trusted(&addr.ip()));
+    from_trusted_proxy
+        && req
+            .headers()
+            .get("X-Forwarded-Proto")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.split(',').next())
+            .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("https"))
+}
+
+fn is_html<B>(response: &ServiceResponse<B>) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"))
+}
+
+/// Replace `CSP_NONCE_PLACEHOLDER` in an HTML page; pages without it are
+/// passed on as they are
+async fn fill_in_nonce<B>(
+    response: ServiceResponse<EitherBody<B>>,
+    nonce: &str,
+) -> Result<ServiceResponse<EitherBody<B>>, Error>
+where
+    B: MessageBody + 'static,
+{
+    let (request, response) = response.into_parts();
+    let (mut response, body) = response.into_parts();
+
+    let body = to_bytes(body)
+        .await
+        .map_err(|_| actix_web::error::ErrorInternalServerError("Failed to read page"))?;
+    let page = match std::str::from_utf8(&body) {
+        Ok(page) if page.contains(CSP_NONCE_PLACEHOLDER) => {
+            page.replace(CSP_NONCE_PLACEHOLDER, nonce)
+        }
+        _ => {
+            let response = response.set_body(EitherBody::right(BoxBody::new(body)));
+            return Ok(ServiceResponse::new(request, response));
+        }
+    };
+
+    let headers = response.headers_mut();
+    headers.remove(header::CONTENT_LENGTH);
+    headers.remove(header::ETAG);
+    headers.remove(header::LAST_MODIFIED);
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+
+    let response = response.set_body(EitherBody::right(BoxBody::new(page)));
+    Ok(ServiceResponse::new(request, response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn filled_in(html: &'static str) -> (String, bool) {
+        let response = actix_web::HttpResponse::Ok()
+            .content_type("text/html")
+            .insert_header((header::ETAG, "\"v1\""))
+            .body(html);
+        let response = actix_web::test::TestRequest::default()
+            .to_srv_response(response)
+            .map_into_left_body();
+
+        let response = fill_in_nonce(response, "n0nce").await.unwrap();
+        let has_etag = response.headers().contains_key(header::ETAG);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        (String::from_utf8(body.to_vec()).unwrap(), has_etag)
+    }
+
+    #[tokio::test]
+    async fn test_only_the_nonce_placeholder_is_filled_in() {
+        let (page, has_etag) =
+            filled_in(r#"<script nonce="{{csp_nonce}}" src="/app.js"></script><script>x()</script>"#)
+                .await;
+        assert_eq!(r#"<script nonce="n0nce" src="/app.js"></script><script>x()</script>"#, page);
+        assert!(!has_etag);
+
+        let (page, has_etag) = filled_in("<style>a{}</style><script>x()</script>").await;
+        assert_eq!("<style>a{}</style><script>x()</script>", page);
+        assert!(has_etag);
+    }
+
+    #[test]
+    fn test_nonces_differ_per_request() {
+        let nonce = generate_nonce();
+        assert_eq!(22, nonce.len());
+        assert_ne!(nonce, generate_nonce());
+    }
+}
@@ -0,0 +1,174 @@
+//! Preference Links
+//!
+//! Signed, expiring links to the subscriber preference center. Every email
+//! sent to a subscriber carries one, so subscribers can manage their
+//! subscription without an account.
+//!
+//! Token format: `{subscriber id}.{expiry unix timestamp}.{signature}`, where
+//! the signature is an HMAC-SHA256 over the first two parts.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::configuration::SubscriptionSettings;
+use crate::error::{AppError, AuthError, ConfigError};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Shortest accepted signing secret, in bytes
+const MIN_SECRET_LEN: usize = 32;
+
+/// Builds and verifies preference center links
+#[derive(Clone)]
+pub struct PreferenceLinks {
+    secret: Vec<u8>,
+    expiry: Duration,
+    base_url: String,
+    topics: Vec<String>,
+}
+
+impl PreferenceLinks {
+    pub fn new(settings: &SubscriptionSettings, base_url: &str) -> Result<Self, ConfigError> {
+        if settings.preference_link_secret.len() < MIN_SECRET_LEN {
+            return Err(ConfigError::InvalidValue(format!(
+                "subscriptions.preference_link_secret must be at least {} bytes",
+                MIN_SECRET_LEN
+            )));
+        }
+        if settings.preference_link_expiry_days < 1 {
+            return Err(ConfigError::InvalidValue(
+                "subscriptions.preference_link_expiry_days must be at least 1".to_string(),
+            ));
+        }
+        if settings.topics.is_empty() {
+            return Err(ConfigError::MissingRequired("subscriptions.topics".to_string()));
+        }
+
+        Ok(Self {
+            secret: settings.preference_link_secret.as_bytes().to_vec(),
+            expiry: Duration::days(settings.preference_link_expiry_days),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            topics: settings.topics.clone(),
+        })
+    }
+
+    /// Topics subscribers can opt out of
+    pub fn topics(&self) -> &[String] {
+        &self.topics
+    }
+
+    /// Create a token for a subscriber, valid for the configured period
+    pub fn token(&self, subscriber_id: Uuid) -> String {
+        let expires_at = (Utc::now() + self.expiry).timestamp();
+        let payload = format!("{}.{}", subscriber_id.simple(), expires_at);
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&payload).finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    /// Check a token's signature and expiry
+    ///
+    /// # Returns
+    /// The subscriber the token was issued for
+    ///
+    /// # Errors
+    /// - `AuthError::TokenInvalid`: Malformed or tampered token
+    /// - `AuthError::TokenExpired`: Token is past its expiry
+    pub fn verify(&self, token: &str) -> Result<Uuid, AppError> {
+        let invalid = || AppError::Auth(AuthError::TokenInvalid);
+
+        let (payload, signature) = token.rsplit_once('.').ok_or_else(invalid)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+        self.mac(payload)
+            .verify_slice(&signature)
+            .map_err(|_| invalid())?;
+
+        let (subscriber_id, expires_at) = payload.split_once('.').ok_or_else(invalid)?;
+        let subscriber_id = Uuid::parse_str(subscriber_id).map_err(|_| invalid())?;
+        let expires_at: i64 = expires_at.parse().map_err(|_| invalid())?;
+        if Utc::now().timestamp() > expires_at {
+            return Err(AppError::Auth(AuthError::TokenExpired));
+        }
+
+        Ok(subscriber_id)
+    }
+
+    /// Preference center page for a subscriber
+    pub fn url(&self, subscriber_id: Uuid) -> String {
+        format!("{}/preferences.html?token={}", self.base_url, self.token(subscriber_id))
+    }
+
+    /// Footer appended to every email sent to a subscriber
+    pub fn email_footer(&self, subscriber_id: Uuid) -> String {
+        format!(
+            r#"
+        <hr>
+        <p style="font-size: 12px; color: #666;">
+            <a href="{}">Manage your subscription preferences or unsubscribe</a>
+        </p>
+        "#,
+            self.url(subscriber_id)
+        )
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(expiry_days: i64) -> PreferenceLinks {
+        PreferenceLinks {
+            secret: b"test-preference-link-secret-32-bytes!!".to_vec(),
+            expiry: Duration::days(expiry_days),
+            base_url: "http://localhost:8000".to_string(),
+            topics: vec!["general".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_token_round_trip() {
+        let links = links(90);
+        let subscriber_id = Uuid::new_v4();
+        assert_eq!(links.verify(&links.token(subscriber_id)).unwrap(), subscriber_id);
+    }
+
+    #[test]
+    fn test_tampered_token_is_rejected() {
+        let links = links(90);
+        let token = links.token(Uuid::new_v4());
+        let (_, rest) = token.split_once('.').unwrap();
+        let forged = format!("{}.{}", Uuid::new_v4().simple(), rest);
+        assert!(matches!(
+            links.verify(&forged),
+            Err(AppError::Auth(AuthError::TokenInvalid))
+        ));
+        assert!(links.verify("not-a-token").is_err());
+    }
+
+    #[test]
+    fn test_expired_token_is_rejected() {
+        let links = links(-1);
+        let token = links.token(Uuid::new_v4());
+        assert!(matches!(
+            links.verify(&token),
+            Err(AppError::Auth(AuthError::TokenExpired))
+        ));
+    }
+
+    #[test]
+    fn test_token_from_other_secret_is_rejected() {
+        let mut other = links(90);
+        other.secret = b"another-preference-link-secret-32-bytes".to_vec();
+        assert!(links(90).verify(&other.token(Uuid::new_v4())).is_err());
+    }
+}
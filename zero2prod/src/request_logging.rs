@@ -9,6 +9,7 @@
 
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use sqlx::PgPool;
 use std::collections::HashMap;
 use uuid::Uuid;
 
@@ -486,6 +487,44 @@ impl RequestFailureLogger {
         }
     }
 
+    /// 감사 로그 기록 후 데이터베이스(audit_logs)에 저장
+    ///
+    /// 저장에 실패해도 요청은 실패시키지 않고 경고만 남깁니다.
+    pub async fn record_audit(pool: &PgPool, audit_log: &AuditLog) {
+        Self::log_audit(audit_log);
+
+        let id = Uuid::parse_str(&audit_log.log_id).unwrap_or_else(|_| Uuid::new_v4());
+        let result = sqlx::query(
+            r#"
+            INSERT INTO audit_logs (
+                id, created_at, action, resource_type, resource_id, user_id,
+                status, message, previous_state, new_state
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+        )
+        .bind(id)
+        .bind(audit_log.timestamp)
+        .bind(&audit_log.action)
+        .bind(&audit_log.resource_type)
+        .bind(&audit_log.resource_id)
+        .bind(&audit_log.user_id)
+        .bind(&audit_log.status)
+        .bind(&audit_log.message)
+        .bind(&audit_log.previous_state)
+        .bind(&audit_log.new_state)
+        .execute(pool)
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!(
+                log_id = %audit_log.log_id,
+                error = %e,
+                "Failed to store audit log entry"
+            );
+        }
+    }
+
     /// 실패 요청 통계 로그
     pub fn log_statistics(stats: &FailureStatistics) {
         tracing::warn!(
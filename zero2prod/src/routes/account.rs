@@ -0,0 +1,346 @@
+//! Account Management Routes
+//!
+//! Lets authenticated users change their password and email address.
+//! Every change is recorded as an audit log entry and revokes the user's
+//! other sessions.
+
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::{
+    confirm_email_change_token, create_email_change_token, generate_access_token,
+    generate_refresh_token, hash_password, require_verified_email, revoke_all_user_tokens,
+    save_refresh_token, verify_password, Claims, JwtKeys, PasswordPolicy,
+};
+use crate::configuration::{AuthSettings, JwtSettings};
+use crate::email_client::EmailClient;
+use crate::error::{AppError, AuthError, DatabaseError, ErrorContext, ValidationError};
+use crate::request_logging::{AuditLog, RequestFailureLogger};
+use crate::routes::auth::AuthResponse;
+use crate::startup::ApplicationBaseUrl;
+use crate::validators::{canonical_email, is_valid_email};
+
+/// Password change request
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// Email change request
+#[derive(Deserialize)]
+pub struct ChangeEmailRequest {
+    pub new_email: String,
+    pub current_password: String,
+}
+
+/// Email change confirmation query
+#[derive(Deserialize)]
+pub struct ConfirmEmailChangeQuery {
+    pub token: String,
+}
+
+/// POST /api/me/password
+///
+/// Change the authenticated user's password.
+/// **Requires valid JWT access token** in Authorization header.
+///
+/// All existing refresh tokens are revoked and a fresh token pair is returned,
+/// so the calling client stays signed in while every other session is logged out.
+///
+/// # Errors
+/// - 400: New password fails strength validation, is breached or too easy
+///   to guess, or equals the current one
+/// - 401: Current password is wrong
+/// - 403: Email is unverified and the policy blocks sensitive actions, or
+///   the request used an API key
+/// - 500: Internal server error
+pub async fn change_password(
+    claims: web::ReqData<Claims>,
+    form: web::Json<ChangePasswordRequest>,
+    pool: web::Data<PgPool>,
+    jwt_config: web::Data<JwtSettings>,
+    jwt_keys: web::Data<JwtKeys>,
+    auth_settings: web::Data<AuthSettings>,
+    password_policy: web::Data<PasswordPolicy>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("password_change");
+    claims.require_session()?;
+    let user_id = claims.user_id()?;
+    require_verified_email(pool.get_ref(), user_id, auth_settings.get_ref()).await?;
+
+    let email = verify_current_password(pool.get_ref(), user_id, &form.current_password, "CHANGE_PASSWORD")
+        .await?;
+
+    if form.current_password == form.new_password {
+        return Err(AppError::Validation(ValidationError::InvalidFormat(
+            "new password must differ from the current password".to_string(),
+        )));
+    }
+
+    let name: String = sqlx::query_scalar("SELECT name FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool.get_ref())
+        .await?;
+    password_policy.check(&form.new_password, &[&email, &name]).await?;
+
+    let password_hash = hash_password(&form.new_password, &auth_settings.password_hashing).await?;
+
+    sqlx::query("UPDATE users SET password_hash = $1, updated_at = $2 WHERE id = $3")
+        .bind(&password_hash)
+        .bind(Utc::now())
+        .bind(user_id)
+        .execute(pool.get_ref())
+        .await?;
+
+    // Log out every other session, then issue a new pair for this client
+    revoke_all_user_tokens(pool.get_ref(), user_id).await?;
+
+    let access_token =
+        generate_access_token(&user_id, &email, jwt_config.get_ref(), jwt_keys.get_ref())?;
+    let refresh_token = generate_refresh_token();
+    save_refresh_token(
+        pool.get_ref(),
+        user_id,
+        &refresh_token,
+        jwt_config.refresh_token_expiry,
+    )
+    .await?;
+
+    let audit_log = AuditLog::new(
+        "CHANGE_PASSWORD".to_string(),
+        "user".to_string(),
+        "SUCCESS".to_string(),
+        "Password changed and other sessions revoked".to_string(),
+    )
+    .with_resource_id(user_id.to_string())
+    .with_user_id(user_id.to_string());
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        "Password changed successfully"
+    );
+
+    Ok(AuthResponse::issue(
+        HttpResponse::Ok(),
+        &auth_settings.session,
+        jwt_config.get_ref(),
+        access_token,
+        refresh_token,
+    ))
+}
+
+/// POST /api/me/email
+///
+/// Request a change of the authenticated user's email address.
+/// **Requires valid JWT access token** in Authorization header.
+///
+/// A confirmation link is sent to the new address; `users.email` is only
+/// updated once that link is followed (see `confirm_email_change`).
+///
+/// # Errors
+/// - 400: New email is invalid or equals the current one
+/// - 401: Current password is wrong
+/// - 403: Email is unverified and the policy blocks sensitive actions, or
+///   the request used an API key
+/// - 409: New email is already registered
+/// - 503: Confirmation email could not be sent
+pub async fn change_email(
+    claims: web::ReqData<Claims>,
+    form: web::Json<ChangeEmailRequest>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("email_change_request");
+    claims.require_session()?;
+    let user_id = claims.user_id()?;
+    require_verified_email(pool.get_ref(), user_id, auth_settings.get_ref()).await?;
+
+    let new_email = is_valid_email(&form.new_email)?;
+    if !email_client.can_deliver_to(&new_email) {
+        return Err(AppError::Validation(ValidationError::InvalidFormat("email".to_string())));
+    }
+    let current_email =
+        verify_current_password(pool.get_ref(), user_id, &form.current_password, "REQUEST_EMAIL_CHANGE")
+            .await?;
+
+    if canonical_email(&new_email) == canonical_email(&current_email) {
+        return Err(AppError::Validation(ValidationError::InvalidFormat(
+            "new email must differ from the current email".to_string(),
+        )));
+    }
+
+    // Fail early instead of sending a link that can never be confirmed
+    let email_taken = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM users WHERE email_canonical = $1)",
+    )
+    .bind(canonical_email(&new_email))
+    .fetch_one(pool.get_ref())
+    .await?;
+    if email_taken {
+        return Err(AppError::Database(
+            DatabaseError::UniqueConstraintViolation(
+                "Email already registered".to_string(),
+            ),
+        ));
+    }
+
+    let token = create_email_change_token(pool.get_ref(), user_id, &new_email).await?;
+
+    let confirmation_link = format!("{}/auth/confirm-email?token={}", base_url.0, token);
+    let html_content = format!(
+        r#"
+        <h1>Confirm your new email address</h1>
+        <p>We received a request to change the email address of your account.</p>
+        <a href="{}">Confirm Email Change</a>
+        <p>This link will expire in 24 hours. If you did not request this change, you can ignore this email.</p>
+        "#,
+        confirmation_link
+    );
+
+    email_client
+        .send_email(&new_email, "Confirm your new email address", &html_content)
+        .await
+        .map_err(|e| {
+            let error = AppError::Email(e);
+            context.log_error(&error);
+            error
+        })?;
+
+    let audit_log = AuditLog::new(
+        "REQUEST_EMAIL_CHANGE".to_string(),
+        "user".to_string(),
+        "SUCCESS".to_string(),
+        "Email change requested, confirmation sent to new address".to_string(),
+    )
+    .with_resource_id(user_id.to_string())
+    .with_user_id(user_id.to_string());
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        "Email change confirmation sent"
+    );
+
+    Ok(HttpResponse::Accepted().json(serde_json::json!({
+        "message": "Confirmation email sent to the new address",
+        "request_id": context.request_id
+    })))
+}
+
+/// GET /auth/confirm-email?token=
+///
+/// Confirm a pending email change from the link sent to the new address.
+/// Swaps `users.email`, updates `updated_at` and revokes all sessions,
+/// so the user must log in again with the new address.
+///
+/// # Errors
+/// - 400: Token is invalid or expired
+/// - 409: New email was registered by someone else in the meantime
+/// - 500: Internal server error
+pub async fn confirm_email_change(
+    query: web::Query<ConfirmEmailChangeQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("email_change_confirmation");
+
+    let change = confirm_email_change_token(pool.get_ref(), &query.token)
+        .await
+        .map_err(|e| {
+            let audit_log = AuditLog::new(
+                "CONFIRM_EMAIL_CHANGE".to_string(),
+                "user".to_string(),
+                "FAILURE".to_string(),
+                format!("Email change confirmation failed: {}", e),
+            );
+            RequestFailureLogger::log_audit(&audit_log);
+            e
+        })?;
+
+    revoke_all_user_tokens(pool.get_ref(), change.user_id).await?;
+
+    let audit_log = AuditLog::new(
+        "CONFIRM_EMAIL_CHANGE".to_string(),
+        "user".to_string(),
+        "SUCCESS".to_string(),
+        "Email address changed and sessions revoked".to_string(),
+    )
+    .with_resource_id(change.user_id.to_string())
+    .with_user_id(change.user_id.to_string())
+    .with_state_change(change.previous_email, change.new_email);
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %change.user_id,
+        "Email change confirmed successfully"
+    );
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Your email address has been updated. Please log in again.",
+        "request_id": context.request_id
+    })))
+}
+
+/// Check the user's current password before a sensitive change
+///
+/// Returns the user's current email on success. A failed check is recorded
+/// as an audit log entry under `action`.
+pub(crate) async fn verify_current_password(
+    pool: &PgPool,
+    user_id: Uuid,
+    password: &str,
+    action: &str,
+) -> Result<String, AppError> {
+    let (email, password_hash) = sqlx::query_as::<_, (String, String)>(
+        "SELECT email, password_hash FROM users WHERE id = $1 AND is_active = true",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(AppError::Auth(AuthError::AccountInactive))?;
+
+    if !verify_password(password, &password_hash).await? {
+        let audit_log = AuditLog::new(
+            action.to_string(),
+            "user".to_string(),
+            "FAILURE".to_string(),
+            "Current password verification failed".to_string(),
+        )
+        .with_resource_id(user_id.to_string())
+        .with_user_id(user_id.to_string());
+        RequestFailureLogger::record_audit(pool, &audit_log).await;
+
+        return Err(AppError::Auth(AuthError::InvalidCredentials));
+    }
+
+    Ok(email)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_password_request_deserialization() {
+        let json = r#"{"current_password": "OldPass123", "new_password": "NewPass456"}"#;
+        let request: ChangePasswordRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(request.current_password, "OldPass123");
+        assert_eq!(request.new_password, "NewPass456");
+    }
+
+    #[test]
+    fn test_confirm_email_change_query_deserialization() {
+        let json = r#"{"token": "abc123"}"#;
+        let query: ConfirmEmailChangeQuery = serde_json::from_str(json).unwrap();
+        assert_eq!(query.token, "abc123");
+    }
+}
@@ -0,0 +1,372 @@
+//! Account Data Routes
+//!
+//! Data subject requests for users: export everything stored about the
+//! account, or delete it. Both require an interactive session.
+
+use actix_web::{http::header, web, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::{is_totp_enabled, schedule_account_deletion, verify_second_factor, Claims};
+use crate::configuration::AuthSettings;
+use crate::error::{AppError, AuthError, ErrorContext};
+use crate::request_logging::{AuditLog, RequestFailureLogger};
+use crate::routes::account::verify_current_password;
+use crate::validators::canonical_email;
+
+/// Account deletion request; `code` is required when 2FA is enabled
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    pub current_password: String,
+    pub code: Option<String>,
+}
+
+/// Everything stored about a user
+#[derive(Serialize)]
+pub struct AccountExport {
+    pub exported_at: String,
+    pub profile: ProfileExport,
+    pub sessions: Vec<SessionExport>,
+    pub api_keys: Vec<ApiKeyExport>,
+    pub linked_identities: Vec<IdentityExport>,
+    pub subscriptions: Vec<SubscriptionExport>,
+    pub audit_log: Vec<AuditLogExport>,
+}
+
+#[derive(Serialize)]
+pub struct ProfileExport {
+    pub id: String,
+    pub email: String,
+    pub name: String,
+    pub role: String,
+    pub email_verified_at: Option<String>,
+    pub two_factor_enabled: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize)]
+pub struct SessionExport {
+    pub id: String,
+    pub created_at: String,
+    pub expires_at: String,
+    pub revoked_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ApiKeyExport {
+    pub id: String,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub expires_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct IdentityExport {
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub created_at: String,
+    pub last_login_at: String,
+}
+
+/// Newsletter subscriptions under the account's email address
+#[derive(Serialize)]
+pub struct SubscriptionExport {
+    pub id: String,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: String,
+}
+
+#[derive(Serialize)]
+pub struct AuditLogExport {
+    pub id: String,
+    pub created_at: String,
+    pub action: String,
+    pub status: String,
+    pub message: String,
+}
+
+/// GET /api/me/export
+///
+/// Download everything stored about the authenticated user as a JSON
+/// attachment: profile, sessions, API keys (without the keys), linked
+/// identity providers, newsletter subscriptions under the account's email
+/// and the account's audit trail. Secrets (password hash, TOTP secret,
+/// token hashes) are left out.
+///
+/// # Errors
+/// - 403: The request used an API key
+/// - 500: Internal server error
+pub async fn export_account_data(
+    claims: web::ReqData<Claims>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("account_export");
+    claims.require_session()?;
+    let user_id = claims.user_id()?;
+    let pool = pool.get_ref();
+
+    #[allow(clippy::type_complexity)]
+    let profile = sqlx::query_as::<
+        _,
+        (String, String, String, Option<DateTime<Utc>>, bool, DateTime<Utc>, DateTime<Utc>),
+    >(
+        r#"
+        SELECT email, name, role, email_verified_at, totp_enabled_at IS NOT NULL,
+               created_at, updated_at
+        FROM users WHERE id = $1 AND is_active = true
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(AppError::Auth(AuthError::AccountInactive))?;
+    let (email, name, role, email_verified_at, two_factor_enabled, created_at, updated_at) =
+        profile;
+
+    let sessions = sqlx::query_as::<_, (Uuid, DateTime<Utc>, DateTime<Utc>, Option<DateTime<Utc>>)>(
+        r#"
+        SELECT id, created_at, expires_at, revoked_at FROM refresh_tokens
+        WHERE user_id = $1 ORDER BY created_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(id, created_at, expires_at, revoked_at)| SessionExport {
+        id: id.to_string(),
+        created_at: created_at.to_rfc3339(),
+        expires_at: expires_at.to_rfc3339(),
+        revoked_at: revoked_at.map(|t| t.to_rfc3339()),
+    })
+    .collect();
+
+    #[allow(clippy::type_complexity)]
+    let api_keys = sqlx::query_as::<
+        _,
+        (
+            Uuid,
+            String,
+            String,
+            Vec<String>,
+            DateTime<Utc>,
+            Option<DateTime<Utc>>,
+            Option<DateTime<Utc>>,
+            Option<DateTime<Utc>>,
+        ),
+    >(
+        r#"
+        SELECT id, name, key_prefix, scopes, created_at, last_used_at, expires_at, revoked_at
+        FROM api_keys WHERE user_id = $1 ORDER BY created_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(
+        |(id, name, key_prefix, scopes, created_at, last_used_at, expires_at, revoked_at)| {
+            ApiKeyExport {
+                id: id.to_string(),
+                name,
+                key_prefix,
+                scopes,
+                created_at: created_at.to_rfc3339(),
+                last_used_at: last_used_at.map(|t| t.to_rfc3339()),
+                expires_at: expires_at.map(|t| t.to_rfc3339()),
+                revoked_at: revoked_at.map(|t| t.to_rfc3339()),
+            }
+        },
+    )
+    .collect();
+
+    let linked_identities = sqlx::query_as::<
+        _,
+        (String, String, Option<String>, DateTime<Utc>, DateTime<Utc>),
+    >(
+        r#"
+        SELECT provider, subject, email, created_at, last_login_at
+        FROM user_identities WHERE user_id = $1 ORDER BY created_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(provider, subject, email, created_at, last_login_at)| IdentityExport {
+        provider,
+        subject,
+        email,
+        created_at: created_at.to_rfc3339(),
+        last_login_at: last_login_at.to_rfc3339(),
+    })
+    .collect();
+
+    let subscriptions = sqlx::query_as::<_, (Uuid, String, String, String, DateTime<Utc>)>(
+        r#"
+        SELECT id, email, name, status, subscribed_at FROM subscriptions
+        WHERE email_canonical = $1
+        "#,
+    )
+    .bind(canonical_email(&email))
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(id, email, name, status, subscribed_at)| SubscriptionExport {
+        id: id.to_string(),
+        email,
+        name,
+        status,
+        subscribed_at: subscribed_at.to_rfc3339(),
+    })
+    .collect();
+
+    let audit_log = sqlx::query_as::<_, (Uuid, DateTime<Utc>, String, String, String)>(
+        r#"
+        SELECT id, created_at, action, status, message FROM audit_logs
+        WHERE user_id = $1 OR (resource_type = 'user' AND resource_id = $1)
+        ORDER BY created_at
+        "#,
+    )
+    .bind(user_id.to_string())
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(id, created_at, action, status, message)| AuditLogExport {
+        id: id.to_string(),
+        created_at: created_at.to_rfc3339(),
+        action,
+        status,
+        message,
+    })
+    .collect();
+
+    let export = AccountExport {
+        exported_at: Utc::now().to_rfc3339(),
+        profile: ProfileExport {
+            id: user_id.to_string(),
+            email,
+            name,
+            role,
+            email_verified_at: email_verified_at.map(|t| t.to_rfc3339()),
+            two_factor_enabled,
+            created_at: created_at.to_rfc3339(),
+            updated_at: updated_at.to_rfc3339(),
+        },
+        sessions,
+        api_keys,
+        linked_identities,
+        subscriptions,
+        audit_log,
+    };
+
+    let audit_log = AuditLog::new(
+        "EXPORT_ACCOUNT_DATA".to_string(),
+        "user".to_string(),
+        "SUCCESS".to_string(),
+        "Account data exported".to_string(),
+    )
+    .with_resource_id(user_id.to_string())
+    .with_user_id(user_id.to_string());
+    RequestFailureLogger::record_audit(pool, &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        "Account data exported"
+    );
+
+    Ok(HttpResponse::Ok()
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"account-export.json\"",
+        ))
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(export))
+}
+
+/// DELETE /api/me
+///
+/// Delete the authenticated user's account. Requires the current password,
+/// and a TOTP or recovery code when 2FA is enabled.
+///
+/// The account is deactivated and signed out everywhere at once, and
+/// erased once the configured grace period is over.
+///
+/// # Errors
+/// - 401: Current password or code is wrong, or the code is missing
+/// - 403: The request used an API key
+/// - 500: Internal server error
+pub async fn delete_account(
+    claims: web::ReqData<Claims>,
+    form: web::Json<DeleteAccountRequest>,
+    pool: web::Data<PgPool>,
+    auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("account_deletion");
+    claims.require_session()?;
+    let user_id = claims.user_id()?;
+
+    verify_current_password(
+        pool.get_ref(),
+        user_id,
+        &form.current_password,
+        "DELETE_ACCOUNT",
+    )
+    .await?;
+
+    if is_totp_enabled(pool.get_ref(), user_id).await? {
+        let code = form.code.as_deref().unwrap_or_default();
+        if let Err(e) = verify_second_factor(pool.get_ref(), user_id, code).await {
+            let audit_log = AuditLog::new(
+                "DELETE_ACCOUNT".to_string(),
+                "user".to_string(),
+                "FAILURE".to_string(),
+                "Invalid or missing two-factor code".to_string(),
+            )
+            .with_resource_id(user_id.to_string())
+            .with_user_id(user_id.to_string());
+            RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+            return Err(e);
+        }
+    }
+
+    let grace_period_days = auth_settings.account_deletion.grace_period_days;
+    let scheduled_at = schedule_account_deletion(pool.get_ref(), user_id, grace_period_days).await?;
+
+    let audit_log = AuditLog::new(
+        "DELETE_ACCOUNT".to_string(),
+        "user".to_string(),
+        "SUCCESS".to_string(),
+        format!(
+            "Account deactivated and scheduled for erasure at {}",
+            scheduled_at.to_rfc3339()
+        ),
+    )
+    .with_resource_id(user_id.to_string())
+    .with_user_id(user_id.to_string());
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        scheduled_at = %scheduled_at,
+        "Account deletion scheduled"
+    );
+
+    Ok(HttpResponse::Accepted().json(serde_json::json!({
+        "message": "Your account has been deactivated and will be deleted",
+        "deletion_scheduled_at": scheduled_at.to_rfc3339(),
+        "request_id": context.request_id
+    })))
+}
@@ -0,0 +1,536 @@
+//! Admin Subscriber Management Routes
+//!
+//! Lets admins browse the subscriber list and act on single subscribers.
+//! Lists are paged with an opaque cursor (newest first), so pages stay
+//! stable while people keep subscribing. Every subscriber returned is
+//! checked with `validate_subscriber_data`; records that fail are still
+//! shown, with the problem in `validation_error`, so bad data can be found
+//! and fixed here instead of breaking the list.
+
+use actix_web::{web, HttpResponse};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::admin_users::escape_like;
+use crate::auth::{require_admin, Claims};
+use crate::data_validation::{validate_subscriber_data, validate_subscription_status};
+use crate::error::{AppError, DatabaseError, ErrorContext, ValidationError};
+use crate::request_logging::{AuditLog, RequestFailureLogger};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+const MAX_SEARCH_LENGTH: usize = 100;
+
+/// Filters and pagination for the subscriber list
+#[derive(Deserialize)]
+pub struct ListSubscribersQuery {
+    pub status: Option<String>,
+    /// Case-insensitive substring of the email address
+    pub email: Option<String>,
+    /// Inclusive lower bound, RFC 3339 timestamp or YYYY-MM-DD (UTC)
+    pub subscribed_from: Option<String>,
+    /// Exclusive upper bound, RFC 3339 timestamp or YYYY-MM-DD (UTC)
+    pub subscribed_to: Option<String>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Status change request
+#[derive(Deserialize)]
+pub struct ChangeStatusRequest {
+    pub status: String,
+}
+
+/// Subscriber as seen by admins
+#[derive(Serialize)]
+pub struct AdminSubscriberResponse {
+    pub id: String,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: String,
+    pub digest_frequency: String,
+    pub excluded_topics: Vec<String>,
+    pub paused_until: Option<String>,
+    pub unsubscribed_at: Option<String>,
+    pub consent_source: Option<String>,
+    pub consent_recorded_at: Option<String>,
+    /// Why the stored record is invalid; `None` for valid records
+    pub validation_error: Option<String>,
+}
+
+/// One page of subscribers
+#[derive(Serialize)]
+pub struct SubscriberListResponse {
+    pub subscribers: Vec<AdminSubscriberResponse>,
+    /// Pass as `cursor` to get the next page; `None` on the last page
+    pub next_cursor: Option<String>,
+    pub limit: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct AdminSubscriberRow {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+    digest_frequency: String,
+    excluded_topics: Vec<String>,
+    paused_until: Option<DateTime<Utc>>,
+    unsubscribed_at: Option<DateTime<Utc>>,
+    consent_source: Option<String>,
+    consent_recorded_at: Option<DateTime<Utc>>,
+}
+
+impl From<AdminSubscriberRow> for AdminSubscriberResponse {
+    fn from(row: AdminSubscriberRow) -> Self {
+        let id = row.id.to_string();
+        let validation_error = validate_subscriber_data(&id, &row.email, &row.name, &row.status)
+            .err()
+            .map(|e| e.to_string());
+        if let Some(error) = &validation_error {
+            tracing::warn!(subscriber_id = %id, error = %error, "Invalid subscriber record");
+        }
+
+        Self {
+            id,
+            email: row.email,
+            name: row.name,
+            status: row.status,
+            subscribed_at: row.subscribed_at.to_rfc3339(),
+            digest_frequency: row.digest_frequency,
+            excluded_topics: row.excluded_topics,
+            paused_until: row.paused_until.map(|t| t.to_rfc3339()),
+            unsubscribed_at: row.unsubscribed_at.map(|t| t.to_rfc3339()),
+            consent_source: row.consent_source,
+            consent_recorded_at: row.consent_recorded_at.map(|t| t.to_rfc3339()),
+            validation_error,
+        }
+    }
+}
+
+const SUBSCRIBER_COLUMNS: &str = r#"
+    id, email, name, status, subscribed_at, digest_frequency, excluded_topics,
+    paused_until, unsubscribed_at, consent_source, consent_recorded_at
+"#;
+
+/// GET /api/admin/subscribers?status=&email=&subscribed_from=&subscribed_to=&cursor=&limit=
+///
+/// List subscribers, newest first.
+///
+/// # Errors
+/// - 400: Invalid status, date, cursor or limit, or search too long
+/// - 403: Not an admin session
+/// - 500: Internal server error
+pub async fn list_subscribers(
+    claims: web::ReqData<Claims>,
+    query: web::Query<ListSubscribersQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(pool.get_ref(), &claims).await?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::Validation(ValidationError::InvalidFormat(
+            "limit".to_string(),
+        )));
+    }
+
+    let status = match query.status.as_deref() {
+        Some(status) => {
+            validate_subscription_status(status)?;
+            Some(status.trim().to_string())
+        }
+        None => None,
+    };
+
+    let email = match query.email.as_deref().map(str::trim) {
+        Some(email) if email.chars().count() > MAX_SEARCH_LENGTH => {
+            return Err(AppError::Validation(ValidationError::TooLong(
+                "email".to_string(),
+                MAX_SEARCH_LENGTH,
+            )));
+        }
+        Some(email) if !email.is_empty() => Some(format!("%{}%", escape_like(email))),
+        _ => None,
+    };
+
+    let subscribed_from = query
+        .subscribed_from
+        .as_deref()
+        .map(|value| parse_date_bound(value, "subscribed_from"))
+        .transpose()?;
+    let subscribed_to = query
+        .subscribed_to
+        .as_deref()
+        .map(|value| parse_date_bound(value, "subscribed_to"))
+        .transpose()?;
+    let (cursor_at, cursor_id) = match query.cursor.as_deref() {
+        Some(cursor) => {
+            let (at, id) = decode_cursor(cursor)?;
+            (Some(at), Some(id))
+        }
+        None => (None, None),
+    };
+
+    // One extra row tells whether there is a next page
+    let mut rows = sqlx::query_as::<_, AdminSubscriberRow>(&format!(
+        r#"
+        SELECT {} FROM subscriptions
+        WHERE ($1::text IS NULL OR status = $1)
+          AND ($2::text IS NULL OR email ILIKE $2)
+          AND ($3::timestamptz IS NULL OR subscribed_at >= $3)
+          AND ($4::timestamptz IS NULL OR subscribed_at < $4)
+          AND ($5::timestamptz IS NULL OR (subscribed_at, id) < ($5, $6))
+        ORDER BY subscribed_at DESC, id DESC
+        LIMIT $7
+        "#,
+        SUBSCRIBER_COLUMNS
+    ))
+    .bind(&status)
+    .bind(&email)
+    .bind(subscribed_from)
+    .bind(subscribed_to)
+    .bind(cursor_at)
+    .bind(cursor_id)
+    .bind(limit + 1)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let next_cursor = if rows.len() as i64 > limit {
+        rows.truncate(limit as usize);
+        rows.last().map(|row| encode_cursor(row.subscribed_at, row.id))
+    } else {
+        None
+    };
+
+    Ok(HttpResponse::Ok().json(SubscriberListResponse {
+        subscribers: rows.into_iter().map(AdminSubscriberResponse::from).collect(),
+        next_cursor,
+        limit,
+    }))
+}
+
+/// GET /api/admin/subscribers/{id}
+///
+/// # Errors
+/// - 403: Not an admin session
+/// - 404: No such subscriber
+/// - 500: Internal server error
+pub async fn get_subscriber(
+    claims: web::ReqData<Claims>,
+    subscriber_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(pool.get_ref(), &claims).await?;
+    let subscriber_id = parse_subscriber_id(&subscriber_id)?;
+
+    let subscriber = fetch_subscriber(pool.get_ref(), subscriber_id).await?;
+
+    Ok(HttpResponse::Ok().json(AdminSubscriberResponse::from(subscriber)))
+}
+
+/// POST /api/admin/subscribers/{id}/confirm
+///
+/// Confirm a pending subscriber without the confirmation email, such as
+/// when they confirmed by other means. Their confirmation tokens are
+/// removed. Confirming a confirmed subscriber changes nothing.
+///
+/// # Errors
+/// - 400: Subscriber has unsubscribed
+/// - 403: Not an admin session
+/// - 404: No such subscriber
+/// - 500: Internal server error
+pub async fn confirm_subscriber(
+    claims: web::ReqData<Claims>,
+    subscriber_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("admin_confirm_subscriber");
+    let admin_id = require_admin(pool.get_ref(), &claims).await?;
+    let subscriber_id = parse_subscriber_id(&subscriber_id)?;
+
+    let subscriber = fetch_subscriber(pool.get_ref(), subscriber_id).await?;
+    match subscriber.status.as_str() {
+        "confirmed" => {
+            return Ok(HttpResponse::Ok().json(AdminSubscriberResponse::from(subscriber)));
+        }
+        // Only the subscriber can undo an unsubscribe
+        "unsubscribed" => {
+            log_subscriber_audit(
+                pool.get_ref(),
+                "ADMIN_CONFIRM_SUBSCRIBER",
+                "FAILURE",
+                "Unsubscribed subscribers cannot be confirmed",
+                subscriber_id,
+                admin_id,
+            )
+            .await;
+            return Err(AppError::Validation(ValidationError::InvalidFormat(
+                "status".to_string(),
+            )));
+        }
+        _ => {}
+    }
+
+    let previous_status = set_status(pool.get_ref(), subscriber_id, "confirmed").await?;
+
+    let audit_log = AuditLog::new(
+        "ADMIN_CONFIRM_SUBSCRIBER".to_string(),
+        "subscription".to_string(),
+        "SUCCESS".to_string(),
+        "Subscriber confirmed by admin".to_string(),
+    )
+    .with_resource_id(subscriber_id.to_string())
+    .with_user_id(admin_id.to_string())
+    .with_state_change(previous_status, "confirmed".to_string());
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        admin_id = %admin_id,
+        subscriber_id = %subscriber_id,
+        "Subscriber confirmed by admin"
+    );
+
+    let subscriber = fetch_subscriber(pool.get_ref(), subscriber_id).await?;
+    Ok(HttpResponse::Ok().json(AdminSubscriberResponse::from(subscriber)))
+}
+
+/// PUT /api/admin/subscribers/{id}/status
+///
+/// Set any status. Unsubscribing records the time; confirming removes the
+/// confirmation tokens.
+///
+/// # Errors
+/// - 400: Invalid status
+/// - 403: Not an admin session
+/// - 404: No such subscriber
+/// - 500: Internal server error
+pub async fn change_subscriber_status(
+    claims: web::ReqData<Claims>,
+    subscriber_id: web::Path<String>,
+    form: web::Json<ChangeStatusRequest>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("admin_change_subscriber_status");
+    let admin_id = require_admin(pool.get_ref(), &claims).await?;
+    let subscriber_id = parse_subscriber_id(&subscriber_id)?;
+    validate_subscription_status(&form.status)?;
+    let status = form.status.trim();
+
+    let previous_status = set_status(pool.get_ref(), subscriber_id, status).await?;
+
+    let audit_log = AuditLog::new(
+        "ADMIN_CHANGE_SUBSCRIBER_STATUS".to_string(),
+        "subscription".to_string(),
+        "SUCCESS".to_string(),
+        format!("Status changed from '{}' to '{}'", previous_status, status),
+    )
+    .with_resource_id(subscriber_id.to_string())
+    .with_user_id(admin_id.to_string())
+    .with_state_change(previous_status, status.to_string());
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        admin_id = %admin_id,
+        subscriber_id = %subscriber_id,
+        status = %status,
+        "Subscriber status changed"
+    );
+
+    let subscriber = fetch_subscriber(pool.get_ref(), subscriber_id).await?;
+    Ok(HttpResponse::Ok().json(AdminSubscriberResponse::from(subscriber)))
+}
+
+/// DELETE /api/admin/subscribers/{id}
+///
+/// Delete a subscriber and their confirmation tokens. Unlike erasure (see
+/// `subscriber_data`) no tombstone is left, so the address can be
+/// imported again.
+///
+/// # Errors
+/// - 403: Not an admin session
+/// - 404: No such subscriber
+/// - 500: Internal server error
+pub async fn delete_subscriber(
+    claims: web::ReqData<Claims>,
+    subscriber_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("admin_delete_subscriber");
+    let admin_id = require_admin(pool.get_ref(), &claims).await?;
+    let subscriber_id = parse_subscriber_id(&subscriber_id)?;
+
+    let result = sqlx::query("DELETE FROM subscriptions WHERE id = $1")
+        .bind(subscriber_id)
+        .execute(pool.get_ref())
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(subscriber_not_found());
+    }
+
+    log_subscriber_audit(
+        pool.get_ref(),
+        "ADMIN_DELETE_SUBSCRIBER",
+        "SUCCESS",
+        "Subscriber deleted by admin",
+        subscriber_id,
+        admin_id,
+    )
+    .await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        admin_id = %admin_id,
+        subscriber_id = %subscriber_id,
+        "Subscriber deleted"
+    );
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "id": subscriber_id.to_string(),
+        "deleted": true,
+        "request_id": context.request_id
+    })))
+}
+
+async fn fetch_subscriber(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<AdminSubscriberRow, AppError> {
+    sqlx::query_as::<_, AdminSubscriberRow>(&format!(
+        "SELECT {} FROM subscriptions WHERE id = $1",
+        SUBSCRIBER_COLUMNS
+    ))
+    .bind(subscriber_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(subscriber_not_found)
+}
+
+/// Set the status and return the previous one
+async fn set_status(pool: &PgPool, subscriber_id: Uuid, status: &str) -> Result<String, AppError> {
+    let mut transaction = pool.begin().await?;
+
+    let previous_status = sqlx::query_scalar::<_, String>(
+        r#"
+        UPDATE subscriptions s
+        SET status = $1,
+            unsubscribed_at = CASE
+                WHEN $1 = 'unsubscribed' THEN COALESCE(s.unsubscribed_at, $2)
+                ELSE NULL
+            END
+        FROM (SELECT id, status FROM subscriptions WHERE id = $3 FOR UPDATE) previous
+        WHERE s.id = previous.id
+        RETURNING previous.status
+        "#,
+    )
+    .bind(status)
+    .bind(Utc::now())
+    .bind(subscriber_id)
+    .fetch_optional(&mut transaction)
+    .await?
+    .ok_or_else(subscriber_not_found)?;
+
+    if status == "confirmed" {
+        sqlx::query("DELETE FROM subscription_tokens WHERE subscriber_id = $1")
+            .bind(subscriber_id)
+            .execute(&mut transaction)
+            .await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(previous_status)
+}
+
+/// Accepts an RFC 3339 timestamp or a plain date (midnight UTC)
+fn parse_date_bound(value: &str, field: &str) -> Result<DateTime<Utc>, AppError> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+        })
+        .map_err(|_| AppError::Validation(ValidationError::InvalidFormat(field.to_string())))
+}
+
+/// Cursor for the position after the given subscriber
+fn encode_cursor(subscribed_at: DateTime<Utc>, id: Uuid) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}.{}", subscribed_at.timestamp_micros(), id.simple()))
+}
+
+fn decode_cursor(cursor: &str) -> Result<(DateTime<Utc>, Uuid), AppError> {
+    let invalid = || AppError::Validation(ValidationError::InvalidFormat("cursor".to_string()));
+
+    let decoded = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+    let (micros, id) = decoded.split_once('.').ok_or_else(invalid)?;
+
+    let micros = micros.parse::<i64>().map_err(|_| invalid())?;
+    let subscribed_at = DateTime::from_timestamp_micros(micros).ok_or_else(invalid)?;
+    let id = Uuid::parse_str(id).map_err(|_| invalid())?;
+
+    Ok((subscribed_at, id))
+}
+
+/// Malformed IDs can't match any subscriber
+fn parse_subscriber_id(subscriber_id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(subscriber_id).map_err(|_| subscriber_not_found())
+}
+
+fn subscriber_not_found() -> AppError {
+    AppError::Database(DatabaseError::NotFound("Subscriber not found".to_string()))
+}
+
+async fn log_subscriber_audit(
+    pool: &PgPool,
+    action: &str,
+    status: &str,
+    message: &str,
+    subscriber_id: Uuid,
+    admin_id: Uuid,
+) {
+    let audit_log = AuditLog::new(
+        action.to_string(),
+        "subscription".to_string(),
+        status.to_string(),
+        message.to_string(),
+    )
+    .with_resource_id(subscriber_id.to_string())
+    .with_user_id(admin_id.to_string());
+    RequestFailureLogger::record_audit(pool, &audit_log).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let subscribed_at = DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap();
+        let id = Uuid::new_v4();
+
+        let cursor = encode_cursor(subscribed_at, id);
+
+        assert_eq!((subscribed_at, id), decode_cursor(&cursor).unwrap());
+        assert!(decode_cursor("not-a-cursor").is_err());
+    }
+
+    #[test]
+    fn test_date_bound_accepts_timestamps_and_dates() {
+        let date = parse_date_bound("2024-03-01", "subscribed_from").unwrap();
+        let timestamp = parse_date_bound("2024-03-01T09:00:00+09:00", "subscribed_from").unwrap();
+
+        assert_eq!(date, timestamp);
+        assert!(parse_date_bound("March 1st", "subscribed_from").is_err());
+    }
+}
@@ -0,0 +1,605 @@
+//! Admin User Management Routes
+//!
+//! Lets admins look up users and act on their accounts. Every route
+//! requires an interactive session of an active admin (see
+//! `require_admin`), and every change is recorded as an audit log entry
+//! with the target as resource and the admin as user.
+
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::{
+    create_password_reset_token, parse_role, require_admin, revoke_all_user_tokens, Claims,
+    ROLE_ADMIN,
+};
+use crate::email_client::{escape_html, EmailClient};
+use crate::error::{AppError, AuthError, DatabaseError, ErrorContext, ValidationError};
+use crate::request_logging::{AuditLog, RequestFailureLogger};
+use crate::startup::ApplicationBaseUrl;
+
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+const MAX_SEARCH_LENGTH: usize = 100;
+
+/// Filters and pagination for the user list
+#[derive(Deserialize)]
+pub struct ListUsersQuery {
+    /// Case-insensitive substring of the email or name
+    pub search: Option<String>,
+    pub role: Option<String>,
+    pub is_active: Option<bool>,
+    /// 1-based page number
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+/// Role change request
+#[derive(Deserialize)]
+pub struct ChangeRoleRequest {
+    pub role: String,
+}
+
+/// User as seen by admins
+#[derive(Serialize)]
+pub struct AdminUserResponse {
+    pub id: String,
+    pub email: String,
+    pub name: String,
+    pub role: String,
+    pub is_active: bool,
+    pub email_verified: bool,
+    pub two_factor_enabled: bool,
+    pub password_reset_required: bool,
+    pub created_at: String,
+}
+
+/// One page of users
+#[derive(Serialize)]
+pub struct UserListResponse {
+    pub users: Vec<AdminUserResponse>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
+
+/// Active refresh token of a user
+#[derive(Serialize)]
+pub struct SessionResponse {
+    pub id: String,
+    pub created_at: String,
+    pub expires_at: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct AdminUserRow {
+    id: Uuid,
+    email: String,
+    name: String,
+    role: String,
+    is_active: bool,
+    email_verified: bool,
+    two_factor_enabled: bool,
+    password_reset_required: bool,
+    created_at: DateTime<Utc>,
+}
+
+impl From<AdminUserRow> for AdminUserResponse {
+    fn from(row: AdminUserRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            email: row.email,
+            name: row.name,
+            role: row.role,
+            is_active: row.is_active,
+            email_verified: row.email_verified,
+            two_factor_enabled: row.two_factor_enabled,
+            password_reset_required: row.password_reset_required,
+            created_at: row.created_at.to_rfc3339(),
+        }
+    }
+}
+
+const USER_COLUMNS: &str = r#"
+    id, email, name, role, is_active,
+    email_verified_at IS NOT NULL AS email_verified,
+    totp_enabled_at IS NOT NULL AS two_factor_enabled,
+    password_reset_required_at IS NOT NULL AS password_reset_required,
+    created_at
+"#;
+
+/// GET /api/admin/users?search=&role=&is_active=&page=&per_page=
+///
+/// List users, newest first.
+///
+/// # Errors
+/// - 400: Unknown role, invalid page/per_page or search too long
+/// - 403: Not an admin session
+/// - 500: Internal server error
+pub async fn list_users(
+    claims: web::ReqData<Claims>,
+    query: web::Query<ListUsersQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(pool.get_ref(), &claims).await?;
+
+    let page = query.page.unwrap_or(1);
+    if page < 1 {
+        return Err(AppError::Validation(ValidationError::InvalidFormat(
+            "page".to_string(),
+        )));
+    }
+    let per_page = query.per_page.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&per_page) {
+        return Err(AppError::Validation(ValidationError::InvalidFormat(
+            "per_page".to_string(),
+        )));
+    }
+    let role = query.role.as_deref().map(parse_role).transpose()?;
+
+    let search = match query.search.as_deref().map(str::trim) {
+        Some(search) if search.chars().count() > MAX_SEARCH_LENGTH => {
+            return Err(AppError::Validation(ValidationError::TooLong(
+                "search".to_string(),
+                MAX_SEARCH_LENGTH,
+            )));
+        }
+        Some(search) if !search.is_empty() => Some(format!("%{}%", escape_like(search))),
+        _ => None,
+    };
+
+    let filter = r#"
+        WHERE ($1::text IS NULL OR email ILIKE $1 OR name ILIKE $1)
+          AND ($2::text IS NULL OR role = $2)
+          AND ($3::bool IS NULL OR is_active = $3)
+    "#;
+
+    let total = sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM users {}", filter))
+        .bind(&search)
+        .bind(role)
+        .bind(query.is_active)
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    let users = sqlx::query_as::<_, AdminUserRow>(&format!(
+        "SELECT {} FROM users {} ORDER BY created_at DESC, id LIMIT $4 OFFSET $5",
+        USER_COLUMNS, filter
+    ))
+    .bind(&search)
+    .bind(role)
+    .bind(query.is_active)
+    .bind(per_page)
+    .bind((page - 1).saturating_mul(per_page))
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(UserListResponse {
+        users: users.into_iter().map(AdminUserResponse::from).collect(),
+        page,
+        per_page,
+        total,
+    }))
+}
+
+/// GET /api/admin/users/{id}
+///
+/// # Errors
+/// - 403: Not an admin session
+/// - 404: No such user
+/// - 500: Internal server error
+pub async fn get_user(
+    claims: web::ReqData<Claims>,
+    user_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(pool.get_ref(), &claims).await?;
+    let user_id = parse_user_id(&user_id)?;
+
+    let user = fetch_user(pool.get_ref(), user_id).await?;
+
+    Ok(HttpResponse::Ok().json(AdminUserResponse::from(user)))
+}
+
+/// GET /api/admin/users/{id}/sessions
+///
+/// List the user's active sessions (unrevoked, unexpired refresh tokens),
+/// newest first.
+///
+/// # Errors
+/// - 403: Not an admin session
+/// - 404: No such user
+/// - 500: Internal server error
+pub async fn list_user_sessions(
+    claims: web::ReqData<Claims>,
+    user_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(pool.get_ref(), &claims).await?;
+    let user_id = parse_user_id(&user_id)?;
+    fetch_user(pool.get_ref(), user_id).await?;
+
+    let sessions = sqlx::query_as::<_, (Uuid, DateTime<Utc>, DateTime<Utc>)>(
+        r#"
+        SELECT id, created_at, expires_at FROM refresh_tokens
+        WHERE user_id = $1 AND is_revoked = false AND expires_at > $2
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .bind(Utc::now())
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let sessions: Vec<SessionResponse> = sessions
+        .into_iter()
+        .map(|(id, created_at, expires_at)| SessionResponse {
+            id: id.to_string(),
+            created_at: created_at.to_rfc3339(),
+            expires_at: expires_at.to_rfc3339(),
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(sessions))
+}
+
+/// POST /api/admin/users/{id}/deactivate
+///
+/// Deactivate an account and revoke all of its refresh tokens. The user
+/// can no longer log in or use API keys; access tokens already issued
+/// stay valid until they expire.
+///
+/// # Errors
+/// - 403: Not an admin session, or an admin deactivating themselves
+/// - 404: No such user
+/// - 500: Internal server error
+pub async fn deactivate_user(
+    claims: web::ReqData<Claims>,
+    user_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("admin_deactivate_user");
+    let admin_id = require_admin(pool.get_ref(), &claims).await?;
+    let user_id = parse_user_id(&user_id)?;
+
+    if user_id == admin_id {
+        log_admin_audit(
+            pool.get_ref(),
+            "ADMIN_DEACTIVATE_USER",
+            "FAILURE",
+            "Admins cannot deactivate their own account",
+            user_id,
+            admin_id,
+        )
+        .await;
+        return Err(AppError::Auth(AuthError::PermissionDenied));
+    }
+
+    set_user_active(pool.get_ref(), user_id, false).await?;
+    revoke_all_user_tokens(pool.get_ref(), user_id).await?;
+
+    log_admin_audit(
+        pool.get_ref(),
+        "ADMIN_DEACTIVATE_USER",
+        "SUCCESS",
+        "User deactivated and sessions revoked",
+        user_id,
+        admin_id,
+    )
+    .await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        admin_id = %admin_id,
+        user_id = %user_id,
+        "User deactivated"
+    );
+
+    let user = fetch_user(pool.get_ref(), user_id).await?;
+    Ok(HttpResponse::Ok().json(AdminUserResponse::from(user)))
+}
+
+/// POST /api/admin/users/{id}/reactivate
+///
+/// Also cancels a deletion the user requested, if it hasn't been carried
+/// out yet.
+///
+/// # Errors
+/// - 403: Not an admin session
+/// - 404: No such user
+/// - 500: Internal server error
+pub async fn reactivate_user(
+    claims: web::ReqData<Claims>,
+    user_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("admin_reactivate_user");
+    let admin_id = require_admin(pool.get_ref(), &claims).await?;
+    let user_id = parse_user_id(&user_id)?;
+
+    set_user_active(pool.get_ref(), user_id, true).await?;
+
+    log_admin_audit(
+        pool.get_ref(),
+        "ADMIN_REACTIVATE_USER",
+        "SUCCESS",
+        "User reactivated",
+        user_id,
+        admin_id,
+    )
+    .await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        admin_id = %admin_id,
+        user_id = %user_id,
+        "User reactivated"
+    );
+
+    let user = fetch_user(pool.get_ref(), user_id).await?;
+    Ok(HttpResponse::Ok().json(AdminUserResponse::from(user)))
+}
+
+/// POST /api/admin/users/{id}/force-password-reset
+///
+/// Require the user to choose a new password: password login is refused,
+/// all sessions are revoked and a reset link is emailed to the user.
+///
+/// # Errors
+/// - 403: Not an admin session
+/// - 404: No such user
+/// - 500: Internal server error, including failure to send the email
+pub async fn force_password_reset(
+    claims: web::ReqData<Claims>,
+    user_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("admin_force_password_reset");
+    let admin_id = require_admin(pool.get_ref(), &claims).await?;
+    let user_id = parse_user_id(&user_id)?;
+
+    let user = sqlx::query_as::<_, (String, String)>(
+        r#"
+        UPDATE users
+        SET password_reset_required_at = COALESCE(password_reset_required_at, $1), updated_at = $1
+        WHERE id = $2
+        RETURNING email, name
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(user_id)
+    .fetch_optional(pool.get_ref())
+    .await?;
+    let Some((email, name)) = user else {
+        return Err(user_not_found());
+    };
+
+    revoke_all_user_tokens(pool.get_ref(), user_id).await?;
+    let token = create_password_reset_token(pool.get_ref(), user_id).await?;
+
+    if let Err(e) =
+        send_password_reset_email(email_client.get_ref(), &base_url.0, &email, &name, &token).await
+    {
+        log_admin_audit(
+            pool.get_ref(),
+            "ADMIN_FORCE_PASSWORD_RESET",
+            "FAILURE",
+            &format!("Password reset required but email failed: {}", e),
+            user_id,
+            admin_id,
+        )
+        .await;
+        return Err(e);
+    }
+
+    log_admin_audit(
+        pool.get_ref(),
+        "ADMIN_FORCE_PASSWORD_RESET",
+        "SUCCESS",
+        "Password reset required, sessions revoked and reset link sent",
+        user_id,
+        admin_id,
+    )
+    .await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        admin_id = %admin_id,
+        user_id = %user_id,
+        "Password reset forced"
+    );
+
+    let user = fetch_user(pool.get_ref(), user_id).await?;
+    Ok(HttpResponse::Ok().json(AdminUserResponse::from(user)))
+}
+
+/// PUT /api/admin/users/{id}/role
+///
+/// # Errors
+/// - 400: Unknown role
+/// - 403: Not an admin session, or an admin demoting themselves
+/// - 404: No such user
+/// - 500: Internal server error
+pub async fn change_user_role(
+    claims: web::ReqData<Claims>,
+    user_id: web::Path<String>,
+    form: web::Json<ChangeRoleRequest>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("admin_change_user_role");
+    let admin_id = require_admin(pool.get_ref(), &claims).await?;
+    let user_id = parse_user_id(&user_id)?;
+    let role = parse_role(&form.role)?;
+
+    // Keeps at least one admin around to undo mistakes
+    if user_id == admin_id && role != ROLE_ADMIN {
+        log_admin_audit(
+            pool.get_ref(),
+            "ADMIN_CHANGE_ROLE",
+            "FAILURE",
+            "Admins cannot remove their own admin role",
+            user_id,
+            admin_id,
+        )
+        .await;
+        return Err(AppError::Auth(AuthError::PermissionDenied));
+    }
+
+    let previous_role = sqlx::query_scalar::<_, String>(
+        r#"
+        UPDATE users u SET role = $1, updated_at = $2
+        FROM (SELECT id, role FROM users WHERE id = $3 FOR UPDATE) previous
+        WHERE u.id = previous.id
+        RETURNING previous.role
+        "#,
+    )
+    .bind(role)
+    .bind(Utc::now())
+    .bind(user_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(user_not_found)?;
+
+    log_admin_audit(
+        pool.get_ref(),
+        "ADMIN_CHANGE_ROLE",
+        "SUCCESS",
+        &format!("Role changed from '{}' to '{}'", previous_role, role),
+        user_id,
+        admin_id,
+    )
+    .await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        admin_id = %admin_id,
+        user_id = %user_id,
+        role = %role,
+        "User role changed"
+    );
+
+    let user = fetch_user(pool.get_ref(), user_id).await?;
+    Ok(HttpResponse::Ok().json(AdminUserResponse::from(user)))
+}
+
+async fn fetch_user(pool: &PgPool, user_id: Uuid) -> Result<AdminUserRow, AppError> {
+    sqlx::query_as::<_, AdminUserRow>(&format!(
+        "SELECT {} FROM users WHERE id = $1",
+        USER_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(user_not_found)
+}
+
+/// Reactivating also cancels a pending self-service deletion; erased
+/// (anonymized) accounts count as missing
+async fn set_user_active(pool: &PgPool, user_id: Uuid, is_active: bool) -> Result<(), AppError> {
+    let result = sqlx::query(
+        r#"
+        UPDATE users
+        SET is_active = $1, updated_at = $2,
+            deletion_requested_at = CASE WHEN $1 THEN NULL ELSE deletion_requested_at END,
+            deletion_scheduled_at = CASE WHEN $1 THEN NULL ELSE deletion_scheduled_at END
+        WHERE id = $3 AND anonymized_at IS NULL
+        "#,
+    )
+    .bind(is_active)
+    .bind(Utc::now())
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(user_not_found());
+    }
+    Ok(())
+}
+
+/// Malformed IDs can't match any user
+fn parse_user_id(user_id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(user_id).map_err(|_| user_not_found())
+}
+
+fn user_not_found() -> AppError {
+    AppError::Database(DatabaseError::NotFound("User not found".to_string()))
+}
+
+/// Escape LIKE wildcards so the search matches literally
+pub(super) fn escape_like(search: &str) -> String {
+    let mut escaped = String::with_capacity(search.len());
+    for c in search.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+async fn log_admin_audit(
+    pool: &PgPool,
+    action: &str,
+    status: &str,
+    message: &str,
+    user_id: Uuid,
+    admin_id: Uuid,
+) {
+    let audit_log = AuditLog::new(
+        action.to_string(),
+        "user".to_string(),
+        status.to_string(),
+        message.to_string(),
+    )
+    .with_resource_id(user_id.to_string())
+    .with_user_id(admin_id.to_string());
+    RequestFailureLogger::record_audit(pool, &audit_log).await;
+}
+
+async fn send_password_reset_email(
+    email_client: &EmailClient,
+    base_url: &str,
+    recipient_email: &str,
+    name: &str,
+    token: &str,
+) -> Result<(), AppError> {
+    let reset_link = format!("{}/?password_reset_token={}", base_url, token);
+    let html_content = format!(
+        r#"
+        <h1>Hi {},</h1>
+        <p>An administrator has required you to choose a new password. You have been signed out everywhere.</p>
+        <a href="{}">Choose a new password</a>
+        <p>This link will expire in 24 hours.</p>
+        "#,
+        escape_html(name), reset_link
+    );
+
+    email_client
+        .send_email(recipient_email, "Please choose a new password", &html_content)
+        .await
+        .map_err(|e| {
+            let audit_log = AuditLog::new(
+                "SEND_PASSWORD_RESET_EMAIL".to_string(),
+                "email".to_string(),
+                "FAILURE".to_string(),
+                format!("Failed to send password reset email: {}", e),
+            );
+            RequestFailureLogger::log_audit(&audit_log);
+            AppError::Email(e)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_like_escapes_wildcards() {
+        assert_eq!("50\\%\\_off\\\\", escape_like("50%_off\\"));
+        assert_eq!("alice", escape_like("alice"));
+    }
+}
@@ -0,0 +1,225 @@
+//! API Key Routes
+//!
+//! Lets authenticated users create, list and revoke API keys for machine
+//! clients. Keys are managed from interactive sessions only; an API key
+//! cannot be used to manage keys.
+
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::{
+    create_api_key, list_api_keys, normalize_scopes, require_admin_role, revoke_api_key,
+    ApiKeyRecord, Claims, SCOPE_NEWSLETTERS_SEND,
+};
+use crate::error::{AppError, DatabaseError, ErrorContext, ValidationError};
+use crate::request_logging::{AuditLog, RequestFailureLogger};
+
+const MAX_API_KEY_NAME_LENGTH: usize = 100;
+const MAX_API_KEY_EXPIRY_DAYS: i64 = 365 * 5;
+
+/// API key creation request
+#[derive(Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+    /// Lifetime in days; omitted means the key never expires
+    pub expires_in_days: Option<i64>,
+}
+
+/// API key metadata returned by list and create
+#[derive(Serialize)]
+pub struct ApiKeyResponse {
+    pub id: String,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub expires_at: Option<String>,
+}
+
+impl From<ApiKeyRecord> for ApiKeyResponse {
+    fn from(record: ApiKeyRecord) -> Self {
+        Self {
+            id: record.id.to_string(),
+            name: record.name,
+            key_prefix: record.key_prefix,
+            scopes: record.scopes,
+            created_at: record.created_at.to_rfc3339(),
+            last_used_at: record.last_used_at.map(|t| t.to_rfc3339()),
+            expires_at: record.expires_at.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+/// API key creation response; the only time the full key is returned
+#[derive(Serialize)]
+pub struct CreatedApiKeyResponse {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+}
+
+/// POST /api/api-keys
+///
+/// Create a named, scoped API key. The key is included in the response
+/// once and only its hash is stored.
+///
+/// # Errors
+/// - 400: Missing/too long name, unknown scopes or invalid expiry
+/// - 403: The request used an API key, or a non-admin asked for the
+///   `newsletters:send` scope
+/// - 500: Internal server error
+pub async fn create_api_key_handler(
+    claims: web::ReqData<Claims>,
+    form: web::Json<CreateApiKeyRequest>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("create_api_key");
+    claims.require_session()?;
+    let user_id = claims.user_id()?;
+
+    let name = form.name.trim();
+    if name.is_empty() {
+        return Err(AppError::Validation(ValidationError::EmptyField(
+            "name".to_string(),
+        )));
+    }
+    if name.chars().count() > MAX_API_KEY_NAME_LENGTH {
+        return Err(AppError::Validation(ValidationError::TooLong(
+            "name".to_string(),
+            MAX_API_KEY_NAME_LENGTH,
+        )));
+    }
+    if let Some(days) = form.expires_in_days {
+        if !(1..=MAX_API_KEY_EXPIRY_DAYS).contains(&days) {
+            return Err(AppError::Validation(ValidationError::InvalidFormat(
+                "expires_in_days".to_string(),
+            )));
+        }
+    }
+    let scopes = normalize_scopes(&form.scopes)?;
+    // Only admins may send newsletters, so only they may delegate it
+    if scopes.iter().any(|scope| scope == SCOPE_NEWSLETTERS_SEND) {
+        require_admin_role(pool.get_ref(), user_id).await?;
+    }
+
+    let new_key = create_api_key(pool.get_ref(), user_id, name, &scopes, form.expires_in_days).await?;
+
+    let audit_log = AuditLog::new(
+        "CREATE_API_KEY".to_string(),
+        "api_key".to_string(),
+        "SUCCESS".to_string(),
+        format!("API key '{}' created with scopes: {}", name, scopes.join(", ")),
+    )
+    .with_resource_id(new_key.id.to_string())
+    .with_user_id(user_id.to_string());
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        api_key_id = %new_key.id,
+        "API key created"
+    );
+
+    Ok(HttpResponse::Created().json(CreatedApiKeyResponse {
+        key: new_key.key,
+        api_key: ApiKeyResponse {
+            id: new_key.id.to_string(),
+            name: name.to_string(),
+            key_prefix: new_key.key_prefix,
+            scopes,
+            created_at: new_key.created_at.to_rfc3339(),
+            last_used_at: None,
+            expires_at: new_key.expires_at.map(|t| t.to_rfc3339()),
+        },
+    }))
+}
+
+/// GET /api/api-keys
+///
+/// List the user's active API keys (without the keys themselves).
+///
+/// # Errors
+/// - 403: The request used an API key
+/// - 500: Internal server error
+pub async fn list_api_keys_handler(
+    claims: web::ReqData<Claims>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    claims.require_session()?;
+    let user_id = claims.user_id()?;
+
+    let keys: Vec<ApiKeyResponse> = list_api_keys(pool.get_ref(), user_id)
+        .await?
+        .into_iter()
+        .map(ApiKeyResponse::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+/// DELETE /api/api-keys/{id}
+///
+/// Revoke one of the user's API keys. It stops working immediately.
+///
+/// # Errors
+/// - 403: The request used an API key
+/// - 404: No active key with this ID belongs to the user
+/// - 500: Internal server error
+pub async fn revoke_api_key_handler(
+    claims: web::ReqData<Claims>,
+    key_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("revoke_api_key");
+    claims.require_session()?;
+    let user_id = claims.user_id()?;
+    // Malformed IDs can't match any key
+    let key_id = Uuid::parse_str(&key_id).map_err(|_| {
+        AppError::Database(DatabaseError::NotFound("API key not found".to_string()))
+    })?;
+
+    if !revoke_api_key(pool.get_ref(), user_id, key_id).await? {
+        return Err(AppError::Database(DatabaseError::NotFound(
+            "API key not found".to_string(),
+        )));
+    }
+
+    let audit_log = AuditLog::new(
+        "REVOKE_API_KEY".to_string(),
+        "api_key".to_string(),
+        "SUCCESS".to_string(),
+        "API key revoked".to_string(),
+    )
+    .with_resource_id(key_id.to_string())
+    .with_user_id(user_id.to_string());
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        api_key_id = %key_id,
+        "API key revoked"
+    );
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_request_expiry_is_optional() {
+        let request: CreateApiKeyRequest = serde_json::from_value(serde_json::json!({
+            "name": "CI",
+            "scopes": ["newsletters:send"]
+        }))
+        .unwrap();
+        assert!(request.expires_in_days.is_none());
+    }
+}
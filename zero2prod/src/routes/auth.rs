@@ -2,19 +2,29 @@
 ///
 /// Handles user registration, login, token refresh, and current user information.
 
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse, HttpResponseBuilder};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::auth::{
-    generate_access_token, generate_refresh_token, hash_password, save_refresh_token,
-    revoke_refresh_token, validate_refresh_token, verify_password, Claims,
+    complete_mfa_challenge, complete_password_reset, confirm_email_verification_token, create_email_verification_token,
+    create_mfa_challenge, find_password_reset_account, generate_access_token, generate_refresh_token, hash_password,
+    cleared_session_cookies, csrf_token_for, is_totp_enabled, mfa_challenge_owner, refresh_token_cookie, save_refresh_token,
+    revoke_refresh_token, session_cookies, upgrade_password_hash, validate_refresh_token,
+    verify_dummy_password, verify_password, Claims, JwtKeys, PasswordPolicy, MFA_CHALLENGE_EXPIRY_SECONDS,
+    SCOPE_PROFILE_READ,
 };
-use crate::configuration::JwtSettings;
-use crate::error::{AppError, ErrorContext, ValidationError};
-use crate::validators::{is_valid_email, is_valid_name};
+use crate::configuration::{AuthSettings, JwtSettings, SessionMode, SessionSettings};
+use crate::email_client::{escape_html, EmailClient};
+use crate::error::{AppError, AuthError, ErrorContext, ValidationError};
+use crate::middleware::ClientIp;
+use crate::request_logging::{AuditLog, RequestFailureLogger};
+use crate::security::LoginThrottle;
+use crate::startup::ApplicationBaseUrl;
+use crate::input_policy::InputPolicy;
+use crate::validators::{canonical_email, is_valid_email};
 
 /// User registration request
 #[derive(Deserialize)]
@@ -31,66 +41,161 @@ pub struct LoginRequest {
     pub password: String,
 }
 
-/// Token refresh request
+/// Second login step for users with two-factor authentication
+#[derive(Deserialize)]
+pub struct MfaLoginRequest {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+/// Token refresh (or logout) request
+///
+/// The refresh token may come from the session cookie instead.
 #[derive(Deserialize)]
 pub struct RefreshRequest {
-    pub refresh_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+/// Email verification query
+#[derive(Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+/// Password reset completion request
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    pub token: String,
+    pub new_password: String,
+}
+
+/// Verification email resend request
+#[derive(Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: String,
 }
 
 /// Authentication response with access and refresh tokens
 #[derive(Serialize)]
 pub struct AuthResponse {
     pub access_token: String,
-    pub refresh_token: String,
+    /// Left out in the cookie session mode, where it is set as a cookie
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// Cookie session mode only: send as `X-CSRF-Token` on state-changing
+    /// requests to `/auth`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csrf_token: Option<String>,
     pub token_type: String,
     pub expires_in: i64,
 }
 
+impl AuthResponse {
+    /// Response issuing a new token pair, in the way `session` asks for
+    pub fn issue(
+        mut response: HttpResponseBuilder,
+        session: &SessionSettings,
+        jwt_config: &JwtSettings,
+        access_token: String,
+        refresh_token: String,
+    ) -> HttpResponse {
+        let mut body = AuthResponse {
+            access_token,
+            refresh_token: None,
+            csrf_token: None,
+            token_type: "Bearer".to_string(),
+            expires_in: jwt_config.access_token_expiry,
+        };
+
+        match session.mode {
+            SessionMode::Tokens => body.refresh_token = Some(refresh_token),
+            SessionMode::Cookie => {
+                body.csrf_token = Some(csrf_token_for(&refresh_token));
+                for cookie in
+                    session_cookies(session, &refresh_token, jwt_config.refresh_token_expiry)
+                {
+                    response.cookie(cookie);
+                }
+            }
+        }
+
+        response.json(body)
+    }
+}
+
+/// Returned by login instead of `AuthResponse` when 2FA is enabled
+#[derive(Serialize)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    pub expires_in: i64,
+}
+
 /// User information response
 #[derive(Serialize)]
 pub struct UserResponse {
     pub id: String,
     pub email: String,
     pub name: String,
+    pub email_verified: bool,
+    pub two_factor_enabled: bool,
     pub created_at: String,
 }
 
 /// POST /auth/register
 ///
 /// Register a new user with email, password, and name.
-/// Returns access token and refresh token on success.
+/// Sends a verification link to the email address and returns access token
+/// and refresh token on success. When the email verification policy is
+/// `block_login`, no tokens are issued until the address is verified.
 ///
 /// # Validation
 /// - Email must be valid format and not already registered
 /// - Password must be 8+ chars with digit, lowercase, and uppercase
+/// - Password must not appear in breach data and must be hard enough to
+///   guess, taking the email and name into account
 /// - Name must be valid (non-empty, no suspicious content)
 ///
 /// # Errors
-/// - 400: Validation errors (invalid email/password/name)
+/// - 400: Validation errors (invalid email/password/name; `WEAK_PASSWORD`
+///   and `BREACHED_PASSWORD` explain why a password was rejected)
 /// - 409: Email already registered (duplicate)
 /// - 500: Internal server error
+#[allow(clippy::too_many_arguments)]
 pub async fn register(
     form: web::Json<RegisterRequest>,
     pool: web::Data<PgPool>,
     jwt_config: web::Data<JwtSettings>,
+    jwt_keys: web::Data<JwtKeys>,
+    auth_settings: web::Data<AuthSettings>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    password_policy: web::Data<PasswordPolicy>,
+    input_policy: web::Data<InputPolicy>,
 ) -> Result<HttpResponse, AppError> {
     let context = ErrorContext::new("user_registration");
 
     // Validate inputs
     let email = is_valid_email(&form.email)?;
-    let name = is_valid_name(&form.name)?;
-    let password_hash = hash_password(&form.password)?;
+    if !email_client.can_deliver_to(&email) {
+        return Err(AppError::Validation(ValidationError::InvalidFormat("email".to_string())));
+    }
+    let name = input_policy.check_name(&form.name)?;
+    password_policy.check(&form.password, &[&email, &name]).await?;
+    let password_hash = hash_password(&form.password, &auth_settings.password_hashing).await?;
 
     // Create user in database
     let user_id = Uuid::new_v4();
     sqlx::query(
         r#"
-        INSERT INTO users (id, email, name, password_hash, created_at, updated_at)
-        VALUES ($1, $2, $3, $4, $5, $6)
+        INSERT INTO users (id, email, email_canonical, name, password_hash, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
         "#,
     )
     .bind(user_id)
     .bind(&email)
+    .bind(canonical_email(&email))
     .bind(&name)
     .bind(&password_hash)
     .bind(Utc::now())
@@ -98,8 +203,38 @@ pub async fn register(
     .execute(pool.get_ref())
     .await?;
 
+    // Send verification email. The account exists at this point, so a delivery
+    // failure is logged rather than failing registration; the user can ask
+    // for a new link via /auth/verify-email/resend.
+    let verification_token = create_email_verification_token(pool.get_ref(), user_id).await?;
+    if let Err(e) = send_verification_email(
+        email_client.get_ref(),
+        &base_url.0,
+        &email,
+        &name,
+        &verification_token,
+    )
+    .await
+    {
+        context.log_error(&e);
+    }
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        "User registered successfully"
+    );
+
+    if auth_settings.email_verification.blocks_login() {
+        return Ok(HttpResponse::Created().json(serde_json::json!({
+            "message": "Registration successful. Please verify your email address before logging in.",
+            "email_verification_required": true
+        })));
+    }
+
     // Generate tokens
-    let access_token = generate_access_token(&user_id, &email, jwt_config.get_ref())?;
+    let access_token =
+        generate_access_token(&user_id, &email, jwt_config.get_ref(), jwt_keys.get_ref())?;
     let refresh_token = generate_refresh_token();
 
     // Save refresh token to database
@@ -111,77 +246,160 @@ pub async fn register(
     )
     .await?;
 
-    tracing::info!(
-        request_id = %context.request_id,
-        user_id = %user_id,
-        "User registered successfully"
-    );
-
-    Ok(HttpResponse::Created().json(AuthResponse {
+    Ok(AuthResponse::issue(
+        HttpResponse::Created(),
+        &auth_settings.session,
+        jwt_config.get_ref(),
         access_token,
         refresh_token,
-        token_type: "Bearer".to_string(),
-        expires_in: jwt_config.access_token_expiry,
-    }))
+    ))
 }
 
 /// POST /auth/login
 ///
 /// Authenticate user with email and password.
-/// Returns access token and refresh token on success.
+/// Returns access token and refresh token on success. If the user has
+/// two-factor authentication enabled, returns an `MfaChallengeResponse`
+/// instead; the tokens are issued by `POST /auth/login/mfa`.
 ///
 /// # Errors
 /// - 400: Validation error (invalid email format)
 /// - 401: Invalid credentials (email not found or wrong password)
-/// - 403: Account is inactive
+/// - 403: Email is unverified under the `block_login` policy, or an admin
+///   required a password reset (`PASSWORD_RESET_REQUIRED`)
+/// - 429: Too many failed attempts for this account or client (`Retry-After` set)
 /// - 500: Internal server error
 ///
 /// # Security Notes
-/// - Uses same error message for "not found" and "wrong password"
+/// - Uses same error message for "not found", "wrong password" and
+///   "account inactive", and runs Argon2 for unknown emails too
 /// - Prevents user enumeration attacks
 /// - Only returns tokens if account is active
+/// - Failed attempts are throttled per email and per client IP; unknown
+///   emails are throttled the same way as real ones
+/// - Failures are only cleared once tokens are issued, so a correct
+///   password followed by a failed second factor doesn't reset them
+/// - The account owner is emailed when their account gets locked
+#[allow(clippy::too_many_arguments)]
 pub async fn login(
+    req: HttpRequest,
     form: web::Json<LoginRequest>,
     pool: web::Data<PgPool>,
     jwt_config: web::Data<JwtSettings>,
+    jwt_keys: web::Data<JwtKeys>,
+    auth_settings: web::Data<AuthSettings>,
+    login_throttle: web::Data<LoginThrottle>,
+    email_client: web::Data<EmailClient>,
 ) -> Result<HttpResponse, AppError> {
     let context = ErrorContext::new("user_login");
 
     // Validate email format
     let email = is_valid_email(&form.email)?;
 
+    // Throttle before touching the database; applies to unknown emails too
+    let client_ip = ClientIp::of(&req).map(|ip| ip.to_string());
+    login_throttle.check(&email, client_ip.as_deref()).inspect_err(|e| {
+        let audit_log = AuditLog::new(
+            "LOGIN".to_string(),
+            "user".to_string(),
+            "FAILURE".to_string(),
+            format!("Login attempt throttled: {}", e),
+        );
+        RequestFailureLogger::log_audit(&audit_log);
+    })?;
+
     // Fetch user from database
-    let user = sqlx::query_as::<_, (Uuid, String, String, bool)>(
-        "SELECT id, email, password_hash, is_active FROM users WHERE email = $1",
+    let user = sqlx::query_as::<_, (Uuid, String, String, String, bool, bool, bool)>(
+        r#"
+        SELECT id, email, name, password_hash, is_active, email_verified_at IS NOT NULL,
+               password_reset_required_at IS NOT NULL
+        FROM users WHERE email_canonical = $1
+        "#,
     )
-    .bind(&email)
+    .bind(canonical_email(&email))
     .fetch_optional(pool.get_ref())
-    .await?
-    .ok_or_else(|| {
-        AppError::Validation(ValidationError::InvalidFormat(
-            "Invalid email or password".to_string(),
-        ))
-    })?;
+    .await?;
+
+    let Some((user_id, user_email, name, password_hash, is_active, email_verified, reset_required)) =
+        user
+    else {
+        // Same Argon2 work as a real account so timing doesn't reveal the email is unknown
+        verify_dummy_password(&form.password, &auth_settings.password_hashing).await?;
+        login_throttle.record_failure(&email, client_ip.as_deref());
+        return Err(invalid_credentials());
+    };
+
+    // Verify password; inactive accounts (deactivated or awaiting deletion)
+    // fail the same way so they can't be told apart from unknown emails
+    let password_valid = verify_password(&form.password, &password_hash).await?;
+    if !password_valid || !is_active {
+        record_login_failure(
+            &login_throttle,
+            &pool,
+            &email_client,
+            &auth_settings,
+            &email,
+            client_ip.as_deref(),
+            user_id,
+            &user_email,
+            &name,
+        )
+        .await;
+        return Err(invalid_credentials());
+    }
+
+    // The old password is no longer trusted; only the emailed link works
+    if reset_required {
+        return Err(AppError::Auth(AuthError::PasswordResetRequired));
+    }
 
-    let (user_id, user_email, password_hash, is_active) = user;
+    // Best effort: a failed upgrade shouldn't fail the login
+    if let Err(e) = upgrade_password_hash(
+        pool.get_ref(),
+        user_id,
+        &form.password,
+        &password_hash,
+        &auth_settings.password_hashing,
+    )
+    .await
+    {
+        tracing::warn!(
+            request_id = %context.request_id,
+            user_id = %user_id,
+            error = %e,
+            "Failed to upgrade password hash"
+        );
+    }
 
-    // Check if account is active
-    if !is_active {
-        return Err(AppError::Validation(ValidationError::InvalidFormat(
-            "Account is inactive".to_string(),
-        )));
+    // Checked only after the password so it doesn't reveal which emails exist
+    if !email_verified && auth_settings.email_verification.blocks_login() {
+        return Err(AppError::Auth(AuthError::EmailNotVerified));
     }
 
-    // Verify password
-    let password_valid = verify_password(&form.password, &password_hash)?;
-    if !password_valid {
-        return Err(AppError::Validation(ValidationError::InvalidFormat(
-            "Invalid email or password".to_string(),
-        )));
+    // Password is correct: ask for the second factor before issuing tokens
+    if is_totp_enabled(pool.get_ref(), user_id).await? {
+        let mfa_token = create_mfa_challenge(pool.get_ref(), user_id).await?;
+
+        tracing::info!(
+            request_id = %context.request_id,
+            user_id = %user_id,
+            "Password accepted, two-factor challenge issued"
+        );
+
+        return Ok(HttpResponse::Ok().json(MfaChallengeResponse {
+            mfa_required: true,
+            mfa_token,
+            expires_in: MFA_CHALLENGE_EXPIRY_SECONDS,
+        }));
     }
 
+    // Only a session clears the failures, so the second step can't be
+    // retried indefinitely behind fresh password logins
+    login_throttle.record_success(&email);
+
     // Generate tokens
-    let access_token = generate_access_token(&user_id, &user_email, jwt_config.get_ref())?;
+    let access_token =
+        generate_access_token(&user_id, &user_email, jwt_config.get_ref(), jwt_keys.get_ref())?;
     let refresh_token = generate_refresh_token();
 
     // Save refresh token to database
@@ -199,12 +417,122 @@ pub async fn login(
         "User logged in successfully"
     );
 
-    Ok(HttpResponse::Ok().json(AuthResponse {
+    Ok(AuthResponse::issue(
+        HttpResponse::Ok(),
+        &auth_settings.session,
+        jwt_config.get_ref(),
         access_token,
         refresh_token,
-        token_type: "Bearer".to_string(),
-        expires_in: jwt_config.access_token_expiry,
-    }))
+    ))
+}
+
+/// POST /auth/login/mfa
+///
+/// Second login step for users with two-factor authentication.
+/// Exchanges the `mfa_token` from `POST /auth/login` and a TOTP or recovery
+/// code for access and refresh tokens.
+///
+/// # Errors
+/// - 401: Challenge token invalid/expired, or wrong code
+/// - 403: Account is inactive
+/// - 429: The account or client is locked after repeated failures
+/// - 500: Internal server error
+///
+/// # Security Notes
+/// - Challenge tokens are single-use and expire after 5 minutes
+/// - A challenge is discarded after 5 wrong codes
+/// - Wrong codes count towards the account lockout like wrong passwords,
+///   and a locked account can't complete a pending challenge
+#[allow(clippy::too_many_arguments)]
+pub async fn login_mfa(
+    req: HttpRequest,
+    form: web::Json<MfaLoginRequest>,
+    pool: web::Data<PgPool>,
+    jwt_config: web::Data<JwtSettings>,
+    jwt_keys: web::Data<JwtKeys>,
+    auth_settings: web::Data<AuthSettings>,
+    login_throttle: web::Data<LoginThrottle>,
+    email_client: web::Data<EmailClient>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("user_login_mfa");
+    let client_ip = ClientIp::of(&req).map(|ip| ip.to_string());
+
+    let owner = mfa_challenge_owner(pool.get_ref(), &form.mfa_token).await?;
+    if let Some((_, email, _)) = &owner {
+        login_throttle.check(email, client_ip.as_deref())?;
+    }
+
+    let result = complete_mfa_challenge(pool.get_ref(), &form.mfa_token, &form.code).await;
+    let user_id = match result {
+        Ok(user_id) => user_id,
+        Err(e) => {
+            let audit_log = AuditLog::new(
+                "LOGIN_MFA".to_string(),
+                "user".to_string(),
+                "FAILURE".to_string(),
+                format!("Two-factor login failed: {}", e),
+            );
+            RequestFailureLogger::log_audit(&audit_log);
+
+            if let (AppError::Auth(AuthError::InvalidMfaCode), Some((owner_id, email, name))) =
+                (&e, &owner)
+            {
+                record_login_failure(
+                    &login_throttle,
+                    &pool,
+                    &email_client,
+                    &auth_settings,
+                    email,
+                    client_ip.as_deref(),
+                    *owner_id,
+                    email,
+                    name,
+                )
+                .await;
+            }
+            return Err(e);
+        }
+    };
+
+    let user_email = sqlx::query_scalar::<_, String>(
+        "SELECT email FROM users WHERE id = $1 AND is_active = true",
+    )
+    .bind(user_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or(AppError::Auth(AuthError::AccountInactive))?;
+
+    // Generate tokens
+    let access_token =
+        generate_access_token(&user_id, &user_email, jwt_config.get_ref(), jwt_keys.get_ref())?;
+    let refresh_token = generate_refresh_token();
+
+    // Save refresh token to database
+    save_refresh_token(
+        pool.get_ref(),
+        user_id,
+        &refresh_token,
+        jwt_config.refresh_token_expiry,
+    )
+    .await?;
+
+    if let Some((_, email, _)) = &owner {
+        login_throttle.record_success(email);
+    }
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        "User logged in successfully with two-factor authentication"
+    );
+
+    Ok(AuthResponse::issue(
+        HttpResponse::Ok(),
+        &auth_settings.session,
+        jwt_config.get_ref(),
+        access_token,
+        refresh_token,
+    ))
 }
 
 /// POST /auth/refresh
@@ -212,27 +540,37 @@ pub async fn login(
 /// Refresh access token using a refresh token.
 /// Implements token rotation: old token is revoked, new token is issued.
 ///
+/// The refresh token is read from the body, or else from the session
+/// cookie; cookie requests need the `X-CSRF-Token` header (checked by
+/// `CsrfMiddleware`).
+///
 /// # Token Rotation Security
 /// - Old refresh token is revoked after new token is issued
 /// - If client uses old token again after refresh, it will be rejected
 /// - Detects token theft: attacker cannot reuse stolen token if legitimate refresh already happened
 ///
 /// # Errors
+/// - 400: No refresh token
 /// - 401: Invalid, expired, or revoked refresh token
-/// - 403: Associated account is inactive
+/// - 403: Associated account is inactive, or the CSRF token is missing
 /// - 500: Internal server error
 pub async fn refresh(
-    form: web::Json<RefreshRequest>,
+    req: HttpRequest,
+    form: Option<web::Json<RefreshRequest>>,
     pool: web::Data<PgPool>,
     jwt_config: web::Data<JwtSettings>,
+    jwt_keys: web::Data<JwtKeys>,
+    auth_settings: web::Data<AuthSettings>,
 ) -> Result<HttpResponse, AppError> {
     let context = ErrorContext::new("token_refresh");
 
+    let presented_token = presented_refresh_token(&req, form)?;
+
     // Validate refresh token and get user_id
-    let user_id = validate_refresh_token(pool.get_ref(), &form.refresh_token).await?;
+    let user_id = validate_refresh_token(pool.get_ref(), &presented_token).await?;
 
     // Revoke old token (token rotation)
-    revoke_refresh_token(pool.get_ref(), &form.refresh_token).await?;
+    revoke_refresh_token(pool.get_ref(), &presented_token).await?;
 
     // Fetch user email
     let user_email = sqlx::query_scalar::<_, String>(
@@ -243,7 +581,8 @@ pub async fn refresh(
     .await?;
 
     // Generate new tokens
-    let access_token = generate_access_token(&user_id, &user_email, jwt_config.get_ref())?;
+    let access_token =
+        generate_access_token(&user_id, &user_email, jwt_config.get_ref(), jwt_keys.get_ref())?;
     let refresh_token = generate_refresh_token();
 
     // Save new refresh token to database
@@ -261,12 +600,100 @@ pub async fn refresh(
         "Token refreshed successfully"
     );
 
-    Ok(HttpResponse::Ok().json(AuthResponse {
+    Ok(AuthResponse::issue(
+        HttpResponse::Ok(),
+        &auth_settings.session,
+        jwt_config.get_ref(),
         access_token,
         refresh_token,
-        token_type: "Bearer".to_string(),
-        expires_in: jwt_config.access_token_expiry,
-    }))
+    ))
+}
+
+/// POST /auth/logout
+///
+/// Revoke the refresh token (from the body or the session cookie) and
+/// clear the session cookies. Access tokens stay valid until they expire.
+///
+/// # Errors
+/// - 400: No refresh token
+/// - 403: The CSRF token is missing
+/// - 500: Internal server error
+pub async fn logout(
+    req: HttpRequest,
+    form: Option<web::Json<RefreshRequest>>,
+    pool: web::Data<PgPool>,
+    auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, AppError> {
+    let refresh_token = presented_refresh_token(&req, form)?;
+
+    // Unknown or already revoked tokens are as good as logged out
+    revoke_refresh_token(pool.get_ref(), &refresh_token).await?;
+
+    let mut response = HttpResponse::NoContent();
+    for cookie in cleared_session_cookies(&auth_settings.session) {
+        response.cookie(cookie);
+    }
+    Ok(response.finish())
+}
+
+/// Count a failed password or second factor towards the lockout
+///
+/// When this failure locks the account, the lock is audited and the owner
+/// is emailed.
+#[allow(clippy::too_many_arguments)]
+async fn record_login_failure(
+    login_throttle: &LoginThrottle,
+    pool: &PgPool,
+    email_client: &web::Data<EmailClient>,
+    auth_settings: &AuthSettings,
+    throttle_email: &str,
+    client_ip: Option<&str>,
+    user_id: Uuid,
+    user_email: &str,
+    name: &str,
+) {
+    if !login_throttle.record_failure(throttle_email, client_ip) {
+        return;
+    }
+
+    let audit_log = AuditLog::new(
+        "ACCOUNT_LOCKED".to_string(),
+        "user".to_string(),
+        "SUCCESS".to_string(),
+        "Account locked after repeated failed logins".to_string(),
+    )
+    .with_resource_id(user_id.to_string())
+    .with_user_id(user_id.to_string());
+    RequestFailureLogger::record_audit(pool, &audit_log).await;
+
+    // Sent in the background so response timing doesn't reveal the lock
+    let email_client = email_client.clone();
+    let user_email = user_email.to_string();
+    let name = name.to_string();
+    let lockout_minutes = auth_settings.login_throttle.lockout_seconds.div_ceil(60);
+    tokio::spawn(async move {
+        let _ = send_lockout_email(&email_client, &user_email, &name, lockout_minutes).await;
+    });
+}
+
+/// The one login failure reported for unknown emails, wrong passwords and inactive accounts
+fn invalid_credentials() -> AppError {
+    AppError::Validation(ValidationError::InvalidFormat(
+        "Invalid email or password".to_string(),
+    ))
+}
+
+/// Refresh token from the request body, or else the session cookie
+fn presented_refresh_token(
+    req: &HttpRequest,
+    form: Option<web::Json<RefreshRequest>>,
+) -> Result<String, AppError> {
+    form.and_then(|form| form.into_inner().refresh_token)
+        .filter(|token| !token.is_empty())
+        .or_else(|| refresh_token_cookie(req))
+        .ok_or_else(|| {
+            AppError::Validation(ValidationError::EmptyField("refresh_token".to_string()))
+        })
 }
 
 /// GET /auth/me
@@ -281,16 +708,20 @@ pub async fn refresh(
 /// # Errors
 /// - 401: Missing or invalid token (handled by middleware)
 /// - 404: User not found (should not happen if token is valid)
-/// - 403: User account is inactive
+/// - 403: User account is inactive, or API key lacks `profile:read`
 /// - 500: Internal server error
 pub async fn get_current_user(
     claims: web::ReqData<Claims>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, AppError> {
+    claims.require_scope(SCOPE_PROFILE_READ)?;
     let user_id = claims.user_id()?;
 
-    let user = sqlx::query_as::<_, (Uuid, String, String, chrono::DateTime<Utc>)>(
-        "SELECT id, email, name, created_at FROM users WHERE id = $1 AND is_active = true",
+    let user = sqlx::query_as::<_, (Uuid, String, String, bool, bool, chrono::DateTime<Utc>)>(
+        r#"
+        SELECT id, email, name, email_verified_at IS NOT NULL, totp_enabled_at IS NOT NULL, created_at
+        FROM users WHERE id = $1 AND is_active = true
+        "#,
     )
     .bind(user_id)
     .fetch_one(pool.get_ref())
@@ -300,6 +731,228 @@ pub async fn get_current_user(
         id: user.0.to_string(),
         email: user.1,
         name: user.2,
-        created_at: user.3.to_rfc3339(),
+        email_verified: user.3,
+        two_factor_enabled: user.4,
+        created_at: user.5.to_rfc3339(),
     }))
 }
+
+/// GET /auth/verify-email?token=
+///
+/// Verify the email address of a registered user from the link sent at
+/// registration.
+///
+/// # Errors
+/// - 400: Token is invalid or expired
+/// - 500: Internal server error
+pub async fn verify_email(
+    query: web::Query<VerifyEmailQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("email_verification");
+
+    let user_id = confirm_email_verification_token(pool.get_ref(), &query.token)
+        .await
+        .map_err(|e| {
+            let audit_log = AuditLog::new(
+                "VERIFY_EMAIL".to_string(),
+                "user".to_string(),
+                "FAILURE".to_string(),
+                format!("Email verification failed: {}", e),
+            );
+            RequestFailureLogger::log_audit(&audit_log);
+            e
+        })?;
+
+    let audit_log = AuditLog::new(
+        "VERIFY_EMAIL".to_string(),
+        "user".to_string(),
+        "SUCCESS".to_string(),
+        "Email address verified".to_string(),
+    )
+    .with_resource_id(user_id.to_string())
+    .with_user_id(user_id.to_string());
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        "Email verified successfully"
+    );
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Thank you for verifying your email address!",
+        "request_id": context.request_id
+    })))
+}
+
+/// POST /auth/verify-email/resend
+///
+/// Send a new verification link to an unverified account.
+///
+/// # Security Notes
+/// - Always returns 202, whether or not the email belongs to an unverified
+///   account, to prevent user enumeration
+pub async fn resend_verification_email(
+    form: web::Json<ResendVerificationRequest>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("email_verification_resend");
+    let email = is_valid_email(&form.email)?;
+
+    let user = sqlx::query_as::<_, (Uuid, String)>(
+        r#"
+        SELECT id, name FROM users
+        WHERE email_canonical = $1 AND is_active = true AND email_verified_at IS NULL
+        "#,
+    )
+    .bind(canonical_email(&email))
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    if let Some((user_id, name)) = user {
+        let token = create_email_verification_token(pool.get_ref(), user_id).await?;
+        if let Err(e) =
+            send_verification_email(email_client.get_ref(), &base_url.0, &email, &name, &token).await
+        {
+            context.log_error(&e);
+        }
+
+        tracing::info!(
+            request_id = %context.request_id,
+            user_id = %user_id,
+            "Verification email resent"
+        );
+    }
+
+    Ok(HttpResponse::Accepted().json(serde_json::json!({
+        "message": "If the account exists and is unverified, a new verification email has been sent",
+        "request_id": context.request_id
+    })))
+}
+
+/// POST /auth/password-reset
+///
+/// Set a new password with the token from a password reset email. All of
+/// the user's sessions are signed out.
+///
+/// # Errors
+/// - 400: Token is invalid or expired, or the new password is rejected
+///   (`WEAK_PASSWORD`, `BREACHED_PASSWORD`)
+/// - 500: Internal server error
+pub async fn reset_password(
+    form: web::Json<PasswordResetRequest>,
+    pool: web::Data<PgPool>,
+    auth_settings: web::Data<AuthSettings>,
+    password_policy: web::Data<PasswordPolicy>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("password_reset");
+
+    let account = find_password_reset_account(pool.get_ref(), &form.token).await?;
+    password_policy
+        .check(&form.new_password, &[&account.email, &account.name])
+        .await?;
+    let new_password_hash =
+        hash_password(&form.new_password, &auth_settings.password_hashing).await?;
+
+    let user_id = complete_password_reset(pool.get_ref(), &form.token, &new_password_hash)
+        .await
+        .inspect_err(|e| {
+            let audit_log = AuditLog::new(
+                "RESET_PASSWORD".to_string(),
+                "user".to_string(),
+                "FAILURE".to_string(),
+                format!("Password reset failed: {}", e),
+            )
+            .with_resource_id(account.user_id.to_string());
+            RequestFailureLogger::log_audit(&audit_log);
+        })?;
+
+    let audit_log = AuditLog::new(
+        "RESET_PASSWORD".to_string(),
+        "user".to_string(),
+        "SUCCESS".to_string(),
+        "Password reset; all sessions revoked".to_string(),
+    )
+    .with_resource_id(user_id.to_string())
+    .with_user_id(user_id.to_string());
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        "Password reset completed"
+    );
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Your password has been reset. Please sign in again.",
+        "request_id": context.request_id
+    })))
+}
+
+/// Sends the email verification link to a newly registered user
+async fn send_verification_email(
+    email_client: &EmailClient,
+    base_url: &str,
+    recipient_email: &str,
+    name: &str,
+    token: &str,
+) -> Result<(), AppError> {
+    let verification_link = format!("{}/auth/verify-email?token={}", base_url, token);
+    let html_content = format!(
+        r#"
+        <h1>Welcome {}!</h1>
+        <p>Please verify your email address by clicking the link below:</p>
+        <a href="{}">Verify Email</a>
+        <p>This link will expire in 24 hours.</p>
+        "#,
+        escape_html(name), verification_link
+    );
+
+    email_client
+        .send_email(recipient_email, "Please verify your email address", &html_content)
+        .await
+        .map_err(|e| {
+            let audit_log = AuditLog::new(
+                "SEND_VERIFICATION_EMAIL".to_string(),
+                "email".to_string(),
+                "FAILURE".to_string(),
+                format!("Failed to send verification email: {}", e),
+            );
+            RequestFailureLogger::log_audit(&audit_log);
+            AppError::Email(e)
+        })
+}
+
+async fn send_lockout_email(
+    email_client: &EmailClient,
+    recipient_email: &str,
+    name: &str,
+    lockout_minutes: u64,
+) -> Result<(), AppError> {
+    let html_content = format!(
+        r#"
+        <h1>Hi {},</h1>
+        <p>Your account was temporarily locked after several failed sign-in attempts.</p>
+        <p>It will unlock automatically in {} minutes.</p>
+        <p>If this wasn't you, we recommend changing your password once you can sign in again.</p>
+        "#,
+        escape_html(name), lockout_minutes
+    );
+
+    email_client
+        .send_email(recipient_email, "Your account has been temporarily locked", &html_content)
+        .await
+        .map_err(|e| {
+            let audit_log = AuditLog::new(
+                "SEND_LOCKOUT_EMAIL".to_string(),
+                "email".to_string(),
+                "FAILURE".to_string(),
+                format!("Failed to send lockout email: {}", e),
+            );
+            RequestFailureLogger::log_audit(&audit_log);
+            AppError::Email(e)
+        })
+}
@@ -46,7 +46,7 @@ async fn get_subscriber_id_from_token(
 ) -> Result<String, AppError> {
     let result = sqlx::query_as::<_, (String,)>(
         r#"
-        SELECT subscriber_id
+        SELECT subscriber_id::text
         FROM subscription_tokens
         WHERE subscription_token = $1
         AND expires_at > NOW()
@@ -83,7 +83,7 @@ async fn update_subscription_status(
         r#"
         UPDATE subscriptions
         SET status = $1
-        WHERE id = $2
+        WHERE id = $2::uuid
         "#,
     )
     .bind(status)
@@ -100,7 +100,7 @@ async fn update_subscription_status(
     sqlx::query(
         r#"
         DELETE FROM subscription_tokens
-        WHERE subscriber_id = $1
+        WHERE subscriber_id = $1::uuid
         "#,
     )
     .bind(subscriber_id)
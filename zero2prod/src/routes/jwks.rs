@@ -0,0 +1,24 @@
+//! JWKS Route
+//!
+//! Publishes the public keys that verify our access tokens so downstream
+//! services can validate them without sharing a secret.
+
+use actix_web::{http::header, web, HttpResponse};
+
+use crate::auth::JwtKeys;
+
+/// How long verifiers may cache the key set
+const JWKS_MAX_AGE_SECONDS: u32 = 300;
+
+/// GET /.well-known/jwks.json
+///
+/// Returns the active signing key and retired keys still accepted for
+/// verification. Empty when tokens are signed with the shared HS256 secret.
+pub async fn jwks(jwt_keys: web::Data<JwtKeys>) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", JWKS_MAX_AGE_SECONDS),
+        ))
+        .json(jwt_keys.jwks())
+}
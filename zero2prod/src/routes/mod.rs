@@ -3,12 +3,46 @@ mod subscriptions;
 mod confirmation;
 mod newsletters;
 mod auth;
+mod account;
+mod account_data;
+mod two_factor;
+mod api_keys;
+mod jwks;
+mod oidc;
+mod admin_users;
+mod preferences;
+mod subscriber_data;
+mod subscriber_bulk;
+mod admin_subscribers;
 
 pub use health_check::health_check;
-pub use subscriptions::subscribe;
+pub use subscriptions::{subscribe, subscription_challenge};
 pub use confirmation::confirm_subscription;
-pub use newsletters::{send_newsletter_to_all, send_newsletter_to_confirmed};
-pub use auth::{register, login, refresh, get_current_user};
+pub use preferences::{get_preferences, update_preferences, unsubscribe};
+pub use subscriber_data::{
+    admin_export_subscriber_data, admin_erase_subscriber_data, export_my_subscriber_data,
+    erase_my_subscriber_data,
+};
+pub use subscriber_bulk::{import_subscribers, get_import_job, export_subscribers};
+pub use admin_subscribers::{
+    list_subscribers, get_subscriber, confirm_subscriber, change_subscriber_status,
+    delete_subscriber,
+};
+pub use newsletters::{publish_newsletter_to_all, publish_newsletter_to_confirmed};
+pub use auth::{
+    register, login, login_mfa, refresh, logout, get_current_user, verify_email,
+    resend_verification_email, reset_password,
+};
+pub use account::{change_password, change_email, confirm_email_change};
+pub use account_data::{export_account_data, delete_account};
+pub use two_factor::{setup_two_factor, confirm_two_factor, disable_two_factor};
+pub use api_keys::{create_api_key_handler, list_api_keys_handler, revoke_api_key_handler};
+pub use jwks::jwks;
+pub use oidc::{oidc_login, oidc_callback};
+pub use admin_users::{
+    list_users, get_user, list_user_sessions, deactivate_user, reactivate_user,
+    force_password_reset, change_user_role,
+};
 
 // greet 함수를 직접 정의
 use actix_web::Responder;
@@ -1,8 +1,12 @@
 use actix_web::{web, HttpResponse};
 use serde::Deserialize;
 use sqlx::PgPool;
+use uuid::Uuid;
+use crate::auth::{require_admin_role, require_verified_email, Claims, SCOPE_NEWSLETTERS_SEND};
+use crate::configuration::AuthSettings;
 use crate::email_client::EmailClient;
-use crate::error::{AppError, DatabaseError, ErrorContext};
+use crate::error::{AppError, DatabaseError, ErrorContext, ValidationError};
+use crate::preference_link::PreferenceLinks;
 use crate::request_logging::{RequestMetadata, FailedRequest, RequestFailureLogger, AuditLog};
 use crate::data_validation::validate_subscriber_data;
 
@@ -10,6 +14,8 @@ use crate::data_validation::validate_subscriber_data;
 pub struct NewsletterData {
     subject: Option<String>,
     html_content: Option<String>,
+    /// Only send to subscribers who haven't opted out of this topic
+    topic: Option<String>,
 }
 
 #[derive(sqlx::FromRow)]
@@ -21,10 +27,11 @@ pub struct SubscriberData {
 }
 
 /// Send email to all subscribers (including unconfirmed)
-pub async fn send_newsletter_to_all(
+async fn send_newsletter_to_all(
     form: web::Json<NewsletterData>,
     pool: web::Data<PgPool>,
     email_client: web::Data<EmailClient>,
+    preference_links: web::Data<PreferenceLinks>,
 ) -> Result<HttpResponse, AppError> {
     let error_context = ErrorContext::new("newsletter_send_all");
 
@@ -93,8 +100,10 @@ pub async fn send_newsletter_to_all(
         "Processing newsletter send to all subscribers"
     );
 
+    let topic = validate_topic(form.topic.as_deref(), &preference_links)?;
+
     // Fetch all subscribers
-    let subscribers = get_all_subscribers(&pool, &error_context).await?;
+    let subscribers = get_all_subscribers(&pool, topic, &error_context).await?;
 
     if subscribers.is_empty() {
         let audit_log = AuditLog::new(
@@ -146,10 +155,11 @@ pub async fn send_newsletter_to_all(
             continue;
         }
 
+        let body = with_preference_footer(html_content, &preference_links, &subscriber.id);
         match email_client.send_email(
             &subscriber.email,
             subject,
-            html_content,
+            &body,
         ).await {
             Ok(_) => {
                 sent_count += 1;
@@ -197,11 +207,63 @@ pub async fn send_newsletter_to_all(
     })))
 }
 
+/// Only active admins with a verified email may mail the subscriber list
+///
+/// # Errors
+/// - `InsufficientScope` for API keys without `newsletters:send`
+/// - `PermissionDenied` if the user isn't an active admin
+/// - `EmailNotVerified` if the verification policy blocks the action
+async fn require_newsletter_sender(
+    pool: &PgPool,
+    claims: &Claims,
+    auth_settings: &AuthSettings,
+) -> Result<(), AppError> {
+    claims.require_scope(SCOPE_NEWSLETTERS_SEND)?;
+    let user_id = claims.user_id()?;
+    require_admin_role(pool, user_id).await?;
+    require_verified_email(pool, user_id, auth_settings).await
+}
+
+/// POST /api/newsletters/send-all
+///
+/// Send a newsletter to all subscribers. Requires an active admin with a
+/// verified email, via a session or an API key with the `newsletters:send`
+/// scope.
+pub async fn publish_newsletter_to_all(
+    claims: web::ReqData<Claims>,
+    form: web::Json<NewsletterData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    preference_links: web::Data<PreferenceLinks>,
+    auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, AppError> {
+    require_newsletter_sender(pool.get_ref(), &claims, auth_settings.get_ref()).await?;
+    send_newsletter_to_all(form, pool, email_client, preference_links).await
+}
+
+/// POST /api/newsletters/send-confirmed
+///
+/// Send a newsletter to confirmed subscribers. Requires an active admin
+/// with a verified email, via a session or an API key with the
+/// `newsletters:send` scope.
+pub async fn publish_newsletter_to_confirmed(
+    claims: web::ReqData<Claims>,
+    form: web::Json<NewsletterData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    preference_links: web::Data<PreferenceLinks>,
+    auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, AppError> {
+    require_newsletter_sender(pool.get_ref(), &claims, auth_settings.get_ref()).await?;
+    send_newsletter_to_confirmed(form, pool, email_client, preference_links).await
+}
+
 /// Send email to only confirmed subscribers
-pub async fn send_newsletter_to_confirmed(
+async fn send_newsletter_to_confirmed(
     form: web::Json<NewsletterData>,
     pool: web::Data<PgPool>,
     email_client: web::Data<EmailClient>,
+    preference_links: web::Data<PreferenceLinks>,
 ) -> Result<HttpResponse, AppError> {
     let error_context = ErrorContext::new("newsletter_send_confirmed");
 
@@ -270,8 +332,10 @@ pub async fn send_newsletter_to_confirmed(
         "Processing newsletter send to confirmed subscribers"
     );
 
+    let topic = validate_topic(form.topic.as_deref(), &preference_links)?;
+
     // Fetch only confirmed subscribers
-    let subscribers = get_confirmed_subscribers(&pool, &error_context).await?;
+    let subscribers = get_confirmed_subscribers(&pool, topic, &error_context).await?;
 
     if subscribers.is_empty() {
         let audit_log = AuditLog::new(
@@ -323,10 +387,11 @@ pub async fn send_newsletter_to_confirmed(
             continue;
         }
 
+        let body = with_preference_footer(html_content, &preference_links, &subscriber.id);
         match email_client.send_email(
             &subscriber.email,
             subject,
-            html_content,
+            &body,
         ).await {
             Ok(_) => {
                 sent_count += 1;
@@ -375,13 +440,23 @@ pub async fn send_newsletter_to_confirmed(
 }
 
 /// Fetch all subscribers from database
+///
+/// Skips subscribers who unsubscribed, paused delivery or opted out of
+/// `topic`.
 async fn get_all_subscribers(
     pool: &web::Data<PgPool>,
+    topic: Option<&str>,
     context: &ErrorContext,
 ) -> Result<Vec<SubscriberData>, AppError> {
     let subscribers = sqlx::query_as::<_, SubscriberData>(
-        "SELECT id, email, name, status FROM subscriptions"
+        r#"
+        SELECT id::text AS id, email, name, status FROM subscriptions
+        WHERE status <> 'unsubscribed'
+        AND (paused_until IS NULL OR paused_until <= now())
+        AND ($1::text IS NULL OR NOT ($1 = ANY(excluded_topics)))
+        "#
     )
+    .bind(topic)
     .fetch_all(pool.get_ref())
     .await
     .map_err(|e| {
@@ -423,13 +498,22 @@ async fn get_all_subscribers(
 }
 
 /// Fetch only confirmed subscribers from database
+///
+/// Skips subscribers who paused delivery or opted out of `topic`.
 async fn get_confirmed_subscribers(
     pool: &web::Data<PgPool>,
+    topic: Option<&str>,
     context: &ErrorContext,
 ) -> Result<Vec<SubscriberData>, AppError> {
     let subscribers = sqlx::query_as::<_, SubscriberData>(
-        "SELECT id, email, name, status FROM subscriptions WHERE status = 'confirmed'"
+        r#"
+        SELECT id::text AS id, email, name, status FROM subscriptions
+        WHERE status = 'confirmed'
+        AND (paused_until IS NULL OR paused_until <= now())
+        AND ($1::text IS NULL OR NOT ($1 = ANY(excluded_topics)))
+        "#
     )
+    .bind(topic)
     .fetch_all(pool.get_ref())
     .await
     .map_err(|e| {
@@ -469,3 +553,39 @@ async fn get_confirmed_subscribers(
 
     Ok(subscribers)
 }
+
+/// Check that a newsletter's topic is one subscribers can opt out of
+fn validate_topic<'a>(
+    topic: Option<&'a str>,
+    preference_links: &PreferenceLinks,
+) -> Result<Option<&'a str>, AppError> {
+    match topic {
+        Some(topic) if !preference_links.topics().iter().any(|t| t == topic) => {
+            let audit_log = AuditLog::new(
+                "VALIDATE_TOPIC".to_string(),
+                "newsletter".to_string(),
+                "FAILURE".to_string(),
+                format!("Unknown newsletter topic: {}", topic),
+            );
+            RequestFailureLogger::log_audit(&audit_log);
+
+            Err(AppError::Validation(ValidationError::InvalidFormat("topic".to_string())))
+        }
+        topic => Ok(topic),
+    }
+}
+
+/// Append the subscriber's preference center link to a newsletter
+fn with_preference_footer(
+    html_content: &str,
+    preference_links: &PreferenceLinks,
+    subscriber_id: &str,
+) -> String {
+    // The id was validated by `validate_subscriber_data` before sending
+    match Uuid::parse_str(subscriber_id) {
+        Ok(subscriber_id) => {
+            format!("{}{}", html_content, preference_links.email_footer(subscriber_id))
+        }
+        Err(_) => html_content.to_string(),
+    }
+}
@@ -0,0 +1,267 @@
+//! OpenID Connect Login Routes
+//!
+//! Browser-facing endpoints of the authorization-code flow. The callback
+//! answers with the same `AuthResponse` (or `MfaChallengeResponse`) as
+//! `POST /auth/login`.
+
+use actix_web::{http::header, web, HttpResponse};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::{
+    create_mfa_challenge, generate_access_token, generate_refresh_token, is_totp_enabled,
+    link_or_provision_user, save_refresh_token, JwtKeys, OidcClient, OidcLinkOutcome,
+    MFA_CHALLENGE_EXPIRY_SECONDS,
+};
+use crate::configuration::{AuthSettings, JwtSettings};
+use crate::error::{AppError, AuthError, ErrorContext};
+use crate::request_logging::{AuditLog, RequestFailureLogger};
+use crate::routes::auth::{AuthResponse, MfaChallengeResponse};
+
+/// Query parameters the provider redirects back with
+#[derive(Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+/// GET /auth/oidc/{provider}/login
+///
+/// Redirects the browser to the provider's authorization endpoint.
+///
+/// # Errors
+/// - 404: Provider is not configured
+/// - 502: Provider discovery failed
+/// - 500: Internal server error
+pub async fn oidc_login(
+    provider: web::Path<String>,
+    pool: web::Data<PgPool>,
+    oidc_client: web::Data<OidcClient>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("oidc_login");
+
+    let authorization_url = oidc_client
+        .begin_login(pool.get_ref(), &provider)
+        .await
+        .inspect_err(|e| {
+            tracing::warn!(
+                request_id = %context.request_id,
+                provider = %provider,
+                error = %e,
+                "Failed to start OIDC login"
+            );
+        })?;
+
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, authorization_url))
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .finish())
+}
+
+/// GET /auth/oidc/{provider}/callback?code=...&state=...
+///
+/// Completes the login and issues tokens for the linked (or newly
+/// provisioned) user.
+///
+/// # Errors
+/// - 401: Missing/unknown/expired state, invalid ID token, or the user
+///   denied the login at the provider
+/// - 403: Account is inactive, or the provider email is not verified
+/// - 404: Provider is not configured
+/// - 502: Provider unreachable or code exchange failed
+/// - 500: Internal server error
+///
+/// # Security Notes
+/// - `state` is single-use and bound to the provider it was issued for
+/// - Users with 2FA enabled still get a second-factor challenge
+pub async fn oidc_callback(
+    provider: web::Path<String>,
+    query: web::Query<OidcCallbackQuery>,
+    pool: web::Data<PgPool>,
+    oidc_client: web::Data<OidcClient>,
+    jwt_config: web::Data<JwtSettings>,
+    jwt_keys: web::Data<JwtKeys>,
+    auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("oidc_callback");
+    let provider = provider.into_inner();
+
+    let Some(state) = query.state.as_deref() else {
+        return Err(AppError::Auth(AuthError::TokenInvalid));
+    };
+
+    if let Some(error) = &query.error {
+        oidc_client.abandon_login(pool.get_ref(), state).await?;
+        log_oidc_audit(
+            pool.get_ref(),
+            "OIDC_LOGIN",
+            "FAILURE",
+            &format!("Provider '{}' returned error '{}'", provider, error),
+            None,
+        )
+        .await;
+        return Err(AppError::Auth(AuthError::InvalidCredentials));
+    }
+
+    let Some(code) = query.code.as_deref() else {
+        oidc_client.abandon_login(pool.get_ref(), state).await?;
+        return Err(AppError::Auth(AuthError::TokenInvalid));
+    };
+
+    let identity = match oidc_client
+        .complete_login(pool.get_ref(), &provider, code, state)
+        .await
+    {
+        Ok(identity) => identity,
+        Err(e) => {
+            log_oidc_audit(
+                pool.get_ref(),
+                "OIDC_LOGIN",
+                "FAILURE",
+                &format!("Login with provider '{}' failed: {}", provider, e),
+                None,
+            )
+            .await;
+            return Err(e);
+        }
+    };
+
+    let (user_id, outcome) =
+        match link_or_provision_user(pool.get_ref(), &identity, &auth_settings.password_hashing).await {
+            Ok(linked) => linked,
+            Err(e) => {
+                log_oidc_audit(
+                    pool.get_ref(),
+                    "OIDC_LOGIN",
+                    "FAILURE",
+                    &format!("Could not link identity from provider '{}': {}", provider, e),
+                    None,
+                )
+                .await;
+                return Err(e);
+            }
+        };
+
+    match outcome {
+        OidcLinkOutcome::Existing => {}
+        OidcLinkOutcome::LinkedByEmail => {
+            log_oidc_audit(
+                pool.get_ref(),
+                "OIDC_IDENTITY_LINKED",
+                "SUCCESS",
+                &format!("Linked identity from provider '{}' by verified email", provider),
+                Some(user_id),
+            )
+            .await
+        }
+        OidcLinkOutcome::ClaimedUnverified => {
+            log_oidc_audit(
+                pool.get_ref(),
+                "OIDC_IDENTITY_LINKED",
+                "SUCCESS",
+                &format!(
+                    "Linked identity from provider '{}' to an unverified account; password, \
+                     2FA, refresh tokens and API keys were reset",
+                    provider
+                ),
+                Some(user_id),
+            )
+            .await
+        }
+        OidcLinkOutcome::Provisioned => {
+            log_oidc_audit(
+                pool.get_ref(),
+                "OIDC_USER_PROVISIONED",
+                "SUCCESS",
+                &format!("Provisioned user for identity from provider '{}'", provider),
+                Some(user_id),
+            )
+            .await
+        }
+    }
+
+    let user: (String, bool) = sqlx::query_as("SELECT email, is_active FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool.get_ref())
+        .await?;
+    let (user_email, is_active) = user;
+    if !is_active {
+        return Err(AppError::Auth(AuthError::AccountInactive));
+    }
+
+    // The provider replaces the password, not the second factor
+    if is_totp_enabled(pool.get_ref(), user_id).await? {
+        let mfa_token = create_mfa_challenge(pool.get_ref(), user_id).await?;
+
+        tracing::info!(
+            request_id = %context.request_id,
+            user_id = %user_id,
+            provider = %provider,
+            "OIDC login accepted, two-factor challenge issued"
+        );
+
+        return Ok(HttpResponse::Ok().json(MfaChallengeResponse {
+            mfa_required: true,
+            mfa_token,
+            expires_in: MFA_CHALLENGE_EXPIRY_SECONDS,
+        }));
+    }
+
+    let access_token =
+        generate_access_token(&user_id, &user_email, jwt_config.get_ref(), jwt_keys.get_ref())?;
+    let refresh_token = generate_refresh_token();
+    save_refresh_token(
+        pool.get_ref(),
+        user_id,
+        &refresh_token,
+        jwt_config.refresh_token_expiry,
+    )
+    .await?;
+
+    log_oidc_audit(
+        pool.get_ref(),
+        "OIDC_LOGIN",
+        "SUCCESS",
+        &format!("Logged in with provider '{}'", provider),
+        Some(user_id),
+    )
+    .await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        provider = %provider,
+        "User logged in with OIDC"
+    );
+
+    Ok(AuthResponse::issue(
+        HttpResponse::Ok(),
+        &auth_settings.session,
+        jwt_config.get_ref(),
+        access_token,
+        refresh_token,
+    ))
+}
+
+async fn log_oidc_audit(
+    pool: &PgPool,
+    action: &str,
+    status: &str,
+    message: &str,
+    user_id: Option<Uuid>,
+) {
+    let mut audit_log = AuditLog::new(
+        action.to_string(),
+        "user".to_string(),
+        status.to_string(),
+        message.to_string(),
+    );
+    if let Some(user_id) = user_id {
+        audit_log = audit_log
+            .with_resource_id(user_id.to_string())
+            .with_user_id(user_id.to_string());
+    }
+    RequestFailureLogger::record_audit(pool, &audit_log).await;
+}
@@ -0,0 +1,286 @@
+//! Subscriber Preference Center
+//!
+//! Lets subscribers manage their subscription from the signed link in every
+//! email, without an account: change their name, opt out of topics, pick a
+//! digest frequency, pause delivery for a few weeks or unsubscribe.
+//!
+//! `digest_frequency` is stored for the delivery side; newsletters are still
+//! sent as they are published.
+
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::{AppError, DatabaseError, ErrorContext, ValidationError};
+use crate::preference_link::PreferenceLinks;
+use crate::request_logging::{AuditLog, RequestFailureLogger};
+use crate::input_policy::InputPolicy;
+
+/// Digest frequencies a subscriber can choose from
+const DIGEST_FREQUENCIES: &[&str] = &["immediate", "daily", "weekly"];
+
+/// Longest pause a subscriber can ask for, in weeks
+const MAX_PAUSE_WEEKS: i64 = 52;
+
+#[derive(Deserialize)]
+pub struct PreferenceQuery {
+    pub token: String,
+}
+
+/// Preference update; fields left out are unchanged
+#[derive(Deserialize)]
+pub struct UpdatePreferencesRequest {
+    pub name: Option<String>,
+    /// Topics the subscriber wants to receive; the rest are opted out
+    pub topics: Option<Vec<String>>,
+    pub digest_frequency: Option<String>,
+    /// Pause delivery for this many weeks from now; 0 resumes delivery
+    pub pause_weeks: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct PreferencesResponse {
+    pub name: String,
+    pub email: String,
+    pub status: String,
+    pub topics: Vec<TopicPreference>,
+    pub digest_frequency: String,
+    pub paused_until: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TopicPreference {
+    pub name: String,
+    pub subscribed: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct SubscriberPreferences {
+    name: String,
+    email: String,
+    status: String,
+    excluded_topics: Vec<String>,
+    digest_frequency: String,
+    paused_until: Option<DateTime<Utc>>,
+}
+
+/// GET /subscriptions/preferences?token=
+///
+/// Current preferences of the subscriber the link was issued for.
+///
+/// # Errors
+/// - 401: Token is invalid or expired
+/// - 404: Subscriber no longer exists
+/// - 500: Internal server error
+pub async fn get_preferences(
+    query: web::Query<PreferenceQuery>,
+    pool: web::Data<PgPool>,
+    preference_links: web::Data<PreferenceLinks>,
+) -> Result<HttpResponse, AppError> {
+    let subscriber_id = preference_links.verify(&query.token)?;
+    let preferences = fetch_preferences(pool.get_ref(), subscriber_id).await?;
+
+    Ok(HttpResponse::Ok().json(preferences_response(preferences, preference_links.topics())))
+}
+
+/// PUT /subscriptions/preferences?token=
+///
+/// Update the subscriber's name, topics, digest frequency or pause.
+///
+/// # Errors
+/// - 400: Invalid name, unknown topic or frequency, pause out of range
+/// - 401: Token is invalid or expired
+/// - 404: Subscriber no longer exists
+/// - 500: Internal server error
+pub async fn update_preferences(
+    query: web::Query<PreferenceQuery>,
+    form: web::Json<UpdatePreferencesRequest>,
+    pool: web::Data<PgPool>,
+    preference_links: web::Data<PreferenceLinks>,
+    input_policy: web::Data<InputPolicy>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("subscription_preferences_update");
+    let subscriber_id = preference_links.verify(&query.token)?;
+    let topics = preference_links.topics();
+
+    let name = form.name.as_deref().map(|name| input_policy.check_name(name)).transpose()?;
+
+    let excluded_topics = match &form.topics {
+        Some(selected) => {
+            if let Some(unknown) = selected.iter().find(|t| !topics.contains(t)) {
+                return Err(AppError::Validation(ValidationError::InvalidFormat(format!(
+                    "topic '{}'",
+                    unknown
+                ))));
+            }
+            Some(
+                topics
+                    .iter()
+                    .filter(|t| !selected.contains(t))
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            )
+        }
+        None => None,
+    };
+
+    if let Some(frequency) = &form.digest_frequency {
+        if !DIGEST_FREQUENCIES.contains(&frequency.as_str()) {
+            return Err(AppError::Validation(ValidationError::InvalidFormat(
+                "digest_frequency".to_string(),
+            )));
+        }
+    }
+
+    // Outer Option: field present; inner Option: paused or resumed
+    let paused_until = match form.pause_weeks {
+        None => None,
+        Some(0) => Some(None),
+        Some(weeks) if (1..=MAX_PAUSE_WEEKS).contains(&weeks) => {
+            Some(Some(Utc::now() + Duration::weeks(weeks)))
+        }
+        Some(_) => {
+            return Err(AppError::Validation(ValidationError::InvalidFormat(format!(
+                "pause_weeks (0 to {})",
+                MAX_PAUSE_WEEKS
+            ))))
+        }
+    };
+
+    let result = sqlx::query(
+        r#"
+        UPDATE subscriptions
+        SET name = COALESCE($1, name),
+            excluded_topics = COALESCE($2, excluded_topics),
+            digest_frequency = COALESCE($3, digest_frequency),
+            paused_until = CASE WHEN $4 THEN $5 ELSE paused_until END
+        WHERE id = $6
+        "#,
+    )
+    .bind(name)
+    .bind(excluded_topics)
+    .bind(form.digest_frequency.as_deref())
+    .bind(paused_until.is_some())
+    .bind(paused_until.flatten())
+    .bind(subscriber_id)
+    .execute(pool.get_ref())
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(subscriber_not_found());
+    }
+
+    let audit_log = AuditLog::new(
+        "UPDATE_PREFERENCES".to_string(),
+        "subscription".to_string(),
+        "SUCCESS".to_string(),
+        "Subscriber preferences updated".to_string(),
+    )
+    .with_resource_id(subscriber_id.to_string());
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        subscriber_id = %subscriber_id,
+        "Subscriber preferences updated"
+    );
+
+    let preferences = fetch_preferences(pool.get_ref(), subscriber_id).await?;
+    Ok(HttpResponse::Ok().json(preferences_response(preferences, topics)))
+}
+
+/// POST /subscriptions/unsubscribe?token=
+///
+/// Stop all emails to the subscriber. Unsubscribing twice is not an error.
+///
+/// # Errors
+/// - 401: Token is invalid or expired
+/// - 404: Subscriber no longer exists
+/// - 500: Internal server error
+pub async fn unsubscribe(
+    query: web::Query<PreferenceQuery>,
+    pool: web::Data<PgPool>,
+    preference_links: web::Data<PreferenceLinks>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("subscription_unsubscribe");
+    let subscriber_id = preference_links.verify(&query.token)?;
+
+    let result = sqlx::query(
+        r#"
+        UPDATE subscriptions
+        SET status = 'unsubscribed', unsubscribed_at = COALESCE(unsubscribed_at, $1)
+        WHERE id = $2
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(subscriber_id)
+    .execute(pool.get_ref())
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(subscriber_not_found());
+    }
+
+    let audit_log = AuditLog::new(
+        "UNSUBSCRIBE".to_string(),
+        "subscription".to_string(),
+        "SUCCESS".to_string(),
+        "Subscriber unsubscribed".to_string(),
+    )
+    .with_resource_id(subscriber_id.to_string());
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        subscriber_id = %subscriber_id,
+        "Subscriber unsubscribed"
+    );
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "You have been unsubscribed",
+        "request_id": context.request_id
+    })))
+}
+
+async fn fetch_preferences(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<SubscriberPreferences, AppError> {
+    sqlx::query_as::<_, SubscriberPreferences>(
+        r#"
+        SELECT name, email, status, excluded_topics, digest_frequency, paused_until
+        FROM subscriptions WHERE id = $1
+        "#,
+    )
+    .bind(subscriber_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(subscriber_not_found)
+}
+
+fn preferences_response(
+    preferences: SubscriberPreferences,
+    topics: &[String],
+) -> PreferencesResponse {
+    // A pause that already ran out is the same as no pause
+    let paused_until = preferences.paused_until.filter(|until| *until > Utc::now());
+
+    PreferencesResponse {
+        name: preferences.name,
+        email: preferences.email,
+        status: preferences.status,
+        topics: topics
+            .iter()
+            .map(|topic| TopicPreference {
+                name: topic.clone(),
+                subscribed: !preferences.excluded_topics.contains(topic),
+            })
+            .collect(),
+        digest_frequency: preferences.digest_frequency,
+        paused_until: paused_until.map(|t| t.to_rfc3339()),
+    }
+}
+
+fn subscriber_not_found() -> AppError {
+    AppError::Database(DatabaseError::NotFound("Subscriber not found".to_string()))
+}
@@ -0,0 +1,427 @@
+//! Bulk Subscriber Routes
+//!
+//! CSV imports (see `subscriber_import`) and streaming CSV/JSONL exports of
+//! the subscriber list. Admin only.
+
+use actix_web::{error::PayloadError, http::header, web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::auth::{require_admin, Claims};
+use crate::csv_stream::csv_line;
+use crate::data_validation::validate_subscription_status;
+use crate::error::{AppError, DatabaseError, ErrorContext, ValidationError};
+use crate::middleware::PayloadLimit;
+use crate::input_policy::InputPolicy;
+use crate::request_logging::{AuditLog, RequestFailureLogger};
+use crate::subscriber_import::{
+    create_import_job, fail_import_job, run_import_job, ImportMode, ImportOptions,
+};
+
+/// Subscribers fetched per page while streaming an export
+const EXPORT_PAGE_SIZE: i64 = 1000;
+
+/// Error report entries returned with a job
+const MAX_RETURNED_ERRORS: i64 = 1000;
+
+const EXPORT_COLUMNS: [&str; 11] = [
+    "id",
+    "email",
+    "name",
+    "status",
+    "subscribed_at",
+    "digest_frequency",
+    "excluded_topics",
+    "paused_until",
+    "unsubscribed_at",
+    "consent_source",
+    "consent_recorded_at",
+];
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub mode: ImportMode,
+    /// Required in `confirm` mode, such as "Signup form on old provider"
+    pub consent_source: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Jsonl,
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    pub status: Option<String>,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+#[derive(Serialize)]
+pub struct ImportJobResponse {
+    pub id: String,
+    pub mode: String,
+    pub consent_source: Option<String>,
+    pub status: String,
+    pub total_rows: i32,
+    pub imported: i32,
+    pub updated: i32,
+    pub skipped: i32,
+    pub failed: i32,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// First failed rows; `failed` has the full count
+    pub errors: Vec<ImportRowError>,
+}
+
+#[derive(Serialize)]
+pub struct ImportRowError {
+    pub row: i32,
+    pub email: Option<String>,
+    pub message: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct ImportJobRow {
+    id: Uuid,
+    mode: String,
+    consent_source: Option<String>,
+    status: String,
+    total_rows: i32,
+    imported: i32,
+    updated: i32,
+    skipped: i32,
+    failed: i32,
+    error: Option<String>,
+    created_at: DateTime<Utc>,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ExportRow {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+    digest_frequency: String,
+    excluded_topics: Vec<String>,
+    paused_until: Option<DateTime<Utc>>,
+    unsubscribed_at: Option<DateTime<Utc>>,
+    consent_source: Option<String>,
+    consent_recorded_at: Option<DateTime<Utc>>,
+}
+
+/// POST /api/admin/subscribers/import?mode=&consent_source=
+///
+/// Upload a CSV of subscribers (header row with `email` and `name`). The
+/// body is spooled to disk as it arrives and processed in the background;
+/// poll the returned job for progress and the per-row error report.
+///
+/// Modes: `skip_existing` (default), `update`, `confirm` (needs
+/// `consent_source`).
+///
+/// # Errors
+/// - 400: Invalid mode or missing consent source
+/// - 403: Caller is not an admin
+/// - 413: Upload over the configured request limit
+/// - 500: Internal server error
+pub async fn import_subscribers(
+    req: HttpRequest,
+    claims: web::ReqData<Claims>,
+    query: web::Query<ImportQuery>,
+    mut payload: web::Payload,
+    pool: web::Data<PgPool>,
+    input_policy: web::Data<InputPolicy>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("subscriber_import");
+    let admin_id = require_admin(pool.get_ref(), &claims).await?;
+    let options = ImportOptions::new(query.mode, query.consent_source.as_deref())?;
+
+    let job_id = create_import_job(pool.get_ref(), admin_id, &options).await?;
+    let path = std::env::temp_dir().join(format!("subscriber-import-{}.csv", job_id));
+
+    if let Err(e) = spool_upload(&req, &mut payload, &path).await {
+        let _ = tokio::fs::remove_file(&path).await;
+        fail_import_job(pool.get_ref(), job_id, &e.to_string()).await;
+        return Err(e);
+    }
+
+    let audit_log = AuditLog::new(
+        "IMPORT_SUBSCRIBERS".to_string(),
+        "subscriber_import".to_string(),
+        "STARTED".to_string(),
+        format!("Import queued in {} mode", options.mode.as_str()),
+    )
+    .with_resource_id(job_id.to_string())
+    .with_user_id(admin_id.to_string());
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tokio::spawn(run_import_job(
+        pool.get_ref().clone(),
+        job_id,
+        admin_id,
+        options,
+        input_policy.into_inner(),
+        path,
+    ));
+
+    tracing::info!(
+        request_id = %context.request_id,
+        job_id = %job_id,
+        admin_id = %admin_id,
+        "Subscriber import queued"
+    );
+
+    Ok(HttpResponse::Accepted().json(serde_json::json!({
+        "job_id": job_id.to_string(),
+        "status": "queued",
+        "status_url": format!("/api/admin/subscribers/import/{}", job_id),
+        "request_id": context.request_id
+    })))
+}
+
+/// GET /api/admin/subscribers/import/{job_id}
+///
+/// Progress and outcome of an import, with the first failed rows.
+///
+/// # Errors
+/// - 400: Malformed job id
+/// - 403: Caller is not an admin
+/// - 404: No such job
+/// - 500: Internal server error
+pub async fn get_import_job(
+    claims: web::ReqData<Claims>,
+    job_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(pool.get_ref(), &claims).await?;
+    let job_id = Uuid::parse_str(&job_id)
+        .map_err(|_| AppError::Validation(ValidationError::InvalidFormat("job_id".to_string())))?;
+
+    let job = sqlx::query_as::<_, ImportJobRow>(
+        r#"
+        SELECT id, mode, consent_source, status, total_rows, imported, updated, skipped, failed,
+               error, created_at, started_at, finished_at
+        FROM subscriber_import_jobs WHERE id = $1
+        "#,
+    )
+    .bind(job_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| {
+        AppError::Database(DatabaseError::NotFound("Import job not found".to_string()))
+    })?;
+
+    let errors = sqlx::query_as::<_, (i32, Option<String>, String)>(
+        r#"
+        SELECT row_number, email, message FROM subscriber_import_errors
+        WHERE job_id = $1 ORDER BY row_number LIMIT $2
+        "#,
+    )
+    .bind(job_id)
+    .bind(MAX_RETURNED_ERRORS)
+    .fetch_all(pool.get_ref())
+    .await?
+    .into_iter()
+    .map(|(row, email, message)| ImportRowError { row, email, message })
+    .collect();
+
+    Ok(HttpResponse::Ok().json(ImportJobResponse {
+        id: job.id.to_string(),
+        mode: job.mode,
+        consent_source: job.consent_source,
+        status: job.status,
+        total_rows: job.total_rows,
+        imported: job.imported,
+        updated: job.updated,
+        skipped: job.skipped,
+        failed: job.failed,
+        error: job.error,
+        created_at: job.created_at.to_rfc3339(),
+        started_at: job.started_at.map(|t| t.to_rfc3339()),
+        finished_at: job.finished_at.map(|t| t.to_rfc3339()),
+        errors,
+    }))
+}
+
+/// GET /api/admin/subscribers/export?status=&format=csv|jsonl
+///
+/// Stream all subscribers, optionally with one status, as CSV (default)
+/// or JSON Lines. Rows are fetched a page at a time.
+///
+/// # Errors
+/// - 400: Invalid status or format
+/// - 403: Caller is not an admin
+pub async fn export_subscribers(
+    claims: web::ReqData<Claims>,
+    query: web::Query<ExportQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("subscriber_export");
+    let admin_id = require_admin(pool.get_ref(), &claims).await?;
+
+    let status = match query.status.as_deref() {
+        Some(status) => {
+            validate_subscription_status(status)?;
+            Some(status.trim().to_string())
+        }
+        None => None,
+    };
+    let format = query.format;
+
+    let audit_log = AuditLog::new(
+        "EXPORT_SUBSCRIBERS".to_string(),
+        "subscription".to_string(),
+        "SUCCESS".to_string(),
+        format!(
+            "Subscriber list exported (status: {})",
+            status.as_deref().unwrap_or("any")
+        ),
+    )
+    .with_user_id(admin_id.to_string());
+    RequestFailureLogger::record_audit(pool.get_ref(), &audit_log).await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        admin_id = %admin_id,
+        "Subscriber export started"
+    );
+
+    let header_line = match format {
+        ExportFormat::Csv => Some(csv_line(EXPORT_COLUMNS)),
+        ExportFormat::Jsonl => None,
+    };
+
+    // State: (pool, status filter, cursor, finished)
+    let pages = futures::stream::unfold(
+        (pool.get_ref().clone(), status, None::<Uuid>, false),
+        move |(pool, status, cursor, finished)| async move {
+            if finished {
+                return None;
+            }
+            match fetch_export_page(&pool, status.as_deref(), cursor).await {
+                Ok(rows) if rows.is_empty() => None,
+                Ok(rows) => {
+                    let finished = (rows.len() as i64) < EXPORT_PAGE_SIZE;
+                    let next_cursor = rows.last().map(|row| row.id);
+                    let chunk = rows.iter().map(|row| format_row(row, format)).collect::<String>();
+                    Some((Ok(web::Bytes::from(chunk)), (pool, status, next_cursor, finished)))
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, "Subscriber export failed mid-stream");
+                    Some((Err(AppError::from(e)), (pool, status, cursor, true)))
+                }
+            }
+        },
+    );
+    let body = futures::stream::iter(header_line.map(|line| Ok(web::Bytes::from(line))))
+        .chain(pages);
+
+    let (content_type, filename) = match format {
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "subscribers.csv"),
+        ExportFormat::Jsonl => ("application/x-ndjson", "subscribers.jsonl"),
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        ))
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .streaming(body))
+}
+
+/// Write the request body to `path` as it arrives
+async fn spool_upload(
+    req: &HttpRequest,
+    payload: &mut web::Payload,
+    path: &std::path::Path,
+) -> Result<(), AppError> {
+    let io_error = |e: std::io::Error| AppError::Internal(format!("Failed to spool import: {}", e));
+
+    let mut file = tokio::fs::File::create(path).await.map_err(io_error)?;
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| match e {
+            PayloadError::Overflow => PayloadLimit::exceeded(req),
+            e => AppError::Internal(format!("Upload interrupted: {}", e)),
+        })?;
+        file.write_all(&chunk).await.map_err(io_error)?;
+    }
+    file.flush().await.map_err(io_error)?;
+
+    Ok(())
+}
+
+async fn fetch_export_page(
+    pool: &PgPool,
+    status: Option<&str>,
+    cursor: Option<Uuid>,
+) -> Result<Vec<ExportRow>, sqlx::Error> {
+    sqlx::query_as::<_, ExportRow>(
+        r#"
+        SELECT id, email, name, status, subscribed_at, digest_frequency, excluded_topics,
+               paused_until, unsubscribed_at, consent_source, consent_recorded_at
+        FROM subscriptions
+        WHERE ($1::text IS NULL OR status = $1)
+        AND ($2::uuid IS NULL OR id > $2)
+        ORDER BY id
+        LIMIT $3
+        "#,
+    )
+    .bind(status)
+    .bind(cursor)
+    .bind(EXPORT_PAGE_SIZE)
+    .fetch_all(pool)
+    .await
+}
+
+fn format_row(row: &ExportRow, format: ExportFormat) -> String {
+    let timestamp = |t: &Option<DateTime<Utc>>| t.map(|t| t.to_rfc3339());
+
+    match format {
+        ExportFormat::Csv => csv_line([
+            row.id.to_string(),
+            row.email.clone(),
+            row.name.clone(),
+            row.status.clone(),
+            row.subscribed_at.to_rfc3339(),
+            row.digest_frequency.clone(),
+            row.excluded_topics.join(";"),
+            timestamp(&row.paused_until).unwrap_or_default(),
+            timestamp(&row.unsubscribed_at).unwrap_or_default(),
+            row.consent_source.clone().unwrap_or_default(),
+            timestamp(&row.consent_recorded_at).unwrap_or_default(),
+        ]),
+        ExportFormat::Jsonl => {
+            let mut line = serde_json::json!({
+                "id": row.id.to_string(),
+                "email": row.email,
+                "name": row.name,
+                "status": row.status,
+                "subscribed_at": row.subscribed_at.to_rfc3339(),
+                "digest_frequency": row.digest_frequency,
+                "excluded_topics": row.excluded_topics,
+                "paused_until": timestamp(&row.paused_until),
+                "unsubscribed_at": timestamp(&row.unsubscribed_at),
+                "consent_source": row.consent_source,
+                "consent_recorded_at": timestamp(&row.consent_recorded_at),
+            })
+            .to_string();
+            line.push('\n');
+            line
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Subscriber Data Routes
+//!
+//! Data subject requests for newsletter subscribers, either handled by an
+//! admin on the subscriber's behalf (by email address) or by the subscriber
+//! through the signed link in every email. See `subscriber_data` for what
+//! is exported and erased.
+
+use actix_web::{http::header, web, HttpResponse};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::{require_admin, Claims};
+use crate::error::{AppError, DatabaseError, ErrorContext};
+use crate::preference_link::PreferenceLinks;
+use crate::request_logging::{AuditLog, RequestFailureLogger};
+use crate::routes::preferences::PreferenceQuery;
+use crate::subscriber_data::{erase_subscriber, export_subscriber_data, SubscriberExport};
+use crate::validators::is_valid_email;
+
+/// Subscriber an admin is handling a request for
+#[derive(Deserialize)]
+pub struct SubscriberEmailRequest {
+    pub email: String,
+}
+
+/// POST /api/admin/subscriber-data/export
+///
+/// Export everything stored about the subscriber with the given email.
+/// The address is sent in the body so it stays out of access logs.
+///
+/// # Errors
+/// - 400: Invalid email
+/// - 403: Caller is not an admin
+/// - 404: No subscription under the address
+/// - 500: Internal server error
+pub async fn admin_export_subscriber_data(
+    claims: web::ReqData<Claims>,
+    form: web::Json<SubscriberEmailRequest>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("admin_subscriber_export");
+    let admin_id = require_admin(pool.get_ref(), &claims).await?;
+    let email = is_valid_email(&form.email)?;
+
+    let export = export_subscriber_data(pool.get_ref(), &email)
+        .await?
+        .ok_or_else(subscriber_not_found)?;
+
+    for subscriber_id in exported_ids(&export) {
+        log_subscriber_data_audit(
+            pool.get_ref(),
+            "EXPORT_SUBSCRIBER_DATA",
+            "Subscriber data exported by an admin",
+            subscriber_id,
+            Some(admin_id),
+        )
+        .await;
+    }
+
+    tracing::info!(
+        request_id = %context.request_id,
+        admin_id = %admin_id,
+        "Subscriber data exported"
+    );
+
+    Ok(export_response(export))
+}
+
+/// POST /api/admin/subscriber-data/erase
+///
+/// Erase every subscription under the given email and keep a hashed
+/// tombstone so imports don't add the address back.
+///
+/// # Errors
+/// - 400: Invalid email
+/// - 403: Caller is not an admin
+/// - 404: No subscription under the address
+/// - 500: Internal server error
+pub async fn admin_erase_subscriber_data(
+    claims: web::ReqData<Claims>,
+    form: web::Json<SubscriberEmailRequest>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("admin_subscriber_erasure");
+    let admin_id = require_admin(pool.get_ref(), &claims).await?;
+    let email = is_valid_email(&form.email)?;
+
+    let erased = erase_subscriber(pool.get_ref(), &email).await?;
+    if erased.is_empty() {
+        return Err(subscriber_not_found());
+    }
+
+    for subscriber_id in &erased {
+        log_subscriber_data_audit(
+            pool.get_ref(),
+            "ERASE_SUBSCRIBER",
+            "Subscriber erased by an admin",
+            *subscriber_id,
+            Some(admin_id),
+        )
+        .await;
+    }
+
+    tracing::info!(
+        request_id = %context.request_id,
+        admin_id = %admin_id,
+        erased_count = erased.len(),
+        "Subscriber erased"
+    );
+
+    Ok(erasure_response(&erased, &context))
+}
+
+/// GET /subscriptions/data?token=
+///
+/// Download everything stored about the subscriber the link was issued for.
+///
+/// # Errors
+/// - 401: Token is invalid or expired
+/// - 404: Subscriber no longer exists
+/// - 500: Internal server error
+pub async fn export_my_subscriber_data(
+    query: web::Query<PreferenceQuery>,
+    pool: web::Data<PgPool>,
+    preference_links: web::Data<PreferenceLinks>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("subscriber_export");
+    let subscriber_id = preference_links.verify(&query.token)?;
+    let email = subscriber_email(pool.get_ref(), subscriber_id).await?;
+
+    let export = export_subscriber_data(pool.get_ref(), &email)
+        .await?
+        .ok_or_else(subscriber_not_found)?;
+
+    log_subscriber_data_audit(
+        pool.get_ref(),
+        "EXPORT_SUBSCRIBER_DATA",
+        "Subscriber data exported by the subscriber",
+        subscriber_id,
+        None,
+    )
+    .await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        subscriber_id = %subscriber_id,
+        "Subscriber data exported"
+    );
+
+    Ok(export_response(export))
+}
+
+/// DELETE /subscriptions/data?token=
+///
+/// Erase the subscriber the link was issued for, along with any other
+/// subscription under the same address.
+///
+/// # Errors
+/// - 401: Token is invalid or expired
+/// - 404: Subscriber no longer exists
+/// - 500: Internal server error
+pub async fn erase_my_subscriber_data(
+    query: web::Query<PreferenceQuery>,
+    pool: web::Data<PgPool>,
+    preference_links: web::Data<PreferenceLinks>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("subscriber_erasure");
+    let subscriber_id = preference_links.verify(&query.token)?;
+    let email = subscriber_email(pool.get_ref(), subscriber_id).await?;
+
+    let erased = erase_subscriber(pool.get_ref(), &email).await?;
+    if erased.is_empty() {
+        return Err(subscriber_not_found());
+    }
+
+    for erased_id in &erased {
+        log_subscriber_data_audit(
+            pool.get_ref(),
+            "ERASE_SUBSCRIBER",
+            "Subscriber erased at the subscriber's request",
+            *erased_id,
+            None,
+        )
+        .await;
+    }
+
+    tracing::info!(
+        request_id = %context.request_id,
+        subscriber_id = %subscriber_id,
+        erased_count = erased.len(),
+        "Subscriber erased"
+    );
+
+    Ok(erasure_response(&erased, &context))
+}
+
+async fn subscriber_email(pool: &PgPool, subscriber_id: Uuid) -> Result<String, AppError> {
+    sqlx::query_scalar::<_, String>("SELECT email FROM subscriptions WHERE id = $1")
+        .bind(subscriber_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(subscriber_not_found)
+}
+
+fn exported_ids(export: &SubscriberExport) -> Vec<Uuid> {
+    export
+        .subscriptions
+        .iter()
+        .filter_map(|subscription| Uuid::parse_str(&subscription.id).ok())
+        .collect()
+}
+
+fn export_response(export: SubscriberExport) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"subscriber-export.json\"",
+        ))
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(export)
+}
+
+fn erasure_response(erased: &[Uuid], context: &ErrorContext) -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "Subscriber data erased",
+        "erased_count": erased.len(),
+        "request_id": context.request_id
+    }))
+}
+
+/// Audit entries never carry the address, only the subscription id
+async fn log_subscriber_data_audit(
+    pool: &PgPool,
+    action: &str,
+    message: &str,
+    subscriber_id: Uuid,
+    admin_id: Option<Uuid>,
+) {
+    let mut audit_log = AuditLog::new(
+        action.to_string(),
+        "subscription".to_string(),
+        "SUCCESS".to_string(),
+        message.to_string(),
+    )
+    .with_resource_id(subscriber_id.to_string());
+    if let Some(admin_id) = admin_id {
+        audit_log = audit_log.with_user_id(admin_id.to_string());
+    }
+    RequestFailureLogger::record_audit(pool, &audit_log).await;
+}
+
+fn subscriber_not_found() -> AppError {
+    AppError::Database(DatabaseError::NotFound("Subscriber not found".to_string()))
+}
@@ -3,9 +3,13 @@ use chrono::Utc;
 use serde::Deserialize;
 use sqlx::PgPool;
 use uuid::Uuid;
-use crate::validators::{is_valid_email, is_valid_name};
-use crate::email_client::EmailClient;
+use crate::input_policy::InputPolicy;
+use crate::validators::{canonical_email, is_valid_email};
+use crate::email_client::{escape_html, EmailClient};
 use crate::confirmation_token::ConfirmationToken;
+use crate::preference_link::PreferenceLinks;
+use crate::subscription_guard::{BotRejection, FormSubmission, SubscriptionGuard};
+use crate::email_deliverability::EmailDeliverability;
 use crate::error::{AppError, DatabaseError, EmailError, ErrorContext};
 use crate::request_logging::{RequestMetadata, FailedRequest, RequestFailureLogger, AuditLog};
 
@@ -13,15 +17,72 @@ use crate::request_logging::{RequestMetadata, FailedRequest, RequestFailureLogge
 pub struct FormData {
     name: Option<String>,
     email: Option<String>,
+    /// Honeypot: hidden from people, so only bots fill it in
+    website: Option<String>,
+    /// From `GET /subscriptions/challenge`
+    form_token: Option<String>,
+    pow_nonce: Option<String>,
 }
 
+/// GET /subscriptions/challenge
+///
+/// Issue the signed form token the subscription form must be submitted
+/// with, and the proof of work difficulty if proof of work is required.
+pub async fn subscription_challenge(guard: web::Data<SubscriptionGuard>) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(guard.challenge())
+}
+
+/// POST /subscriptions
+///
+/// Subscribe and send the confirmation email. If the address's domain looks
+/// like a typo of a well-known provider, the response carries the corrected
+/// address as `{"suggestion": "..."}` so the form can ask "did you mean";
+/// the address as typed is subscribed either way.
 pub async fn subscribe(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
     email_client: web::Data<EmailClient>,
+    preference_links: web::Data<PreferenceLinks>,
+    guard: web::Data<SubscriptionGuard>,
+    deliverability: web::Data<EmailDeliverability>,
+    input_policy: web::Data<InputPolicy>,
 ) -> Result<HttpResponse, AppError> {
     let error_context = ErrorContext::new("subscription_creation");
 
+    // Bot checks come first, so that bots learn nothing from validation
+    let submission = FormSubmission {
+        website: form.website.as_deref(),
+        form_token: form.form_token.as_deref(),
+        pow_nonce: form.pow_nonce.as_deref(),
+        email: form.email.as_deref().unwrap_or_default(),
+    };
+    if let Err(rejection) = guard.check(&submission) {
+        tracing::warn!(
+            request_id = %error_context.request_id,
+            reason = rejection.reason(),
+            "Subscription rejected by bot protection"
+        );
+
+        // 봇 차단 감사 로그
+        let audit_log = AuditLog::new(
+            "BOT_CHECK".to_string(),
+            "subscription".to_string(),
+            "FAILURE".to_string(),
+            format!("Bot protection rejected submission: {}", rejection.reason()),
+        );
+        RequestFailureLogger::log_audit(&audit_log);
+
+        // Honeypot hits look like success, so the bot doesn't adapt
+        return match rejection {
+            BotRejection::Honeypot => Ok(HttpResponse::Ok().finish()),
+            _ => Err(AppError::Validation(crate::error::ValidationError::BotCheckFailed(
+                rejection.reason().to_string(),
+            ))),
+        };
+    }
+
     // Validate name
     let name = form.name.as_ref()
         .ok_or_else(|| {
@@ -40,7 +101,7 @@ pub async fn subscribe(
 
             error
         })?;
-    let name = is_valid_name(name)
+    let name = input_policy.check_name(name)
         .map_err(|e| {
             // 검증 실패 감사 로그
             let audit_log = AuditLog::new(
@@ -81,12 +142,66 @@ pub async fn subscribe(
             RequestFailureLogger::log_audit(&audit_log);
             AppError::Validation(e)
         })?;
+    if !email_client.can_deliver_to(&email) {
+        return Err(AppError::Validation(
+            crate::error::ValidationError::InvalidFormat("email".to_string())
+        ));
+    }
+
+    // Throwaway domains and domains without mail servers; a likely typo
+    // only comes back as a suggestion for the form to offer
+    let suggestion = deliverability.check(&email).await.map_err(|e| {
+        // 수신 불가 주소 감사 로그
+        let audit_log = AuditLog::new(
+            "CHECK_EMAIL_DELIVERABILITY".to_string(),
+            "subscription".to_string(),
+            "FAILURE".to_string(),
+            format!("Email deliverability check failed: {}", e),
+        );
+        RequestFailureLogger::log_audit(&audit_log);
+        e
+    })?;
 
     tracing::info!(
         request_id = %error_context.request_id,
         "Processing new subscription (sensitive data redacted)"
     );
 
+    // A form token sends one confirmation email; checked only now so that
+    // fixing a validation error doesn't need a new token
+    guard
+        .redeem_form_token(pool.get_ref(), submission.form_token)
+        .await
+        .map_err(|e| {
+            // 봇 차단 감사 로그
+            let audit_log = AuditLog::new(
+                "BOT_CHECK".to_string(),
+                "subscription".to_string(),
+                "FAILURE".to_string(),
+                format!("Bot protection rejected submission: {}", e),
+            );
+            RequestFailureLogger::log_audit(&audit_log);
+            e
+        })?;
+
+    // Cap confirmation emails to the address, whoever is asking for them
+    guard
+        .reserve_confirmation_email(pool.get_ref(), &email)
+        .await
+        .map_err(|e| {
+            error_context.log_error(&e);
+
+            // 수신자별 발송 제한 감사 로그
+            let audit_log = AuditLog::new(
+                "THROTTLE_CONFIRMATION_EMAIL".to_string(),
+                "email".to_string(),
+                "FAILURE".to_string(),
+                format!("Confirmation email not sent: {}", e),
+            );
+            RequestFailureLogger::log_audit(&audit_log);
+            e
+        })?;
+
     let subscriber_id = Uuid::new_v4();
 
     // Insert subscriber into database
@@ -102,6 +217,7 @@ pub async fn subscribe(
         &email,
         &name,
         &confirmation_token,
+        &preference_links.email_footer(subscriber_id),
         &error_context,
     )
     .await?;
@@ -112,7 +228,10 @@ pub async fn subscribe(
         "Subscription created successfully"
     );
 
-    Ok(HttpResponse::Ok().finish())
+    Ok(match suggestion {
+        Some(suggestion) => HttpResponse::Ok().json(serde_json::json!({ "suggestion": suggestion })),
+        None => HttpResponse::Ok().finish(),
+    })
 }
 
 /// Creates a new subscriber in the database with proper error handling
@@ -124,10 +243,12 @@ async fn create_subscriber(
     context: &ErrorContext,
 ) -> Result<(), AppError> {
     sqlx::query(
-        "INSERT INTO subscriptions (id, email, name, subscribed_at, status) VALUES ($1, $2, $3, $4, $5)"
+        "INSERT INTO subscriptions (id, email, email_canonical, name, subscribed_at, status) \
+         VALUES ($1, $2, $3, $4, $5, $6)"
     )
     .bind(subscriber_id)
     .bind(email)
+    .bind(canonical_email(email))
     .bind(name)
     .bind(Utc::now())
     .bind("pending")
@@ -210,7 +331,7 @@ async fn save_confirmation_token(
         "#
     )
     .bind(token.token())
-    .bind(subscriber_id.to_string())
+    .bind(subscriber_id)
     .bind(token.created_at())
     .bind(token.expires_at())
     .execute(pool.get_ref())
@@ -238,6 +359,7 @@ async fn send_confirmation_email_flow(
     recipient_email: &str,
     name: &str,
     token: &ConfirmationToken,
+    footer: &str,
     context: &ErrorContext,
 ) -> Result<(), AppError> {
     let confirmation_link = format!(
@@ -251,8 +373,9 @@ async fn send_confirmation_email_flow(
         <p>Please confirm your email subscription by clicking the link below:</p>
         <a href="{}">Confirm Subscription</a>
         <p>This link will expire in 24 hours.</p>
+        {}
         "#,
-        name, confirmation_link
+        escape_html(name), confirmation_link, footer
     );
 
     send_confirmation_email(email_client, recipient_email, &html_content)
@@ -0,0 +1,256 @@
+//! Two-Factor Authentication Routes
+//!
+//! TOTP enrollment, confirmation and removal for authenticated users.
+//! The second login step itself lives in `routes::auth::login_mfa`.
+
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::{
+    begin_totp_enrollment, disable_totp, enable_totp, generate_totp_secret, is_totp_enabled,
+    require_verified_email, totp_provisioning_uri, verify_second_factor, verify_totp_code_at,
+    Claims,
+};
+use crate::configuration::{AuthSettings, JwtSettings};
+use crate::error::{AppError, AuthError, ErrorContext, ValidationError};
+use crate::request_logging::{AuditLog, RequestFailureLogger};
+use crate::routes::account::verify_current_password;
+
+/// 2FA setup request
+#[derive(Deserialize)]
+pub struct TwoFactorSetupRequest {
+    pub current_password: String,
+}
+
+/// 2FA confirmation request
+#[derive(Deserialize)]
+pub struct TwoFactorConfirmRequest {
+    pub code: String,
+}
+
+/// 2FA disable request
+#[derive(Deserialize)]
+pub struct TwoFactorDisableRequest {
+    pub current_password: String,
+    pub code: String,
+}
+
+/// 2FA setup response with the secret for authenticator apps
+#[derive(Serialize)]
+pub struct TwoFactorSetupResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// 2FA confirmation response with single-use recovery codes
+#[derive(Serialize)]
+pub struct TwoFactorConfirmResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+/// POST /api/me/2fa/setup
+///
+/// Start TOTP enrollment for the authenticated user.
+/// Returns a new secret and `otpauth://` URI to add to an authenticator app.
+/// 2FA is not active until confirmed with a code via `/api/me/2fa/confirm`.
+///
+/// # Errors
+/// - 400: 2FA is already enabled
+/// - 401: Current password is wrong
+/// - 403: Email is unverified and the policy blocks sensitive actions, or
+///   the request used an API key
+/// - 500: Internal server error
+pub async fn setup_two_factor(
+    claims: web::ReqData<Claims>,
+    form: web::Json<TwoFactorSetupRequest>,
+    pool: web::Data<PgPool>,
+    jwt_config: web::Data<JwtSettings>,
+    auth_settings: web::Data<AuthSettings>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("two_factor_setup");
+    claims.require_session()?;
+    let user_id = claims.user_id()?;
+    require_verified_email(pool.get_ref(), user_id, auth_settings.get_ref()).await?;
+
+    let email = verify_current_password(
+        pool.get_ref(),
+        user_id,
+        &form.current_password,
+        "SETUP_TWO_FACTOR",
+    )
+    .await?;
+
+    if is_totp_enabled(pool.get_ref(), user_id).await? {
+        return Err(AppError::Validation(ValidationError::InvalidFormat(
+            "two-factor authentication is already enabled".to_string(),
+        )));
+    }
+
+    let secret = generate_totp_secret();
+    let otpauth_uri = totp_provisioning_uri(&secret, &jwt_config.issuer, &email)?;
+    begin_totp_enrollment(pool.get_ref(), user_id, &secret).await?;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        "Two-factor enrollment started"
+    );
+
+    Ok(HttpResponse::Ok().json(TwoFactorSetupResponse {
+        secret,
+        otpauth_uri,
+    }))
+}
+
+/// POST /api/me/2fa/confirm
+///
+/// Finish TOTP enrollment with a code from the authenticator app.
+/// Enables 2FA and returns recovery codes, which are shown only once.
+///
+/// # Errors
+/// - 400: Enrollment was not started, or 2FA is already enabled
+/// - 401: Code is wrong
+/// - 403: The request used an API key
+/// - 500: Internal server error
+pub async fn confirm_two_factor(
+    claims: web::ReqData<Claims>,
+    form: web::Json<TwoFactorConfirmRequest>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("two_factor_confirm");
+    claims.require_session()?;
+    let user_id = claims.user_id()?;
+
+    let (secret, enabled) = sqlx::query_as::<_, (Option<String>, bool)>(
+        "SELECT totp_secret, totp_enabled_at IS NOT NULL FROM users WHERE id = $1 AND is_active = true",
+    )
+    .bind(user_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or(AppError::Auth(AuthError::AccountInactive))?;
+
+    if enabled {
+        return Err(AppError::Validation(ValidationError::InvalidFormat(
+            "two-factor authentication is already enabled".to_string(),
+        )));
+    }
+    let secret = secret.ok_or_else(|| {
+        AppError::Validation(ValidationError::InvalidFormat(
+            "two-factor setup has not been started".to_string(),
+        ))
+    })?;
+
+    let Some(step) = verify_totp_code_at(&secret, &form.code, Utc::now().timestamp() as u64)? else {
+        log_two_factor_audit(
+            pool.get_ref(),
+            "CONFIRM_TWO_FACTOR",
+            user_id,
+            "FAILURE",
+            "Invalid enrollment code",
+        )
+        .await;
+        return Err(AppError::Auth(AuthError::InvalidMfaCode));
+    };
+
+    let recovery_codes = enable_totp(pool.get_ref(), user_id, step).await?;
+
+    log_two_factor_audit(
+        pool.get_ref(),
+        "CONFIRM_TWO_FACTOR",
+        user_id,
+        "SUCCESS",
+        "Two-factor authentication enabled",
+    )
+    .await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        "Two-factor authentication enabled"
+    );
+
+    Ok(HttpResponse::Ok().json(TwoFactorConfirmResponse { recovery_codes }))
+}
+
+/// POST /api/me/2fa/disable
+///
+/// Turn off 2FA. Requires both the current password and a TOTP or
+/// recovery code.
+///
+/// # Errors
+/// - 400: 2FA is not enabled
+/// - 401: Current password or code is wrong
+/// - 403: The request used an API key
+/// - 500: Internal server error
+pub async fn disable_two_factor(
+    claims: web::ReqData<Claims>,
+    form: web::Json<TwoFactorDisableRequest>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let context = ErrorContext::new("two_factor_disable");
+    claims.require_session()?;
+    let user_id = claims.user_id()?;
+
+    verify_current_password(
+        pool.get_ref(),
+        user_id,
+        &form.current_password,
+        "DISABLE_TWO_FACTOR",
+    )
+    .await?;
+
+    if !is_totp_enabled(pool.get_ref(), user_id).await? {
+        return Err(AppError::Validation(ValidationError::InvalidFormat(
+            "two-factor authentication is not enabled".to_string(),
+        )));
+    }
+
+    if let Err(e) = verify_second_factor(pool.get_ref(), user_id, &form.code).await {
+        log_two_factor_audit(pool.get_ref(), "DISABLE_TWO_FACTOR", user_id, "FAILURE", "Invalid code")
+            .await;
+        return Err(e);
+    }
+
+    disable_totp(pool.get_ref(), user_id).await?;
+
+    log_two_factor_audit(
+        pool.get_ref(),
+        "DISABLE_TWO_FACTOR",
+        user_id,
+        "SUCCESS",
+        "Two-factor authentication disabled",
+    )
+    .await;
+
+    tracing::info!(
+        request_id = %context.request_id,
+        user_id = %user_id,
+        "Two-factor authentication disabled"
+    );
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Two-factor authentication disabled",
+        "request_id": context.request_id
+    })))
+}
+
+async fn log_two_factor_audit(
+    pool: &PgPool,
+    action: &str,
+    user_id: Uuid,
+    status: &str,
+    message: &str,
+) {
+    let audit_log = AuditLog::new(
+        action.to_string(),
+        "user".to_string(),
+        status.to_string(),
+        message.to_string(),
+    )
+    .with_resource_id(user_id.to_string())
+    .with_user_id(user_id.to_string());
+    RequestFailureLogger::record_audit(pool, &audit_log).await;
+}
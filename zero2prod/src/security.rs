@@ -1,26 +1,28 @@
 /// Security middleware module for protecting against common web attacks
 /// Features:
 /// - Rate limiting (DoS protection)
-/// - Content-length validation (Payload bomb protection)
-/// - Security headers (CSRF, XSS, Clickjacking protection)
+/// - Security headers (XSS, Clickjacking, cross-origin isolation)
+/// - Login throttling and account lockout (credential stuffing protection)
 
+use ipnet::IpNet;
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use crate::configuration::{LoginThrottleSettings, SecurityHeaderSettings};
+use crate::error::{AuthError, ConfigError};
 
 /// Configuration for rate limiting
 pub struct RateLimitConfig {
     /// Max requests per minute per IP
     pub requests_per_minute: u32,
-    /// Max content length in bytes
-    pub max_content_length: u64,
 }
 
 impl Default for RateLimitConfig {
     fn default() -> Self {
         Self {
-            requests_per_minute: 10,  // 10 requests per minute per IP = DoS protection
-            max_content_length: 1024, // 1KB max for subscription form
+            requests_per_minute: 10, // 10 requests per minute per IP = DoS protection
         }
     }
 }
@@ -34,22 +36,25 @@ struct TokenBucket {
 }
 
 impl TokenBucket {
-    fn new(capacity: u32, requests_per_minute: u32) -> Self {
+    fn new(capacity: u32, requests_per_minute: u32, now: SystemTime) -> Self {
         Self {
             tokens: capacity as f64,
-            last_refill: SystemTime::now(),
+            last_refill: now,
             capacity,
             refill_rate: requests_per_minute as f64 / 60.0,
         }
     }
 
-    fn try_take_token(&mut self) -> bool {
-        // Calculate elapsed time and refill tokens
-        if let Ok(elapsed) = self.last_refill.elapsed() {
+    fn refill(&mut self, now: SystemTime) {
+        if let Ok(elapsed) = now.duration_since(self.last_refill) {
             let elapsed_secs = elapsed.as_secs_f64();
             self.tokens = (self.tokens + elapsed_secs * self.refill_rate).min(self.capacity as f64);
-            self.last_refill = SystemTime::now();
+            self.last_refill = now;
         }
+    }
+
+    fn try_take_token(&mut self, now: SystemTime) -> bool {
+        self.refill(now);
 
         // Try to take a token
         if self.tokens >= 1.0 {
@@ -59,107 +64,746 @@ impl TokenBucket {
             false
         }
     }
+
+    /// Seconds until the bucket holds `tokens` tokens again
+    fn seconds_until(&self, tokens: f64) -> u64 {
+        let missing = (tokens - self.tokens).max(0.0);
+        (missing / self.refill_rate).ceil() as u64
+    }
+
+    /// A full bucket behaves exactly like a new one, so it can be dropped
+    fn is_idle(&self, now: SystemTime) -> bool {
+        let elapsed = now
+            .duration_since(self.last_refill)
+            .unwrap_or_default()
+            .as_secs_f64();
+        self.tokens + elapsed * self.refill_rate >= self.capacity as f64
+    }
+}
+
+/// How often idle buckets are looked for
+const RATE_LIMIT_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Quota left for a key after a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the full limit is available again
+    pub reset_seconds: u64,
+}
+
+/// A request over the limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitExceeded {
+    pub status: RateLimitStatus,
+    /// Seconds until the next request will be allowed
+    pub retry_after_seconds: u64,
+}
+
+struct Buckets {
+    buckets: HashMap<String, TokenBucket>,
+    last_sweep: SystemTime,
 }
 
-/// Rate limiter manager - tracks limits per IP address
+/// Rate limiter manager - tracks limits per key (client IP, user, API key)
+///
+/// Buckets that have refilled completely are evicted every minute, so
+/// memory only grows with the number of recently active keys.
 pub struct RateLimiterManager {
     config: RateLimitConfig,
-    limiters: Arc<Mutex<HashMap<String, TokenBucket>>>,
+    limiters: Mutex<Buckets>,
 }
 
 impl RateLimiterManager {
     pub fn new(config: RateLimitConfig) -> Self {
         Self {
             config,
-            limiters: Arc::new(Mutex::new(HashMap::new())),
+            limiters: Mutex::new(Buckets {
+                buckets: HashMap::new(),
+                last_sweep: SystemTime::now(),
+            }),
         }
     }
 
-    /// Check if request from IP is allowed
-    pub fn check_rate_limit(&self, ip: &str) -> Result<(), String> {
+    /// Check if a request for `key` is allowed, taking a token if so
+    pub fn check_rate_limit(&self, key: &str) -> Result<RateLimitStatus, RateLimitExceeded> {
+        self.check_rate_limit_at(key, SystemTime::now())
+    }
+
+    fn check_rate_limit_at(
+        &self,
+        key: &str,
+        now: SystemTime,
+    ) -> Result<RateLimitStatus, RateLimitExceeded> {
         let mut limiters = self.limiters.lock().unwrap();
 
+        let sweep_due = now
+            .duration_since(limiters.last_sweep)
+            .is_ok_and(|elapsed| elapsed >= RATE_LIMIT_SWEEP_INTERVAL);
+        if sweep_due {
+            limiters.buckets.retain(|_, bucket| !bucket.is_idle(now));
+            limiters.last_sweep = now;
+        }
+
+        let requests_per_minute = self.config.requests_per_minute;
         let limiter = limiters
-            .entry(ip.to_string())
-            .or_insert_with(|| {
-                TokenBucket::new(self.config.requests_per_minute, self.config.requests_per_minute)
-            });
+            .buckets
+            .entry(key.to_string())
+            .or_insert_with(|| TokenBucket::new(requests_per_minute, requests_per_minute, now));
+
+        let allowed = limiter.try_take_token(now);
+        let status = RateLimitStatus {
+            limit: requests_per_minute,
+            remaining: limiter.tokens.floor() as u32,
+            reset_seconds: limiter.seconds_until(limiter.capacity as f64),
+        };
 
-        if limiter.try_take_token() {
-            Ok(())
+        if allowed {
+            Ok(status)
         } else {
-            Err(format!(
-                "Rate limit exceeded: max {} requests per minute",
-                self.config.requests_per_minute
-            ))
+            Err(RateLimitExceeded {
+                status,
+                retry_after_seconds: limiter.seconds_until(1.0).max(1),
+            })
+        }
+    }
+
+    pub fn requests_per_minute(&self) -> u32 {
+        self.config.requests_per_minute
+    }
+
+    /// Number of keys currently tracked
+    pub fn tracked_keys(&self) -> usize {
+        self.limiters.lock().unwrap().buckets.len()
+    }
+}
+
+/// Length of a sliding rate limit window
+pub const RATE_LIMIT_WINDOW_SECONDS: f64 = 60.0;
+
+/// Decide a request from sliding window counters
+///
+/// The previous fixed window's count is weighted by how much of it still
+/// overlaps the last minute. `current` already includes this request, and
+/// `elapsed_seconds` is how far into the current window it arrived.
+pub fn sliding_window_decision(
+    limit: u32,
+    previous: u64,
+    current: u64,
+    elapsed_seconds: f64,
+) -> Result<RateLimitStatus, RateLimitExceeded> {
+    let window = RATE_LIMIT_WINDOW_SECONDS;
+    let elapsed = elapsed_seconds.clamp(0.0, window);
+    let weight = 1.0 - elapsed / window;
+    let estimate = previous as f64 * weight + current as f64;
+    let limit_f = limit as f64;
+
+    let status = RateLimitStatus {
+        limit,
+        remaining: (limit_f - estimate).max(0.0).floor() as u32,
+        reset_seconds: (window - elapsed).ceil() as u64,
+    };
+    if estimate <= limit_f {
+        return Ok(status);
+    }
+
+    // Wait until the weighted count leaves room for one more request
+    let room = limit_f - 1.0;
+    let wait = if (current as f64) <= room && previous > 0 {
+        let target_weight = (room - current as f64) / previous as f64;
+        (1.0 - target_weight) * window - elapsed
+    } else {
+        let target_weight = if current > 0 { room.max(0.0) / current as f64 } else { 1.0 };
+        (window - elapsed) + (1.0 - target_weight) * window
+    };
+
+    Err(RateLimitExceeded {
+        status,
+        retry_after_seconds: (wait.ceil() as u64).max(1),
+    })
+}
+
+/// Proxies whose `X-Forwarded-For` header is believed
+///
+/// Without trusted proxies the client IP is the peer address, so clients
+/// can't dodge limits by sending their own `X-Forwarded-For`.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies {
+    networks: Vec<IpNet>,
+}
+
+impl TrustedProxies {
+    /// Parse IP addresses and CIDR ranges
+    pub fn parse(entries: &[String]) -> Result<Self, ConfigError> {
+        let networks = entries
+            .iter()
+            .map(|entry| {
+                let entry = entry.trim();
+                entry
+                    .parse::<IpNet>()
+                    .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| {
+                        ConfigError::InvalidValue(format!(
+                            "rate_limit.trusted_proxies: '{}' is not an IP address or CIDR range",
+                            entry
+                        ))
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { networks })
+    }
+
+    pub fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(ip))
+    }
+
+    /// The client's IP address
+    ///
+    /// When the peer is a trusted proxy, `X-Forwarded-For` is read from the
+    /// right, skipping further trusted proxies; the first other address is
+    /// the client. Anything left of it could have been made up by the
+    /// client and is ignored.
+    pub fn client_ip(&self, peer: Option<IpAddr>, forwarded_for: Option<&str>) -> Option<IpAddr> {
+        let peer = peer?;
+        if !self.is_trusted(&peer) {
+            return Some(peer);
+        }
+
+        let mut client = peer;
+        for hop in forwarded_for.unwrap_or_default().rsplit(',') {
+            match hop.trim().parse::<IpAddr>() {
+                Ok(ip) => {
+                    client = ip;
+                    if !self.is_trusted(&ip) {
+                        break;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+        Some(client)
+    }
+}
+
+/// Tracked entries beyond which stale ones are evicted on the next failure
+const LOGIN_THROTTLE_PRUNE_THRESHOLD: usize = 10_000;
+
+/// Failed login attempts for one account or client IP
+struct FailureRecord {
+    failures: u32,
+    first_failure: SystemTime,
+    next_attempt_at: Option<SystemTime>,
+    locked_until: Option<SystemTime>,
+}
+
+impl FailureRecord {
+    fn new(now: SystemTime) -> Self {
+        Self {
+            failures: 0,
+            first_failure: now,
+            next_attempt_at: None,
+            locked_until: None,
+        }
+    }
+
+    fn is_stale(&self, now: SystemTime, window: Duration) -> bool {
+        let lock_over = self.locked_until.is_none_or(|until| until <= now);
+        let delay_over = self.next_attempt_at.is_none_or(|at| at <= now);
+        let window_over = now
+            .duration_since(self.first_failure)
+            .is_ok_and(|elapsed| elapsed > window);
+        lock_over && delay_over && window_over
+    }
+}
+
+/// Login throttle - tracks failed logins per account and per client IP
+///
+/// Accounts are keyed by the normalized email as submitted, whether or not
+/// a user exists for it, so throttling responses never reveal which emails
+/// are registered. After `delay_after_failures` failures each further attempt
+/// must wait an exponentially growing delay; at `max_account_failures` the
+/// account is locked for `lockout_seconds`. Client IPs are only locked, at
+/// the higher `max_ip_failures`, so shared addresses aren't slowed down by a
+/// single user's typos.
+pub struct LoginThrottle {
+    settings: LoginThrottleSettings,
+    accounts: Mutex<HashMap<String, FailureRecord>>,
+    ips: Mutex<HashMap<String, FailureRecord>>,
+}
+
+impl LoginThrottle {
+    pub fn new(settings: LoginThrottleSettings) -> Self {
+        Self {
+            settings,
+            accounts: Mutex::new(HashMap::new()),
+            ips: Mutex::new(HashMap::new()),
         }
     }
 
-    /// Validate content length
-    pub fn check_content_length(&self, length: u64) -> Result<(), String> {
-        if length > self.config.max_content_length {
-            return Err(format!(
-                "Content length {} exceeds maximum {}",
-                length, self.config.max_content_length
-            ));
+    /// Check whether a login attempt may proceed to password verification
+    pub fn check(&self, email: &str, ip: Option<&str>) -> Result<(), AuthError> {
+        self.check_at(email, ip, SystemTime::now())
+    }
+
+    /// Record a failed login. Returns true if this failure locked the account.
+    pub fn record_failure(&self, email: &str, ip: Option<&str>) -> bool {
+        self.record_failure_at(email, ip, SystemTime::now())
+    }
+
+    /// Clear the account's failures after a successful login
+    ///
+    /// The IP counter is kept: one valid credential in a stuffing run
+    /// shouldn't reset the budget for the rest of the list.
+    pub fn record_success(&self, email: &str) {
+        self.accounts.lock().unwrap().remove(&account_key(email));
+    }
+
+    fn check_at(&self, email: &str, ip: Option<&str>, now: SystemTime) -> Result<(), AuthError> {
+        if let Some(ip) = ip {
+            let ips = self.ips.lock().unwrap();
+            if let Some(retry_after_seconds) = ips
+                .get(ip)
+                .and_then(|record| record.locked_until)
+                .and_then(|until| seconds_until(until, now))
+            {
+                return Err(AuthError::TooManyLoginAttempts { retry_after_seconds });
+            }
         }
+
+        let accounts = self.accounts.lock().unwrap();
+        if let Some(record) = accounts.get(&account_key(email)) {
+            if let Some(retry_after_seconds) =
+                record.locked_until.and_then(|until| seconds_until(until, now))
+            {
+                return Err(AuthError::AccountLocked { retry_after_seconds });
+            }
+            if let Some(retry_after_seconds) =
+                record.next_attempt_at.and_then(|at| seconds_until(at, now))
+            {
+                return Err(AuthError::TooManyLoginAttempts { retry_after_seconds });
+            }
+        }
+
         Ok(())
     }
+
+    fn record_failure_at(&self, email: &str, ip: Option<&str>, now: SystemTime) -> bool {
+        let window = Duration::from_secs(self.settings.failure_window_seconds);
+
+        if let Some(ip) = ip {
+            let mut ips = self.ips.lock().unwrap();
+            prune(&mut ips, now, window);
+            let record = ips
+                .entry(ip.to_string())
+                .or_insert_with(|| FailureRecord::new(now));
+            self.register_failure(record, now, self.settings.max_ip_failures, false);
+        }
+
+        let mut accounts = self.accounts.lock().unwrap();
+        prune(&mut accounts, now, window);
+        let record = accounts
+            .entry(account_key(email))
+            .or_insert_with(|| FailureRecord::new(now));
+        self.register_failure(record, now, self.settings.max_account_failures, true)
+    }
+
+    /// Count a failure against `record`, returning true if it is now locked
+    fn register_failure(
+        &self,
+        record: &mut FailureRecord,
+        now: SystemTime,
+        lockout_threshold: u32,
+        progressive_delay: bool,
+    ) -> bool {
+        let window = Duration::from_secs(self.settings.failure_window_seconds);
+        if record.is_stale(now, window) || record.locked_until.is_some_and(|until| until <= now) {
+            *record = FailureRecord::new(now);
+        }
+
+        record.failures += 1;
+
+        if record.failures >= lockout_threshold {
+            let newly_locked = record.locked_until.is_none();
+            record.locked_until = Some(now + Duration::from_secs(self.settings.lockout_seconds));
+            record.next_attempt_at = None;
+            return newly_locked;
+        }
+
+        if progressive_delay && record.failures >= self.settings.delay_after_failures {
+            let exponent = (record.failures - self.settings.delay_after_failures).min(16);
+            let delay = self
+                .settings
+                .base_delay_seconds
+                .saturating_mul(1 << exponent)
+                .min(self.settings.lockout_seconds);
+            record.next_attempt_at = Some(now + Duration::from_secs(delay));
+        }
+
+        false
+    }
+}
+
+fn account_key(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Whole seconds (rounded up) from `now` until `until`, if still in the future
+fn seconds_until(until: SystemTime, now: SystemTime) -> Option<u64> {
+    let remaining = until.duration_since(now).ok().filter(|d| !d.is_zero())?;
+    Some(remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0))
+}
+
+fn prune(records: &mut HashMap<String, FailureRecord>, now: SystemTime, window: Duration) {
+    if records.len() >= LOGIN_THROTTLE_PRUNE_THRESHOLD {
+        records.retain(|_, record| !record.is_stale(now, window));
+    }
+}
+
+/// Headers that can differ per route scope
+#[derive(Clone, Debug)]
+struct ScopedHeaders {
+    content_security_policy: String,
+    permissions_policy: String,
+    cross_origin_opener_policy: String,
+    cross_origin_resource_policy: String,
 }
 
 /// Security headers for HTTP responses
-pub struct SecurityHeaders;
+///
+/// The defaults apply to every response; scopes override them for paths
+/// under a prefix (the first matching scope wins). `{nonce}` in a Content
+/// Security Policy is replaced with the request's nonce, and headers
+/// configured as an empty string are left out.
+#[derive(Clone, Debug)]
+pub struct SecurityHeaders {
+    defaults: ScopedHeaders,
+    scopes: Vec<(String, ScopedHeaders)>,
+    /// `Strict-Transport-Security` value, sent on HTTPS requests only
+    hsts: String,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        Self::from_settings(&SecurityHeaderSettings::default())
+            .expect("default security headers are valid")
+    }
+}
 
 impl SecurityHeaders {
     /// Get security headers to prevent common attacks
     pub fn get_headers() -> Vec<(String, String)> {
-        vec![
-            // CSRF Protection
-            ("X-CSRF-Token".to_string(), "required".to_string()),
+        Self::default().headers_for("/", "", false)
+    }
+
+    pub fn from_settings(settings: &SecurityHeaderSettings) -> Result<Self, ConfigError> {
+        let defaults = ScopedHeaders {
+            content_security_policy: settings.content_security_policy.clone(),
+            permissions_policy: settings.permissions_policy.clone(),
+            cross_origin_opener_policy: settings.cross_origin_opener_policy.clone(),
+            cross_origin_resource_policy: settings.cross_origin_resource_policy.clone(),
+        };
+        validate_header_values("security_headers", &defaults)?;
+
+        let scopes = settings
+            .scopes
+            .iter()
+            .map(|scope| {
+                let field = format!("security_headers.scopes.{}", scope.path_prefix);
+                if !scope.path_prefix.starts_with('/') {
+                    return Err(ConfigError::InvalidValue(format!(
+                        "{}: path_prefix must start with '/'",
+                        field
+                    )));
+                }
+                let headers = ScopedHeaders {
+                    content_security_policy: scope
+                        .content_security_policy
+                        .clone()
+                        .unwrap_or_else(|| defaults.content_security_policy.clone()),
+                    permissions_policy: scope
+                        .permissions_policy
+                        .clone()
+                        .unwrap_or_else(|| defaults.permissions_policy.clone()),
+                    cross_origin_opener_policy: scope
+                        .cross_origin_opener_policy
+                        .clone()
+                        .unwrap_or_else(|| defaults.cross_origin_opener_policy.clone()),
+                    cross_origin_resource_policy: scope
+                        .cross_origin_resource_policy
+                        .clone()
+                        .unwrap_or_else(|| defaults.cross_origin_resource_policy.clone()),
+                };
+                validate_header_values(&field, &headers)?;
+                Ok((scope.path_prefix.trim_end_matches('/').to_string(), headers))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let mut hsts = format!("max-age={}", settings.hsts_max_age_seconds);
+        if settings.hsts_include_subdomains {
+            hsts.push_str("; includeSubDomains");
+        }
+
+        Ok(Self {
+            defaults,
+            scopes,
+            hsts,
+        })
+    }
 
+    /// Headers for a response to `path`
+    pub fn headers_for(&self, path: &str, nonce: &str, https: bool) -> Vec<(String, String)> {
+        let scoped = self
+            .scopes
+            .iter()
+            .find(|(prefix, _)| path_in_scope(path, prefix))
+            .map(|(_, headers)| headers)
+            .unwrap_or(&self.defaults);
+
+        let mut headers = vec![
             // XSS Protection
             ("X-Content-Type-Options".to_string(), "nosniff".to_string()),
             ("X-Frame-Options".to_string(), "SAMEORIGIN".to_string()),
             ("X-XSS-Protection".to_string(), "1; mode=block".to_string()),
 
-            // Content Security Policy (basic)
-            ("Content-Security-Policy".to_string(),
-             "default-src 'self'; script-src 'self'; style-src 'self' 'unsafe-inline'".to_string()),
-
             // Referrer Policy (data theft protection)
             ("Referrer-Policy".to_string(), "strict-origin-when-cross-origin".to_string()),
 
-            // HSTS (HTTPS only)
-            ("Strict-Transport-Security".to_string(), "max-age=31536000; includeSubDomains".to_string()),
-        ]
+            (
+                "Content-Security-Policy".to_string(),
+                scoped.content_security_policy.replace("{nonce}", nonce),
+            ),
+            ("Permissions-Policy".to_string(), scoped.permissions_policy.clone()),
+            ("Cross-Origin-Opener-Policy".to_string(), scoped.cross_origin_opener_policy.clone()),
+            (
+                "Cross-Origin-Resource-Policy".to_string(),
+                scoped.cross_origin_resource_policy.clone(),
+            ),
+        ];
+
+        // HSTS is ignored over plain HTTP and would break local development
+        if https {
+            headers.push(("Strict-Transport-Security".to_string(), self.hsts.clone()));
+        }
+
+        headers.retain(|(_, value)| !value.is_empty());
+        headers
+    }
+}
+
+/// Whether `path` is `prefix` itself or below it
+///
+/// `prefix` has no trailing slash; an empty prefix covers every path.
+pub(crate) fn path_in_scope(path: &str, prefix: &str) -> bool {
+    prefix.is_empty()
+        || path == prefix
+        || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn validate_header_values(field: &str, headers: &ScopedHeaders) -> Result<(), ConfigError> {
+    let values = [
+        &headers.content_security_policy,
+        &headers.permissions_policy,
+        &headers.cross_origin_opener_policy,
+        &headers.cross_origin_resource_policy,
+    ];
+    let printable = |value: &String| value.chars().all(|c| c == '\t' || (' '..='~').contains(&c));
+    if values.into_iter().all(printable) {
+        Ok(())
+    } else {
+        Err(ConfigError::InvalidValue(format!(
+            "{}: header values must be printable ASCII",
+            field
+        )))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::configuration::SecurityHeaderScopeSettings;
 
     #[test]
     fn test_rate_limiter_allows_initial_request() {
         let config = RateLimitConfig {
             requests_per_minute: 10,
-            max_content_length: 1024,
         };
         let manager = RateLimiterManager::new(config);
         assert!(manager.check_rate_limit("127.0.0.1").is_ok());
     }
 
     #[test]
-    fn test_content_length_validation() {
+    fn test_rate_limiter_reports_quota_and_retry_after() {
         let config = RateLimitConfig {
-            requests_per_minute: 10,
-            max_content_length: 1024,
+            requests_per_minute: 2,
+        };
+        let manager = RateLimiterManager::new(config);
+        let now = SystemTime::now();
+
+        let status = manager.check_rate_limit_at("k", now).unwrap();
+        assert_eq!((2, 1, 30), (status.limit, status.remaining, status.reset_seconds));
+        manager.check_rate_limit_at("k", now).unwrap();
+
+        let exceeded = manager.check_rate_limit_at("k", now).unwrap_err();
+        assert_eq!(0, exceeded.status.remaining);
+        assert_eq!(30, exceeded.retry_after_seconds);
+        assert!(manager.check_rate_limit_at("other", now).is_ok());
+        assert!(manager.check_rate_limit_at("k", now + Duration::from_secs(30)).is_ok());
+    }
+
+    #[test]
+    fn test_rate_limiter_evicts_idle_buckets() {
+        let config = RateLimitConfig {
+            requests_per_minute: 60,
         };
         let manager = RateLimiterManager::new(config);
+        let now = SystemTime::now();
+        for i in 0..100 {
+            manager.check_rate_limit_at(&format!("10.0.0.{}", i), now).unwrap();
+        }
+        assert_eq!(100, manager.tracked_keys());
 
-        assert!(manager.check_content_length(512).is_ok());
-        assert!(manager.check_content_length(1024).is_ok());
-        assert!(manager.check_content_length(2048).is_err());
+        // Refilled buckets are dropped by the next sweep; busy ones stay
+        let later = now + Duration::from_secs(61);
+        for _ in 0..30 {
+            manager.check_rate_limit_at("busy", later - Duration::from_secs(1)).unwrap();
+        }
+        manager.check_rate_limit_at("busy", later).unwrap();
+        assert_eq!(1, manager.tracked_keys());
+    }
+
+    #[test]
+    fn test_sliding_window_weights_previous_window() {
+        // Half way through: 10 * 0.5 + 5 = 10 of 10 allowed
+        let status = sliding_window_decision(10, 10, 5, 30.0).unwrap();
+        assert_eq!((0, 30), (status.remaining, status.reset_seconds));
+
+        // One more is over; room opens once the previous window weighs 3
+        let exceeded = sliding_window_decision(10, 10, 6, 30.0).unwrap_err();
+        assert_eq!(12, exceeded.retry_after_seconds);
+
+        // Over on the current window alone: wait into the next one
+        let exceeded = sliding_window_decision(2, 0, 4, 45.0).unwrap_err();
+        assert_eq!(15 + 45, exceeded.retry_after_seconds);
+
+        assert_eq!(9, sliding_window_decision(10, 0, 1, 0.0).unwrap().remaining);
+    }
+
+    #[test]
+    fn test_client_ip_only_trusts_forwarded_for_from_trusted_proxies() {
+        let proxies =
+            TrustedProxies::parse(&["10.0.0.0/8".to_string(), "192.0.2.1".to_string()]).unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let proxy: IpAddr = "10.1.2.3".parse().unwrap();
+
+        // Untrusted peers can't spoof their address
+        assert_eq!(Some(client), proxies.client_ip(Some(client), Some("198.51.100.1")));
+        // Rightmost untrusted hop wins; spoofed entries further left are ignored
+        assert_eq!(
+            Some(client),
+            proxies.client_ip(Some(proxy), Some("198.51.100.1, 203.0.113.7, 192.0.2.1"))
+        );
+        assert_eq!(Some(proxy), proxies.client_ip(Some(proxy), None));
+        assert!(TrustedProxies::parse(&["not-an-ip".to_string()]).is_err());
+    }
+
+    fn throttle_settings() -> LoginThrottleSettings {
+        LoginThrottleSettings {
+            delay_after_failures: 3,
+            base_delay_seconds: 1,
+            max_account_failures: 5,
+            max_ip_failures: 8,
+            lockout_seconds: 900,
+            failure_window_seconds: 900,
+        }
+    }
+
+    #[test]
+    fn test_login_throttle_delays_then_locks_account() {
+        let throttle = LoginThrottle::new(throttle_settings());
+        let start = SystemTime::now();
+        let email = "john@example.com";
+
+        for i in 0..2 {
+            let now = start + Duration::from_secs(i);
+            assert!(throttle.check_at(email, None, now).is_ok());
+            assert!(!throttle.record_failure_at(email, None, now));
+        }
+
+        // Third failure starts the delay: 1s, then 2s
+        let now = start + Duration::from_secs(10);
+        assert!(!throttle.record_failure_at(email, None, now));
+        assert!(matches!(
+            throttle.check_at(email, None, now),
+            Err(AuthError::TooManyLoginAttempts { retry_after_seconds: 1 })
+        ));
+
+        let now = now + Duration::from_secs(1);
+        assert!(throttle.check_at(email, None, now).is_ok());
+        assert!(!throttle.record_failure_at(email, None, now));
+        assert!(matches!(
+            throttle.check_at(email, None, now),
+            Err(AuthError::TooManyLoginAttempts { retry_after_seconds: 2 })
+        ));
+
+        // Fifth failure locks the account, reported only once
+        let now = now + Duration::from_secs(2);
+        assert!(throttle.record_failure_at(email, None, now));
+        assert!(matches!(
+            throttle.check_at(email, None, now),
+            Err(AuthError::AccountLocked { retry_after_seconds: 900 })
+        ));
+        assert!(!throttle.record_failure_at(email, None, now));
+
+        // Lock expires on its own
+        let now = now + Duration::from_secs(901);
+        assert!(throttle.check_at(email, None, now).is_ok());
+    }
+
+    #[test]
+    fn test_login_throttle_keys_accounts_case_insensitively() {
+        let throttle = LoginThrottle::new(throttle_settings());
+        let now = SystemTime::now();
+        for _ in 0..5 {
+            throttle.record_failure_at("John@Example.com", None, now);
+        }
+        assert!(throttle.check_at("john@example.com", None, now).is_err());
+        assert!(throttle.check_at("jane@example.com", None, now).is_ok());
+    }
+
+    #[test]
+    fn test_login_throttle_locks_ip_across_accounts() {
+        let throttle = LoginThrottle::new(throttle_settings());
+        let now = SystemTime::now();
+        let ip = Some("203.0.113.7");
+        for i in 0..8 {
+            throttle.record_failure_at(&format!("user{}@example.com", i), ip, now);
+        }
+        assert!(matches!(
+            throttle.check_at("fresh@example.com", ip, now),
+            Err(AuthError::TooManyLoginAttempts { retry_after_seconds: 900 })
+        ));
+        assert!(throttle.check_at("fresh@example.com", Some("198.51.100.1"), now).is_ok());
+    }
+
+    #[test]
+    fn test_login_throttle_success_and_window_reset_account() {
+        let throttle = LoginThrottle::new(throttle_settings());
+        let start = SystemTime::now();
+        let email = "john@example.com";
+
+        for _ in 0..2 {
+            throttle.record_failure_at(email, None, start);
+        }
+        throttle.record_success(email);
+        throttle.record_failure_at(email, None, start);
+        throttle.record_failure_at(email, None, start);
+        assert!(throttle.check_at(email, None, start).is_ok());
+
+        // Old failures fall out of the window
+        let later = start + Duration::from_secs(901);
+        throttle.record_failure_at(email, None, later);
+        assert!(throttle.check_at(email, None, later).is_ok());
     }
 
     #[test]
@@ -172,4 +816,43 @@ mod tests {
         assert!(header_names.contains(&&"X-Content-Type-Options".to_string()));
         assert!(header_names.contains(&&"Content-Security-Policy".to_string()));
     }
+
+    #[test]
+    fn test_security_headers_scopes_nonces_and_hsts() {
+        let settings = SecurityHeaderSettings {
+            scopes: vec![SecurityHeaderScopeSettings {
+                path_prefix: "/api".to_string(),
+                content_security_policy: Some("default-src 'none'".to_string()),
+                permissions_policy: Some(String::new()),
+                cross_origin_opener_policy: None,
+                cross_origin_resource_policy: None,
+            }],
+            ..SecurityHeaderSettings::default()
+        };
+        let headers = SecurityHeaders::from_settings(&settings).unwrap();
+        let value = |headers: &[(String, String)], name: &str| {
+            headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone())
+        };
+
+        let page = headers.headers_for("/index.html", "abc123", false);
+        assert!(value(&page, "Content-Security-Policy").unwrap().contains("'nonce-abc123'"));
+        assert!(value(&page, "Permissions-Policy").is_some());
+        assert!(value(&page, "Strict-Transport-Security").is_none());
+
+        let api = headers.headers_for("/api/me", "abc123", true);
+        assert_eq!(Some("default-src 'none'".to_string()), value(&api, "Content-Security-Policy"));
+        assert_eq!(None, value(&api, "Permissions-Policy"));
+        assert_eq!(Some("same-origin".to_string()), value(&api, "Cross-Origin-Opener-Policy"));
+        assert!(value(&api, "Strict-Transport-Security").unwrap().starts_with("max-age="));
+
+        // Only whole path segments match
+        let other = headers.headers_for("/apiary", "abc123", false);
+        assert!(value(&other, "Content-Security-Policy").unwrap().contains("nonce"));
+    }
+
+    #[test]
+    fn test_no_csrf_placeholder_header() {
+        let headers = SecurityHeaders::get_headers();
+        assert!(headers.iter().all(|(name, _)| name != "X-CSRF-Token"));
+    }
 }
@@ -4,49 +4,200 @@ use sqlx::PgPool;
 use std::net::TcpListener;
 use actix_web::dev::Server;
 
-use crate::configuration::JwtSettings;
+use crate::configuration::Settings;
 use crate::logger::LoggerMiddleware;
-use crate::middleware::JwtMiddleware;
+use crate::auth::{run_account_purge_worker, JwtKeys, OidcClient, PasswordPolicy};
+use crate::middleware::{
+    run_rate_limit_cleanup_worker, CorsMiddleware, CorsPolicies, CsrfMiddleware, JwtMiddleware,
+    PayloadLimitMiddleware, PayloadLimits, RateLimitMiddleware, RateLimiter,
+    SecurityHeadersMiddleware,
+};
+use crate::preference_link::PreferenceLinks;
+use crate::subscription_guard::SubscriptionGuard;
+use crate::email_deliverability::EmailDeliverability;
+use crate::input_policy::InputPolicy;
+use crate::security::{LoginThrottle, SecurityHeaders};
 use crate::routes::{
-    confirm_subscription, get_current_user, health_check, login, refresh, register, send_newsletter_to_all,
-    send_newsletter_to_confirmed, subscribe,
+    admin_erase_subscriber_data, admin_export_subscriber_data, change_email, change_password,
+    change_subscriber_status, change_user_role, confirm_email_change, confirm_subscriber,
+    confirm_subscription, confirm_two_factor, create_api_key_handler, deactivate_user,
+    delete_account, delete_subscriber, disable_two_factor, erase_my_subscriber_data,
+    export_account_data, export_my_subscriber_data, export_subscribers, force_password_reset,
+    get_current_user, get_import_job, get_preferences, get_subscriber, get_user, health_check,
+    import_subscribers, jwks, list_api_keys_handler, list_subscribers, list_user_sessions,
+    list_users, login, login_mfa, logout, oidc_callback, oidc_login, publish_newsletter_to_all,
+    publish_newsletter_to_confirmed, reactivate_user, refresh, register,
+    resend_verification_email, reset_password, revoke_api_key_handler, setup_two_factor,
+    subscribe, subscription_challenge, unsubscribe, update_preferences, verify_email,
 };
 
+/// Public base URL of the application, used to build links in outgoing emails
+pub struct ApplicationBaseUrl(pub String);
+
 pub fn run(
     listener: TcpListener,
     connection: PgPool,
-    jwt_config: JwtSettings,
+    configuration: Settings,
 ) -> Result<Server, std::io::Error> {
+    let email_client = configuration.email_client.client().map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string())
+    })?;
+
+    let jwt_keys = JwtKeys::from_settings(&configuration.jwt).map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string())
+    })?;
+
+    let oidc_http_client = reqwest::Client::builder()
+        .timeout(configuration.email_client.timeout())
+        .build()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    let oidc_client = OidcClient::new(
+        &configuration.auth.oidc,
+        &configuration.application.base_url,
+        oidc_http_client,
+    )
+    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+
+    let password_policy = PasswordPolicy::new(&configuration.auth.password_policy).map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string())
+    })?;
+
+    let preference_links =
+        PreferenceLinks::new(&configuration.subscriptions, &configuration.application.base_url)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    let subscription_guard = SubscriptionGuard::new(&configuration.subscriptions)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    let email_deliverability = EmailDeliverability::new(&configuration.subscriptions.deliverability)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    let input_policy = InputPolicy::from_settings(&configuration.input_policy)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+
+    let rate_limiter = RateLimiter::from_settings(&configuration.rate_limit, &connection)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    if rate_limiter.uses_postgres() {
+        tokio::spawn(run_rate_limit_cleanup_worker(connection.clone()));
+    }
+    let trusted_proxies = rate_limiter.trusted_proxies().clone();
+
+    let security_headers = SecurityHeaders::from_settings(&configuration.security_headers)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+
+    let cors_policies = CorsPolicies::from_settings(&configuration.cors)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+
+    // Erases accounts whose deletion grace period is over
+    tokio::spawn(run_account_purge_worker(
+        connection.clone(),
+        configuration.auth.account_deletion.clone(),
+    ));
+
+    let jwt_config = configuration.jwt.clone();
     let connection = web::Data::new(connection);
     let jwt_config_data = web::Data::new(jwt_config.clone());
+    let jwt_keys = web::Data::new(jwt_keys);
+    let email_client = web::Data::new(email_client);
+    let base_url = web::Data::new(ApplicationBaseUrl(configuration.application.base_url.clone()));
+    let auth_settings = web::Data::new(configuration.auth.clone());
+    let oidc_client = web::Data::new(oidc_client);
+    let password_policy = web::Data::new(password_policy);
+    let preference_links = web::Data::new(preference_links);
+    let subscription_guard = web::Data::new(subscription_guard);
+    let email_deliverability = web::Data::new(email_deliverability);
+    let input_policy = web::Data::new(input_policy);
+    // Shared across workers so failures count the same on every connection
+    let login_throttle = web::Data::new(LoginThrottle::new(configuration.auth.login_throttle.clone()));
+    let rate_limiter = web::Data::new(rate_limiter);
+    let payload_limits = web::Data::new(PayloadLimits::from_settings(&configuration.request_limits));
+    let security_headers = web::Data::new(security_headers);
+    let cors_policies = web::Data::new(cors_policies);
 
     let server = HttpServer::new(move || {
         App::new()
             // Global middleware
+            .wrap(CsrfMiddleware)
+            .wrap(PayloadLimitMiddleware::new(payload_limits.clone()))
+            .wrap(RateLimitMiddleware::new(rate_limiter.clone()))
+            .wrap(CorsMiddleware::new(cors_policies.clone()))
+            .wrap(SecurityHeadersMiddleware::new(security_headers.clone(), trusted_proxies.clone()))
             .wrap(Logger::default())      // Standard logging
             .wrap(LoggerMiddleware)       // Custom logging
 
             // Shared state
             .app_data(connection.clone())
             .app_data(jwt_config_data.clone())
+            .app_data(jwt_keys.clone())
+            .app_data(email_client.clone())
+            .app_data(base_url.clone())
+            .app_data(auth_settings.clone())
+            .app_data(login_throttle.clone())
+            .app_data(oidc_client.clone())
+            .app_data(password_policy.clone())
+            .app_data(preference_links.clone())
+            .app_data(subscription_guard.clone())
+            .app_data(email_deliverability.clone())
+            .app_data(input_policy.clone())
+            .app_data(payload_limits.json_config())
+            .app_data(payload_limits.form_config())
 
             // Public routes (no authentication required)
             .route("/health_check", web::get().to(health_check))
+            .route("/.well-known/jwks.json", web::get().to(jwks))
             .route("/auth/register", web::post().to(register))
             .route("/auth/login", web::post().to(login))
+            .route("/auth/login/mfa", web::post().to(login_mfa))
             .route("/auth/refresh", web::post().to(refresh))
+            .route("/auth/logout", web::post().to(logout))
+            .route("/auth/confirm-email", web::get().to(confirm_email_change))
+            .route("/auth/verify-email", web::get().to(verify_email))
+            .route("/auth/verify-email/resend", web::post().to(resend_verification_email))
+            .route("/auth/password-reset", web::post().to(reset_password))
+            .route("/auth/oidc/{provider}/login", web::get().to(oidc_login))
+            .route("/auth/oidc/{provider}/callback", web::get().to(oidc_callback))
 
-            // Protected routes (require JWT authentication)
+            // Protected routes (require a JWT or API key)
             .service(
                 web::scope("/api")
-                    .wrap(JwtMiddleware::new(jwt_config.clone()))
+                    .wrap(JwtMiddleware::new(jwt_config.clone(), jwt_keys.clone()))
                     .route("/me", web::get().to(get_current_user))
+                    .route("/me", web::delete().to(delete_account))
+                    .route("/me/export", web::get().to(export_account_data))
+                    .route("/me/password", web::post().to(change_password))
+                    .route("/me/email", web::post().to(change_email))
+                    .route("/me/2fa/setup", web::post().to(setup_two_factor))
+                    .route("/me/2fa/confirm", web::post().to(confirm_two_factor))
+                    .route("/me/2fa/disable", web::post().to(disable_two_factor))
+                    .route("/api-keys", web::post().to(create_api_key_handler))
+                    .route("/api-keys", web::get().to(list_api_keys_handler))
+                    .route("/api-keys/{id}", web::delete().to(revoke_api_key_handler))
+                    .route("/newsletters/send-all", web::post().to(publish_newsletter_to_all))
+                    .route("/newsletters/send-confirmed", web::post().to(publish_newsletter_to_confirmed))
+                    .route("/admin/users", web::get().to(list_users))
+                    .route("/admin/users/{id}", web::get().to(get_user))
+                    .route("/admin/users/{id}/sessions", web::get().to(list_user_sessions))
+                    .route("/admin/users/{id}/deactivate", web::post().to(deactivate_user))
+                    .route("/admin/users/{id}/reactivate", web::post().to(reactivate_user))
+                    .route("/admin/users/{id}/force-password-reset", web::post().to(force_password_reset))
+                    .route("/admin/users/{id}/role", web::put().to(change_user_role))
+                    .route("/admin/subscriber-data/export", web::post().to(admin_export_subscriber_data))
+                    .route("/admin/subscriber-data/erase", web::post().to(admin_erase_subscriber_data))
+                    .route("/admin/subscribers/import", web::post().to(import_subscribers))
+                    .route("/admin/subscribers/import/{job_id}", web::get().to(get_import_job))
+                    .route("/admin/subscribers/export", web::get().to(export_subscribers))
+                    .route("/admin/subscribers", web::get().to(list_subscribers))
+                    .route("/admin/subscribers/{id}", web::get().to(get_subscriber))
+                    .route("/admin/subscribers/{id}", web::delete().to(delete_subscriber))
+                    .route("/admin/subscribers/{id}/confirm", web::post().to(confirm_subscriber))
+                    .route("/admin/subscribers/{id}/status", web::put().to(change_subscriber_status))
             )
             .route("/auth/me", web::get().to(get_current_user))
             .route("/subscriptions", web::post().to(subscribe))
+            .route("/subscriptions/challenge", web::get().to(subscription_challenge))
             .route("/subscriptions/confirm", web::get().to(confirm_subscription))
-            .route("/newsletters/send-all", web::post().to(send_newsletter_to_all))
-            .route("/newsletters/send-confirmed", web::post().to(send_newsletter_to_confirmed))
+            .route("/subscriptions/preferences", web::get().to(get_preferences))
+            .route("/subscriptions/preferences", web::put().to(update_preferences))
+            .route("/subscriptions/unsubscribe", web::post().to(unsubscribe))
+            .route("/subscriptions/data", web::get().to(export_my_subscriber_data))
+            .route("/subscriptions/data", web::delete().to(erase_my_subscriber_data))
             
             // Static file serving (must be last to not override API routes)
             .service(fs::Files::new("/", "./public").index_file("index.html"))
@@ -0,0 +1,254 @@
+//! Subscriber Data Requests
+//!
+//! Export and erasure of everything stored about a newsletter subscriber:
+//! the subscription row, its confirmation tokens and the subscriber's audit
+//! trail. Newsletter deliveries are not stored per subscriber, so there is
+//! no delivery history to include.
+//!
+//! Erasure leaves a tombstone holding only a SHA-256 hash of the address,
+//! which imports check so that an erased subscriber isn't added back.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::validators::canonical_email;
+
+/// Everything stored about the subscriptions under one email address
+#[derive(Serialize)]
+pub struct SubscriberExport {
+    pub exported_at: String,
+    pub subscriptions: Vec<SubscriptionRecord>,
+    pub confirmation_tokens: Vec<ConfirmationTokenRecord>,
+    pub audit_log: Vec<SubscriberAuditRecord>,
+}
+
+#[derive(Serialize)]
+pub struct SubscriptionRecord {
+    pub id: String,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: String,
+    pub excluded_topics: Vec<String>,
+    pub digest_frequency: String,
+    pub paused_until: Option<String>,
+    pub unsubscribed_at: Option<String>,
+    pub consent_source: Option<String>,
+    pub consent_recorded_at: Option<String>,
+}
+
+/// Confirmation token metadata; the token itself is left out
+#[derive(Serialize)]
+pub struct ConfirmationTokenRecord {
+    pub subscriber_id: String,
+    pub created_at: String,
+    pub expires_at: String,
+}
+
+#[derive(Serialize)]
+pub struct SubscriberAuditRecord {
+    pub id: String,
+    pub subscriber_id: String,
+    pub created_at: String,
+    pub action: String,
+    pub status: String,
+    pub message: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct SubscriptionRow {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+    excluded_topics: Vec<String>,
+    digest_frequency: String,
+    paused_until: Option<DateTime<Utc>>,
+    unsubscribed_at: Option<DateTime<Utc>>,
+    consent_source: Option<String>,
+    consent_recorded_at: Option<DateTime<Utc>>,
+}
+
+/// Collect everything stored about the subscriptions under `email`
+///
+/// Addresses are matched case-insensitively.
+///
+/// # Returns
+/// `None` if there is no subscription under the address
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn export_subscriber_data(
+    pool: &PgPool,
+    email: &str,
+) -> Result<Option<SubscriberExport>, AppError> {
+    let rows = sqlx::query_as::<_, SubscriptionRow>(
+        r#"
+        SELECT id, email, name, status, subscribed_at, excluded_topics, digest_frequency,
+               paused_until, unsubscribed_at, consent_source, consent_recorded_at
+        FROM subscriptions WHERE email_canonical = $1
+        ORDER BY subscribed_at
+        "#,
+    )
+    .bind(canonical_email(email))
+    .fetch_all(pool)
+    .await?;
+    if rows.is_empty() {
+        return Ok(None);
+    }
+
+    let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+    let id_strings: Vec<String> = ids.iter().map(Uuid::to_string).collect();
+
+    let confirmation_tokens = sqlx::query_as::<_, (Uuid, DateTime<Utc>, DateTime<Utc>)>(
+        r#"
+        SELECT subscriber_id, created_at, expires_at FROM subscription_tokens
+        WHERE subscriber_id = ANY($1) ORDER BY created_at
+        "#,
+    )
+    .bind(&ids)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(subscriber_id, created_at, expires_at)| ConfirmationTokenRecord {
+        subscriber_id: subscriber_id.to_string(),
+        created_at: created_at.to_rfc3339(),
+        expires_at: expires_at.to_rfc3339(),
+    })
+    .collect();
+
+    let audit_log = sqlx::query_as::<_, (Uuid, String, DateTime<Utc>, String, String, String)>(
+        r#"
+        SELECT id, resource_id, created_at, action, status, message FROM audit_logs
+        WHERE resource_type = 'subscription' AND resource_id = ANY($1)
+        ORDER BY created_at
+        "#,
+    )
+    .bind(&id_strings)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|(id, subscriber_id, created_at, action, status, message)| SubscriberAuditRecord {
+        id: id.to_string(),
+        subscriber_id,
+        created_at: created_at.to_rfc3339(),
+        action,
+        status,
+        message,
+    })
+    .collect();
+
+    let subscriptions = rows
+        .into_iter()
+        .map(|row| SubscriptionRecord {
+            id: row.id.to_string(),
+            email: row.email,
+            name: row.name,
+            status: row.status,
+            subscribed_at: row.subscribed_at.to_rfc3339(),
+            excluded_topics: row.excluded_topics,
+            digest_frequency: row.digest_frequency,
+            paused_until: row.paused_until.map(|t| t.to_rfc3339()),
+            unsubscribed_at: row.unsubscribed_at.map(|t| t.to_rfc3339()),
+            consent_source: row.consent_source,
+            consent_recorded_at: row.consent_recorded_at.map(|t| t.to_rfc3339()),
+        })
+        .collect();
+
+    Ok(Some(SubscriberExport {
+        exported_at: Utc::now().to_rfc3339(),
+        subscriptions,
+        confirmation_tokens,
+        audit_log,
+    }))
+}
+
+/// Erase every subscription under `email` and leave a tombstone
+///
+/// Deleting the subscription removes its confirmation tokens with it. Audit
+/// entries stay, but not the old and new values they recorded.
+///
+/// # Returns
+/// Ids of the erased subscriptions; empty if there were none
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn erase_subscriber(pool: &PgPool, email: &str) -> Result<Vec<Uuid>, AppError> {
+    let mut transaction = pool.begin().await?;
+
+    let ids = sqlx::query_scalar::<_, Uuid>(
+        "DELETE FROM subscriptions WHERE email_canonical = $1 RETURNING id",
+    )
+    .bind(canonical_email(email))
+    .fetch_all(&mut transaction)
+    .await?;
+    if ids.is_empty() {
+        return Ok(ids);
+    }
+
+    let id_strings: Vec<String> = ids.iter().map(Uuid::to_string).collect();
+    sqlx::query(
+        r#"
+        UPDATE audit_logs SET previous_state = NULL, new_state = NULL
+        WHERE resource_type = 'subscription' AND resource_id = ANY($1)
+        "#,
+    )
+    .bind(&id_strings)
+    .execute(&mut transaction)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO subscription_tombstones (email_hash, erased_at) VALUES ($1, $2)
+        ON CONFLICT (email_hash) DO UPDATE SET erased_at = EXCLUDED.erased_at
+        "#,
+    )
+    .bind(email_tombstone_hash(email))
+    .bind(Utc::now())
+    .execute(&mut transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(ids)
+}
+
+/// Whether the address belongs to an erased subscriber
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn is_erased_subscriber(pool: &PgPool, email: &str) -> Result<bool, AppError> {
+    let erased = sqlx::query_scalar::<_, String>(
+        "SELECT email_hash FROM subscription_tombstones WHERE email_hash = $1",
+    )
+    .bind(email_tombstone_hash(email))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(erased.is_some())
+}
+
+/// Hash stored in place of an erased address (of its canonical form)
+pub fn email_tombstone_hash(email: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(canonical_email(email).as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tombstone_hash_ignores_case_and_whitespace() {
+        let hash = email_tombstone_hash("Jane@Example.com ");
+        assert_eq!(hash, email_tombstone_hash("jane@example.com"));
+        assert_ne!(hash, email_tombstone_hash("john@example.com"));
+        assert!(!hash.contains("jane"));
+    }
+}
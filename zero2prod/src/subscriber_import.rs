@@ -0,0 +1,471 @@
+//! Subscriber Imports
+//!
+//! Bulk subscriber imports from CSV, run as tracked background jobs. The
+//! upload is spooled to a temporary file, then parsed and applied row by
+//! row; progress and a per-row error report are kept in the database.
+//!
+//! The CSV needs a header row with `email` and `name` columns (in any
+//! order, other columns are ignored). Every row goes through the same
+//! validation as a signup. Imports never send confirmation emails, never
+//! touch subscribers who unsubscribed and never bring back erased ones.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::io::AsyncReadExt;
+use uuid::Uuid;
+
+use crate::csv_stream::CsvRecordReader;
+use crate::error::{AppError, ValidationError};
+use crate::input_policy::InputPolicy;
+use crate::request_logging::{AuditLog, RequestFailureLogger};
+use crate::subscriber_data::is_erased_subscriber;
+use crate::validators::{canonical_email, is_valid_email};
+
+/// Rows applied between progress updates
+const PROGRESS_INTERVAL: i32 = 500;
+
+/// Most failed rows kept in the error report; later failures are only
+/// counted
+const MAX_REPORTED_ERRORS: i32 = 10_000;
+
+/// Longest accepted consent source description
+const MAX_CONSENT_SOURCE_LENGTH: usize = 200;
+
+/// What to do with rows for addresses that are already subscribed
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Leave existing subscribers alone; new ones start as pending
+    #[default]
+    SkipExisting,
+    /// Update existing subscribers' names; new ones start as pending
+    Update,
+    /// Add or update subscribers as confirmed, recording where their
+    /// consent came from
+    Confirm,
+}
+
+impl ImportMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportMode::SkipExisting => "skip_existing",
+            ImportMode::Update => "update",
+            ImportMode::Confirm => "confirm",
+        }
+    }
+}
+
+/// Settings of one import, checked before the upload is accepted
+#[derive(Clone, Debug)]
+pub struct ImportOptions {
+    pub mode: ImportMode,
+    pub consent_source: Option<String>,
+}
+
+impl ImportOptions {
+    /// # Errors
+    /// - `ValidationError::EmptyField`: `confirm` mode without a consent source
+    /// - `ValidationError::TooLong`: Consent source is too long
+    pub fn new(mode: ImportMode, consent_source: Option<&str>) -> Result<Self, ValidationError> {
+        let consent_source = consent_source
+            .map(str::trim)
+            .filter(|source| !source.is_empty())
+            .map(str::to_string);
+
+        if let Some(source) = &consent_source {
+            if source.chars().count() > MAX_CONSENT_SOURCE_LENGTH {
+                return Err(ValidationError::TooLong(
+                    "consent_source".to_string(),
+                    MAX_CONSENT_SOURCE_LENGTH,
+                ));
+            }
+            if source.chars().any(char::is_control) {
+                return Err(ValidationError::InvalidFormat("consent_source".to_string()));
+            }
+        }
+        if mode == ImportMode::Confirm && consent_source.is_none() {
+            return Err(ValidationError::EmptyField("consent_source".to_string()));
+        }
+
+        Ok(Self { mode, consent_source })
+    }
+}
+
+/// Create an import job in the `queued` state
+///
+/// # Errors
+/// Returns error if database operation fails
+pub async fn create_import_job(
+    pool: &PgPool,
+    admin_id: Uuid,
+    options: &ImportOptions,
+) -> Result<Uuid, AppError> {
+    let job_id = Uuid::new_v4();
+
+    sqlx::query(
+        r#"
+        INSERT INTO subscriber_import_jobs
+        (id, created_by, mode, consent_source, status, created_at)
+        VALUES ($1, $2, $3, $4, 'queued', $5)
+        "#,
+    )
+    .bind(job_id)
+    .bind(admin_id)
+    .bind(options.mode.as_str())
+    .bind(options.consent_source.as_deref())
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(job_id)
+}
+
+/// Mark a queued job as failed, such as when the upload didn't complete
+pub async fn fail_import_job(pool: &PgPool, job_id: Uuid, error: &str) {
+    let result = sqlx::query(
+        r#"
+        UPDATE subscriber_import_jobs SET status = 'failed', error = $1, finished_at = $2
+        WHERE id = $3
+        "#,
+    )
+    .bind(error)
+    .bind(Utc::now())
+    .bind(job_id)
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!(job_id = %job_id, error = %e, "Failed to record import job failure");
+    }
+}
+
+/// Process a spooled upload, then delete it
+///
+/// Meant to be spawned; the outcome is recorded on the job.
+pub async fn run_import_job(
+    pool: PgPool,
+    job_id: Uuid,
+    admin_id: Uuid,
+    options: ImportOptions,
+    input_policy: Arc<InputPolicy>,
+    path: PathBuf,
+) {
+    let result = process_import(&pool, job_id, &options, &input_policy, &path).await;
+
+    if let Err(e) = tokio::fs::remove_file(&path).await {
+        tracing::warn!(job_id = %job_id, error = %e, "Failed to remove import upload");
+    }
+
+    let (status, message) = match result {
+        Ok(counts) => (
+            "SUCCESS",
+            format!(
+                "Import finished: {} imported, {} updated, {} skipped, {} failed",
+                counts.imported, counts.updated, counts.skipped, counts.failed
+            ),
+        ),
+        Err(e) => {
+            fail_import_job(&pool, job_id, &e).await;
+            ("FAILURE", format!("Import failed: {}", e))
+        }
+    };
+
+    let audit_log = AuditLog::new(
+        "IMPORT_SUBSCRIBERS".to_string(),
+        "subscriber_import".to_string(),
+        status.to_string(),
+        message.clone(),
+    )
+    .with_resource_id(job_id.to_string())
+    .with_user_id(admin_id.to_string());
+    RequestFailureLogger::record_audit(&pool, &audit_log).await;
+
+    tracing::info!(job_id = %job_id, "{}", message);
+}
+
+#[derive(Default)]
+struct ImportCounts {
+    total_rows: i32,
+    imported: i32,
+    updated: i32,
+    skipped: i32,
+    failed: i32,
+}
+
+enum RowOutcome {
+    Imported,
+    Updated,
+    Skipped,
+}
+
+/// Column positions from the header row
+struct Columns {
+    email: usize,
+    name: usize,
+}
+
+impl Columns {
+    fn from_header(header: &[String]) -> Result<Self, String> {
+        let position = |column: &str| {
+            header.iter().position(|h| {
+                // Spreadsheet exports often start with a byte order mark
+                h.trim_start_matches('\u{feff}').trim().eq_ignore_ascii_case(column)
+            })
+        };
+
+        match (position("email"), position("name")) {
+            (Some(email), Some(name)) => Ok(Self { email, name }),
+            _ => Err("CSV header must include 'email' and 'name' columns".to_string()),
+        }
+    }
+}
+
+async fn process_import(
+    pool: &PgPool,
+    job_id: Uuid,
+    options: &ImportOptions,
+    input_policy: &InputPolicy,
+    path: &Path,
+) -> Result<ImportCounts, String> {
+    sqlx::query(
+        "UPDATE subscriber_import_jobs SET status = 'running', started_at = $1 WHERE id = $2",
+    )
+    .bind(Utc::now())
+    .bind(job_id)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut file = tokio::fs::File::open(path).await.map_err(|e| e.to_string())?;
+    let mut reader = CsvRecordReader::new();
+    let mut buffer = vec![0; 64 * 1024];
+    let mut columns = None;
+    let mut counts = ImportCounts::default();
+    // The header is row 1
+    let mut row_number = 1;
+
+    loop {
+        let read = file.read(&mut buffer).await.map_err(|e| e.to_string())?;
+        let records = if read == 0 {
+            reader.finish()
+        } else {
+            reader.feed(&buffer[..read])
+        };
+
+        for record in records {
+            let Some(columns) = &columns else {
+                columns = Some(Columns::from_header(&record)?);
+                continue;
+            };
+            row_number += 1;
+            counts.total_rows += 1;
+
+            let email = record.get(columns.email).map(String::as_str).unwrap_or_default();
+            let name = record.get(columns.name).map(String::as_str).unwrap_or_default();
+            match import_row(pool, options, input_policy, email, name).await {
+                Ok(RowOutcome::Imported) => counts.imported += 1,
+                Ok(RowOutcome::Updated) => counts.updated += 1,
+                Ok(RowOutcome::Skipped) => counts.skipped += 1,
+                Err(message) => {
+                    counts.failed += 1;
+                    if counts.failed <= MAX_REPORTED_ERRORS {
+                        record_row_error(pool, job_id, row_number, email, &message).await?;
+                    }
+                }
+            }
+
+            if counts.total_rows % PROGRESS_INTERVAL == 0 {
+                save_progress(pool, job_id, &counts, None).await?;
+            }
+        }
+
+        if read == 0 {
+            break;
+        }
+    }
+
+    if columns.is_none() {
+        return Err("CSV file is empty".to_string());
+    }
+
+    save_progress(pool, job_id, &counts, Some("completed")).await?;
+
+    Ok(counts)
+}
+
+/// Apply one row; errors are reported back as row messages
+async fn import_row(
+    pool: &PgPool,
+    options: &ImportOptions,
+    input_policy: &InputPolicy,
+    email: &str,
+    name: &str,
+) -> Result<RowOutcome, String> {
+    let email = is_valid_email(email).map_err(|e| e.to_string())?;
+    let name = input_policy.check_name(name).map_err(|e| e.to_string())?;
+
+    if is_erased_subscriber(pool, &email).await.map_err(|e| e.to_string())? {
+        return Err("address belongs to an erased subscriber".to_string());
+    }
+
+    let existing = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT id, status FROM subscriptions WHERE email_canonical = $1",
+    )
+    .bind(canonical_email(&email))
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let now = Utc::now();
+    match (existing, options.mode) {
+        (None, mode) => {
+            let confirmed = mode == ImportMode::Confirm;
+            let inserted = sqlx::query(
+                r#"
+                INSERT INTO subscriptions
+                (id, email, email_canonical, name, subscribed_at, status, consent_source,
+                 consent_recorded_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (email_canonical) DO NOTHING
+                "#,
+            )
+            .bind(Uuid::new_v4())
+            .bind(&email)
+            .bind(canonical_email(&email))
+            .bind(&name)
+            .bind(now)
+            .bind(if confirmed { "confirmed" } else { "pending" })
+            .bind(options.consent_source.as_deref().filter(|_| confirmed))
+            .bind(Some(now).filter(|_| confirmed))
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+            Ok(if inserted.rows_affected() == 0 {
+                RowOutcome::Skipped
+            } else {
+                RowOutcome::Imported
+            })
+        }
+        // An import must not override an unsubscribe
+        (Some((_, status)), _) if status == "unsubscribed" => Ok(RowOutcome::Skipped),
+        (Some(_), ImportMode::SkipExisting) => Ok(RowOutcome::Skipped),
+        (Some((id, _)), ImportMode::Update) => {
+            sqlx::query("UPDATE subscriptions SET name = $1 WHERE id = $2")
+                .bind(&name)
+                .bind(id)
+                .execute(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(RowOutcome::Updated)
+        }
+        (Some((id, _)), ImportMode::Confirm) => {
+            sqlx::query(
+                r#"
+                UPDATE subscriptions
+                SET name = $1, status = 'confirmed', consent_source = $2, consent_recorded_at = $3
+                WHERE id = $4
+                "#,
+            )
+            .bind(&name)
+            .bind(options.consent_source.as_deref())
+            .bind(now)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+            // Confirmed through the import, so the pending token is moot
+            sqlx::query("DELETE FROM subscription_tokens WHERE subscriber_id = $1")
+                .bind(id)
+                .execute(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(RowOutcome::Updated)
+        }
+    }
+}
+
+async fn record_row_error(
+    pool: &PgPool,
+    job_id: Uuid,
+    row_number: i32,
+    email: &str,
+    message: &str,
+) -> Result<(), String> {
+    // Truncated so a malformed row can't bloat the report
+    let email: String = email.chars().take(254).collect();
+
+    sqlx::query(
+        r#"
+        INSERT INTO subscriber_import_errors (job_id, row_number, email, message)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(job_id)
+    .bind(row_number)
+    .bind(Some(email).filter(|e| !e.is_empty()))
+    .bind(message)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+async fn save_progress(
+    pool: &PgPool,
+    job_id: Uuid,
+    counts: &ImportCounts,
+    status: Option<&str>,
+) -> Result<(), String> {
+    sqlx::query(
+        r#"
+        UPDATE subscriber_import_jobs
+        SET total_rows = $1, imported = $2, updated = $3, skipped = $4, failed = $5,
+            status = COALESCE($6, status),
+            finished_at = CASE WHEN $6 IS NULL THEN finished_at ELSE $7 END
+        WHERE id = $8
+        "#,
+    )
+    .bind(counts.total_rows)
+    .bind(counts.imported)
+    .bind(counts.updated)
+    .bind(counts.skipped)
+    .bind(counts.failed)
+    .bind(status)
+    .bind(Utc::now())
+    .bind(job_id)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirm_mode_requires_consent_source() {
+        assert!(ImportOptions::new(ImportMode::Confirm, None).is_err());
+        assert!(ImportOptions::new(ImportMode::Confirm, Some("  ")).is_err());
+        assert!(ImportOptions::new(ImportMode::Confirm, Some("Old provider export")).is_ok());
+        assert!(ImportOptions::new(ImportMode::SkipExisting, None).is_ok());
+    }
+
+    #[test]
+    fn test_header_columns_in_any_order() {
+        let header: Vec<String> =
+            ["\u{feff}Name", "id", "EMAIL"].iter().map(|s| s.to_string()).collect();
+        let columns = Columns::from_header(&header).unwrap();
+        assert_eq!((columns.email, columns.name), (2, 0));
+
+        let header = vec!["email".to_string()];
+        assert!(Columns::from_header(&header).is_err());
+    }
+}
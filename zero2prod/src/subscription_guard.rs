@@ -0,0 +1,423 @@
+//! Subscription Bot Protection
+//!
+//! `POST /subscriptions` makes us email whatever address it is given, so
+//! unchecked it lets anyone mailbomb a third party through us. Submissions
+//! go through layered checks (see `BotProtectionSettings`):
+//!
+//! - a hidden honeypot field that people leave empty,
+//! - a signed form token from `GET /subscriptions/challenge` that must be at
+//!   least `min_fill_seconds` old when the form is submitted,
+//! - optionally, a proof of work bound to the token and the address, so that
+//!   every recipient costs the sender some hashing,
+//! - a cap on confirmation emails per recipient address, whoever asks.
+//!
+//! Form token format: `{issued unix time in ms}.{nonce}.{signature}`, where
+//! the signature is an HMAC-SHA256 over the first two parts, keyed with the
+//! preference link secret. A token sends at most one confirmation email: its
+//! nonce is recorded in `used_form_tokens` until the token expires.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::configuration::{BotProtectionSettings, SubscriptionSettings};
+use crate::error::{AppError, ConfigError, ValidationError};
+use crate::subscriber_data::email_tombstone_hash;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Keeps form tokens from passing as any other signed value
+const FORM_TOKEN_CONTEXT: &[u8] = b"subscription-form:";
+
+/// Longest accepted proof of work nonce
+const MAX_POW_NONCE_LEN: usize = 64;
+
+/// Why a submission was taken for a bot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BotRejection {
+    /// The hidden honeypot field was filled in
+    Honeypot,
+    MissingFormToken,
+    InvalidFormToken,
+    ExpiredFormToken,
+    /// Submitted sooner after fetching the form token than a person could
+    TooFast,
+    ProofOfWorkFailed,
+    /// The form token already sent a confirmation email
+    ReusedFormToken,
+}
+
+impl BotRejection {
+    /// Short reason for audit logs and error messages
+    pub fn reason(&self) -> &'static str {
+        match self {
+            BotRejection::Honeypot => "honeypot field filled in",
+            BotRejection::MissingFormToken => "form token missing",
+            BotRejection::InvalidFormToken => "form token invalid",
+            BotRejection::ExpiredFormToken => "form token expired",
+            BotRejection::TooFast => "form submitted too quickly",
+            BotRejection::ProofOfWorkFailed => "proof of work missing or invalid",
+            BotRejection::ReusedFormToken => "form token already used",
+        }
+    }
+}
+
+/// Bot protection fields of a subscription form
+pub struct FormSubmission<'a> {
+    /// Hidden honeypot field
+    pub website: Option<&'a str>,
+    pub form_token: Option<&'a str>,
+    pub pow_nonce: Option<&'a str>,
+    /// Address as submitted; the proof of work covers it
+    pub email: &'a str,
+}
+
+/// Body of `GET /subscriptions/challenge`
+#[derive(serde::Serialize)]
+pub struct SubscriptionChallenge {
+    pub form_token: String,
+    pub min_fill_seconds: i64,
+    /// Leading zero bits required of `SHA-256(form_token:email:pow_nonce)`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_of_work_difficulty: Option<u32>,
+}
+
+/// Checks subscription form submissions for bots
+pub struct SubscriptionGuard {
+    secret: Vec<u8>,
+    settings: BotProtectionSettings,
+}
+
+impl SubscriptionGuard {
+    pub fn new(settings: &SubscriptionSettings) -> Result<Self, ConfigError> {
+        let bot_protection = &settings.bot_protection;
+        if bot_protection.min_fill_seconds < 0 {
+            return Err(ConfigError::InvalidValue(
+                "subscriptions.bot_protection.min_fill_seconds must not be negative".to_string(),
+            ));
+        }
+        if bot_protection.form_token_expiry_minutes * 60 <= bot_protection.min_fill_seconds {
+            return Err(ConfigError::InvalidValue(
+                "subscriptions.bot_protection.form_token_expiry_minutes must be longer than \
+                 min_fill_seconds"
+                    .to_string(),
+            ));
+        }
+        if bot_protection.proof_of_work_difficulty > 32 {
+            return Err(ConfigError::InvalidValue(
+                "subscriptions.bot_protection.proof_of_work_difficulty must be at most 32"
+                    .to_string(),
+            ));
+        }
+        if bot_protection.max_confirmation_emails_per_address > 0
+            && bot_protection.confirmation_email_window_hours < 1
+        {
+            return Err(ConfigError::InvalidValue(
+                "subscriptions.bot_protection.confirmation_email_window_hours must be at least 1"
+                    .to_string(),
+            ));
+        }
+
+        Ok(Self {
+            secret: settings.preference_link_secret.as_bytes().to_vec(),
+            settings: bot_protection.clone(),
+        })
+    }
+
+    /// Issue a form token, with the proof of work asked for if any
+    pub fn challenge(&self) -> SubscriptionChallenge {
+        let mut nonce = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let issued_at = Utc::now().timestamp_millis();
+        let payload = format!("{}.{}", issued_at, URL_SAFE_NO_PAD.encode(nonce));
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&payload).finalize().into_bytes());
+
+        SubscriptionChallenge {
+            form_token: format!("{}.{}", payload, signature),
+            min_fill_seconds: self.settings.min_fill_seconds,
+            proof_of_work_difficulty: self.proof_of_work_difficulty(),
+        }
+    }
+
+    /// Run the form checks on a submission
+    ///
+    /// # Errors
+    /// The first check the submission failed
+    pub fn check(&self, submission: &FormSubmission) -> Result<(), BotRejection> {
+        if self.settings.honeypot && submission.website.is_some_and(|w| !w.trim().is_empty()) {
+            return Err(BotRejection::Honeypot);
+        }
+
+        if !self.form_token_required() {
+            return Ok(());
+        }
+
+        let form_token = submission
+            .form_token
+            .filter(|token| !token.is_empty())
+            .ok_or(BotRejection::MissingFormToken)?;
+        let (issued_at, _nonce) = self.verify_form_token(form_token)?;
+        let age_millis = Utc::now().timestamp_millis() - issued_at;
+        if age_millis < 0 {
+            return Err(BotRejection::InvalidFormToken);
+        }
+        if age_millis > self.settings.form_token_expiry_minutes * 60_000 {
+            return Err(BotRejection::ExpiredFormToken);
+        }
+        if age_millis < self.settings.min_fill_seconds * 1000 {
+            return Err(BotRejection::TooFast);
+        }
+
+        if let Some(difficulty) = self.proof_of_work_difficulty() {
+            let nonce = submission
+                .pow_nonce
+                .filter(|nonce| !nonce.is_empty() && nonce.len() <= MAX_POW_NONCE_LEN)
+                .ok_or(BotRejection::ProofOfWorkFailed)?;
+            if proof_of_work_bits(form_token, submission.email, nonce) < difficulty {
+                return Err(BotRejection::ProofOfWorkFailed);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Mark the form token of a submission that passed `check` as used
+    ///
+    /// Does nothing when form tokens aren't required.
+    ///
+    /// # Errors
+    /// - `ValidationError::BotCheckFailed`: The token was used before
+    /// - `AppError::Database`: The used tokens couldn't be read or updated
+    pub async fn redeem_form_token(
+        &self,
+        pool: &PgPool,
+        form_token: Option<&str>,
+    ) -> Result<(), AppError> {
+        if !self.form_token_required() {
+            return Ok(());
+        }
+        let rejected = |rejection: BotRejection| {
+            AppError::Validation(ValidationError::BotCheckFailed(rejection.reason().to_string()))
+        };
+        let form_token = form_token.ok_or_else(|| rejected(BotRejection::MissingFormToken))?;
+        let (issued_at, nonce) = self.verify_form_token(form_token).map_err(rejected)?;
+
+        sqlx::query("DELETE FROM used_form_tokens WHERE expires_at <= now()")
+            .execute(pool)
+            .await?;
+
+        let expires_at = issued_at + self.settings.form_token_expiry_minutes * 60_000;
+        let redeemed = sqlx::query(
+            r#"
+            INSERT INTO used_form_tokens (nonce, expires_at)
+            VALUES ($1, to_timestamp($2::float8 / 1000))
+            ON CONFLICT (nonce) DO NOTHING
+            "#,
+        )
+        .bind(nonce)
+        .bind(expires_at)
+        .execute(pool)
+        .await?
+        .rows_affected();
+        if redeemed == 0 {
+            return Err(rejected(BotRejection::ReusedFormToken));
+        }
+
+        Ok(())
+    }
+
+    /// Count a confirmation email to `email` against its per-address cap
+    ///
+    /// Concurrent reservations for one address are serialized with an
+    /// advisory lock on its hash, so the cap holds under parallel requests.
+    ///
+    /// # Errors
+    /// - `ValidationError::TooManyConfirmationEmails`: The address got as many
+    ///   confirmation emails as allowed within the window
+    /// - `AppError::Database`: The counts couldn't be read or updated
+    pub async fn reserve_confirmation_email(
+        &self,
+        pool: &PgPool,
+        email: &str,
+    ) -> Result<(), AppError> {
+        let max = self.settings.max_confirmation_emails_per_address;
+        if max <= 0 {
+            return Ok(());
+        }
+        let window_hours = self.settings.confirmation_email_window_hours;
+        let email_hash = email_tombstone_hash(email);
+
+        sqlx::query(
+            r#"
+            DELETE FROM confirmation_email_sends
+            WHERE sent_at <= now() - make_interval(hours => $1)
+            "#,
+        )
+        .bind(window_hours)
+        .execute(pool)
+        .await?;
+
+        let mut transaction = pool.begin().await?;
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(&email_hash)
+            .execute(&mut transaction)
+            .await?;
+
+        let (sent, retry_after_seconds) = sqlx::query_as::<_, (i64, Option<f64>)>(
+            r#"
+            SELECT COUNT(*),
+                   extract(epoch FROM MIN(sent_at) + make_interval(hours => $2) - now())::float8
+            FROM confirmation_email_sends
+            WHERE email_hash = $1
+            "#,
+        )
+        .bind(&email_hash)
+        .bind(window_hours)
+        .fetch_one(&mut transaction)
+        .await?;
+        if sent >= max {
+            return Err(AppError::Validation(ValidationError::TooManyConfirmationEmails {
+                retry_after_seconds: retry_after_seconds.unwrap_or(0.0).ceil().max(1.0) as u64,
+            }));
+        }
+
+        sqlx::query("INSERT INTO confirmation_email_sends (email_hash, sent_at) VALUES ($1, now())")
+            .bind(&email_hash)
+            .execute(&mut transaction)
+            .await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Whether submissions need a form token at all
+    fn form_token_required(&self) -> bool {
+        self.settings.min_fill_seconds > 0 || self.proof_of_work_difficulty().is_some()
+    }
+
+    fn proof_of_work_difficulty(&self) -> Option<u32> {
+        Some(self.settings.proof_of_work_difficulty).filter(|difficulty| *difficulty > 0)
+    }
+
+    /// Issue time (unix ms) and nonce of a form token with a valid signature
+    fn verify_form_token<'a>(&self, form_token: &'a str) -> Result<(i64, &'a str), BotRejection> {
+        let invalid = BotRejection::InvalidFormToken;
+
+        let (payload, signature) = form_token.rsplit_once('.').ok_or(invalid)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid)?;
+        self.mac(payload).verify_slice(&signature).map_err(|_| invalid)?;
+
+        let (issued_at, nonce) = payload.split_once('.').ok_or(invalid)?;
+        Ok((issued_at.parse().map_err(|_| invalid)?, nonce))
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(FORM_TOKEN_CONTEXT);
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+/// Leading zero bits of `SHA-256(form_token:email:nonce)`
+///
+/// The address is trimmed and lowercased first, like everywhere else we
+/// compare addresses.
+pub fn proof_of_work_bits(form_token: &str, email: &str, nonce: &str) -> u32 {
+    let digest = Sha256::digest(
+        format!("{}:{}:{}", form_token, email.trim().to_lowercase(), nonce).as_bytes(),
+    );
+
+    let mut bits = 0;
+    for byte in digest {
+        bits += byte.leading_zeros();
+        if byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(min_fill_seconds: i64, proof_of_work_difficulty: u32) -> SubscriptionGuard {
+        SubscriptionGuard {
+            secret: b"test-preference-link-secret-32-bytes!!".to_vec(),
+            settings: BotProtectionSettings {
+                min_fill_seconds,
+                proof_of_work_difficulty,
+                ..BotProtectionSettings::default()
+            },
+        }
+    }
+
+    fn submission<'a>(
+        form_token: Option<&'a str>,
+        pow_nonce: Option<&'a str>,
+    ) -> FormSubmission<'a> {
+        FormSubmission {
+            website: None,
+            form_token,
+            pow_nonce,
+            email: "ursula@example.com",
+        }
+    }
+
+    #[test]
+    fn test_honeypot_is_checked_first() {
+        let guard = guard(0, 0);
+        let mut form = submission(None, None);
+        assert_eq!(Ok(()), guard.check(&form));
+
+        form.website = Some("https://spam.example.com");
+        assert_eq!(Err(BotRejection::Honeypot), guard.check(&form));
+    }
+
+    #[test]
+    fn test_form_token_must_be_signed_and_old_enough() {
+        let guard = guard(3, 0);
+        let token = guard.challenge().form_token;
+
+        assert_eq!(Err(BotRejection::MissingFormToken), guard.check(&submission(None, None)));
+        assert_eq!(Err(BotRejection::TooFast), guard.check(&submission(Some(&token), None)));
+
+        let (payload, signature) = token.rsplit_once('.').unwrap();
+        let (_, nonce) = payload.split_once('.').unwrap();
+        let ten_seconds_ago = Utc::now().timestamp_millis() - 10_000;
+        let backdated = format!("{}.{}.{}", ten_seconds_ago, nonce, signature);
+        assert_eq!(
+            Err(BotRejection::InvalidFormToken),
+            guard.check(&submission(Some(&backdated), None))
+        );
+
+        let payload = format!("{}.{}", ten_seconds_ago, nonce);
+        let signature = URL_SAFE_NO_PAD.encode(guard.mac(&payload).finalize().into_bytes());
+        let old_enough = format!("{}.{}", payload, signature);
+        assert_eq!(Ok(()), guard.check(&submission(Some(&old_enough), None)));
+    }
+
+    #[test]
+    fn test_proof_of_work_is_required_when_enabled() {
+        let guard = guard(0, 8);
+        let token = guard.challenge().form_token;
+        let nonce = (0u64..)
+            .map(|n| n.to_string())
+            .find(|nonce| proof_of_work_bits(&token, "ursula@example.com", nonce) >= 8)
+            .unwrap();
+
+        assert_eq!(Ok(()), guard.check(&submission(Some(&token), Some(&nonce))));
+        assert_eq!(
+            Err(BotRejection::ProofOfWorkFailed),
+            guard.check(&submission(Some(&token), None))
+        );
+        // Addresses are compared the way they are stored
+        assert!(proof_of_work_bits(&token, " Ursula@Example.com", &nonce) >= 8);
+    }
+}
@@ -3,46 +3,45 @@
 /// 1. DoS Protection: Input length limits
 /// 2. Data Theft Protection: Input sanitization
 /// 3. Phishing Protection: Email validation
-/// 4. SQL Injection Prevention: Query validation
+///
+/// Names are checked by the input policy (see `input_policy`); queries are
+/// parameterized, so input is not screened for SQL.
 
 use regex::Regex;
 use lazy_static::lazy_static;
 use crate::error::ValidationError;
+use crate::input_policy::DEFAULT_INPUT_POLICY;
+use unicode_normalization::UnicodeNormalization;
 
 const MAX_EMAIL_LENGTH: usize = 254; // RFC 5321
-const MAX_NAME_LENGTH: usize = 256;  // Custom limit as per requirements
 const MIN_EMAIL_LENGTH: usize = 5;   // Minimum valid email length
-const MIN_NAME_LENGTH: usize = 1;    // At least one character
+const MAX_DOMAIN_LENGTH: usize = 253; // RFC 1035, in ASCII form
 
 lazy_static! {
-    // RFC 5322 simplified email regex (practical validation)
-    static ref EMAIL_REGEX: Regex = Regex::new(
-        r"^[a-zA-Z0-9.!#$%&'*+/=?^_`{|}~-]+@[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?(?:\.[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?)*$"
+    // Local part characters of RFC 5322 dot-atoms; RFC 6531 adds any
+    // non-ASCII character (see `is_local_part_char`)
+    static ref LOCAL_PART_ASCII_REGEX: Regex = Regex::new(
+        r"^[a-zA-Z0-9.!#$%&'*+/=?^_`{|}~-]+$"
     ).unwrap();
 
-    // Regex to detect potentially malicious SQL patterns
-    static ref SQL_INJECTION_PATTERNS: [Regex; 6] = [
-        // Union-based SQL injection
-        Regex::new(r"(?i)\s+UNION\s+").unwrap(),
-        // Comment-based injection
-        Regex::new(r"(--|;|/\*|\*/|xp_|sp_)").unwrap(),
-        // Stacked queries
-        Regex::new(r"(?i);\s*(INSERT|UPDATE|DELETE|DROP|CREATE|ALTER)").unwrap(),
-        // Time-based blind injection
-        Regex::new(r"(?i)(SLEEP|WAITFOR|BENCHMARK|DBMS_LOCK)").unwrap(),
-        // Boolean-based injection - quotes handled with character class
-        Regex::new(r#"(?i)(\bOR\b|\bAND\b)\s*(['"][0-9]*['"]|[0-9]*)\s*=\s*(['"][0-9]*['"]|[0-9]*|True|False)"#).unwrap(),
-        // Function-based injection
-        Regex::new(r"(?i)(CAST|CONVERT|SUBSTRING|CONCAT|LOAD_FILE)").unwrap(),
-    ];
+    // Domain in ASCII (A-label) form, after IDNA conversion
+    static ref DOMAIN_REGEX: Regex = Regex::new(
+        r"^[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?(?:\.[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?)*$"
+    ).unwrap();
 }
 
 /// Validates email address
-/// - Checks format using RFC 5322 simplified regex
+/// - Parses RFC 5321 addresses with the RFC 6531 (SMTPUTF8) extensions:
+///   UTF-8 local parts and internationalized domain names
 /// - Verifies length constraints
 /// - Detects potential phishing patterns
+///
+/// # Returns
+/// The address to store and send to: local part NFC-normalized as typed,
+/// domain converted to lowercase punycode (`Bücher.de` → `xn--bcher-kva.de`)
 pub fn is_valid_email(email: &str) -> Result<String, ValidationError> {
     let trimmed = email.trim();
+    let invalid = || ValidationError::InvalidFormat("email".to_string());
 
     // Length validation - prevent DoS attacks with extremely long inputs
     if trimmed.is_empty() {
@@ -57,62 +56,81 @@ pub fn is_valid_email(email: &str) -> Result<String, ValidationError> {
         return Err(ValidationError::TooLong("email".to_string(), MAX_EMAIL_LENGTH));
     }
 
-    // Format validation - RFC 5322 simplified
-    if !EMAIL_REGEX.is_match(trimmed) {
-        return Err(ValidationError::InvalidFormat("email".to_string()));
+    // Format validation - dot-atom local part, hostname domain
+    let normalized: String = trimmed.nfc().collect();
+    let (local_part, domain) = normalized.rsplit_once('@').ok_or_else(invalid)?;
+    if !is_valid_local_part(local_part) {
+        return Err(invalid());
+    }
+    let domain = idna::domain_to_ascii(domain).map_err(|_| invalid())?;
+    if domain.len() > MAX_DOMAIN_LENGTH || !DOMAIN_REGEX.is_match(&domain) {
+        return Err(invalid());
     }
 
     // Check for suspicious patterns (phishing protection)
-    if has_suspicious_email_patterns(trimmed) {
+    if has_suspicious_email_patterns(&normalized) {
         return Err(ValidationError::SuspiciousContent("email".to_string()));
     }
 
-    // Check for SQL injection patterns in email
-    if contains_sql_injection_patterns(trimmed) {
-        return Err(ValidationError::PossibleSQLInjection);
+    let address = format!("{}@{}", local_part, domain);
+    if address.len() > MAX_EMAIL_LENGTH {
+        return Err(ValidationError::TooLong("email".to_string(), MAX_EMAIL_LENGTH));
     }
-
-    Ok(trimmed.to_string())
+    Ok(address)
 }
 
-/// Validates subscriber name
-/// - Checks length constraints
-/// - Validates against control characters
-/// - Detects SQL injection patterns
-pub fn is_valid_name(name: &str) -> Result<String, ValidationError> {
-    let trimmed = name.trim();
-
-    // Length validation - prevent DoS attacks
-    if trimmed.is_empty() {
-        return Err(ValidationError::EmptyField("name".to_string()));
-    }
-
-    if trimmed.len() < MIN_NAME_LENGTH {
-        return Err(ValidationError::TooShort("name".to_string(), MIN_NAME_LENGTH));
-    }
+/// Form of a valid address used to compare addresses
+///
+/// Domains are case-insensitive; local parts technically aren't, but no
+/// mail system we send to treats `Jane@` and `jane@` as different people.
+/// `lower()` in Postgres only folds ASCII, so this is computed here and
+/// stored next to the address.
+pub fn canonical_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
 
-    if trimmed.len() > MAX_NAME_LENGTH {
-        return Err(ValidationError::TooLong("name".to_string(), MAX_NAME_LENGTH));
-    }
+/// Whether sending to `email` needs the SMTPUTF8 extension (RFC 6531)
+///
+/// After `is_valid_email` the domain is ASCII, so this is only the case
+/// for UTF-8 local parts.
+pub fn requires_smtputf8(email: &str) -> bool {
+    !email.is_ascii()
+}
 
-    // Check for control characters and suspicious content
-    if has_suspicious_name_patterns(trimmed) {
-        return Err(ValidationError::SuspiciousContent("name".to_string()));
-    }
+fn is_valid_local_part(local_part: &str) -> bool {
+    !local_part.is_empty()
+        && !local_part.starts_with('.')
+        && !local_part.ends_with('.')
+        && !local_part.contains("..")
+        && local_part.chars().all(is_local_part_char)
+}
 
-    // Check for SQL injection patterns
-    if contains_sql_injection_patterns(trimmed) {
-        return Err(ValidationError::PossibleSQLInjection);
-    }
+fn is_local_part_char(c: char) -> bool {
+    if c.is_ascii() {
+        return LOCAL_PART_ASCII_REGEX.is_match(c.encode_utf8(&mut [0; 4]));
+    }
+    // UTF8-non-ascii, short of characters that can't be seen or typed
+    !c.is_control()
+        && !c.is_whitespace()
+        && !matches!(
+            c,
+            '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2060}'..='\u{2069}' | '\u{FEFF}'
+        )
+}
 
-    Ok(trimmed.to_string())
+/// Validates subscriber name against the default input policy
+///
+/// Handlers check names with the configured `InputPolicy` instead; this is
+/// for names from elsewhere, such as identity providers.
+pub fn is_valid_name(name: &str) -> Result<String, ValidationError> {
+    DEFAULT_INPUT_POLICY.check_name(name)
 }
 
 /// Detects suspicious patterns in email addresses that might indicate phishing
 fn has_suspicious_email_patterns(email: &str) -> bool {
 
     // Check for extremely long local part (before @) - phishing indicator
-    if let Some(at_pos) = email.find('@') {
+    if let Some(at_pos) = email.rfind('@') {
         let local_part = &email[..at_pos];
         if local_part.len() > 64 {
             return true;
@@ -132,36 +150,6 @@ fn has_suspicious_email_patterns(email: &str) -> bool {
     false
 }
 
-/// Detects suspicious patterns in names
-fn has_suspicious_name_patterns(name: &str) -> bool {
-    // Check for null bytes (data theft protection)
-    if name.contains('\0') {
-        return true;
-    }
-
-    // Check for control characters
-    if name.chars().any(|c| c.is_control()) {
-        return true;
-    }
-
-    // Check for excessive special characters (potential injection)
-    let special_char_count = name.chars()
-        .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && *c != '-' && *c != '.' && *c != '_' && *c != '\'')
-        .count();
-
-    if special_char_count > 5 {
-        return true;
-    }
-
-    false
-}
-
-/// Checks if input contains SQL injection patterns
-fn contains_sql_injection_patterns(input: &str) -> bool {
-    SQL_INJECTION_PATTERNS.iter().any(|pattern| pattern.is_match(input))
-}
-
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -199,11 +187,35 @@ mod tests {
         assert!(is_valid_email("user; DROP TABLE@example.com").is_err());
     }
 
+    #[test]
+    fn test_internationalized_email() {
+        // IDN domains are stored in punycode
+        assert_eq!("hans@xn--bcher-kva.de", is_valid_email("hans@Bücher.de").unwrap());
+        assert_eq!("민수@xn--3e0b707e.kr", is_valid_email("민수@한국.kr").unwrap());
+        // UTF-8 local parts are kept, NFC-normalized
+        assert_eq!("j\u{00FC}rgen@example.de", is_valid_email("ju\u{0308}rgen@example.de").unwrap());
+
+        assert!(is_valid_email("jürgen\u{202E}@example.de").is_err());
+        assert!(is_valid_email(".jane@example.com").is_err());
+        assert!(is_valid_email("jane..doe@example.com").is_err());
+        assert!(is_valid_email("jane@exa mple.com").is_err());
+    }
+
+    #[test]
+    fn test_canonical_email_and_smtputf8() {
+        let email = is_valid_email("Jürgen@BÜCHER.de").unwrap();
+
+        assert_eq!("jürgen@xn--bcher-kva.de", canonical_email(&email));
+        assert!(requires_smtputf8(&email));
+        assert!(!requires_smtputf8(&is_valid_email("hans@Bücher.de").unwrap()));
+    }
+
     #[test]
     fn test_valid_name() {
         assert!(is_valid_name("John Doe").is_ok());
         assert!(is_valid_name("Jean-Pierre").is_ok());
         assert!(is_valid_name("O'Brien").is_ok());
+        assert!(is_valid_name("O'Brien-Smith").is_ok());
     }
 
     #[test]
@@ -215,9 +227,10 @@ mod tests {
     }
 
     #[test]
-    fn test_sql_injection_in_name() {
-        assert!(is_valid_name("John'; DROP TABLE subscribers--").is_err());
-        assert!(is_valid_name("Name UNION SELECT *").is_err());
+    fn test_sql_lookalike_names_are_not_rejected() {
+        // Queries are parameterized; names are stored as typed
+        assert!(is_valid_name("Robert'); DROP TABLE Students;--").is_ok());
+        assert!(is_valid_name("Union Select").is_ok());
     }
 
     #[test]
@@ -0,0 +1,226 @@
+mod common;
+
+use common::{spawn_app, register_user, TestApp};
+use zero2prod::auth::purge_deleted_accounts;
+use zero2prod::configuration::AccountDeletionMode;
+use serde_json::{json, Value};
+
+async fn delete_account(app: &TestApp, access_token: &str, password: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .delete(&format!("{}/api/me", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "current_password": password }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// Move the user's scheduled deletion into the past
+async fn end_grace_period(app: &TestApp, email: &str) {
+    sqlx::query(
+        "UPDATE users SET deletion_scheduled_at = NOW() - INTERVAL '1 minute' WHERE email = $1",
+    )
+    .bind(email)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to update deletion schedule");
+}
+
+// --- Export Tests ---
+
+#[tokio::test]
+async fn export_returns_profile_sessions_subscriptions_and_audit_trail() {
+    let app = spawn_app().await;
+    let tokens = register_user(&app, "john@example.com", "John Doe").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    sqlx::query(
+        r#"
+        INSERT INTO subscriptions (id, email, email_canonical, name, subscribed_at, status)
+        VALUES ($1, 'John@Example.com', 'john@example.com', 'John', NOW(), 'confirmed')
+        "#,
+    )
+    .bind(uuid::Uuid::new_v4())
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to insert subscription");
+
+    // Recorded in the audit trail
+    let response = delete_account(&app, access_token, "WrongPass999").await;
+    assert_eq!(401, response.status().as_u16());
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/api/me/export", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    assert!(response
+        .headers()
+        .get("Content-Disposition")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("attachment"));
+    let export: Value = response.json().await.unwrap();
+    assert_eq!("john@example.com", export["profile"]["email"]);
+    assert!(export["profile"].get("password_hash").is_none());
+    assert_eq!(1, export["sessions"].as_array().unwrap().len());
+    assert_eq!("confirmed", export["subscriptions"][0]["status"]);
+    let audit_log = export["audit_log"].as_array().unwrap();
+    assert!(audit_log
+        .iter()
+        .any(|entry| entry["action"] == "DELETE_ACCOUNT" && entry["status"] == "FAILURE"));
+}
+
+#[tokio::test]
+async fn export_and_delete_reject_api_keys() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let tokens = register_user(&app, "john@example.com", "John Doe").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    let created: Value = client
+        .post(&format!("{}/api/api-keys", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "name": "CI", "scopes": ["profile:read"] }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    let api_key = created["key"].as_str().unwrap();
+
+    let response = client
+        .get(&format!("{}/api/me/export", &app.address))
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(403, response.status().as_u16());
+
+    let response = delete_account(&app, api_key, "SecurePass123").await;
+    assert_eq!(403, response.status().as_u16());
+}
+
+// --- Deletion Tests ---
+
+#[tokio::test]
+async fn delete_account_deactivates_and_revokes_sessions() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let tokens = register_user(&app, "john@example.com", "John Doe").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+    let refresh_token = tokens["refresh_token"].as_str().unwrap();
+
+    let response = delete_account(&app, access_token, "SecurePass123").await;
+    assert_eq!(202, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert!(body["deletion_scheduled_at"].is_string());
+
+    let response = client
+        .post(&format!("{}/auth/refresh", &app.address))
+        .json(&json!({ "refresh_token": refresh_token }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+
+    let response = client
+        .post(&format!("{}/auth/login", &app.address))
+        .json(&json!({ "email": "john@example.com", "password": "SecurePass123" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+
+    // Nothing is erased before the grace period is over
+    let purged = purge_deleted_accounts(&app.db_pool, AccountDeletionMode::Anonymize)
+        .await
+        .unwrap();
+    assert_eq!(0, purged);
+}
+
+#[tokio::test]
+async fn purge_anonymizes_accounts_after_grace_period() {
+    let app = spawn_app().await;
+    let tokens = register_user(&app, "john@example.com", "John Doe").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+    let user_id: uuid::Uuid = sqlx::query_scalar("SELECT id FROM users WHERE email = 'john@example.com'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    assert_eq!(202, delete_account(&app, access_token, "SecurePass123").await.status().as_u16());
+    end_grace_period(&app, "john@example.com").await;
+
+    let purged = purge_deleted_accounts(&app.db_pool, AccountDeletionMode::Anonymize)
+        .await
+        .unwrap();
+    assert_eq!(1, purged);
+
+    let (email, name, anonymized): (String, String, bool) = sqlx::query_as(
+        "SELECT email, name, anonymized_at IS NOT NULL FROM users WHERE id = $1",
+    )
+    .bind(user_id)
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert!(email.ends_with("@deleted.invalid"));
+    assert_eq!("Deleted user", name);
+    assert!(anonymized);
+
+    let sessions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM refresh_tokens WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, sessions);
+
+    // The audit trail is kept
+    let audit_entries: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM audit_logs WHERE resource_id = $1 AND action = 'PURGE_ACCOUNT'",
+    )
+    .bind(user_id.to_string())
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(1, audit_entries);
+
+    // The address is free again
+    let response = reqwest::Client::new()
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({
+            "name": "John Doe",
+            "email": "john@example.com",
+            "password": "SecurePass123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(201, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn purge_hard_deletes_accounts_after_grace_period() {
+    let app = spawn_app().await;
+    let tokens = register_user(&app, "john@example.com", "John Doe").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    assert_eq!(202, delete_account(&app, access_token, "SecurePass123").await.status().as_u16());
+    end_grace_period(&app, "john@example.com").await;
+
+    let purged = purge_deleted_accounts(&app.db_pool, AccountDeletionMode::HardDelete)
+        .await
+        .unwrap();
+    assert_eq!(1, purged);
+
+    let users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, users);
+}
@@ -0,0 +1,309 @@
+mod common;
+
+use common::{spawn_app, TestApp};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+/// Register a user with a verified email and return the token response
+async fn register_user(app: &TestApp, email: &str, password: &str) -> Value {
+    let tokens = reqwest::Client::new()
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({
+            "name": "John Doe",
+            "email": email,
+            "password": password
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse response");
+
+    sqlx::query("UPDATE users SET email_verified_at = NOW() WHERE email = $1")
+        .bind(email)
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to verify user");
+
+    tokens
+}
+
+/// Extract the confirmation link from the email captured by the mock server
+async fn confirmation_link(app: &TestApp) -> String {
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests.last().unwrap().body).unwrap();
+    let html = body["Html"].as_str().unwrap();
+    let re = regex::Regex::new(r#"href="([^"]+)""#).unwrap();
+    re.captures(html).unwrap()[1].to_string()
+}
+
+// --- Change Password Tests ---
+
+#[tokio::test]
+async fn change_password_returns_new_tokens_and_revokes_other_sessions() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let tokens = register_user(&app, "john@example.com", "SecurePass123").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+    let old_refresh_token = tokens["refresh_token"].as_str().unwrap();
+
+    let response = client
+        .post(&format!("{}/api/me/password", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({
+            "current_password": "SecurePass123",
+            "new_password": "EvenBetterPass456"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    let response_body: Value = response.json().await.expect("Failed to parse response");
+    assert!(response_body.get("access_token").is_some());
+    assert!(response_body.get("refresh_token").is_some());
+
+    // Old session can no longer refresh
+    let response = client
+        .post(&format!("{}/auth/refresh", &app.address))
+        .json(&json!({ "refresh_token": old_refresh_token }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+
+    // Old password no longer works, new one does
+    let response = client
+        .post(&format!("{}/auth/login", &app.address))
+        .json(&json!({ "email": "john@example.com", "password": "SecurePass123" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+
+    let response = client
+        .post(&format!("{}/auth/login", &app.address))
+        .json(&json!({ "email": "john@example.com", "password": "EvenBetterPass456" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn change_password_returns_401_for_wrong_current_password() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let tokens = register_user(&app, "john@example.com", "SecurePass123").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    let response = client
+        .post(&format!("{}/api/me/password", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({
+            "current_password": "WrongPass123",
+            "new_password": "EvenBetterPass456"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn change_password_returns_400_for_weak_new_password() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let tokens = register_user(&app, "john@example.com", "SecurePass123").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    let response = client
+        .post(&format!("{}/api/me/password", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({
+            "current_password": "SecurePass123",
+            "new_password": "weak"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn change_password_rejects_breached_new_password() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let tokens = register_user(&app, "john@example.com", "SecurePass123").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    let response = client
+        .post(&format!("{}/api/me/password", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({
+            "current_password": "SecurePass123",
+            "new_password": "Qwerty123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(400, response.status().as_u16());
+    let body: Value = response.json().await.expect("Failed to parse response");
+    assert_eq!("BREACHED_PASSWORD", body["code"]);
+}
+
+#[tokio::test]
+async fn account_endpoints_require_auth() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    for path in ["/api/me/password", "/api/me/email"] {
+        let response = client
+            .post(&format!("{}{}", &app.address, path))
+            .json(&json!({}))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        assert_eq!(401, response.status().as_u16(),
+            "Endpoint {} should require authentication", path);
+    }
+}
+
+// --- Change Email Tests ---
+
+#[tokio::test]
+async fn change_email_only_applies_after_confirmation() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let tokens = register_user(&app, "john@example.com", "SecurePass123").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+    let refresh_token = tokens["refresh_token"].as_str().unwrap();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = client
+        .post(&format!("{}/api/me/email", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({
+            "new_email": "john.new@example.com",
+            "current_password": "SecurePass123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(202, response.status().as_u16());
+
+    // Email is unchanged until the link is followed
+    let email: String = sqlx::query_scalar("SELECT email FROM users")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch user");
+    assert_eq!(email, "john@example.com");
+
+    let link = confirmation_link(&app).await;
+    assert!(link.starts_with(&app.address));
+
+    let response = client.get(&link).send().await.expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let email: String = sqlx::query_scalar("SELECT email FROM users")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch user");
+    assert_eq!(email, "john.new@example.com");
+
+    // Existing sessions are revoked
+    let response = client
+        .post(&format!("{}/auth/refresh", &app.address))
+        .json(&json!({ "refresh_token": refresh_token }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+
+    // Link is single-use
+    let response = client.get(&link).send().await.expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn change_email_returns_409_for_registered_email() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    register_user(&app, "taken@example.com", "SecurePass123").await;
+    let tokens = register_user(&app, "john@example.com", "SecurePass123").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = client
+        .post(&format!("{}/api/me/email", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({
+            "new_email": "taken@example.com",
+            "current_password": "SecurePass123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn change_email_returns_401_for_wrong_current_password() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let tokens = register_user(&app, "john@example.com", "SecurePass123").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    let response = client
+        .post(&format!("{}/api/me/email", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({
+            "new_email": "john.new@example.com",
+            "current_password": "WrongPass123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn confirm_email_change_returns_400_for_invalid_token() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(&format!("{}/auth/confirm-email?token=not-a-real-token", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(400, response.status().as_u16());
+}
@@ -0,0 +1,244 @@
+mod common;
+
+use common::{spawn_app, admin_token, register_user, TestApp};
+use uuid::Uuid;
+use serde_json::{json, Value};
+
+/// Insert a subscriber who subscribed `days_ago` days ago and return its id
+async fn insert_subscriber(app: &TestApp, email: &str, status: &str, days_ago: i32) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query(
+        r#"
+        INSERT INTO subscriptions (id, email, email_canonical, name, subscribed_at, status)
+        VALUES ($1, $2, lower($2), 'Sub Scriber', now() - make_interval(days => $3), $4)
+        "#,
+    )
+    .bind(id)
+    .bind(email)
+    .bind(days_ago)
+    .bind(status)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to insert subscriber");
+    id
+}
+
+async fn get(app: &TestApp, token: &str, path: &str, query: &[(&str, &str)]) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(&format!("{}/api/admin/subscribers{}", &app.address, path))
+        .header("Authorization", format!("Bearer {}", token))
+        .query(query)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+fn emails(page: &Value) -> Vec<&str> {
+    page["subscribers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|subscriber| subscriber["email"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn list_pages_through_subscribers_with_a_cursor() {
+    let app = spawn_app().await;
+    let access_token = &admin_token(&app).await;
+    for day in 0..5 {
+        insert_subscriber(&app, &format!("sub{}@example.com", day), "confirmed", day).await;
+    }
+
+    let first: Value = get(&app, access_token, "", &[("limit", "2")]).await.json().await.unwrap();
+    assert_eq!(vec!["sub0@example.com", "sub1@example.com"], emails(&first));
+    assert!(first["subscribers"][0]["validation_error"].is_null());
+
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let second: Value = get(&app, access_token, "", &[("limit", "2"), ("cursor", cursor)])
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(vec!["sub2@example.com", "sub3@example.com"], emails(&second));
+
+    let cursor = second["next_cursor"].as_str().unwrap();
+    let last: Value = get(&app, access_token, "", &[("limit", "2"), ("cursor", cursor)])
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(vec!["sub4@example.com"], emails(&last));
+    assert!(last["next_cursor"].is_null());
+
+    let response = get(&app, access_token, "", &[("cursor", "garbage")]).await;
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn list_filters_by_status_date_range_and_email() {
+    let app = spawn_app().await;
+    let access_token = &admin_token(&app).await;
+    insert_subscriber(&app, "alice@example.com", "confirmed", 1).await;
+    insert_subscriber(&app, "bob@example.com", "pending", 2).await;
+    insert_subscriber(&app, "alice@old.example.com", "confirmed", 40).await;
+    insert_subscriber(&app, "100%_off@example.com", "unsubscribed", 3).await;
+
+    let page: Value = get(&app, access_token, "", &[("status", "confirmed")])
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(vec!["alice@example.com", "alice@old.example.com"], emails(&page));
+
+    let from = (chrono::Utc::now() - chrono::Duration::days(10)).to_rfc3339();
+    let page: Value = get(&app, access_token, "", &[("email", "ALICE"), ("subscribed_from", &from)])
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(vec!["alice@example.com"], emails(&page));
+
+    let to = (chrono::Utc::now() - chrono::Duration::days(30)).date_naive().to_string();
+    let page: Value = get(&app, access_token, "", &[("subscribed_to", &to)])
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(vec!["alice@old.example.com"], emails(&page));
+
+    // Wildcards in the search match literally
+    let page: Value = get(&app, access_token, "", &[("email", "%_")]).await.json().await.unwrap();
+    assert_eq!(vec!["100%_off@example.com"], emails(&page));
+
+    for query in [("status", "archived"), ("subscribed_from", "yesterday"), ("limit", "0")] {
+        let response = get(&app, access_token, "", &[query]).await;
+        assert_eq!(400, response.status().as_u16(), "{:?}", query);
+    }
+}
+
+#[tokio::test]
+async fn invalid_stored_records_are_flagged() {
+    let app = spawn_app().await;
+    let access_token = &admin_token(&app).await;
+    let id = insert_subscriber(&app, "not-an-email", "confirmed", 0).await;
+
+    let subscriber: Value = get(&app, access_token, &format!("/{}", id), &[])
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!("not-an-email", subscriber["email"]);
+    assert!(subscriber["validation_error"].as_str().unwrap().contains("email"));
+}
+
+#[tokio::test]
+async fn admin_confirms_changes_status_and_deletes_subscribers() {
+    let app = spawn_app().await;
+    let access_token = &admin_token(&app).await;
+    let client = reqwest::Client::new();
+    let id = insert_subscriber(&app, "pending@example.com", "pending", 0).await;
+    sqlx::query(
+        r#"
+        INSERT INTO subscription_tokens (subscription_token, subscriber_id, created_at, expires_at)
+        VALUES ('token', $1, now(), now() + interval '1 day')
+        "#,
+    )
+    .bind(id)
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let confirmed: Value = client
+        .post(&format!("{}/api/admin/subscribers/{}/confirm", &app.address, id))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!("confirmed", confirmed["status"]);
+    let tokens: i64 = sqlx::query_scalar("SELECT count(*) FROM subscription_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, tokens);
+
+    let unsubscribed: Value = client
+        .put(&format!("{}/api/admin/subscribers/{}/status", &app.address, id))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "status": "unsubscribed" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!("unsubscribed", unsubscribed["status"]);
+    assert!(unsubscribed["unsubscribed_at"].is_string());
+
+    // Only the subscriber can undo an unsubscribe
+    let response = client
+        .post(&format!("{}/api/admin/subscribers/{}/confirm", &app.address, id))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(400, response.status().as_u16());
+
+    let response = client
+        .put(&format!("{}/api/admin/subscribers/{}/status", &app.address, id))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "status": "archived" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(400, response.status().as_u16());
+
+    let response = client
+        .delete(&format!("{}/api/admin/subscribers/{}", &app.address, id))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(404, get(&app, access_token, &format!("/{}", id), &[]).await.status().as_u16());
+
+    let actions: Vec<String> = sqlx::query_scalar(
+        "SELECT action FROM audit_logs WHERE resource_id = $1 ORDER BY created_at",
+    )
+    .bind(id.to_string())
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        vec![
+            "ADMIN_CONFIRM_SUBSCRIBER",
+            "ADMIN_CHANGE_SUBSCRIBER_STATUS",
+            "ADMIN_CONFIRM_SUBSCRIBER",
+            "ADMIN_DELETE_SUBSCRIBER",
+        ],
+        actions
+    );
+}
+
+#[tokio::test]
+async fn subscriber_endpoints_reject_non_admins() {
+    let app = spawn_app().await;
+    let tokens = register_user(&app, "user@example.com", "Regular User").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+    let id = insert_subscriber(&app, "sub@example.com", "pending", 0).await;
+
+    assert_eq!(403, get(&app, access_token, "", &[]).await.status().as_u16());
+    assert_eq!(403, get(&app, access_token, &format!("/{}", id), &[]).await.status().as_u16());
+
+    let response = reqwest::Client::new()
+        .delete(&format!("{}/api/admin/subscribers/{}", &app.address, id))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+}
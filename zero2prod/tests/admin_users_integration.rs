@@ -0,0 +1,306 @@
+mod common;
+
+use common::{spawn_app, admin_token, login, register_user, TestApp};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn user_id(app: &TestApp, email: &str) -> String {
+    let id: uuid::Uuid = sqlx::query_scalar("SELECT id FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch user");
+    id.to_string()
+}
+
+// --- Access Control Tests ---
+
+#[tokio::test]
+async fn admin_endpoints_reject_non_admins() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let tokens = register_user(&app, "john@example.com", "John Doe").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    let response = client
+        .get(&format!("{}/api/admin/users", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(403, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("PERMISSION_DENIED", body["code"]);
+
+    let response = client
+        .get(&format!("{}/api/admin/users", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+}
+
+// --- Listing Tests ---
+
+#[tokio::test]
+async fn list_users_searches_and_paginates() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let token = admin_token(&app).await;
+    register_user(&app, "alice@example.com", "Alice Smith").await;
+    register_user(&app, "bob@example.com", "Bob Smith").await;
+    register_user(&app, "carol@example.com", "Carol Jones").await;
+
+    let response = client
+        .get(&format!("{}/api/admin/users?search=smith&per_page=1", &app.address))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(2, body["total"]);
+    assert_eq!(1, body["users"].as_array().unwrap().len());
+    // Newest first
+    assert_eq!("bob@example.com", body["users"][0]["email"]);
+
+    let body: Value = client
+        .get(&format!("{}/api/admin/users?search=smith&per_page=1&page=2", &app.address))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!("alice@example.com", body["users"][0]["email"]);
+
+    let body: Value = client
+        .get(&format!("{}/api/admin/users?role=admin", &app.address))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(1, body["total"]);
+    assert_eq!("admin@example.com", body["users"][0]["email"]);
+
+    // Wildcards in the search are matched literally
+    let body: Value = client
+        .get(&format!("{}/api/admin/users?search=%25", &app.address))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(0, body["total"]);
+
+    let response = client
+        .get(&format!("{}/api/admin/users?per_page=1000", &app.address))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn list_user_sessions_returns_active_refresh_tokens() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let token = admin_token(&app).await;
+    register_user(&app, "john@example.com", "John Doe").await;
+    login(&app, "john@example.com", "SecurePass123").await;
+    let john_id = user_id(&app, "john@example.com").await;
+
+    let response = client
+        .get(&format!("{}/api/admin/users/{}/sessions", &app.address, john_id))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let sessions: Value = response.json().await.unwrap();
+    assert_eq!(2, sessions.as_array().unwrap().len());
+
+    let response = client
+        .get(&format!("{}/api/admin/users/{}/sessions", &app.address, uuid::Uuid::new_v4()))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(404, response.status().as_u16());
+}
+
+// --- Account Action Tests ---
+
+#[tokio::test]
+async fn deactivate_revokes_sessions_and_blocks_login_until_reactivated() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let token = admin_token(&app).await;
+    let tokens = register_user(&app, "john@example.com", "John Doe").await;
+    let refresh_token = tokens["refresh_token"].as_str().unwrap();
+    let john_id = user_id(&app, "john@example.com").await;
+
+    let response = client
+        .post(&format!("{}/api/admin/users/{}/deactivate", &app.address, john_id))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(false, body["is_active"]);
+
+    let response = client
+        .post(&format!("{}/auth/refresh", &app.address))
+        .json(&json!({ "refresh_token": refresh_token }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+    assert_eq!(400, login(&app, "john@example.com", "SecurePass123").await.status().as_u16());
+
+    let response = client
+        .post(&format!("{}/api/admin/users/{}/reactivate", &app.address, john_id))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(200, login(&app, "john@example.com", "SecurePass123").await.status().as_u16());
+}
+
+#[tokio::test]
+async fn admins_cannot_deactivate_or_demote_themselves() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let token = admin_token(&app).await;
+    let admin_id = user_id(&app, "admin@example.com").await;
+
+    let response = client
+        .post(&format!("{}/api/admin/users/{}/deactivate", &app.address, admin_id))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(403, response.status().as_u16());
+
+    let response = client
+        .put(&format!("{}/api/admin/users/{}/role", &app.address, admin_id))
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&json!({ "role": "user" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(403, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn change_role_grants_admin_access() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let token = admin_token(&app).await;
+    let tokens = register_user(&app, "john@example.com", "John Doe").await;
+    let john_token = tokens["access_token"].as_str().unwrap();
+    let john_id = user_id(&app, "john@example.com").await;
+
+    let response = client
+        .put(&format!("{}/api/admin/users/{}/role", &app.address, john_id))
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&json!({ "role": "superuser" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+
+    let response = client
+        .put(&format!("{}/api/admin/users/{}/role", &app.address, john_id))
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&json!({ "role": "admin" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("admin", body["role"]);
+
+    // Takes effect without a new token
+    let response = client
+        .get(&format!("{}/api/admin/users/{}", &app.address, john_id))
+        .header("Authorization", format!("Bearer {}", john_token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn force_password_reset_blocks_login_until_reset_with_emailed_token() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let token = admin_token(&app).await;
+    let tokens = register_user(&app, "john@example.com", "John Doe").await;
+    let refresh_token = tokens["refresh_token"].as_str().unwrap();
+    let john_id = user_id(&app, "john@example.com").await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = client
+        .post(&format!("{}/api/admin/users/{}/force-password-reset", &app.address, john_id))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(true, body["password_reset_required"]);
+
+    // Existing sessions are revoked and the old password no longer logs in
+    let response = client
+        .post(&format!("{}/auth/refresh", &app.address))
+        .json(&json!({ "refresh_token": refresh_token }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+    let response = login(&app, "john@example.com", "SecurePass123").await;
+    assert_eq!(403, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("PASSWORD_RESET_REQUIRED", body["code"]);
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    let email: Value = serde_json::from_slice(&requests.last().unwrap().body).unwrap();
+    let re = regex::Regex::new(r"password_reset_token=([A-Za-z0-9]+)").unwrap();
+    let reset_token = re.captures(email["Html"].as_str().unwrap()).unwrap()[1].to_string();
+
+    let response = client
+        .post(&format!("{}/auth/password-reset", &app.address))
+        .json(&json!({ "token": reset_token, "new_password": "EvenBetterPass456" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    assert_eq!(200, login(&app, "john@example.com", "EvenBetterPass456").await.status().as_u16());
+
+    // Tokens are single-use
+    let response = client
+        .post(&format!("{}/auth/password-reset", &app.address))
+        .json(&json!({ "token": reset_token, "new_password": "AnotherGoodPass789" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+}
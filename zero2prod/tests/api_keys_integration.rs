@@ -0,0 +1,319 @@
+mod common;
+
+use common::{admin_token, spawn_app, TestApp};
+use serde_json::{json, Value};
+
+/// Register a user and return an access token
+async fn register_user(app: &TestApp) -> String {
+    let body: Value = reqwest::Client::new()
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({
+            "name": "John Doe",
+            "email": "john@example.com",
+            "password": "SecurePass123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse response");
+    body["access_token"].as_str().unwrap().to_string()
+}
+
+async fn create_key(app: &TestApp, access_token: &str, body: Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(&format!("{}/api/api-keys", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn get_me(app: &TestApp, credential: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(&format!("{}/api/me", &app.address))
+        .header("Authorization", format!("Bearer {}", credential))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn created_key_is_shown_once_and_authenticates() {
+    let app = spawn_app().await;
+    let access_token = register_user(&app).await;
+
+    let response = create_key(
+        &app,
+        &access_token,
+        json!({ "name": "CI", "scopes": ["profile:read"] }),
+    )
+    .await;
+    assert_eq!(201, response.status().as_u16());
+    let created: Value = response.json().await.expect("Failed to parse response");
+    let key = created["key"].as_str().unwrap();
+    assert!(key.starts_with("z2p_"));
+    assert!(key.starts_with(created["key_prefix"].as_str().unwrap()));
+    assert!(created["expires_at"].is_null());
+
+    // Stored hashed, never in plaintext
+    let stored: String = sqlx::query_scalar("SELECT key_hash FROM api_keys")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_ne!(key, stored);
+
+    // Listing never returns the key
+    let keys: Value = reqwest::Client::new()
+        .get(&format!("{}/api/api-keys", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse response");
+    assert_eq!(1, keys.as_array().unwrap().len());
+    assert_eq!("CI", keys[0]["name"]);
+    assert!(keys[0].get("key").is_none());
+    assert!(keys[0]["last_used_at"].is_null());
+
+    let response = get_me(&app, key).await;
+    assert_eq!(200, response.status().as_u16());
+    let user: Value = response.json().await.expect("Failed to parse response");
+    assert_eq!("john@example.com", user["email"]);
+
+    let last_used: Option<chrono::DateTime<chrono::Utc>> =
+        sqlx::query_scalar("SELECT last_used_at FROM api_keys")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert!(last_used.is_some());
+}
+
+#[tokio::test]
+async fn key_is_limited_to_its_scopes() {
+    let app = spawn_app().await;
+    let access_token = admin_token(&app).await;
+    let client = reqwest::Client::new();
+
+    let created: Value = create_key(
+        &app,
+        &access_token,
+        json!({ "name": "Release notes", "scopes": ["newsletters:send"] }),
+    )
+    .await
+    .json()
+    .await
+    .expect("Failed to parse response");
+    let key = created["key"].as_str().unwrap();
+
+    let response = client
+        .post(&format!("{}/api/newsletters/send-confirmed", &app.address))
+        .header("Authorization", format!("Bearer {}", key))
+        .json(&json!({ "subject": "v1.2", "html_content": "<p>Released</p>" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let response = get_me(&app, key).await;
+    assert_eq!(403, response.status().as_u16());
+    let body: Value = response.json().await.expect("Failed to parse response");
+    assert_eq!("INSUFFICIENT_SCOPE", body["code"]);
+}
+
+#[tokio::test]
+async fn key_cannot_manage_account_or_keys() {
+    let app = spawn_app().await;
+    let access_token = admin_token(&app).await;
+
+    let created: Value = create_key(
+        &app,
+        &access_token,
+        json!({ "name": "CI", "scopes": ["profile:read", "newsletters:send"] }),
+    )
+    .await
+    .json()
+    .await
+    .expect("Failed to parse response");
+    let key = created["key"].as_str().unwrap();
+
+    let response = create_key(&app, key, json!({ "name": "More", "scopes": ["profile:read"] })).await;
+    assert_eq!(403, response.status().as_u16());
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/api/me/password", &app.address))
+        .header("Authorization", format!("Bearer {}", key))
+        .json(&json!({ "current_password": "SecurePass123", "new_password": "Another123Pass" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(403, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn revoked_key_is_rejected() {
+    let app = spawn_app().await;
+    let access_token = register_user(&app).await;
+    let client = reqwest::Client::new();
+
+    let created: Value = create_key(
+        &app,
+        &access_token,
+        json!({ "name": "CI", "scopes": ["profile:read"] }),
+    )
+    .await
+    .json()
+    .await
+    .expect("Failed to parse response");
+    let key = created["key"].as_str().unwrap();
+    let id = created["id"].as_str().unwrap();
+
+    let response = client
+        .delete(&format!("{}/api/api-keys/{}", &app.address, id))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(204, response.status().as_u16());
+
+    assert_eq!(401, get_me(&app, key).await.status().as_u16());
+
+    // Revoking again, or an unknown key, is a 404
+    let response = client
+        .delete(&format!("{}/api/api-keys/{}", &app.address, id))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn expired_key_is_rejected() {
+    let app = spawn_app().await;
+    let access_token = register_user(&app).await;
+
+    let response = create_key(
+        &app,
+        &access_token,
+        json!({ "name": "Temp", "scopes": ["profile:read"], "expires_in_days": 30 }),
+    )
+    .await;
+    let created: Value = response.json().await.expect("Failed to parse response");
+    assert!(created["expires_at"].is_string());
+    let key = created["key"].as_str().unwrap();
+    assert_eq!(200, get_me(&app, key).await.status().as_u16());
+
+    sqlx::query("UPDATE api_keys SET expires_at = NOW() - INTERVAL '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(401, get_me(&app, key).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn create_key_validates_input() {
+    let app = spawn_app().await;
+    let access_token = register_user(&app).await;
+
+    let invalid_bodies = vec![
+        json!({ "name": "", "scopes": ["profile:read"] }),
+        json!({ "name": "CI", "scopes": [] }),
+        json!({ "name": "CI", "scopes": ["admin"] }),
+        json!({ "name": "CI", "scopes": ["profile:read"], "expires_in_days": 0 }),
+    ];
+
+    for body in invalid_bodies {
+        let response = create_key(&app, &access_token, body.clone()).await;
+        assert_eq!(400, response.status().as_u16(), "Expected 400 for {}", body);
+    }
+}
+
+#[tokio::test]
+async fn unknown_api_key_is_rejected() {
+    let app = spawn_app().await;
+
+    let response = get_me(&app, "z2p_notarealkeynotarealkeynotarealkeynotarealkey").await;
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn newsletters_cannot_be_sent_without_credentials() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    for path in [
+        "/newsletters/send-all",
+        "/newsletters/send-confirmed",
+        "/api/newsletters/send-all",
+        "/api/newsletters/send-confirmed",
+    ] {
+        let response = client
+            .post(&format!("{}{}", &app.address, path))
+            .json(&json!({ "subject": "Spam", "html_content": "<p>Spam</p>" }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert!(
+            response.status().is_client_error(),
+            "{} accepted a newsletter without credentials",
+            path
+        );
+    }
+    assert!(app.email_server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn plain_users_cannot_send_newsletters() {
+    let app = spawn_app().await;
+    let access_token = register_user(&app).await;
+    sqlx::query("UPDATE users SET email_verified_at = NOW()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.email_server.reset().await;
+    let client = reqwest::Client::new();
+
+    for path in ["/api/newsletters/send-all", "/api/newsletters/send-confirmed"] {
+        let response = client
+            .post(&format!("{}{}", &app.address, path))
+            .header("Authorization", format!("Bearer {}", access_token))
+            .json(&json!({ "subject": "Spam", "html_content": "<p>Spam</p>" }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(403, response.status().as_u16(), "{} accepted a plain user", path);
+    }
+
+    let response = create_key(
+        &app,
+        &access_token,
+        json!({ "name": "Spam", "scopes": ["newsletters:send"] }),
+    )
+    .await;
+    assert_eq!(403, response.status().as_u16());
+    assert!(app.email_server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn unverified_admins_cannot_send_newsletters() {
+    let app = spawn_app().await;
+    let access_token = admin_token(&app).await;
+    sqlx::query("UPDATE users SET email_verified_at = NULL")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/api/newsletters/send-all", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "subject": "Spam", "html_content": "<p>Spam</p>" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(403, response.status().as_u16());
+}
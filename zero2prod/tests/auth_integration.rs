@@ -1,54 +1,8 @@
-use std::net::TcpListener;
-use zero2prod::startup::run;
-use zero2prod::configuration::{get_configuration, DatabaseSettings};
-use sqlx::{PgPool, Executor, Connection, PgConnection, Row};
-use serde_json::{json, Value};
-
-pub struct TestApp {
-    pub address: String,
-    pub db_pool: PgPool,
-}
-
-async fn spawn_app() -> TestApp {
-    let listener = TcpListener::bind("127.0.0.1:0")
-        .expect("Failed to bind random port");
-    let port = listener.local_addr().unwrap().port();
-    let address = format!("http://127.0.0.1:{}", port);
-
-    let mut configuration = get_configuration().expect("Failed to read configuration.");
-    configuration.database.database_name = uuid::Uuid::new_v4().to_string();
-    let connection_pool = configure_database(&configuration.database).await;
-
-    let jwt_config = configuration.jwt.clone();
-    let server = run(listener, connection_pool.clone(), jwt_config)
-        .expect("Failed to bind address");
-    let _ = tokio::spawn(server);
+mod common;
 
-    TestApp {
-        address,
-        db_pool: connection_pool,
-    }
-}
-
-pub async fn configure_database(config: &DatabaseSettings) -> PgPool {
-    // Create database
-    let mut connection = PgConnection::connect(&config.connection_string_without_db())
-        .await
-        .expect("Failed to connect to Postgres");
-    connection
-        .execute(&*format!(r#"CREATE DATABASE "{}";"#, config.database_name))
-        .await
-        .expect("Failed to create database.");
-    // Migrate database
-    let connection_pool = PgPool::connect(&config.connection_string())
-        .await
-        .expect("Failed to connect to Postgres.");
-    sqlx::migrate!("./migrations")
-        .run(&connection_pool)
-        .await
-        .expect("Failed to migrate the database.");
-    connection_pool
-}
+use common::spawn_app;
+use sqlx::Row;
+use serde_json::{json, Value};
 
 // --- Registration Tests ---
 
@@ -150,6 +104,76 @@ async fn register_returns_400_for_weak_password() {
     }
 }
 
+#[tokio::test]
+async fn register_rejects_breached_password() {
+    let app = spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({
+            "name": "Test User",
+            "email": "test@example.com",
+            "password": "Password123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(400, response.status().as_u16());
+    let body: Value = response.json().await.expect("Failed to parse response");
+    assert_eq!("BREACHED_PASSWORD", body["code"]);
+}
+
+#[tokio::test]
+async fn register_rejects_password_built_from_name_or_email() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let register = |email: &'static str, name: &'static str| {
+        client
+            .post(&format!("{}/auth/register", &app.address))
+            .json(&json!({ "name": name, "email": email, "password": "Fitzgerald1920" }))
+            .send()
+    };
+
+    let response = register("zelda@fitzgerald.example", "Zelda Sayre").await.unwrap();
+    assert_eq!(400, response.status().as_u16());
+    let body: Value = response.json().await.expect("Failed to parse response");
+    assert_eq!("WEAK_PASSWORD", body["code"]);
+    assert!(body["message"]
+        .as_str()
+        .unwrap()
+        .contains("Avoid using your name or email address"));
+
+    // The same password is fine for someone it says nothing about
+    let response = register("john@example.com", "John Doe").await.unwrap();
+    assert_eq!(201, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn register_applies_the_input_policy_to_names() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let register = |email: &'static str, name: &'static str| {
+        client
+            .post(&format!("{}/auth/register", &app.address))
+            .json(&json!({ "name": name, "email": email, "password": "Earthsea-Tombs-1971" }))
+            .send()
+    };
+
+    // Apostrophes and hyphens used to trip the SQL heuristics
+    let response = register("siobhan@example.com", "Siobhán O'Brien-Smith").await.unwrap();
+    assert_eq!(201, response.status().as_u16());
+
+    // Cyrillic "а" posing as a Latin one
+    let response = register("admin@example.com", "\u{0410}dmin").await.unwrap();
+    assert_eq!(400, response.status().as_u16());
+    let body: Value = response.json().await.expect("Failed to parse response");
+    assert_eq!("INPUT_POLICY_VIOLATION", body["code"]);
+    assert!(body["message"].as_str().unwrap().contains("confusable_characters"));
+}
+
 #[tokio::test]
 async fn register_returns_409_for_duplicate_email() {
     let app = spawn_app().await;
@@ -282,6 +306,78 @@ async fn login_returns_400_for_invalid_password() {
     assert_eq!(400, response.status().as_u16());
 }
 
+#[tokio::test]
+async fn register_stores_argon2id_hash() {
+    let app = spawn_app().await;
+
+    reqwest::Client::new()
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({
+            "name": "John Doe",
+            "email": "john@example.com",
+            "password": "SecurePass123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    let password_hash: String = sqlx::query_scalar("SELECT password_hash FROM users")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(password_hash.starts_with("$argon2id$v=19$m=19456,t=2,p=1$"));
+}
+
+#[tokio::test]
+async fn login_upgrades_legacy_bcrypt_hash() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    client
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({
+            "name": "John Doe",
+            "email": "john@example.com",
+            "password": "SecurePass123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Simulate an account created before the switch to Argon2id
+    let legacy_hash = bcrypt::hash("SecurePass123", 4).unwrap();
+    sqlx::query("UPDATE users SET password_hash = $1")
+        .bind(&legacy_hash)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let login = |password: &'static str| {
+        client
+            .post(&format!("{}/auth/login", &app.address))
+            .json(&json!({ "email": "john@example.com", "password": password }))
+            .send()
+    };
+
+    // A wrong password leaves the hash alone
+    assert_eq!(400, login("WrongPassword123").await.unwrap().status().as_u16());
+    let password_hash: String = sqlx::query_scalar("SELECT password_hash FROM users")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(legacy_hash, password_hash);
+
+    assert_eq!(200, login("SecurePass123").await.unwrap().status().as_u16());
+    let password_hash: String = sqlx::query_scalar("SELECT password_hash FROM users")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(password_hash.starts_with("$argon2id$"));
+
+    // The upgraded hash keeps working
+    assert_eq!(200, login("SecurePass123").await.unwrap().status().as_u16());
+}
+
 #[tokio::test]
 async fn login_returns_400_for_nonexistent_user() {
     let app = spawn_app().await;
@@ -525,8 +621,8 @@ async fn all_protected_endpoints_require_auth() {
         "/auth/me",
         "/subscriptions",
         "/subscriptions/confirm",
-        "/newsletters/send-all",
-        "/newsletters/send-confirmed",
+        "/api/newsletters/send-all",
+        "/api/newsletters/send-confirmed",
     ];
 
     for path in protected_paths {
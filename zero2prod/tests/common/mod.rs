@@ -0,0 +1,122 @@
+//! Fixtures shared by the integration tests.
+//!
+//! Every test binary compiles its own copy of this module and uses only part
+//! of it.
+#![allow(dead_code)]
+
+use std::net::TcpListener;
+use zero2prod::startup::run;
+use zero2prod::configuration::{get_configuration, DatabaseSettings, Settings};
+use sqlx::{PgPool, Executor, Connection, PgConnection};
+use serde_json::{json, Value};
+use wiremock::MockServer;
+
+pub struct TestApp {
+    pub address: String,
+    pub port: u16,
+    pub db_pool: PgPool,
+    pub email_server: MockServer,
+}
+
+/// Start the application against a fresh database with the checked-in configuration
+pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Start the application against a fresh database, adjusting the configuration first
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.database.database_name = uuid::Uuid::new_v4().to_string();
+    let connection_pool = configure_database(&configuration.database).await;
+    spawn_instance(connection_pool, configure).await
+}
+
+/// Start another instance of the application on an existing database
+pub async fn spawn_instance(connection_pool: PgPool, configure: impl FnOnce(&mut Settings)) -> TestApp {
+    let email_server = MockServer::start().await;
+
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .expect("Failed to bind random port");
+    let port = listener.local_addr().unwrap().port();
+    let address = format!("http://127.0.0.1:{}", port);
+
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.email_client.base_url = email_server.uri();
+    configuration.application.base_url = address.clone();
+    configure(&mut configuration);
+
+    let server = run(listener, connection_pool.clone(), configuration)
+        .expect("Failed to bind address");
+    let _ = tokio::spawn(server);
+
+    TestApp {
+        address,
+        port,
+        db_pool: connection_pool,
+        email_server,
+    }
+}
+
+pub async fn configure_database(config: &DatabaseSettings) -> PgPool {
+    // Create database
+    let mut connection = PgConnection::connect(&config.connection_string_without_db())
+        .await
+        .expect("Failed to connect to Postgres");
+    connection
+        .execute(&*format!(r#"CREATE DATABASE "{}";"#, config.database_name))
+        .await
+        .expect("Failed to create database.");
+    // Migrate database
+    let connection_pool = PgPool::connect(&config.connection_string())
+        .await
+        .expect("Failed to connect to Postgres.");
+    sqlx::migrate!("./migrations")
+        .run(&connection_pool)
+        .await
+        .expect("Failed to migrate the database.");
+    connection_pool
+}
+
+/// Register a user with a verified email and return the token response
+pub async fn register_user(app: &TestApp, email: &str, name: &str) -> Value {
+    let tokens = reqwest::Client::new()
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({
+            "name": name,
+            "email": email,
+            "password": "SecurePass123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse response");
+
+    sqlx::query("UPDATE users SET email_verified_at = NOW() WHERE email = $1")
+        .bind(email)
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to verify user");
+
+    tokens
+}
+
+/// Register an admin (promoted directly in the database) and return the access token
+pub async fn admin_token(app: &TestApp) -> String {
+    let tokens = register_user(app, "admin@example.com", "Ada Admin").await;
+    sqlx::query("UPDATE users SET role = 'admin' WHERE email = 'admin@example.com'")
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to promote admin");
+    tokens["access_token"].as_str().unwrap().to_string()
+}
+
+pub async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(&format!("{}/auth/login", &app.address))
+        .json(&json!({ "email": email, "password": password }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use common::{spawn_app_with, TestApp};
+use zero2prod::configuration::{
+    CorsScopeSettings, CorsSettings,
+};
+
+async fn spawn_app(cors: CorsSettings) -> TestApp {
+    spawn_app_with(|configuration| configuration.cors = cors).await
+}
+
+const ADMIN_ORIGIN: &str = "https://admin.example.com";
+
+fn api_scope() -> CorsSettings {
+    CorsSettings {
+        scopes: vec![CorsScopeSettings {
+            path_prefix: "/api".to_string(),
+            allowed_origins: vec![ADMIN_ORIGIN.to_string()],
+            allowed_methods: vec!["GET".to_string(), "POST".to_string(), "DELETE".to_string()],
+            allowed_headers: vec!["Content-Type".to_string(), "Authorization".to_string()],
+            expose_headers: vec!["RateLimit-Remaining".to_string()],
+            allow_credentials: true,
+            max_age_seconds: 300,
+        }],
+    }
+}
+
+async fn preflight(app: &TestApp, origin: &str, method: &str, headers: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .request(reqwest::Method::OPTIONS, &format!("{}/api/me", &app.address))
+        .header("Origin", origin)
+        .header("Access-Control-Request-Method", method)
+        .header("Access-Control-Request-Headers", headers)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+fn header(response: &reqwest::Response, name: &str) -> Option<String> {
+    response.headers().get(name).map(|h| h.to_str().unwrap().to_string())
+}
+
+#[tokio::test]
+async fn cross_origin_requests_get_no_cors_headers_by_default() {
+    let app = spawn_app(CorsSettings::default()).await;
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/health_check", &app.address))
+        .header("Origin", ADMIN_ORIGIN)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(response.status().is_success());
+    assert_eq!(None, header(&response, "Access-Control-Allow-Origin"));
+}
+
+#[tokio::test]
+async fn preflight_from_an_allowed_origin_is_answered_without_authentication() {
+    let app = spawn_app(api_scope()).await;
+
+    let response = preflight(&app, ADMIN_ORIGIN, "DELETE", "authorization, content-type").await;
+
+    assert_eq!(204, response.status().as_u16());
+    assert_eq!(Some(ADMIN_ORIGIN.to_string()), header(&response, "Access-Control-Allow-Origin"));
+    assert_eq!(Some("true".to_string()), header(&response, "Access-Control-Allow-Credentials"));
+    assert_eq!(
+        Some("GET, POST, DELETE".to_string()),
+        header(&response, "Access-Control-Allow-Methods")
+    );
+    assert_eq!(Some("300".to_string()), header(&response, "Access-Control-Max-Age"));
+}
+
+#[tokio::test]
+async fn preflight_is_refused_for_other_origins_methods_and_headers() {
+    let app = spawn_app(api_scope()).await;
+
+    for (origin, method, headers) in [
+        ("https://evil.example.com", "GET", ""),
+        (ADMIN_ORIGIN, "PUT", ""),
+        (ADMIN_ORIGIN, "GET", "x-custom-header"),
+    ] {
+        let response = preflight(&app, origin, method, headers).await;
+
+        assert_eq!(403, response.status().as_u16(), "{} {} {}", origin, method, headers);
+        assert_eq!(None, header(&response, "Access-Control-Allow-Origin"));
+    }
+}
+
+#[tokio::test]
+async fn errors_are_readable_by_allowed_origins_only() {
+    let app = spawn_app(api_scope()).await;
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/api/me", &app.address))
+        .header("Origin", ADMIN_ORIGIN)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+    assert_eq!(Some(ADMIN_ORIGIN.to_string()), header(&response, "Access-Control-Allow-Origin"));
+    assert!(header(&response, "Vary").unwrap().contains("Origin"));
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/api/me", &app.address))
+        .header("Origin", "https://evil.example.com")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+    assert_eq!(None, header(&response, "Access-Control-Allow-Origin"));
+}
+
+#[tokio::test]
+async fn allowed_origins_can_read_exposed_headers() {
+    let mut cors = api_scope();
+    cors.scopes[0].path_prefix = "/health_check".to_string();
+    let app = spawn_app(cors).await;
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/health_check", &app.address))
+        .header("Origin", ADMIN_ORIGIN)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(response.status().is_success());
+    assert_eq!(Some(ADMIN_ORIGIN.to_string()), header(&response, "Access-Control-Allow-Origin"));
+    assert_eq!(
+        Some("RateLimit-Remaining".to_string()),
+        header(&response, "Access-Control-Expose-Headers")
+    );
+}
@@ -0,0 +1,221 @@
+mod common;
+
+use common::{spawn_app_with, TestApp};
+use std::net::{Ipv4Addr, SocketAddr};
+use zero2prod::configuration::Settings;
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::rdata::{A, MX};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use serde_json::Value;
+use tokio::net::UdpSocket;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn spawn_app(configure: impl FnOnce(&mut Settings)) -> TestApp {
+    let app = spawn_app_with(|configuration| {
+        configuration.rate_limit.enabled = false;
+        configuration.subscriptions.bot_protection.min_fill_seconds = 0;
+        configure(configuration);
+    })
+    .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app
+}
+
+/// Local stand-in for a DNS server, answering over UDP for a few zones:
+///
+/// - `mx.example` has an MX record
+/// - `address-only.example` has only an A record
+/// - `null-mx.example` has a null MX (RFC 7505)
+/// - anything else doesn't exist
+async fn spawn_dns_server() -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.expect("Failed to bind DNS socket");
+    let address = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buffer = [0u8; 512];
+        loop {
+            let Ok((length, peer)) = socket.recv_from(&mut buffer).await else {
+                continue;
+            };
+            let Ok(request) = Message::from_vec(&buffer[..length]) else {
+                continue;
+            };
+            let response = dns_response(&request);
+            let _ = socket.send_to(&response.to_vec().unwrap(), peer).await;
+        }
+    });
+
+    address
+}
+
+fn dns_response(request: &Message) -> Message {
+    let mut response = Message::new();
+    response
+        .set_id(request.id())
+        .set_message_type(MessageType::Response)
+        .set_op_code(request.op_code())
+        .set_recursion_desired(request.recursion_desired())
+        .set_recursion_available(true)
+        .set_authoritative(true);
+
+    let Some(query) = request.queries().first() else {
+        return response;
+    };
+    response.add_query(query.clone());
+
+    let name = query.name().clone();
+    let record = |rdata| Record::from_rdata(name.clone(), 300, rdata);
+    let exchange = |exchange: &str| Name::from_ascii(exchange).unwrap();
+    match (name.to_ascii().to_lowercase().as_str(), query.query_type()) {
+        ("mx.example.", RecordType::MX) => {
+            response.add_answer(record(RData::MX(MX::new(10, exchange("smtp.mx.example.")))));
+        }
+        ("address-only.example.", RecordType::A) => {
+            response.add_answer(record(RData::A(A(Ipv4Addr::new(192, 0, 2, 25)))));
+        }
+        ("null-mx.example.", RecordType::MX) => {
+            response.add_answer(record(RData::MX(MX::new(0, exchange(".")))));
+        }
+        ("mx.example." | "address-only.example." | "null-mx.example.", _) => {}
+        _ => {
+            response.set_response_code(ResponseCode::NXDomain);
+        }
+    }
+    response
+}
+
+async fn subscribe(app: &TestApp, email: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(&format!("{}/subscriptions", &app.address))
+        .form(&[("name", "Jane Doe"), ("email", email)])
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn assert_undeliverable(response: reqwest::Response) -> Value {
+    assert_eq!(400, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("UNDELIVERABLE_EMAIL", body["code"]);
+    body
+}
+
+async fn subscriber_count(app: &TestApp) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn misspelled_provider_domains_get_a_suggestion() {
+    let app = spawn_app(|_| {}).await;
+
+    let response = subscribe(&app, "jane.doe@gmial.com").await;
+
+    // Only a suggestion: the address as typed is still subscribed
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("jane.doe@gmail.com", body["suggestion"]);
+    assert_eq!(1, subscriber_count(&app).await);
+
+    let response = subscribe(&app, "jane.doe@gmail.com").await;
+    assert_eq!(200, response.status().as_u16());
+    assert!(response.text().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn regional_provider_domains_are_accepted() {
+    let app = spawn_app(|_| {}).await;
+
+    // A typo or two from hotmail.de / hotmail.com, but a real domain
+    let response = subscribe(&app, "jane@hotmail.es").await;
+
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(1, subscriber_count(&app).await);
+}
+
+#[tokio::test]
+async fn misspelled_domains_without_mail_servers_are_rejected_with_a_suggestion() {
+    let dns_server = spawn_dns_server().await;
+    let app = spawn_app(|configuration| {
+        configuration.subscriptions.deliverability.check_dns = true;
+        configuration.subscriptions.deliverability.dns_servers = vec![dns_server];
+    })
+    .await;
+
+    let body = assert_undeliverable(subscribe(&app, "jane.doe@gmial.com").await).await;
+
+    assert_eq!("jane.doe@gmail.com", body["suggestion"]);
+    assert!(body["message"].as_str().unwrap().contains("did you mean jane.doe@gmail.com?"));
+    assert_eq!(0, subscriber_count(&app).await);
+    assert!(app.email_server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn disposable_domains_are_rejected_including_ones_added_later() {
+    let list = std::env::temp_dir().join(format!("disposable-{}.txt", uuid::Uuid::new_v4()));
+    std::fs::write(&list, "burner.example\n").unwrap();
+    let list_path = list.to_string_lossy().to_string();
+    let app = spawn_app(|configuration| {
+        configuration.subscriptions.deliverability.disposable_domains_file = Some(list_path);
+    })
+    .await;
+
+    let body = assert_undeliverable(subscribe(&app, "bot@mailinator.com").await).await;
+    assert!(body.get("suggestion").is_none());
+    assert_undeliverable(subscribe(&app, "bot@burner.example").await).await;
+
+    // The list is picked up again once it changes
+    assert_eq!(200, subscribe(&app, "bot@throwaway.example").await.status().as_u16());
+    std::fs::write(&list, "burner.example\nthrowaway.example\n").unwrap();
+    assert_undeliverable(subscribe(&app, "bot2@throwaway.example").await).await;
+
+    assert_eq!(1, subscriber_count(&app).await);
+    std::fs::remove_file(&list).unwrap();
+}
+
+#[tokio::test]
+async fn domains_need_mail_servers_when_dns_checks_are_on() {
+    let dns_server = spawn_dns_server().await;
+    let app = spawn_app(|configuration| {
+        configuration.subscriptions.deliverability.check_dns = true;
+        configuration.subscriptions.deliverability.dns_servers = vec![dns_server];
+    })
+    .await;
+
+    assert_eq!(200, subscribe(&app, "jane@mx.example").await.status().as_u16());
+    // No MX record: mail goes to the domain's address
+    assert_eq!(200, subscribe(&app, "jane@address-only.example").await.status().as_u16());
+
+    let body = assert_undeliverable(subscribe(&app, "jane@null-mx.example").await).await;
+    assert!(body["message"].as_str().unwrap().contains("null-mx.example has no mail servers"));
+    assert_undeliverable(subscribe(&app, "jane@missing.example").await).await;
+
+    assert_eq!(2, subscriber_count(&app).await);
+}
+
+#[tokio::test]
+async fn unreachable_name_servers_do_not_block_subscriptions() {
+    // Receives queries but never answers them
+    let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let dns_server = silent.local_addr().unwrap();
+    let app = spawn_app(|configuration| {
+        configuration.subscriptions.deliverability.check_dns = true;
+        configuration.subscriptions.deliverability.dns_servers = vec![dns_server];
+        configuration.subscriptions.deliverability.dns_timeout_milliseconds = 200;
+    })
+    .await;
+
+    let response = subscribe(&app, "jane@mx.example").await;
+
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(1, subscriber_count(&app).await);
+}
@@ -0,0 +1,234 @@
+mod common;
+
+use common::{spawn_app_with, TestApp};
+use zero2prod::configuration::EmailVerificationPolicy;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn spawn_app(policy: EmailVerificationPolicy) -> TestApp {
+    spawn_app_with(|configuration| configuration.auth.email_verification = policy).await
+}
+
+async fn mount_email_mock(app: &TestApp, expected: u64) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected)
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn register(app: &TestApp) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({
+            "name": "John Doe",
+            "email": "john@example.com",
+            "password": "SecurePass123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn login(app: &TestApp) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(&format!("{}/auth/login", &app.address))
+        .json(&json!({
+            "email": "john@example.com",
+            "password": "SecurePass123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// Extract the verification link from the email captured by the mock server
+async fn verification_link(app: &TestApp) -> String {
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests.last().unwrap().body).unwrap();
+    let html = body["Html"].as_str().unwrap();
+    let re = regex::Regex::new(r#"href="([^"]+)""#).unwrap();
+    re.captures(html).unwrap()[1].to_string()
+}
+
+#[tokio::test]
+async fn register_sends_verification_email() {
+    let app = spawn_app(EmailVerificationPolicy::BlockSensitiveActions).await;
+    mount_email_mock(&app, 1).await;
+
+    let response = register(&app).await;
+    assert_eq!(201, response.status().as_u16());
+
+    let verified: bool = sqlx::query_scalar("SELECT email_verified_at IS NOT NULL FROM users")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch user");
+    assert!(!verified);
+}
+
+#[tokio::test]
+async fn verification_link_marks_email_as_verified() {
+    let app = spawn_app(EmailVerificationPolicy::BlockSensitiveActions).await;
+    mount_email_mock(&app, 1).await;
+    let client = reqwest::Client::new();
+
+    let tokens: Value = register(&app).await.json().await.expect("Failed to parse response");
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    let link = verification_link(&app).await;
+    assert!(link.starts_with(&format!("{}/auth/verify-email?token=", app.address)));
+
+    let response = client.get(&link).send().await.expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let response = client
+        .get(&format!("{}/api/me", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let user: Value = response.json().await.expect("Failed to parse response");
+    assert_eq!(user["email_verified"], true);
+
+    // Link is single-use
+    let response = client.get(&link).send().await.expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn verify_email_returns_400_for_invalid_token() {
+    let app = spawn_app(EmailVerificationPolicy::BlockSensitiveActions).await;
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/auth/verify-email?token=not-a-real-token", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn unverified_user_cannot_perform_sensitive_actions() {
+    let app = spawn_app(EmailVerificationPolicy::BlockSensitiveActions).await;
+
+    let tokens: Value = register(&app).await.json().await.expect("Failed to parse response");
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/api/me/password", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({
+            "current_password": "SecurePass123",
+            "new_password": "EvenBetterPass456"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(403, response.status().as_u16());
+    let response_body: Value = response.json().await.expect("Failed to parse response");
+    assert_eq!(response_body["code"], "EMAIL_NOT_VERIFIED");
+}
+
+#[tokio::test]
+async fn block_login_policy_requires_verification_before_login() {
+    let app = spawn_app(EmailVerificationPolicy::BlockLogin).await;
+    mount_email_mock(&app, 1).await;
+    let client = reqwest::Client::new();
+
+    let response = register(&app).await;
+    assert_eq!(201, response.status().as_u16());
+    let response_body: Value = response.json().await.expect("Failed to parse response");
+    assert!(response_body.get("access_token").is_none());
+    assert_eq!(response_body["email_verification_required"], true);
+
+    let response = login(&app).await;
+    assert_eq!(403, response.status().as_u16());
+    let response_body: Value = response.json().await.expect("Failed to parse response");
+    assert_eq!(response_body["code"], "EMAIL_NOT_VERIFIED");
+
+    let link = verification_link(&app).await;
+    let response = client.get(&link).send().await.expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let response = login(&app).await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn optional_policy_allows_unverified_login() {
+    let app = spawn_app(EmailVerificationPolicy::Optional).await;
+
+    register(&app).await;
+    let response = login(&app).await;
+
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn resend_verification_does_not_reveal_unknown_emails() {
+    let app = spawn_app(EmailVerificationPolicy::BlockLogin).await;
+    mount_email_mock(&app, 0).await;
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/auth/verify-email/resend", &app.address))
+        .json(&json!({ "email": "nobody@example.com" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(202, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn resend_verification_sends_new_link_to_unverified_user() {
+    let app = spawn_app(EmailVerificationPolicy::BlockLogin).await;
+    mount_email_mock(&app, 2).await;
+    let client = reqwest::Client::new();
+
+    register(&app).await;
+    let first_link = verification_link(&app).await;
+
+    let response = client
+        .post(&format!("{}/auth/verify-email/resend", &app.address))
+        .json(&json!({ "email": "john@example.com" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(202, response.status().as_u16());
+
+    // Only the newest link is valid
+    let response = client.get(&first_link).send().await.expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+
+    let second_link = verification_link(&app).await;
+    let response = client.get(&second_link).send().await.expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn markup_in_name_is_escaped_in_verification_email() {
+    let app = spawn_app(EmailVerificationPolicy::BlockSensitiveActions).await;
+    mount_email_mock(&app, 1).await;
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({
+            "name": "<a href=\"https://evil.example\">Claim prize</a>",
+            "email": "john@example.com",
+            "password": "SecurePass123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(201, response.status().as_u16());
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let html = body["Html"].as_str().unwrap();
+    assert!(html.contains("&lt;a href=&quot;https://evil.example&quot;&gt;Claim prize&lt;/a&gt;"));
+    assert!(!html.contains("https://evil.example\""));
+}
@@ -1,53 +1,6 @@
-use std::net::TcpListener;
-use zero2prod::startup::run;
-use zero2prod::configuration::{get_configuration, DatabaseSettings};
-use sqlx::{PgPool, Executor, Connection, PgConnection, Row};
+mod common;
 
-pub struct TestApp {
-    pub address: String,
-    pub db_pool: PgPool,
-}
-
-async fn spawn_app() -> TestApp {
-    let listener = TcpListener::bind("127.0.0.1:0")
-        .expect("Failed to bind random port");
-    let port = listener.local_addr().unwrap().port();
-    let address = format!("http://127.0.0.1:{}", port);
-
-    let mut configuration = get_configuration().expect("Failed to read configuration.");
-    configuration.database.database_name = uuid::Uuid::new_v4().to_string();
-    let connection_pool = configure_database(&configuration.database).await;
-
-    let jwt_config = configuration.jwt.clone();
-    let server = run(listener, connection_pool.clone(), jwt_config)
-        .expect("Failed to bind address");
-    let _ = tokio::spawn(server);
-
-    TestApp {
-        address,
-        db_pool: connection_pool,
-    }
-}
-
-pub async fn configure_database(config: &DatabaseSettings) -> PgPool {
-    // Create database
-    let mut connection = PgConnection::connect(&config.connection_string_without_db())
-        .await
-        .expect("Failed to connect to Postgres");
-    connection
-        .execute(&*format!(r#"CREATE DATABASE "{}";"#, config.database_name))
-        .await
-        .expect("Failed to create database.");
-    // Migrate database
-    let connection_pool = PgPool::connect(&config.connection_string())
-        .await
-        .expect("Failed to connect to Postgres.");
-    sqlx::migrate!("./migrations")
-        .run(&connection_pool)
-        .await
-        .expect("Failed to migrate the database.");
-    connection_pool
-}
+use common::spawn_app;
 
 #[tokio::test]
 async fn health_check_works() {
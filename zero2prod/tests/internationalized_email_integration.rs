@@ -0,0 +1,147 @@
+mod common;
+
+use common::{spawn_app_with, TestApp};
+use zero2prod::configuration::Settings;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn spawn_app(configure: impl FnOnce(&mut Settings)) -> TestApp {
+    let app = spawn_app_with(|configuration| {
+        configuration.rate_limit.enabled = false;
+        configuration.subscriptions.bot_protection.min_fill_seconds = 0;
+        configure(configuration);
+    })
+    .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app
+}
+
+async fn subscribe(app: &TestApp, email: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(&format!("{}/subscriptions", &app.address))
+        .form(&[("name", "Jürgen Müller"), ("email", email)])
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn post_json(app: &TestApp, endpoint: &str, body: Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(&format!("{}{}", &app.address, endpoint))
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn sent_emails(app: &TestApp) -> Vec<Value> {
+    app.email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| serde_json::from_slice(&request.body).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn subscribing_with_an_idn_address_stores_and_sends_to_punycode() {
+    let app = spawn_app(|_| {}).await;
+
+    let response = subscribe(&app, "Jürgen@Bücher.de").await;
+    assert_eq!(200, response.status().as_u16());
+
+    let (email, canonical): (String, String) =
+        sqlx::query_as("SELECT email, email_canonical FROM subscriptions")
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch subscriber");
+    assert_eq!("Jürgen@xn--bcher-kva.de", email);
+    assert_eq!("jürgen@xn--bcher-kva.de", canonical);
+
+    let emails = sent_emails(&app).await;
+    assert_eq!(1, emails.len());
+    assert_eq!("Jürgen@xn--bcher-kva.de", emails[0]["to"]);
+    assert_eq!(true, emails[0]["SmtpUtf8"]);
+}
+
+#[tokio::test]
+async fn ascii_recipients_are_sent_without_smtputf8() {
+    let app = spawn_app(|_| {}).await;
+
+    let response = subscribe(&app, "hans@Bücher.de").await;
+    assert_eq!(200, response.status().as_u16());
+
+    let emails = sent_emails(&app).await;
+    assert_eq!("hans@xn--bcher-kva.de", emails[0]["to"]);
+    assert!(emails[0].get("SmtpUtf8").is_none());
+}
+
+#[tokio::test]
+async fn addresses_differing_only_in_case_are_the_same_subscriber() {
+    let app = spawn_app(|_| {}).await;
+
+    assert_eq!(200, subscribe(&app, "jürgen@bücher.de").await.status().as_u16());
+    let response = subscribe(&app, "JÜRGEN@xn--bcher-kva.DE").await;
+
+    assert_eq!(409, response.status().as_u16());
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(1, count);
+}
+
+#[tokio::test]
+async fn user_emails_are_unique_and_matched_regardless_of_case() {
+    let app = spawn_app(|_| {}).await;
+
+    let response = post_json(
+        &app,
+        "/auth/register",
+        json!({"name": "Jürgen Müller", "email": "Jürgen@Bücher.de", "password": "SecurePass123"}),
+    )
+    .await;
+    assert_eq!(201, response.status().as_u16());
+
+    let response = post_json(
+        &app,
+        "/auth/register",
+        json!({"name": "Jürgen Müller", "email": "JÜRGEN@bücher.de", "password": "SecurePass123"}),
+    )
+    .await;
+    assert_eq!(409, response.status().as_u16());
+
+    let response = post_json(
+        &app,
+        "/auth/login",
+        json!({"email": "jürgen@BÜCHER.de", "password": "SecurePass123"}),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn utf8_recipients_are_rejected_when_smtputf8_is_unsupported() {
+    let app = spawn_app(|configuration| configuration.email_client.smtputf8 = false).await;
+
+    let response = subscribe(&app, "jürgen@example.de").await;
+    assert_eq!(400, response.status().as_u16());
+
+    // ASCII local parts with IDN domains don't need SMTPUTF8
+    let response = subscribe(&app, "hans@bücher.de").await;
+    assert_eq!(200, response.status().as_u16());
+
+    let emails: Vec<String> = sqlx::query_scalar("SELECT email FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(vec!["hans@xn--bcher-kva.de".to_string()], emails);
+}
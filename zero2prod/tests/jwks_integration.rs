@@ -0,0 +1,185 @@
+mod common;
+
+use common::{spawn_app_with, TestApp};
+use zero2prod::auth::{generate_access_token, JwtKeys};
+use zero2prod::configuration::{get_configuration, JwtKeyAlgorithm, JwtKeySettings, JwtSettings};
+use serde_json::{json, Value};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+
+async fn spawn_app(configure_jwt: impl FnOnce(&mut JwtSettings)) -> TestApp {
+    spawn_app_with(|configuration| configure_jwt(&mut configuration.jwt)).await
+}
+
+fn key(kid: &str, algorithm: JwtKeyAlgorithm, name: &str, private: bool) -> JwtKeySettings {
+    JwtKeySettings {
+        kid: kid.to_string(),
+        algorithm,
+        public_key_path: format!("tests/fixtures/jwt/{}.pub.pem", name),
+        private_key_path: private.then(|| format!("tests/fixtures/jwt/{}.pem", name)),
+    }
+}
+
+async fn register_user(app: &TestApp) -> String {
+    let body: Value = reqwest::Client::new()
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({
+            "name": "John Doe",
+            "email": "john@example.com",
+            "password": "SecurePass123"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse response");
+    body["access_token"].as_str().unwrap().to_string()
+}
+
+async fn fetch_jwks(app: &TestApp) -> JwkSet {
+    let response = reqwest::get(&format!("{}/.well-known/jwks.json", &app.address))
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    assert!(response.headers().get("Cache-Control").is_some());
+    response.json().await.expect("Failed to parse response")
+}
+
+#[tokio::test]
+async fn tokens_verify_against_published_jwks() {
+    let app = spawn_app(|jwt| {
+        jwt.active_key_id = Some("rsa-2024".to_string());
+        jwt.keys = vec![
+            key("rsa-2024", JwtKeyAlgorithm::RS256, "rsa-a", true),
+            key("ed-2023", JwtKeyAlgorithm::EdDSA, "ed25519", false),
+        ];
+    })
+    .await;
+
+    let jwks = fetch_jwks(&app).await;
+    assert_eq!(2, jwks.keys.len());
+    assert!(jwks.find("ed-2023").is_some());
+
+    // A downstream service only needs the JWKS to verify our tokens
+    let access_token = register_user(&app).await;
+    let header = decode_header(&access_token).unwrap();
+    assert_eq!(Algorithm::RS256, header.alg);
+    let jwk = jwks.find(&header.kid.unwrap()).expect("Signing key not published");
+
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.set_issuer(&["zero2prod"]);
+    let claims = decode::<Value>(&access_token, &DecodingKey::from_jwk(jwk).unwrap(), &validation)
+        .expect("Token should verify with the published key")
+        .claims;
+    assert_eq!("john@example.com", claims["email"]);
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/api/me", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn eddsa_active_key_signs_tokens() {
+    let app = spawn_app(|jwt| {
+        jwt.active_key_id = Some("ed".to_string());
+        jwt.keys = vec![key("ed", JwtKeyAlgorithm::EdDSA, "ed25519", true)];
+    })
+    .await;
+
+    let access_token = register_user(&app).await;
+    let header = decode_header(&access_token).unwrap();
+    assert_eq!(Algorithm::EdDSA, header.alg);
+    assert_eq!(Some("ed".to_string()), header.kid);
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/api/me", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn jwks_is_empty_with_shared_secret_signing() {
+    let app = spawn_app(|_| {}).await;
+
+    let jwks = fetch_jwks(&app).await;
+    assert!(jwks.keys.is_empty());
+
+    let access_token = register_user(&app).await;
+    assert_eq!(Algorithm::HS256, decode_header(&access_token).unwrap().alg);
+}
+
+#[tokio::test]
+async fn token_signed_with_unknown_kid_is_rejected() {
+    let app = spawn_app(|jwt| {
+        jwt.active_key_id = Some("rsa-a".to_string());
+        jwt.keys = vec![key("rsa-a", JwtKeyAlgorithm::RS256, "rsa-a", true)];
+    })
+    .await;
+    register_user(&app).await;
+
+    // Valid signature from a key the server doesn't list
+    let claims = json!({
+        "sub": uuid::Uuid::new_v4().to_string(),
+        "email": "john@example.com",
+        "exp": chrono::Utc::now().timestamp() + 600,
+        "iat": chrono::Utc::now().timestamp(),
+        "iss": "zero2prod"
+    });
+    let mut header = jsonwebtoken::Header::new(Algorithm::RS256);
+    header.kid = Some("rsa-b".to_string());
+    let pem = std::fs::read("tests/fixtures/jwt/rsa-b.pem").unwrap();
+    let token = jsonwebtoken::encode(
+        &header,
+        &claims,
+        &jsonwebtoken::EncodingKey::from_rsa_pem(&pem).unwrap(),
+    )
+    .unwrap();
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/api/me", &app.address))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn legacy_hs256_tokens_are_rejected_once_the_secret_is_retired() {
+    for accept_legacy_hs256 in [true, false] {
+        let app = spawn_app(|jwt| {
+            jwt.active_key_id = Some("rsa-2024".to_string());
+            jwt.keys = vec![key("rsa-2024", JwtKeyAlgorithm::RS256, "rsa-a", true)];
+            jwt.accept_legacy_hs256 = accept_legacy_hs256;
+        })
+        .await;
+        register_user(&app).await;
+        let user_id: uuid::Uuid = sqlx::query_scalar("SELECT id FROM users")
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch user");
+
+        // Signed with the shared secret and no kid, as before the rotation
+        let legacy = get_configuration().unwrap().jwt;
+        let legacy_keys = JwtKeys::from_settings(&legacy).unwrap();
+        let token =
+            generate_access_token(&user_id, "john@example.com", &legacy, &legacy_keys).unwrap();
+
+        let response = reqwest::Client::new()
+            .get(&format!("{}/api/me", &app.address))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        let expected = if accept_legacy_hs256 { 200 } else { 401 };
+        assert_eq!(expected, response.status().as_u16());
+    }
+}
@@ -0,0 +1,231 @@
+mod common;
+
+use common::{spawn_app_with, login, TestApp};
+use zero2prod::configuration::LoginThrottleSettings;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn spawn_app(login_throttle: LoginThrottleSettings) -> TestApp {
+    spawn_app_with(|configuration| configuration.auth.login_throttle = login_throttle).await
+}
+
+/// Lock after 3 failures with no progressive delay
+fn lockout_settings() -> LoginThrottleSettings {
+    LoginThrottleSettings {
+        delay_after_failures: 100,
+        base_delay_seconds: 1,
+        max_account_failures: 3,
+        max_ip_failures: 100,
+        lockout_seconds: 600,
+        failure_window_seconds: 600,
+    }
+}
+
+async fn register_user(app: &TestApp, email: &str, password: &str) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .up_to_n_times(1)
+        .mount(&app.email_server)
+        .await;
+
+    reqwest::Client::new()
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({ "name": "John Doe", "email": email, "password": password }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+}
+
+/// Wait for background email sends and return how many were received
+async fn emails_received(app: &TestApp) -> usize {
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    app.email_server.received_requests().await.unwrap().len()
+}
+
+#[tokio::test]
+async fn account_is_locked_after_repeated_failures() {
+    let app = spawn_app(lockout_settings()).await;
+    register_user(&app, "john@example.com", "SecurePass123").await;
+
+    for _ in 0..3 {
+        let response = login(&app, "john@example.com", "WrongPass123").await;
+        assert_eq!(400, response.status().as_u16());
+    }
+
+    // Even the correct password is refused while locked
+    let response = login(&app, "john@example.com", "SecurePass123").await;
+    assert_eq!(429, response.status().as_u16());
+    let retry_after: u64 = response.headers()["Retry-After"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 600);
+    let body: Value = response.json().await.expect("Failed to parse response");
+    assert_eq!("ACCOUNT_LOCKED", body["code"]);
+}
+
+#[tokio::test]
+async fn lockout_sends_one_notification_email() {
+    let app = spawn_app(lockout_settings()).await;
+    register_user(&app, "john@example.com", "SecurePass123").await;
+    let emails_before = emails_received(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    for _ in 0..5 {
+        login(&app, "john@example.com", "WrongPass123").await;
+    }
+
+    assert_eq!(emails_before + 1, emails_received(&app).await);
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests.last().unwrap().body).unwrap();
+    assert_eq!("john@example.com", body["to"]);
+}
+
+#[tokio::test]
+async fn unknown_email_is_throttled_like_a_real_account() {
+    let app = spawn_app(lockout_settings()).await;
+    register_user(&app, "john@example.com", "SecurePass123").await;
+    let emails_before = emails_received(&app).await;
+
+    let mut known = Vec::new();
+    let mut unknown = Vec::new();
+    for _ in 0..4 {
+        known.push(login(&app, "john@example.com", "WrongPass123").await.status().as_u16());
+        unknown.push(login(&app, "ghost@example.com", "WrongPass123").await.status().as_u16());
+    }
+
+    assert_eq!(vec![400, 400, 400, 429], known);
+    assert_eq!(known, unknown);
+    // Only the real account owner is notified
+    assert_eq!(emails_before + 1, emails_received(&app).await);
+}
+
+#[tokio::test]
+async fn progressive_delay_applies_before_lockout() {
+    let app = spawn_app(LoginThrottleSettings {
+        delay_after_failures: 2,
+        base_delay_seconds: 30,
+        ..lockout_settings()
+    })
+    .await;
+    register_user(&app, "john@example.com", "SecurePass123").await;
+
+    login(&app, "john@example.com", "WrongPass123").await;
+    login(&app, "john@example.com", "WrongPass123").await;
+
+    let response = login(&app, "john@example.com", "SecurePass123").await;
+    assert_eq!(429, response.status().as_u16());
+    assert_eq!("30", response.headers()["Retry-After"]);
+    let body: Value = response.json().await.expect("Failed to parse response");
+    assert_eq!("TOO_MANY_LOGIN_ATTEMPTS", body["code"]);
+}
+
+#[tokio::test]
+async fn successful_login_resets_failure_count() {
+    let app = spawn_app(lockout_settings()).await;
+    register_user(&app, "john@example.com", "SecurePass123").await;
+
+    for _ in 0..2 {
+        login(&app, "john@example.com", "WrongPass123").await;
+    }
+    let response = login(&app, "john@example.com", "SecurePass123").await;
+    assert_eq!(200, response.status().as_u16());
+
+    for _ in 0..2 {
+        login(&app, "john@example.com", "WrongPass123").await;
+    }
+    let response = login(&app, "john@example.com", "SecurePass123").await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn client_ip_is_locked_across_accounts() {
+    let app = spawn_app(LoginThrottleSettings {
+        max_ip_failures: 4,
+        ..lockout_settings()
+    })
+    .await;
+    register_user(&app, "john@example.com", "SecurePass123").await;
+
+    for i in 0..4 {
+        login(&app, &format!("user{}@example.com", i), "WrongPass123").await;
+    }
+
+    let response = login(&app, "john@example.com", "SecurePass123").await;
+    assert_eq!(429, response.status().as_u16());
+    assert!(response.headers().get("Retry-After").is_some());
+}
+
+#[tokio::test]
+async fn inactive_accounts_fail_like_unknown_emails() {
+    let app = spawn_app(lockout_settings()).await;
+    register_user(&app, "john@example.com", "SecurePass123").await;
+    sqlx::query("UPDATE users SET is_active = false WHERE email = 'john@example.com'")
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to deactivate user");
+
+    let outcome = |response: reqwest::Response| async move {
+        let status = response.status().as_u16();
+        let body: Value = response.json().await.expect("Failed to parse response");
+        (status, body["code"].clone(), body["message"].clone())
+    };
+    let unknown = outcome(login(&app, "ghost@example.com", "SecurePass123").await).await;
+
+    // The correct password gets the same answer and counts as a failure
+    for _ in 0..3 {
+        let inactive = outcome(login(&app, "john@example.com", "SecurePass123").await).await;
+        assert_eq!(unknown, inactive);
+    }
+    assert_eq!(
+        429,
+        login(&app, "john@example.com", "SecurePass123").await.status().as_u16()
+    );
+}
+
+#[tokio::test]
+async fn wrong_second_factor_codes_lock_the_account() {
+    let app = spawn_app(lockout_settings()).await;
+    register_user(&app, "john@example.com", "SecurePass123").await;
+    // RFC 6238 test secret; "000000" is never accepted for it within the test
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET totp_secret = 'GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ', totp_enabled_at = NOW(),
+            email_verified_at = NOW()
+        "#,
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // The correct password alone doesn't clear the failures...
+    for _ in 0..3 {
+        let body: Value = login(&app, "john@example.com", "SecurePass123")
+            .await
+            .json()
+            .await
+            .expect("Failed to parse response");
+        let response = reqwest::Client::new()
+            .post(&format!("{}/auth/login/mfa", &app.address))
+            .json(&json!({ "mfa_token": body["mfa_token"], "code": "000000" }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(401, response.status().as_u16());
+    }
+
+    // ...so repeated fresh challenges end in a lockout
+    let response = login(&app, "john@example.com", "SecurePass123").await;
+    assert_eq!(429, response.status().as_u16());
+    let body: Value = response.json().await.expect("Failed to parse response");
+    assert_eq!("ACCOUNT_LOCKED", body["code"]);
+}
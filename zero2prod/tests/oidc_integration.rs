@@ -0,0 +1,533 @@
+mod common;
+
+use common::{register_user, spawn_app_with, TestApp};
+use zero2prod::auth::JwtKeys;
+use zero2prod::configuration::{
+    JwtKeyAlgorithm, JwtKeySettings, JwtSettings,
+    OidcProviderSettings,
+};
+use serde_json::{json, Value};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use sha2::{Digest, Sha256};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const CLIENT_ID: &str = "zero2prod-client";
+const IDP_KEY_ID: &str = "idp-key";
+
+/// Login redirect parameters the mock issuer would receive
+struct AuthorizationRequest {
+    state: String,
+    nonce: String,
+    code_challenge: String,
+}
+
+/// The application with the mock issuer registered as the "mock" provider
+struct OidcTestApp {
+    app: TestApp,
+    issuer: MockServer,
+}
+
+impl std::ops::Deref for OidcTestApp {
+    type Target = TestApp;
+
+    fn deref(&self) -> &TestApp {
+        &self.app
+    }
+}
+
+async fn spawn_app() -> OidcTestApp {
+    let issuer = MockServer::start().await;
+    mount_issuer(&issuer).await;
+
+    let issuer_url = issuer.uri();
+    let app = spawn_app_with(|configuration| {
+        configuration.auth.oidc.providers = vec![OidcProviderSettings {
+            name: "mock".to_string(),
+            issuer_url,
+            client_id: CLIENT_ID.to_string(),
+            client_secret: "client-secret".to_string(),
+            scopes: vec![],
+        }];
+    })
+    .await;
+
+    OidcTestApp { app, issuer }
+}
+
+/// Serve discovery and JWKS for a local issuer signing with the rsa-a fixture
+async fn mount_issuer(issuer: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "issuer": issuer.uri(),
+            "authorization_endpoint": format!("{}/authorize", issuer.uri()),
+            "token_endpoint": format!("{}/token", issuer.uri()),
+            "jwks_uri": format!("{}/jwks", issuer.uri()),
+        })))
+        .mount(issuer)
+        .await;
+
+    let idp_keys = JwtKeys::from_settings(&JwtSettings {
+        secret: "unused-secret-for-the-mock-issuer".to_string(),
+        access_token_expiry: 900,
+        refresh_token_expiry: 3600,
+        issuer: "mock".to_string(),
+        active_key_id: Some(IDP_KEY_ID.to_string()),
+        keys: vec![JwtKeySettings {
+            kid: IDP_KEY_ID.to_string(),
+            algorithm: JwtKeyAlgorithm::RS256,
+            public_key_path: "tests/fixtures/jwt/rsa-a.pub.pem".to_string(),
+            private_key_path: Some("tests/fixtures/jwt/rsa-a.pem".to_string()),
+        }],
+        accept_legacy_hs256: false,
+    })
+    .expect("Failed to load issuer keys");
+
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(idp_keys.jwks()))
+        .mount(issuer)
+        .await;
+}
+
+fn id_token(issuer: &MockServer, claims: Value) -> String {
+    let mut body = json!({
+        "iss": issuer.uri(),
+        "aud": CLIENT_ID,
+        "sub": "idp-user-1",
+        "exp": chrono::Utc::now().timestamp() + 300,
+        "iat": chrono::Utc::now().timestamp(),
+    });
+    for (key, value) in claims.as_object().unwrap() {
+        body[key] = value.clone();
+    }
+
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(IDP_KEY_ID.to_string());
+    let key = EncodingKey::from_rsa_pem(include_bytes!("fixtures/jwt/rsa-a.pem")).unwrap();
+    encode(&header, &body, &key).unwrap()
+}
+
+async fn mount_token_response(app: &OidcTestApp, id_token: String) {
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": "provider-access-token",
+            "token_type": "Bearer",
+            "id_token": id_token,
+        })))
+        .mount(&app.issuer)
+        .await;
+}
+
+fn no_redirect_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+}
+
+async fn start_login(app: &OidcTestApp) -> AuthorizationRequest {
+    let response = no_redirect_client()
+        .get(&format!("{}/auth/oidc/mock/login", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(302, response.status().as_u16());
+
+    let location = response.headers()["Location"].to_str().unwrap();
+    let url = reqwest::Url::parse(location).unwrap();
+    assert!(location.starts_with(&format!("{}/authorize", app.issuer.uri())));
+
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .unwrap_or_else(|| panic!("Missing {} parameter", name))
+    };
+    assert_eq!("code", param("response_type"));
+    assert_eq!(CLIENT_ID, param("client_id"));
+    assert_eq!("S256", param("code_challenge_method"));
+    assert_eq!(
+        format!("{}/auth/oidc/mock/callback", app.address),
+        param("redirect_uri")
+    );
+
+    AuthorizationRequest {
+        state: param("state"),
+        nonce: param("nonce"),
+        code_challenge: param("code_challenge"),
+    }
+}
+
+async fn callback(app: &OidcTestApp, state: &str) -> reqwest::Response {
+    no_redirect_client()
+        .get(&format!("{}/auth/oidc/mock/callback", &app.address))
+        .query(&[("code", "authorization-code"), ("state", state)])
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn user_count(app: &OidcTestApp) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM users")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn oidc_login_provisions_user_and_issues_tokens() {
+    let app = spawn_app().await;
+    let login = start_login(&app).await;
+    mount_token_response(
+        &app,
+        id_token(
+            &app.issuer,
+            json!({
+                "nonce": login.nonce,
+                "email": "ada@example.com",
+                "email_verified": true,
+                "name": "Ada Lovelace",
+            }),
+        ),
+    )
+    .await;
+
+    let response = callback(&app, &login.state).await;
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("Bearer", body["token_type"]);
+    assert!(body["refresh_token"].is_string());
+
+    // The tokens are ordinary session tokens
+    let me: Value = reqwest::Client::new()
+        .get(&format!("{}/api/me", &app.address))
+        .header("Authorization", format!("Bearer {}", body["access_token"].as_str().unwrap()))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!("ada@example.com", me["email"]);
+    assert_eq!("Ada Lovelace", me["name"]);
+
+    let verified: bool = sqlx::query_scalar(
+        "SELECT email_verified_at IS NOT NULL FROM users WHERE email = 'ada@example.com'",
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert!(verified);
+
+    // The code was exchanged with the PKCE verifier matching the challenge
+    let requests = app.issuer.received_requests().await.unwrap();
+    let token_request = requests
+        .iter()
+        .find(|r| r.url.path() == "/token")
+        .expect("Token endpoint not called");
+    let form: Vec<(String, String)> = reqwest::Url::parse(&format!(
+        "http://form.invalid/?{}",
+        String::from_utf8_lossy(&token_request.body)
+    ))
+    .unwrap()
+    .query_pairs()
+    .into_owned()
+    .collect();
+    let field = |name: &str| form.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+    assert_eq!(Some("authorization_code".to_string()), field("grant_type"));
+    let verifier = field("code_verifier").expect("Missing code_verifier");
+    assert_eq!(
+        login.code_challenge,
+        URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+    );
+}
+
+#[tokio::test]
+async fn repeat_oidc_login_reuses_linked_user() {
+    let app = spawn_app().await;
+
+    for _ in 0..2 {
+        let login = start_login(&app).await;
+        app.issuer.reset().await;
+        mount_issuer(&app.issuer).await;
+        mount_token_response(
+            &app,
+            id_token(
+                &app.issuer,
+                json!({ "nonce": login.nonce, "email": "ada@example.com", "email_verified": true }),
+            ),
+        )
+        .await;
+        assert_eq!(200, callback(&app, &login.state).await.status().as_u16());
+    }
+
+    assert_eq!(1, user_count(&app).await);
+}
+
+#[tokio::test]
+async fn oidc_login_links_existing_user_by_verified_email() {
+    let app = spawn_app().await;
+    register_user(&app, "john@example.com", "John Doe").await;
+
+    let login = start_login(&app).await;
+    mount_token_response(
+        &app,
+        id_token(
+            &app.issuer,
+            json!({ "nonce": login.nonce, "email": "John@Example.com", "email_verified": true }),
+        ),
+    )
+    .await;
+
+    assert_eq!(200, callback(&app, &login.state).await.status().as_u16());
+    assert_eq!(1, user_count(&app).await);
+
+    let (email, verified): (String, bool) = sqlx::query_as(
+        r#"
+        SELECT u.email, u.email_verified_at IS NOT NULL
+        FROM user_identities i JOIN users u ON u.id = i.user_id
+        WHERE i.provider = 'mock' AND i.subject = 'idp-user-1'
+        "#,
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!("john@example.com", email);
+    assert!(verified);
+
+    // The owner's password keeps working
+    let response = common::login(&app, "john@example.com", "SecurePass123").await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn oidc_login_resets_credentials_of_unverified_user_with_same_email() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    // Someone registers the address before its owner signs in
+    let tokens: Value = client
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({
+            "name": "Mallory",
+            "email": "john@example.com",
+            "password": "SecurePass123"
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let access_token = tokens["access_token"].as_str().unwrap();
+    let refresh_token = tokens["refresh_token"].as_str().unwrap();
+    let created: Value = client
+        .post(&format!("{}/api/api-keys", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "name": "Backdoor", "scopes": ["profile:read"] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let api_key = created["key"].as_str().unwrap();
+
+    let login_request = start_login(&app).await;
+    mount_token_response(
+        &app,
+        id_token(
+            &app.issuer,
+            json!({ "nonce": login_request.nonce, "email": "john@example.com", "email_verified": true }),
+        ),
+    )
+    .await;
+    assert_eq!(200, callback(&app, &login_request.state).await.status().as_u16());
+    assert_eq!(1, user_count(&app).await);
+
+    let verified: bool =
+        sqlx::query_scalar("SELECT email_verified_at IS NOT NULL FROM users WHERE email = 'john@example.com'")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert!(verified);
+
+    // Nothing set up before the owner arrived still works
+    let response = common::login(&app, "john@example.com", "SecurePass123").await;
+    assert_eq!(400, response.status().as_u16());
+
+    let response = client
+        .post(&format!("{}/auth/refresh", &app.address))
+        .json(&json!({ "refresh_token": refresh_token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(400, response.status().as_u16());
+
+    let response = client
+        .get(&format!("{}/api/me", &app.address))
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(401, response.status().as_u16());
+
+    let audited: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM audit_logs WHERE action = 'OIDC_IDENTITY_LINKED' AND message LIKE '%unverified%'",
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(1, audited);
+}
+
+#[tokio::test]
+async fn oidc_login_leaves_inactive_users_untouched() {
+    let app = spawn_app().await;
+    let response = reqwest::Client::new()
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({
+            "name": "John Doe",
+            "email": "john@example.com",
+            "password": "SecurePass123"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(201, response.status().as_u16());
+    sqlx::query("UPDATE users SET is_active = false")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let password_hash = || async {
+        sqlx::query_scalar::<_, String>("SELECT password_hash FROM users")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+    };
+    let before = password_hash().await;
+
+    let login_request = start_login(&app).await;
+    mount_token_response(
+        &app,
+        id_token(
+            &app.issuer,
+            json!({ "nonce": login_request.nonce, "email": "john@example.com", "email_verified": true }),
+        ),
+    )
+    .await;
+    assert_eq!(403, callback(&app, &login_request.state).await.status().as_u16());
+
+    assert_eq!(before, password_hash().await);
+    let verified: bool = sqlx::query_scalar("SELECT email_verified_at IS NOT NULL FROM users")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(!verified);
+    let identities: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM user_identities")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, identities);
+    let refresh_tokens: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM refresh_tokens WHERE is_revoked = false")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(1, refresh_tokens);
+}
+
+#[tokio::test]
+async fn oidc_callback_rejects_unknown_or_replayed_state() {
+    let app = spawn_app().await;
+
+    assert_eq!(401, callback(&app, "not-a-real-state").await.status().as_u16());
+
+    let login = start_login(&app).await;
+    mount_token_response(
+        &app,
+        id_token(
+            &app.issuer,
+            json!({ "nonce": login.nonce, "email": "ada@example.com", "email_verified": true }),
+        ),
+    )
+    .await;
+    assert_eq!(200, callback(&app, &login.state).await.status().as_u16());
+    assert_eq!(401, callback(&app, &login.state).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn oidc_callback_rejects_nonce_mismatch() {
+    let app = spawn_app().await;
+    let login = start_login(&app).await;
+    mount_token_response(
+        &app,
+        id_token(
+            &app.issuer,
+            json!({ "nonce": "some-other-nonce", "email": "ada@example.com", "email_verified": true }),
+        ),
+    )
+    .await;
+
+    assert_eq!(401, callback(&app, &login.state).await.status().as_u16());
+    assert_eq!(0, user_count(&app).await);
+}
+
+#[tokio::test]
+async fn oidc_callback_rejects_id_token_for_another_client() {
+    let app = spawn_app().await;
+    let login = start_login(&app).await;
+    mount_token_response(
+        &app,
+        id_token(
+            &app.issuer,
+            json!({
+                "aud": "someone-else",
+                "nonce": login.nonce,
+                "email": "ada@example.com",
+                "email_verified": true,
+            }),
+        ),
+    )
+    .await;
+
+    assert_eq!(401, callback(&app, &login.state).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn oidc_login_requires_verified_email_to_provision() {
+    let app = spawn_app().await;
+    let login = start_login(&app).await;
+    mount_token_response(
+        &app,
+        id_token(
+            &app.issuer,
+            json!({ "nonce": login.nonce, "email": "ada@example.com", "email_verified": false }),
+        ),
+    )
+    .await;
+
+    let response = callback(&app, &login.state).await;
+    assert_eq!(403, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("EMAIL_NOT_VERIFIED", body["code"]);
+    assert_eq!(0, user_count(&app).await);
+}
+
+#[tokio::test]
+async fn oidc_login_returns_404_for_unknown_provider() {
+    let app = spawn_app().await;
+
+    let response = no_redirect_client()
+        .get(&format!("{}/auth/oidc/unknown/login", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(404, response.status().as_u16());
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::{spawn_app_with, TestApp};
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+async fn spawn_app() -> TestApp {
+    spawn_app_with(|configuration| configuration.rate_limit.enabled = false).await
+}
+
+async fn assert_payload_too_large(response: reqwest::Response) {
+    assert_eq!(413, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("PAYLOAD_TOO_LARGE", body["code"]);
+}
+
+#[tokio::test]
+async fn oversized_subscription_is_refused_with_413() {
+    let app = spawn_app().await;
+    let body = format!("name=le%20guin&email={}%40example.com", "a".repeat(2000));
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_payload_too_large(response).await;
+    let saved: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, saved);
+}
+
+#[tokio::test]
+async fn chunked_bodies_are_capped_too() {
+    let app = spawn_app().await;
+    let body = format!("name=le%20guin&email={}%40example.com", "a".repeat(2000));
+
+    // reqwest always sends a Content-Length for a known body
+    let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", app.port)).await.unwrap();
+    let mut request = "POST /subscriptions HTTP/1.1\r\nHost: localhost\r\n\
+        Content-Type: application/x-www-form-urlencoded\r\n\
+        Transfer-Encoding: chunked\r\nConnection: close\r\n\r\n"
+        .to_string();
+    for chunk in body.as_bytes().chunks(500) {
+        request.push_str(&format!("{:x}\r\n{}\r\n", chunk.len(), std::str::from_utf8(chunk).unwrap()));
+    }
+    request.push_str("0\r\n\r\n");
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+    assert!(response.contains("PAYLOAD_TOO_LARGE"));
+}
+
+#[tokio::test]
+async fn json_routes_use_the_default_limit() {
+    let app = spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/auth/login", &app.address))
+        .json(&json!({ "email": "ursula@example.com", "password": "a".repeat(20_000) }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_payload_too_large(response).await;
+}
+
+#[tokio::test]
+async fn newsletter_content_gets_a_larger_limit() {
+    let app = spawn_app().await;
+    let newsletter = |size: usize| {
+        json!({
+            "subject": "Newsletter",
+            "html_content": format!("<p>{}</p>", "a".repeat(size)),
+        })
+    };
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/api/newsletters/send-all", &app.address))
+        .json(&newsletter(100_000))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_ne!(413, response.status().as_u16());
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/api/newsletters/send-all", &app.address))
+        .json(&newsletter(2 * 1024 * 1024))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_payload_too_large(response).await;
+}
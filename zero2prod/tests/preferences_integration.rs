@@ -0,0 +1,327 @@
+mod common;
+
+use common::{admin_token, spawn_app_with, TestApp};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+use zero2prod::preference_link::PreferenceLinks;
+use zero2prod::configuration::get_configuration;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn spawn_app() -> TestApp {
+    spawn_app_with(|configuration| configuration.subscriptions.bot_protection.min_fill_seconds = 0).await
+}
+
+/// Access token of an admin, who may send newsletters
+///
+/// Clears the registration email from the mock so tests only see newsletters.
+async fn sender_token(app: &TestApp) -> String {
+    let access_token = admin_token(app).await;
+    app.email_server.reset().await;
+    access_token
+}
+
+/// Links signed the way the application signs them
+fn preference_links(app: &TestApp) -> PreferenceLinks {
+    let configuration = get_configuration().expect("Failed to read configuration.");
+    PreferenceLinks::new(&configuration.subscriptions, &app.address)
+        .expect("Invalid subscription settings")
+}
+
+async fn mount_email_mock(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+/// Insert a confirmed subscriber and return its id
+async fn insert_subscriber(app: &TestApp, email: &str) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query(
+        r#"
+        INSERT INTO subscriptions (id, email, email_canonical, name, subscribed_at, status)
+        VALUES ($1, $2, lower($2), 'Jane Doe', now(), 'confirmed')
+        "#,
+    )
+    .bind(id)
+    .bind(email)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to insert subscriber");
+    id
+}
+
+/// Preference token from the link in the last email sent
+async fn token_from_last_email(app: &TestApp) -> String {
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests.last().unwrap().body).unwrap();
+    let html = body["Html"].as_str().unwrap();
+    let re = regex::Regex::new(r#"preferences\.html\?token=([^"]+)""#).unwrap();
+    re.captures(html).expect("No preference link in email")[1].to_string()
+}
+
+async fn get_preferences(app: &TestApp, token: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(&format!("{}/subscriptions/preferences", &app.address))
+        .query(&[("token", token)])
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn update_preferences(app: &TestApp, token: &str, body: Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .put(&format!("{}/subscriptions/preferences", &app.address))
+        .query(&[("token", token)])
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn confirmation_email_links_to_preference_center() {
+    let app = spawn_app().await;
+    mount_email_mock(&app).await;
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/subscriptions", &app.address))
+        .form(&[("name", "Jane Doe"), ("email", "jane@example.com")])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let token = token_from_last_email(&app).await;
+    let response = get_preferences(&app, &token).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let preferences: Value = response.json().await.unwrap();
+    assert_eq!("jane@example.com", preferences["email"]);
+    assert_eq!("pending", preferences["status"]);
+    assert_eq!("immediate", preferences["digest_frequency"]);
+    assert!(preferences["paused_until"].is_null());
+    let topics = preferences["topics"].as_array().unwrap();
+    assert!(!topics.is_empty());
+    assert!(topics.iter().all(|t| t["subscribed"] == true));
+
+    // Confirming still works alongside the preference link
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let re = regex::Regex::new(r#"confirm\?token=([^"]+)""#).unwrap();
+    let confirmation_token = re.captures(body["Html"].as_str().unwrap()).unwrap()[1].to_string();
+    let response = reqwest::Client::new()
+        .get(&format!("{}/subscriptions/confirm", &app.address))
+        .query(&[("token", confirmation_token.as_str())])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let preferences: Value = get_preferences(&app, &token).await.json().await.unwrap();
+    assert_eq!("confirmed", preferences["status"]);
+}
+
+#[tokio::test]
+async fn markup_in_name_is_escaped_in_confirmation_email() {
+    let app = spawn_app().await;
+    mount_email_mock(&app).await;
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/subscriptions", &app.address))
+        .form(&[("name", "<a href=\"https://evil.example\">Claim prize</a>"), ("email", "jane@example.com")])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let html = body["Html"].as_str().unwrap();
+    assert!(html.contains("&lt;a href=&quot;https://evil.example&quot;&gt;Claim prize&lt;/a&gt;"));
+    assert!(!html.contains("https://evil.example\""));
+}
+
+#[tokio::test]
+async fn invalid_or_tampered_tokens_are_rejected() {
+    let app = spawn_app().await;
+    let subscriber_id = insert_subscriber(&app, "jane@example.com").await;
+    let token = preference_links(&app).token(subscriber_id);
+
+    // Someone else's id under this subscriber's signature
+    let (_, rest) = token.split_once('.').unwrap();
+    let forged = format!("{}.{}", Uuid::new_v4().simple(), rest);
+
+    for bad in ["", "garbage", &forged] {
+        let response = get_preferences(&app, bad).await;
+        assert_eq!(401, response.status().as_u16(), "token {:?} was accepted", bad);
+    }
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/subscriptions/unsubscribe", &app.address))
+        .query(&[("token", forged.as_str())])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn subscriber_can_update_preferences() {
+    let app = spawn_app().await;
+    let subscriber_id = insert_subscriber(&app, "jane@example.com").await;
+    let token = preference_links(&app).token(subscriber_id);
+
+    let response = update_preferences(
+        &app,
+        &token,
+        json!({
+            "name": "Jane Smith",
+            "topics": ["general"],
+            "digest_frequency": "weekly",
+            "pause_weeks": 2
+        }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let preferences: Value = response.json().await.unwrap();
+    assert_eq!("Jane Smith", preferences["name"]);
+    assert_eq!("weekly", preferences["digest_frequency"]);
+    assert!(preferences["paused_until"].is_string());
+    for topic in preferences["topics"].as_array().unwrap() {
+        assert_eq!(topic["name"] == "general", topic["subscribed"] == true);
+    }
+
+    let (excluded_topics, paused_until) =
+        sqlx::query_as::<_, (Vec<String>, Option<chrono::DateTime<Utc>>)>(
+            "SELECT excluded_topics, paused_until FROM subscriptions WHERE id = $1",
+        )
+        .bind(subscriber_id)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(!excluded_topics.is_empty());
+    assert!(!excluded_topics.contains(&"general".to_string()));
+    assert!(paused_until.unwrap() > Utc::now() + Duration::days(13));
+
+    // Resume delivery; other fields stay as they are
+    let response = update_preferences(&app, &token, json!({ "pause_weeks": 0 })).await;
+    let preferences: Value = response.json().await.unwrap();
+    assert!(preferences["paused_until"].is_null());
+    assert_eq!("Jane Smith", preferences["name"]);
+    assert_eq!("weekly", preferences["digest_frequency"]);
+}
+
+#[tokio::test]
+async fn invalid_preference_updates_are_rejected() {
+    let app = spawn_app().await;
+    let subscriber_id = insert_subscriber(&app, "jane@example.com").await;
+    let token = preference_links(&app).token(subscriber_id);
+
+    for body in [
+        json!({ "topics": ["no-such-topic"] }),
+        json!({ "digest_frequency": "hourly" }),
+        json!({ "pause_weeks": 53 }),
+        json!({ "pause_weeks": -1 }),
+        json!({ "name": "" }),
+    ] {
+        let response = update_preferences(&app, &token, body.clone()).await;
+        assert_eq!(400, response.status().as_u16(), "update {} was accepted", body);
+    }
+}
+
+#[tokio::test]
+async fn unsubscribe_stops_newsletters() {
+    let app = spawn_app().await;
+    let sender = sender_token(&app).await;
+    mount_email_mock(&app).await;
+    let subscriber_id = insert_subscriber(&app, "jane@example.com").await;
+    let token = preference_links(&app).token(subscriber_id);
+
+    for _ in 0..2 {
+        let response = reqwest::Client::new()
+            .post(&format!("{}/subscriptions/unsubscribe", &app.address))
+            .query(&[("token", token.as_str())])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    let preferences: Value = get_preferences(&app, &token).await.json().await.unwrap();
+    assert_eq!("unsubscribed", preferences["status"]);
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/api/newsletters/send-all", &app.address))
+        .header("Authorization", format!("Bearer {}", sender))
+        .json(&json!({ "subject": "News", "html_content": "<p>Hello</p>" }))
+        .send()
+        .await
+        .unwrap();
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(0, body["sent_count"]);
+    assert!(app.email_server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn topic_newsletter_skips_opted_out_and_paused_subscribers() {
+    let app = spawn_app().await;
+    let sender = sender_token(&app).await;
+    mount_email_mock(&app).await;
+
+    let receiving = insert_subscriber(&app, "receiving@example.com").await;
+    let opted_out = insert_subscriber(&app, "opted-out@example.com").await;
+    let paused = insert_subscriber(&app, "paused@example.com").await;
+
+    update_preferences(
+        &app,
+        &preference_links(&app).token(opted_out),
+        json!({ "topics": ["general"] }),
+    )
+    .await;
+    update_preferences(
+        &app,
+        &preference_links(&app).token(paused),
+        json!({ "pause_weeks": 4 }),
+    )
+    .await;
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/api/newsletters/send-confirmed", &app.address))
+        .header("Authorization", format!("Bearer {}", sender))
+        .json(&json!({
+            "subject": "Upcoming events",
+            "html_content": "<p>See you there</p>",
+            "topic": "events"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(1, body["sent_count"]);
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    assert_eq!(1, requests.len());
+    let email: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!("receiving@example.com", email["to"]);
+    let token = token_from_last_email(&app).await;
+    assert_eq!(receiving, preference_links(&app).verify(&token).unwrap());
+
+    // Unknown topics are refused rather than sent to everyone
+    let response = reqwest::Client::new()
+        .post(&format!("{}/api/newsletters/send-confirmed", &app.address))
+        .header("Authorization", format!("Bearer {}", sender))
+        .json(&json!({
+            "subject": "Oops",
+            "html_content": "<p>Oops</p>",
+            "topic": "no-such-topic"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(400, response.status().as_u16());
+}
@@ -0,0 +1,194 @@
+mod common;
+
+use common::{spawn_app_with, TestApp};
+use zero2prod::configuration::{
+    RateLimitKey, RateLimitPolicySettings, RateLimitSettings,
+    RateLimitStorage,
+};
+use sqlx::PgPool;
+use serde_json::{json, Value};
+
+async fn spawn_app(rate_limit: RateLimitSettings) -> TestApp {
+    spawn_app_with(|configuration| configuration.rate_limit = rate_limit).await
+}
+
+/// Start an instance on an existing database
+async fn spawn_instance(connection_pool: PgPool, rate_limit: RateLimitSettings) -> TestApp {
+    common::spawn_instance(connection_pool, |configuration| configuration.rate_limit = rate_limit).await
+}
+
+fn policy(
+    name: &str,
+    path_prefix: &str,
+    methods: &[&str],
+    requests_per_minute: u32,
+    key: RateLimitKey,
+) -> RateLimitPolicySettings {
+    RateLimitPolicySettings {
+        name: name.to_string(),
+        path_prefix: path_prefix.to_string(),
+        methods: methods.iter().map(|m| m.to_string()).collect(),
+        requests_per_minute,
+        key,
+    }
+}
+
+fn settings(trusted_proxies: &[&str], policies: Vec<RateLimitPolicySettings>) -> RateLimitSettings {
+    RateLimitSettings {
+        enabled: true,
+        storage: RateLimitStorage::Memory,
+        storage_timeout_milliseconds: 50,
+        trusted_proxies: trusted_proxies.iter().map(|p| p.to_string()).collect(),
+        policies,
+    }
+}
+
+async fn login(app: &TestApp, forwarded_for: Option<&str>) -> reqwest::Response {
+    let mut request = reqwest::Client::new()
+        .post(&format!("{}/auth/login", &app.address))
+        .json(&json!({ "email": "nobody@example.com", "password": "WrongPass123" }));
+    if let Some(forwarded_for) = forwarded_for {
+        request = request.header("X-Forwarded-For", forwarded_for);
+    }
+    request.send().await.expect("Failed to execute request.")
+}
+
+fn header(response: &reqwest::Response, name: &str) -> String {
+    response.headers()[name].to_str().unwrap().to_string()
+}
+
+/// Seconds in a header that counts down with the refill, so only its range is fixed
+fn seconds_header(response: &reqwest::Response, name: &str) -> u64 {
+    header(response, name).parse().unwrap()
+}
+
+#[tokio::test]
+async fn requests_over_the_limit_get_429_with_rate_limit_headers() {
+    let app = spawn_app(settings(&[], vec![
+        policy("login", "/auth/login", &["POST"], 2, RateLimitKey::Ip),
+    ]))
+    .await;
+
+    let response = login(&app, None).await;
+    assert_ne!(429, response.status().as_u16());
+    assert_eq!("2", header(&response, "RateLimit-Limit"));
+    assert_eq!("1", header(&response, "RateLimit-Remaining"));
+    assert!((1..=30).contains(&seconds_header(&response, "RateLimit-Reset")));
+    login(&app, None).await;
+
+    let response = login(&app, None).await;
+    assert_eq!(429, response.status().as_u16());
+    assert!((1..=30).contains(&seconds_header(&response, "Retry-After")));
+    assert_eq!("0", header(&response, "RateLimit-Remaining"));
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("RATE_LIMITED", body["code"]);
+
+    // Routes without a matching policy aren't limited
+    let response = reqwest::Client::new()
+        .get(&format!("{}/health_check", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    assert!(response.headers().get("RateLimit-Limit").is_none());
+}
+
+#[tokio::test]
+async fn forwarded_for_is_only_believed_from_trusted_proxies() {
+    let policies = || vec![policy("login", "/auth/login", &["POST"], 1, RateLimitKey::Ip)];
+
+    // The test client connects from 127.0.0.1, which isn't trusted here
+    let app = spawn_app(settings(&[], policies())).await;
+    assert_ne!(429, login(&app, Some("198.51.100.1")).await.status().as_u16());
+    assert_eq!(429, login(&app, Some("198.51.100.2")).await.status().as_u16());
+
+    let app = spawn_app(settings(&["127.0.0.0/8"], policies())).await;
+    assert_ne!(429, login(&app, Some("198.51.100.1")).await.status().as_u16());
+    assert_ne!(429, login(&app, Some("198.51.100.2")).await.status().as_u16());
+    assert_eq!(429, login(&app, Some("203.0.113.9, 198.51.100.1")).await.status().as_u16());
+}
+
+#[tokio::test]
+async fn user_keyed_limits_are_counted_per_user() {
+    let app = spawn_app(settings(&[], vec![
+        policy("api", "/api", &[], 2, RateLimitKey::User),
+    ]))
+    .await;
+
+    let mut tokens = Vec::new();
+    for email in ["first@example.com", "second@example.com"] {
+        let body: Value = reqwest::Client::new()
+            .post(&format!("{}/auth/register", &app.address))
+            .json(&json!({ "name": "Rate Limited", "email": email, "password": "SecurePass123" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        tokens.push(body["access_token"].as_str().unwrap().to_string());
+    }
+
+    let me = |token: &str| {
+        reqwest::Client::new()
+            .get(&format!("{}/api/me", &app.address))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+    };
+    assert_ne!(429, me(&tokens[0]).await.unwrap().status().as_u16());
+    assert_ne!(429, me(&tokens[0]).await.unwrap().status().as_u16());
+    assert_eq!(429, me(&tokens[0]).await.unwrap().status().as_u16());
+    assert_ne!(429, me(&tokens[1]).await.unwrap().status().as_u16());
+}
+
+fn postgres_settings(requests_per_minute: u32, timeout_milliseconds: u64) -> RateLimitSettings {
+    RateLimitSettings {
+        storage: RateLimitStorage::Postgres,
+        storage_timeout_milliseconds: timeout_milliseconds,
+        ..settings(&[], vec![
+            policy("login", "/auth/login", &["POST"], requests_per_minute, RateLimitKey::Ip),
+        ])
+    }
+}
+
+#[tokio::test]
+async fn postgres_storage_shares_limits_between_instances() {
+    let first = spawn_app(postgres_settings(3, 5000)).await;
+    let second = spawn_instance(first.db_pool.clone(), postgres_settings(3, 5000)).await;
+
+    assert_ne!(429, login(&first, None).await.status().as_u16());
+    assert_ne!(429, login(&second, None).await.status().as_u16());
+    let response = login(&first, None).await;
+    assert_ne!(429, response.status().as_u16());
+    assert_eq!("0", header(&response, "RateLimit-Remaining"));
+
+    let response = login(&second, None).await;
+    assert_eq!(429, response.status().as_u16());
+    assert!(header(&response, "Retry-After").parse::<u64>().unwrap() >= 1);
+
+    let counted: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(count), 0)::bigint FROM rate_limit_counters WHERE policy = 'login'",
+    )
+    .fetch_one(&first.db_pool)
+    .await
+    .unwrap();
+    assert!(counted >= 4);
+}
+
+#[tokio::test]
+async fn postgres_storage_falls_back_to_local_limits_when_slow() {
+    let app = spawn_app(postgres_settings(2, 50)).await;
+
+    // Counting blocks behind this lock until the timeout, so every request
+    // is counted locally
+    let mut lock = app.db_pool.begin().await.unwrap();
+    sqlx::query("LOCK TABLE rate_limit_counters IN EXCLUSIVE MODE")
+        .execute(&mut lock)
+        .await
+        .unwrap();
+
+    assert_ne!(429, login(&app, None).await.status().as_u16());
+    assert_ne!(429, login(&app, None).await.status().as_u16());
+    assert_eq!(429, login(&app, None).await.status().as_u16());
+    lock.rollback().await.unwrap();
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use common::{spawn_app_with, TestApp};
+
+async fn get(app: &TestApp, path: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(&format!("{}{}", &app.address, path))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+fn header(response: &reqwest::Response, name: &str) -> String {
+    response.headers()[name].to_str().unwrap().to_string()
+}
+
+fn assert_common_headers(response: &reqwest::Response) {
+    assert_eq!("nosniff", header(response, "X-Content-Type-Options"));
+    assert_eq!("SAMEORIGIN", header(response, "X-Frame-Options"));
+    assert_eq!("same-origin", header(response, "Cross-Origin-Opener-Policy"));
+    assert_eq!("same-origin", header(response, "Cross-Origin-Resource-Policy"));
+    assert!(header(response, "Permissions-Policy").contains("camera=()"));
+    assert!(response.headers().get("Strict-Transport-Security").is_none());
+    assert!(response.headers().get("X-CSRF-Token").is_none());
+}
+
+#[tokio::test]
+async fn api_responses_get_security_headers() {
+    let app = spawn_app_with(|_| {}).await;
+
+    let response = get(&app, "/health_check").await;
+    assert!(response.status().is_success());
+    assert_common_headers(&response);
+
+    // Errors from the JWT middleware get them too, with the /api policy
+    let response = get(&app, "/api/me").await;
+    assert_eq!(401, response.status().as_u16());
+    assert_common_headers(&response);
+    assert_eq!(
+        "default-src 'none'; frame-ancestors 'none'",
+        header(&response, "Content-Security-Policy")
+    );
+}
+
+#[tokio::test]
+async fn static_pages_get_a_fresh_csp_nonce_on_their_scripts() {
+    let app = spawn_app_with(|_| {}).await;
+
+    let mut nonces = Vec::new();
+    for _ in 0..2 {
+        let response = get(&app, "/").await;
+        assert_eq!(200, response.status().as_u16());
+        assert_common_headers(&response);
+        assert!(response.headers().get("ETag").is_none());
+
+        let csp = header(&response, "Content-Security-Policy");
+        let nonce = csp
+            .split("'nonce-")
+            .nth(1)
+            .and_then(|rest| rest.split('\'').next())
+            .expect("CSP has no nonce")
+            .to_string();
+
+        let page = response.text().await.unwrap();
+        assert!(page.contains(&format!(r#"<script nonce="{}" src="/app.js">"#, nonce)));
+        assert!(!page.contains("style=\""));
+        assert!(!page.contains("{{csp_nonce}}"));
+        nonces.push(nonce);
+    }
+    assert_ne!(nonces[0], nonces[1]);
+
+    // Assets other than pages are served untouched
+    let response = get(&app, "/app.js").await;
+    assert_eq!(200, response.status().as_u16());
+    assert_common_headers(&response);
+    assert!(response.headers().get("ETag").is_some());
+}
+
+#[tokio::test]
+async fn hsts_is_only_sent_for_https_through_trusted_proxies() {
+    let app = spawn_app_with(|configuration| {
+        configuration.rate_limit.trusted_proxies = vec!["127.0.0.1".to_string()];
+        configuration.security_headers.hsts_max_age_seconds = 600;
+        configuration.security_headers.hsts_include_subdomains = false;
+    })
+    .await;
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/health_check", &app.address))
+        .header("X-Forwarded-Proto", "https")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!("max-age=600", header(&response, "Strict-Transport-Security"));
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/health_check", &app.address))
+        .header("X-Forwarded-Proto", "http")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.headers().get("Strict-Transport-Security").is_none());
+}
+
+#[tokio::test]
+async fn forwarded_proto_from_untrusted_peers_is_ignored() {
+    let app = spawn_app_with(|_| {}).await;
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/health_check", &app.address))
+        .header("X-Forwarded-Proto", "https")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.headers().get("Strict-Transport-Security").is_none());
+}
+
+#[tokio::test]
+async fn headers_can_be_configured_and_left_out() {
+    let app = spawn_app_with(|configuration| {
+        configuration.security_headers.permissions_policy = String::new();
+        configuration.security_headers.cross_origin_resource_policy = "cross-origin".to_string();
+    })
+    .await;
+
+    let response = get(&app, "/health_check").await;
+    assert!(response.headers().get("Permissions-Policy").is_none());
+    assert_eq!("cross-origin", header(&response, "Cross-Origin-Resource-Policy"));
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use common::{spawn_app_with, TestApp};
+use zero2prod::configuration::SessionMode;
+use serde_json::{json, Value};
+
+async fn spawn_app(mode: SessionMode) -> TestApp {
+    spawn_app_with(|configuration| {
+        configuration.rate_limit.enabled = false;
+        configuration.auth.session.mode = mode;
+    })
+    .await
+}
+
+/// Cookies set by a response, as (name, full Set-Cookie header) pairs
+fn set_cookies(response: &reqwest::Response) -> Vec<(String, String)> {
+    response
+        .headers()
+        .get_all("Set-Cookie")
+        .iter()
+        .map(|h| {
+            let cookie = h.to_str().unwrap().to_string();
+            (cookie.split('=').next().unwrap().to_string(), cookie)
+        })
+        .collect()
+}
+
+fn cookie_value(cookies: &[(String, String)], name: &str) -> String {
+    let (_, cookie) = cookies.iter().find(|(n, _)| n == name).expect("Cookie not set");
+    let pair = cookie.split(';').next().unwrap();
+    pair.split_once('=').unwrap().1.to_string()
+}
+
+/// Register a user and log in, returning the response cookies and body
+async fn log_in(app: &TestApp) -> (Vec<(String, String)>, Value) {
+    let client = reqwest::Client::new();
+    let credentials = json!({ "email": "ursula@example.com", "password": "Earthsea1968" });
+    client
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({ "name": "Ursula Le Guin", "email": "ursula@example.com", "password": "Earthsea1968" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    let response = client
+        .post(&format!("{}/auth/login", &app.address))
+        .json(&credentials)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let cookies = set_cookies(&response);
+    let body: Value = response.json().await.expect("Failed to parse response");
+    (cookies, body)
+}
+
+async fn refresh(app: &TestApp, refresh_token: &str, csrf_token: Option<&str>) -> reqwest::Response {
+    let mut request = reqwest::Client::new()
+        .post(&format!("{}/auth/refresh", &app.address))
+        .header("Cookie", format!("refresh_token={}", refresh_token))
+        .json(&json!({}));
+    if let Some(csrf_token) = csrf_token {
+        request = request.header("X-CSRF-Token", csrf_token);
+    }
+    request.send().await.expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn cookie_mode_keeps_the_refresh_token_out_of_the_body() {
+    let app = spawn_app(SessionMode::Cookie).await;
+
+    let (cookies, body) = log_in(&app).await;
+
+    assert!(body["access_token"].is_string());
+    assert!(body.get("refresh_token").is_none());
+    let refresh_cookie = &cookies.iter().find(|(n, _)| n == "refresh_token").unwrap().1;
+    assert!(refresh_cookie.contains("HttpOnly"));
+    assert!(refresh_cookie.contains("Path=/auth"));
+    assert!(refresh_cookie.contains("SameSite=Strict"));
+    assert_eq!(body["csrf_token"].as_str().unwrap(), cookie_value(&cookies, "csrf_token"));
+}
+
+#[tokio::test]
+async fn refresh_with_the_session_cookie_needs_the_csrf_token() {
+    let app = spawn_app(SessionMode::Cookie).await;
+    let (cookies, body) = log_in(&app).await;
+    let refresh_token = cookie_value(&cookies, "refresh_token");
+    let csrf_token = body["csrf_token"].as_str().unwrap();
+
+    for csrf in [None, Some("forged")] {
+        let response = refresh(&app, &refresh_token, csrf).await;
+        assert_eq!(403, response.status().as_u16());
+        let error: Value = response.json().await.unwrap();
+        assert_eq!("CSRF_TOKEN_INVALID", error["code"]);
+    }
+
+    let response = refresh(&app, &refresh_token, Some(csrf_token)).await;
+    assert_eq!(200, response.status().as_u16());
+    let cookies = set_cookies(&response);
+    assert_ne!(refresh_token, cookie_value(&cookies, "refresh_token"));
+}
+
+#[tokio::test]
+async fn logout_revokes_the_session_and_clears_its_cookies() {
+    let app = spawn_app(SessionMode::Cookie).await;
+    let (cookies, body) = log_in(&app).await;
+    let refresh_token = cookie_value(&cookies, "refresh_token");
+    let csrf_token = body["csrf_token"].as_str().unwrap();
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/auth/logout", &app.address))
+        .header("Cookie", format!("refresh_token={}", refresh_token))
+        .header("X-CSRF-Token", csrf_token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(204, response.status().as_u16());
+    let cleared = set_cookies(&response);
+    assert_eq!("", cookie_value(&cleared, "refresh_token"));
+    assert_eq!("", cookie_value(&cleared, "csrf_token"));
+    let response = refresh(&app, &refresh_token, Some(csrf_token)).await;
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn token_mode_sets_no_cookies() {
+    let app = spawn_app(SessionMode::Tokens).await;
+
+    let (cookies, body) = log_in(&app).await;
+
+    assert!(cookies.is_empty());
+    assert!(body["refresh_token"].is_string());
+    assert!(body.get("csrf_token").is_none());
+}
@@ -0,0 +1,279 @@
+mod common;
+
+use common::{spawn_app, admin_token, register_user, TestApp};
+use uuid::Uuid;
+use zero2prod::subscriber_data::email_tombstone_hash;
+use serde_json::Value;
+
+async fn insert_subscriber(app: &TestApp, email: &str, status: &str) {
+    sqlx::query(
+        r#"
+        INSERT INTO subscriptions (id, email, email_canonical, name, subscribed_at, status)
+        VALUES ($1, $2, lower($2), 'Existing Name', now(), $3)
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(email)
+    .bind(status)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to insert subscriber");
+}
+
+async fn import(
+    app: &TestApp,
+    token: &str,
+    query: &[(&str, &str)],
+    csv: String,
+) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(&format!("{}/api/admin/subscribers/import", &app.address))
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Content-Type", "text/csv")
+        .query(query)
+        .body(csv)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// Start an import and poll the job until it's done
+async fn import_and_wait(app: &TestApp, token: &str, query: &[(&str, &str)], csv: String) -> Value {
+    let response = import(app, token, query, csv).await;
+    assert_eq!(202, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    let status_url = format!("{}{}", app.address, body["status_url"].as_str().unwrap());
+
+    for _ in 0..200 {
+        let job: Value = reqwest::Client::new()
+            .get(&status_url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if job["status"] == "completed" || job["status"] == "failed" {
+            return job;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("Import did not finish");
+}
+
+async fn subscriber(app: &TestApp, email: &str) -> (String, String, Option<String>) {
+    sqlx::query_as("SELECT name, status, consent_source FROM subscriptions WHERE email = $1")
+        .bind(email)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Subscriber not found")
+}
+
+#[tokio::test]
+async fn import_applies_rows_and_reports_failures() {
+    let app = spawn_app().await;
+    let token = admin_token(&app).await;
+    insert_subscriber(&app, "jane@example.com", "pending").await;
+    insert_subscriber(&app, "gone@example.com", "unsubscribed").await;
+    sqlx::query("INSERT INTO subscription_tombstones (email_hash, erased_at) VALUES ($1, now())")
+        .bind(email_tombstone_hash("erased@example.com"))
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let csv = "\u{feff}Name,source,Email\r\n\
+        New Person,old,new@example.com\r\n\
+        Jane Updated,old,JANE@example.com\r\n\
+        Bad Email,old,not-an-email\r\n\
+        Gone,old,gone@example.com\r\n\
+        Erased,old,erased@example.com\r\n\
+        ,old,noname@example.com\r\n\
+        \"Doe, John\",old,john@example.com"
+        .to_string();
+    let job = import_and_wait(&app, &token, &[], csv).await;
+
+    assert_eq!("completed", job["status"]);
+    assert_eq!("skip_existing", job["mode"]);
+    assert_eq!(7, job["total_rows"]);
+    assert_eq!(2, job["imported"]);
+    assert_eq!(0, job["updated"]);
+    assert_eq!(2, job["skipped"]);
+    assert_eq!(3, job["failed"]);
+
+    let rows: Vec<i64> = job["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["row"].as_i64().unwrap())
+        .collect();
+    assert_eq!(vec![4, 6, 7], rows);
+    assert_eq!("not-an-email", job["errors"][0]["email"]);
+
+    assert_eq!(
+        ("New Person".to_string(), "pending".to_string(), None),
+        subscriber(&app, "new@example.com").await
+    );
+    assert_eq!("Doe, John", subscriber(&app, "john@example.com").await.0);
+    assert_eq!("Existing Name", subscriber(&app, "jane@example.com").await.0);
+    assert_eq!("unsubscribed", subscriber(&app, "gone@example.com").await.1);
+    let erased: i64 =
+        sqlx::query_scalar("SELECT count(*) FROM subscriptions WHERE email = 'erased@example.com'")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(0, erased);
+}
+
+#[tokio::test]
+async fn import_update_and_confirm_modes() {
+    let app = spawn_app().await;
+    let token = admin_token(&app).await;
+    insert_subscriber(&app, "jane@example.com", "pending").await;
+
+    let job = import_and_wait(
+        &app,
+        &token,
+        &[("mode", "update")],
+        "email,name\njane@example.com,Jane Updated\n".to_string(),
+    )
+    .await;
+    assert_eq!(1, job["updated"]);
+    assert_eq!(
+        ("Jane Updated".to_string(), "pending".to_string(), None),
+        subscriber(&app, "jane@example.com").await
+    );
+
+    // Confirming needs a record of where consent came from
+    let response = import(
+        &app,
+        &token,
+        &[("mode", "confirm")],
+        "email,name\njane@example.com,Jane\n".to_string(),
+    )
+    .await;
+    assert_eq!(400, response.status().as_u16());
+
+    let job = import_and_wait(
+        &app,
+        &token,
+        &[("mode", "confirm"), ("consent_source", "Old provider signup form")],
+        "email,name\njane@example.com,Jane Doe\nnew@example.com,New Person\n".to_string(),
+    )
+    .await;
+    assert_eq!(1, job["updated"]);
+    assert_eq!(1, job["imported"]);
+    for email in ["jane@example.com", "new@example.com"] {
+        let (_, status, consent_source) = subscriber(&app, email).await;
+        assert_eq!("confirmed", status);
+        assert_eq!(Some("Old provider signup form".to_string()), consent_source);
+    }
+}
+
+#[tokio::test]
+async fn import_without_required_columns_fails() {
+    let app = spawn_app().await;
+    let token = admin_token(&app).await;
+
+    let job = import_and_wait(&app, &token, &[], "address\njane@example.com\n".to_string()).await;
+    assert_eq!("failed", job["status"]);
+    assert!(job["error"].as_str().unwrap().contains("email"));
+
+    let job = import_and_wait(&app, &token, &[], String::new()).await;
+    assert_eq!("failed", job["status"]);
+}
+
+#[tokio::test]
+async fn import_handles_many_rows() {
+    let app = spawn_app().await;
+    let token = admin_token(&app).await;
+
+    let mut csv = String::from("email,name\n");
+    for i in 0..1200 {
+        csv.push_str(&format!("user{}@example.com,User {}\n", i, i));
+    }
+    let job = import_and_wait(&app, &token, &[], csv).await;
+
+    assert_eq!("completed", job["status"]);
+    assert_eq!(1200, job["total_rows"]);
+    assert_eq!(1200, job["imported"]);
+}
+
+#[tokio::test]
+async fn export_streams_csv_and_jsonl_filtered_by_status() {
+    let app = spawn_app().await;
+    let token = admin_token(&app).await;
+    insert_subscriber(&app, "jane@example.com", "confirmed").await;
+    insert_subscriber(&app, "john@example.com", "pending").await;
+    insert_subscriber(&app, "=formula@example.com", "confirmed").await;
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/api/admin/subscribers/export", &app.address))
+        .header("Authorization", format!("Bearer {}", token))
+        .query(&[("status", "confirmed")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    assert!(response
+        .headers()
+        .get("content-type")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("text/csv"));
+    let csv = response.text().await.unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(3, lines.len());
+    assert!(lines[0].starts_with("id,email,name,status"));
+    assert!(csv.contains("jane@example.com"));
+    assert!(csv.contains("'=formula@example.com"));
+    assert!(!csv.contains("john@example.com"));
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/api/admin/subscribers/export", &app.address))
+        .header("Authorization", format!("Bearer {}", token))
+        .query(&[("format", "jsonl")])
+        .send()
+        .await
+        .unwrap();
+    let body = response.text().await.unwrap();
+    let records: Vec<Value> =
+        body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(3, records.len());
+    assert!(records.iter().any(|r| r["email"] == "john@example.com" && r["status"] == "pending"));
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/api/admin/subscribers/export", &app.address))
+        .header("Authorization", format!("Bearer {}", token))
+        .query(&[("status", "bogus")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn bulk_endpoints_reject_non_admins() {
+    let app = spawn_app().await;
+    let tokens = register_user(&app, "john@example.com", "John Doe").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    let csv = "email,name\na@example.com,A\n".to_string();
+    let response = import(&app, access_token, &[], csv).await;
+    assert_eq!(403, response.status().as_u16());
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/api/admin/subscribers/export", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    let jobs: i64 = sqlx::query_scalar("SELECT count(*) FROM subscriber_import_jobs")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, jobs);
+}
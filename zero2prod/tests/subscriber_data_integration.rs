@@ -0,0 +1,206 @@
+mod common;
+
+use common::{spawn_app, admin_token, register_user, TestApp};
+use uuid::Uuid;
+use zero2prod::preference_link::PreferenceLinks;
+use zero2prod::subscriber_data::{email_tombstone_hash, is_erased_subscriber};
+use zero2prod::configuration::get_configuration;
+use serde_json::{json, Value};
+
+/// Links signed the way the application signs them
+fn preference_links(app: &TestApp) -> PreferenceLinks {
+    let configuration = get_configuration().expect("Failed to read configuration.");
+    PreferenceLinks::new(&configuration.subscriptions, &app.address)
+        .expect("Invalid subscription settings")
+}
+
+/// Insert a subscriber with a pending confirmation token and return its id
+async fn insert_subscriber(app: &TestApp, email: &str) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query(
+        r#"
+        INSERT INTO subscriptions (id, email, email_canonical, name, subscribed_at, status)
+        VALUES ($1, $2, lower($2), 'Jane Doe', now(), 'pending')
+        "#,
+    )
+    .bind(id)
+    .bind(email)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to insert subscriber");
+    sqlx::query(
+        r#"
+        INSERT INTO subscription_tokens (subscription_token, subscriber_id, created_at, expires_at)
+        VALUES ($1, $2, now(), now() + interval '1 day')
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(id)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to insert confirmation token");
+    id
+}
+
+async fn admin_request(app: &TestApp, token: &str, action: &str, email: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(&format!("{}/api/admin/subscriber-data/{}", &app.address, action))
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&json!({ "email": email }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn count(app: &TestApp, query: &str) -> i64 {
+    sqlx::query_scalar(query)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count rows")
+}
+
+#[tokio::test]
+async fn admin_export_returns_subscription_tokens_and_audit_trail() {
+    let app = spawn_app().await;
+    let token = admin_token(&app).await;
+    let subscriber_id = insert_subscriber(&app, "jane@example.com").await;
+
+    // Leaves an audit entry for the subscriber
+    reqwest::Client::new()
+        .put(&format!("{}/subscriptions/preferences", &app.address))
+        .query(&[("token", preference_links(&app).token(subscriber_id))])
+        .json(&json!({ "digest_frequency": "weekly" }))
+        .send()
+        .await
+        .unwrap();
+
+    let response = admin_request(&app, &token, "export", "Jane@Example.com").await;
+    assert_eq!(200, response.status().as_u16());
+    assert!(response
+        .headers()
+        .get("content-disposition")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("attachment"));
+
+    let export: Value = response.json().await.unwrap();
+    assert_eq!(subscriber_id.to_string(), export["subscriptions"][0]["id"]);
+    assert_eq!("weekly", export["subscriptions"][0]["digest_frequency"]);
+    assert_eq!(1, export["confirmation_tokens"].as_array().unwrap().len());
+    assert!(export["confirmation_tokens"][0].get("subscription_token").is_none());
+    let actions: Vec<&str> = export["audit_log"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["action"].as_str().unwrap())
+        .collect();
+    assert!(actions.contains(&"UPDATE_PREFERENCES"));
+
+    let response = admin_request(&app, &token, "export", "nobody@example.com").await;
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn admin_erasure_cascades_and_leaves_tombstone() {
+    let app = spawn_app().await;
+    let token = admin_token(&app).await;
+    let subscriber_id = insert_subscriber(&app, "jane@example.com").await;
+    insert_subscriber(&app, "john@example.com").await;
+
+    let response = admin_request(&app, &token, "erase", "JANE@example.com").await;
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(1, body["erased_count"]);
+
+    assert_eq!(
+        0,
+        count(&app, "SELECT count(*) FROM subscriptions WHERE email = 'jane@example.com'").await
+    );
+    assert_eq!(1, count(&app, "SELECT count(*) FROM subscription_tokens").await);
+
+    let email_hash: String =
+        sqlx::query_scalar("SELECT email_hash FROM subscription_tombstones")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(email_tombstone_hash("jane@example.com"), email_hash);
+    assert!(is_erased_subscriber(&app.db_pool, "Jane@Example.com").await.unwrap());
+    assert!(!is_erased_subscriber(&app.db_pool, "john@example.com").await.unwrap());
+
+    let erasures = count(
+        &app,
+        &format!(
+            "SELECT count(*) FROM audit_logs WHERE action = 'ERASE_SUBSCRIBER' AND resource_id = '{}'",
+            subscriber_id
+        ),
+    )
+    .await;
+    assert_eq!(1, erasures);
+    assert_eq!(
+        0,
+        count(&app, "SELECT count(*) FROM audit_logs WHERE message LIKE '%jane@example.com%'").await
+    );
+
+    let response = admin_request(&app, &token, "erase", "jane@example.com").await;
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn subscriber_data_admin_endpoints_reject_non_admins() {
+    let app = spawn_app().await;
+    let tokens = register_user(&app, "john@example.com", "John Doe").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+    insert_subscriber(&app, "jane@example.com").await;
+
+    for action in ["export", "erase"] {
+        let response = admin_request(&app, access_token, action, "jane@example.com").await;
+        assert_eq!(403, response.status().as_u16());
+    }
+    assert_eq!(1, count(&app, "SELECT count(*) FROM subscriptions").await);
+}
+
+#[tokio::test]
+async fn subscriber_can_export_and_erase_own_data_with_link() {
+    let app = spawn_app().await;
+    let subscriber_id = insert_subscriber(&app, "jane@example.com").await;
+    let link_token = preference_links(&app).token(subscriber_id);
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(&format!("{}/subscriptions/data", &app.address))
+        .query(&[("token", link_token.as_str())])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    let export: Value = response.json().await.unwrap();
+    assert_eq!("jane@example.com", export["subscriptions"][0]["email"]);
+
+    let response = client
+        .delete(&format!("{}/subscriptions/data", &app.address))
+        .query(&[("token", "forged")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(401, response.status().as_u16());
+
+    let response = client
+        .delete(&format!("{}/subscriptions/data", &app.address))
+        .query(&[("token", link_token.as_str())])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(0, count(&app, "SELECT count(*) FROM subscriptions").await);
+    assert!(is_erased_subscriber(&app.db_pool, "jane@example.com").await.unwrap());
+
+    // The link stops working once the subscriber is gone
+    let response = client
+        .get(&format!("{}/subscriptions/data", &app.address))
+        .query(&[("token", link_token.as_str())])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(404, response.status().as_u16());
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use common::{spawn_app_with, TestApp};
+use zero2prod::configuration::BotProtectionSettings;
+use zero2prod::subscriber_data::email_tombstone_hash;
+use zero2prod::subscription_guard::proof_of_work_bits;
+use serde_json::Value;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn spawn_app(bot_protection: BotProtectionSettings) -> TestApp {
+    let app = spawn_app_with(|configuration| {
+        configuration.rate_limit.enabled = false;
+        configuration.subscriptions.bot_protection = bot_protection;
+    })
+    .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app
+}
+
+fn settings(min_fill_seconds: i64, proof_of_work_difficulty: u32) -> BotProtectionSettings {
+    BotProtectionSettings {
+        min_fill_seconds,
+        proof_of_work_difficulty,
+        ..BotProtectionSettings::default()
+    }
+}
+
+async fn challenge(app: &TestApp) -> Value {
+    reqwest::Client::new()
+        .get(&format!("{}/subscriptions/challenge", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse response")
+}
+
+async fn subscribe(app: &TestApp, fields: &[(&str, &str)]) -> reqwest::Response {
+    subscribe_as(app, "ursula@example.com", fields).await
+}
+
+async fn subscribe_as(app: &TestApp, email: &str, fields: &[(&str, &str)]) -> reqwest::Response {
+    let mut form = vec![("name", "Ursula Le Guin"), ("email", email)];
+    form.extend_from_slice(fields);
+    reqwest::Client::new()
+        .post(&format!("{}/subscriptions", &app.address))
+        .form(&form)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn assert_bot_check_failed(response: reqwest::Response) {
+    assert_eq!(400, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("BOT_CHECK_FAILED", body["code"]);
+}
+
+async fn subscriber_count(app: &TestApp) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn honeypot_submissions_look_successful_but_send_nothing() {
+    let app = spawn_app(settings(0, 0)).await;
+
+    let response = subscribe(&app, &[("website", "https://spam.example.com")]).await;
+
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(0, subscriber_count(&app).await);
+    assert!(app.email_server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn form_must_be_submitted_with_a_form_token_after_the_minimum_fill_time() {
+    let app = spawn_app(settings(2, 0)).await;
+    let challenge = challenge(&app).await;
+    let form_token = challenge["form_token"].as_str().unwrap();
+    assert_eq!(2, challenge["min_fill_seconds"]);
+    assert!(challenge.get("proof_of_work_difficulty").is_none());
+
+    assert_bot_check_failed(subscribe(&app, &[]).await).await;
+    assert_bot_check_failed(subscribe(&app, &[("form_token", "1.forged.token")]).await).await;
+    assert_bot_check_failed(subscribe(&app, &[("form_token", form_token)]).await).await;
+    assert_eq!(0, subscriber_count(&app).await);
+
+    tokio::time::sleep(std::time::Duration::from_millis(2100)).await;
+    let response = subscribe(&app, &[("form_token", form_token)]).await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(1, subscriber_count(&app).await);
+}
+
+#[tokio::test]
+async fn proof_of_work_is_required_when_enabled() {
+    let app = spawn_app(settings(0, 8)).await;
+    let challenge = challenge(&app).await;
+    let form_token = challenge["form_token"].as_str().unwrap();
+    assert_eq!(8, challenge["proof_of_work_difficulty"]);
+
+    assert_bot_check_failed(subscribe(&app, &[("form_token", form_token)]).await).await;
+
+    let nonce = (0u64..)
+        .map(|n| n.to_string())
+        .find(|nonce| proof_of_work_bits(form_token, "ursula@example.com", nonce) >= 8)
+        .unwrap();
+    let response = subscribe(&app, &[("form_token", form_token), ("pow_nonce", &nonce)]).await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn confirmation_emails_are_capped_per_recipient() {
+    let app = spawn_app(settings(0, 0)).await;
+    for _ in 0..3 {
+        sqlx::query("INSERT INTO confirmation_email_sends (email_hash, sent_at) VALUES ($1, now())")
+            .bind(email_tombstone_hash("ursula@example.com"))
+            .execute(&app.db_pool)
+            .await
+            .unwrap();
+    }
+
+    let response = subscribe(&app, &[]).await;
+
+    assert_eq!(429, response.status().as_u16());
+    let retry_after: u64 = response.headers()["Retry-After"].to_str().unwrap().parse().unwrap();
+    assert!(retry_after > 23 * 3600);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!("TOO_MANY_CONFIRMATION_EMAILS", body["code"]);
+    assert_eq!(0, subscriber_count(&app).await);
+    assert!(app.email_server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn form_token_sends_only_one_confirmation_email() {
+    let app = spawn_app(settings(1, 0)).await;
+    let challenge = challenge(&app).await;
+    let form_token = challenge["form_token"].as_str().unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    // Failed validation doesn't use up the token
+    let response = subscribe_as(&app, "not-an-email", &[("form_token", form_token)]).await;
+    assert_eq!(400, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_ne!("BOT_CHECK_FAILED", body["code"]);
+
+    let response = subscribe(&app, &[("form_token", form_token)]).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let response = subscribe_as(&app, "other@example.com", &[("form_token", form_token)]).await;
+    assert_bot_check_failed(response).await;
+    assert_eq!(1, subscriber_count(&app).await);
+    assert_eq!(1, app.email_server.received_requests().await.unwrap().len());
+}
+
+#[tokio::test]
+async fn concurrent_subscriptions_cannot_exceed_the_confirmation_email_cap() {
+    let app = spawn_app(settings(0, 0)).await;
+
+    let responses = futures::future::join_all((0..10).map(|_| subscribe(&app, &[]))).await;
+
+    // Only the first submission subscribes, but each of the first three got
+    // through the cap
+    let throttled = responses.iter().filter(|r| r.status().as_u16() == 429).count();
+    assert_eq!(7, throttled);
+    let reserved: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM confirmation_email_sends")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(3, reserved);
+}
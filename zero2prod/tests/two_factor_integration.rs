@@ -0,0 +1,284 @@
+mod common;
+
+use common::{spawn_app, spawn_app_with, TestApp};
+use serde_json::{json, Value};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+const EMAIL: &str = "john@example.com";
+const PASSWORD: &str = "SecurePass123";
+
+/// Register a verified user and return an access token
+async fn register_user(app: &TestApp) -> String {
+    reqwest::Client::new()
+        .post(&format!("{}/auth/register", &app.address))
+        .json(&json!({ "name": "John Doe", "email": EMAIL, "password": PASSWORD }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    sqlx::query("UPDATE users SET email_verified_at = NOW() WHERE email = $1")
+        .bind(EMAIL)
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to verify user");
+
+    let body = login(app).await;
+    body["access_token"].as_str().unwrap().to_string()
+}
+
+async fn login(app: &TestApp) -> Value {
+    reqwest::Client::new()
+        .post(&format!("{}/auth/login", &app.address))
+        .json(&json!({ "email": EMAIL, "password": PASSWORD }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse response")
+}
+
+/// Generate the TOTP code for `secret` at `offset` seconds from now
+fn code_at(secret: &str, offset: i64) -> String {
+    let bytes = Secret::Encoded(secret.to_string()).to_bytes().unwrap();
+    let totp = TOTP::new(Algorithm::SHA1, 6, 1, 30, bytes, None, String::new()).unwrap();
+    totp.generate((chrono::Utc::now().timestamp() + offset) as u64)
+}
+
+/// Enroll the user in 2FA and return (secret, recovery_codes)
+async fn enable_two_factor(app: &TestApp, access_token: &str) -> (String, Vec<String>) {
+    let client = reqwest::Client::new();
+    let setup: Value = client
+        .post(&format!("{}/api/me/2fa/setup", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "current_password": PASSWORD }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse response");
+    let secret = setup["secret"].as_str().unwrap().to_string();
+
+    let confirm: Value = client
+        .post(&format!("{}/api/me/2fa/confirm", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "code": code_at(&secret, 0) }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse response");
+    let recovery_codes = confirm["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c.as_str().unwrap().to_string())
+        .collect();
+
+    (secret, recovery_codes)
+}
+
+async fn login_mfa(app: &TestApp, mfa_token: &str, code: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(&format!("{}/auth/login/mfa", &app.address))
+        .json(&json!({ "mfa_token": mfa_token, "code": code }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn setup_returns_secret_and_otpauth_uri() {
+    let app = spawn_app().await;
+    let access_token = register_user(&app).await;
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/api/me/2fa/setup", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "current_password": PASSWORD }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.expect("Failed to parse response");
+    let secret = body["secret"].as_str().unwrap();
+    let uri = body["otpauth_uri"].as_str().unwrap();
+    assert!(uri.starts_with("otpauth://totp/"));
+    assert!(uri.contains(&format!("secret={}", secret)));
+
+    // Not active until confirmed
+    let body = login(&app).await;
+    assert!(body.get("access_token").is_some());
+}
+
+#[tokio::test]
+async fn setup_rejects_wrong_password() {
+    let app = spawn_app().await;
+    let access_token = register_user(&app).await;
+
+    let response = reqwest::Client::new()
+        .post(&format!("{}/api/me/2fa/setup", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "current_password": "WrongPass123" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn confirm_rejects_wrong_code() {
+    let app = spawn_app().await;
+    let access_token = register_user(&app).await;
+    let client = reqwest::Client::new();
+
+    client
+        .post(&format!("{}/api/me/2fa/setup", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "current_password": PASSWORD }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    let response = client
+        .post(&format!("{}/api/me/2fa/confirm", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "code": "000000" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(401, response.status().as_u16());
+    let enabled: bool = sqlx::query_scalar("SELECT totp_enabled_at IS NOT NULL FROM users")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(!enabled);
+}
+
+#[tokio::test]
+async fn login_with_two_factor_requires_second_step() {
+    let app = spawn_app().await;
+    let access_token = register_user(&app).await;
+    let (secret, recovery_codes) = enable_two_factor(&app, &access_token).await;
+    assert_eq!(10, recovery_codes.len());
+
+    let body = login(&app).await;
+    assert_eq!(true, body["mfa_required"]);
+    assert!(body.get("access_token").is_none());
+    let mfa_token = body["mfa_token"].as_str().unwrap();
+
+    // Wrong code is rejected
+    let response = login_mfa(&app, mfa_token, "000000").await;
+    assert_eq!(401, response.status().as_u16());
+
+    // The enrollment step is already used, so take the next one
+    let code = code_at(&secret, 30);
+    let response = login_mfa(&app, mfa_token, &code).await;
+    assert_eq!(200, response.status().as_u16());
+    let tokens: Value = response.json().await.expect("Failed to parse response");
+    assert!(tokens.get("access_token").is_some());
+    assert!(tokens.get("refresh_token").is_some());
+
+    // Challenge tokens are single-use
+    let response = login_mfa(&app, mfa_token, &code).await;
+    assert_eq!(401, response.status().as_u16());
+
+    // The same code cannot be replayed with a fresh challenge
+    let body = login(&app).await;
+    let response = login_mfa(&app, body["mfa_token"].as_str().unwrap(), &code).await;
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn concurrent_guesses_cannot_exceed_attempt_limit() {
+    // Only the challenge's own limit applies, not the account lockout
+    let app = spawn_app_with(|configuration| {
+        configuration.auth.login_throttle.delay_after_failures = 100;
+        configuration.auth.login_throttle.max_account_failures = 100;
+    })
+    .await;
+    let access_token = register_user(&app).await;
+    enable_two_factor(&app, &access_token).await;
+
+    let body = login(&app).await;
+    let mfa_token = body["mfa_token"].as_str().unwrap();
+
+    // Stays within the login rate limit, which counts the two logins above
+    let guesses = (0..8).map(|_| login_mfa(&app, mfa_token, "000000"));
+    let responses = futures::future::join_all(guesses).await;
+
+    let mut checked = 0;
+    for response in responses {
+        let body: Value = response.json().await.expect("Failed to parse response");
+        match body["code"].as_str().unwrap() {
+            "INVALID_MFA_CODE" => checked += 1,
+            code => assert_eq!("TOKEN_INVALID", code),
+        }
+    }
+    // Only as many codes were checked as the challenge allows
+    assert_eq!(5, checked);
+}
+
+#[tokio::test]
+async fn recovery_codes_are_single_use() {
+    let app = spawn_app().await;
+    let access_token = register_user(&app).await;
+    let (_, recovery_codes) = enable_two_factor(&app, &access_token).await;
+
+    let body = login(&app).await;
+    let response = login_mfa(&app, body["mfa_token"].as_str().unwrap(), &recovery_codes[0]).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let body = login(&app).await;
+    let response = login_mfa(&app, body["mfa_token"].as_str().unwrap(), &recovery_codes[0]).await;
+    assert_eq!(401, response.status().as_u16());
+
+    let body = login(&app).await;
+    let response = login_mfa(&app, body["mfa_token"].as_str().unwrap(), &recovery_codes[1]).await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn disable_requires_password_and_code() {
+    let app = spawn_app().await;
+    let access_token = register_user(&app).await;
+    let (secret, _) = enable_two_factor(&app, &access_token).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(&format!("{}/api/me/2fa/disable", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "current_password": PASSWORD, "code": "000000" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+
+    let response = client
+        .post(&format!("{}/api/me/2fa/disable", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "current_password": "WrongPass123", "code": code_at(&secret, 30) }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+
+    let response = client
+        .post(&format!("{}/api/me/2fa/disable", &app.address))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .json(&json!({ "current_password": PASSWORD, "code": code_at(&secret, 30) }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    // Login no longer asks for a second factor
+    let body = login(&app).await;
+    assert!(body.get("access_token").is_some());
+    assert!(body.get("mfa_required").is_none());
+}